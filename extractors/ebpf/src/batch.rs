@@ -0,0 +1,237 @@
+use shared::nats_subjects::batch_subject;
+use shared::protobuf::event::{Event, EventBatch};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Accumulates events per subject into EventBatches. A batch is complete
+/// once it contains `max_events` events or its first event was added
+/// `max_delay` ago, whichever comes first. With a maximum size, a batch is
+/// also complete before the next event would make it larger than
+/// `max_bytes`, as the NATS server rejects larger payloads.
+///
+/// The events keep their timestamps and stay in the order they were added.
+pub struct Batcher {
+    max_events: usize,
+    max_delay: Duration,
+    max_bytes: Option<usize>,
+    pending: HashMap<String, Pending>,
+}
+
+struct Pending {
+    started: Instant,
+    events: Vec<Event>,
+    traced: Vec<Instant>,
+    // The encoded length of the EventBatch with these events.
+    bytes: usize,
+}
+
+/// A complete batch of events to publish.
+pub struct Batch {
+    /// The batch subject to publish on, e.g. `netmsg.batch`.
+    pub subject: String,
+    pub batch: EventBatch,
+    /// When the tracepoint of each of the events fired, to record their
+    /// publish latency.
+    pub traced: Vec<Instant>,
+}
+
+/// Events ready to be published after adding an event to the [Batcher].
+pub enum Ready {
+    Batch(Batch),
+    /// An event too large to fit into a batch of its own. It's published by
+    /// itself on its subject, e.g. `netmsg`.
+    Single {
+        subject: String,
+        event: Event,
+        traced: Instant,
+    },
+}
+
+impl Batcher {
+    pub fn new(max_events: usize, max_delay: Duration) -> Self {
+        Batcher {
+            max_events,
+            max_delay,
+            max_bytes: None,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Completes the batches before they get larger than `max_bytes`, e.g.
+    /// the maximum NATS payload.
+    pub fn with_max_bytes(mut self, max_bytes: Option<usize>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Adds an event for the subject. Returns what's ready to be published:
+    /// the previous batch if this event doesn't fit into it anymore, and the
+    /// subject's batch if it's complete with this event. An event too large
+    /// for any batch is returned by itself, after the subject's pending batch
+    /// to keep the order.
+    pub fn push(
+        &mut self,
+        subject: &str,
+        event: Event,
+        traced: Instant,
+        now: Instant,
+    ) -> Vec<Ready> {
+        let mut ready = vec![];
+        let event_bytes = event.batched_len();
+        if let Some(max_bytes) = self.max_bytes {
+            if event_bytes > max_bytes {
+                ready.extend(self.take(subject).map(Ready::Batch));
+                ready.push(Ready::Single {
+                    subject: subject.to_string(),
+                    event,
+                    traced,
+                });
+                return ready;
+            }
+            let pending_bytes = self.pending.get(subject).map(|pending| pending.bytes);
+            if pending_bytes.is_some_and(|bytes| bytes + event_bytes > max_bytes) {
+                ready.extend(self.take(subject).map(Ready::Batch));
+            }
+        }
+
+        let pending = self
+            .pending
+            .entry(subject.to_string())
+            .or_insert_with(|| Pending {
+                started: now,
+                events: Vec::with_capacity(self.max_events),
+                traced: Vec::with_capacity(self.max_events),
+                bytes: 0,
+            });
+        pending.events.push(event);
+        pending.traced.push(traced);
+        pending.bytes += event_bytes;
+        if pending.events.len() >= self.max_events {
+            ready.extend(self.take(subject).map(Ready::Batch));
+        }
+        ready
+    }
+
+    /// Returns the batches whose first event was added at least `max_delay`
+    /// before `now`.
+    pub fn take_expired(&mut self, now: Instant) -> Vec<Batch> {
+        let expired: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.started) >= self.max_delay)
+            .map(|(subject, _)| subject.clone())
+            .collect();
+        expired
+            .iter()
+            .filter_map(|subject| self.take(subject))
+            .collect()
+    }
+
+    /// When the next batch expires, if there are any pending events.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending
+            .values()
+            .map(|pending| pending.started + self.max_delay)
+            .min()
+    }
+
+    fn take(&mut self, subject: &str) -> Option<Batch> {
+        self.pending.remove(subject).map(|pending| Batch {
+            subject: batch_subject(subject),
+            batch: EventBatch {
+                events: pending.events,
+            },
+            traced: pending.traced,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::prost::Message;
+    use shared::testing::events::event;
+
+    fn timestamps(batch: &Batch) -> Vec<u64> {
+        batch.batch.events.iter().map(|e| e.timestamp).collect()
+    }
+
+    fn batch(mut ready: Vec<Ready>) -> Batch {
+        assert_eq!(ready.len(), 1);
+        match ready.pop() {
+            Some(Ready::Batch(batch)) => batch,
+            _ => panic!("expected a batch"),
+        }
+    }
+
+    #[test]
+    fn test_batcher_size() {
+        let mut batcher = Batcher::new(3, Duration::from_secs(1));
+        let now = Instant::now();
+        assert!(batcher.push("netmsg", event(3), now, now).is_empty());
+        assert!(batcher.push("netconn", event(1), now, now).is_empty());
+        assert!(batcher.push("netmsg", event(1), now, now).is_empty());
+        let batch = batch(batcher.push("netmsg", event(2), now, now));
+        assert_eq!(batch.subject, "netmsg.batch");
+        // the order and timestamps of the events are kept
+        assert_eq!(timestamps(&batch), vec![3, 1, 2]);
+        assert_eq!(batch.traced.len(), 3);
+        // the netconn batch is still pending
+        assert_eq!(batcher.next_deadline(), Some(now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_batcher_delay() {
+        let delay = Duration::from_millis(100);
+        let mut batcher = Batcher::new(100, delay);
+        let start = Instant::now();
+        assert_eq!(batcher.next_deadline(), None);
+        batcher.push("netmsg", event(1), start, start);
+        batcher.push("netconn", event(2), start, start + delay / 2);
+        batcher.push("netmsg", event(3), start, start + delay / 2);
+
+        assert!(batcher.take_expired(start + delay / 2).is_empty());
+        let expired = batcher.take_expired(start + delay);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].subject, "netmsg.batch");
+        assert_eq!(timestamps(&expired[0]), vec![1, 3]);
+        assert_eq!(batcher.next_deadline(), Some(start + delay / 2 + delay));
+
+        let expired = batcher.take_expired(start + delay * 2);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(timestamps(&expired[0]), vec![2]);
+        assert_eq!(batcher.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_batcher_max_bytes() {
+        let event_bytes = event(1).batched_len();
+        let mut batcher =
+            Batcher::new(100, Duration::from_secs(1)).with_max_bytes(Some(event_bytes * 2));
+        let now = Instant::now();
+        assert!(batcher.push("netmsg", event(1), now, now).is_empty());
+        assert!(batcher.push("netmsg", event(2), now, now).is_empty());
+        // the third event doesn't fit, the first two are published
+        let full = batch(batcher.push("netmsg", event(3), now, now));
+        assert_eq!(timestamps(&full), vec![1, 2]);
+        assert!(full.batch.encoded_len() <= event_bytes * 2);
+
+        // an event too large for a batch is published by itself, after the
+        // pending batch
+        let mut large = event(4);
+        large
+            .labels
+            .insert("node".to_string(), "x".repeat(event_bytes * 2));
+        let mut ready = batcher.push("netmsg", large, now, now);
+        assert_eq!(ready.len(), 2);
+        match ready.pop() {
+            Some(Ready::Single { subject, event, .. }) => {
+                assert_eq!(subject, "netmsg");
+                assert_eq!(event.timestamp, 4);
+            }
+            _ => panic!("expected a single event"),
+        }
+        assert_eq!(timestamps(&batch(ready)), vec![3]);
+        assert_eq!(batcher.next_deadline(), None);
+    }
+}
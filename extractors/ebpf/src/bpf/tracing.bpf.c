@@ -24,11 +24,15 @@
 #define PAGE_SIZE 4096
 #define NET_MSG_PAGES 128
 
+// All events start with the kernel time (bpf_ktime_get_ns()) the tracepoint
+// fired at. This allows ordering the events read from different ring buffers.
+
 // NET MESSAGES
 
-#define METADATA_SIZE 8 + MAX_PEER_ADDR_LENGTH + MAX_PEER_CONN_TYPE_LENGTH + MAX_MSG_TYPE_LENGTH + 1 + 8
+#define METADATA_SIZE 8 + 8 + MAX_PEER_ADDR_LENGTH + MAX_PEER_CONN_TYPE_LENGTH + MAX_MSG_TYPE_LENGTH + 1 + 8
 
 struct Metadata {
+    u64     ktime;
     u64     id;
     char    addr[MAX_PEER_ADDR_LENGTH];
     char    conn_type[MAX_PEER_CONN_TYPE_LENGTH];
@@ -67,6 +71,7 @@ RINGBUFFER(net_msg_huge, (MAX_HUGE_MSG_LENGTH + METADATA_SIZE) * 128) // ~ 536 M
 
 // Helper function to set some of the tracepoint arguments to Metadata.
 void set_meta_data1(struct Metadata *meta, u64 id, bool inbound, u64 msg_size) {
+  meta->ktime = bpf_ktime_get_ns();
   meta->id = id;
   meta->msg_inbound = inbound;
   meta->msg_size = msg_size;
@@ -193,6 +198,7 @@ RINGBUFFER(net_conn_misbehaving, NET_CONN_PAGES)
 
 struct Connection
 {
+    u64     ktime;
     u64     id;
     char    addr[MAX_PEER_ADDR_LENGTH];
     char    type[MAX_PEER_CONN_TYPE_LENGTH];
@@ -219,12 +225,14 @@ struct OutboundConnection
 
 struct MisbehavingConnection
 {
+    u64     ktime;
     u64     id;
     char    message[MAX_MISBEHAVING_MESSAGE_LENGTH];
 };
 
 // Helper function to set some of the tracepoint arguments to Connection.
 void set_conn_data1(struct Connection *conn, u64 id, u64 network) {
+  conn->ktime = bpf_ktime_get_ns();
   conn->id = id;
   conn->network = network;
 }
@@ -274,6 +282,7 @@ int BPF_USDT(handle_net_conn_inbound_evicted, u64 id, void *addr, void *type, u6
 SEC("usdt")
 int BPF_USDT(handle_net_conn_misbehaving, u64 id, void *message) {
     struct MisbehavingConnection misbehaving = {};
+    misbehaving.ktime = bpf_ktime_get_ns();
     misbehaving.id = id;
     bpf_probe_read_user_str(&misbehaving.message, sizeof(misbehaving.message), message);
     return bpf_ringbuf_output(&net_conn_misbehaving, &misbehaving, sizeof(misbehaving), 0);
@@ -287,6 +296,7 @@ RINGBUFFER(addrman_insert_new, ADDRMAN_PAGES)
 RINGBUFFER(addrman_insert_tried, ADDRMAN_PAGES)
 
 struct AddrmanNew {
+    u64     ktime;
     bool    inserted;
     s32     bucket;
     s32     bucket_pos;
@@ -297,6 +307,7 @@ struct AddrmanNew {
 };
 
 struct AddrmanTried {
+    u64     ktime;
     s32     bucket;
     s32     bucket_pos;
     char    addr[MAX_PEER_ADDR_LENGTH];
@@ -308,6 +319,7 @@ struct AddrmanTried {
 SEC("usdt")
 int BPF_USDT(handle_addrman_new, bool inserted, s32 bucket, s32 bucket_pos, void *addr, u32 addr_AS, void *source, u32 source_AS) {
     struct AddrmanNew new = {};
+    new.ktime = bpf_ktime_get_ns();
     new.inserted = inserted;
     new.bucket = bucket;
     new.bucket_pos = bucket_pos;
@@ -321,6 +333,7 @@ int BPF_USDT(handle_addrman_new, bool inserted, s32 bucket, s32 bucket_pos, void
 SEC("usdt")
 int BPF_USDT(handle_addrman_tried, s32 bucket, s32 bucket_pos, void *addr, u32 addr_AS, void *source, u32 source_AS) {
     struct AddrmanTried tried = {};
+    tried.ktime = bpf_ktime_get_ns();
     tried.bucket = bucket;
     tried.bucket_pos = bucket_pos;
     bpf_probe_read_user_str(&tried.addr, sizeof(tried.addr), addr);
@@ -344,12 +357,14 @@ RINGBUFFER(mempool_rejected, MEMPOOL_PAGES)
 #define REJECTION_REASON_LENGTH 113
 
 struct MempoolAdded {
+    u64     ktime;
     u8      txid[TXID_LENGHT];
     s32     vsize;
     s64     fee;
 };
 
 struct MempoolRemoved {
+    u64     ktime;
     u8      txid[TXID_LENGHT];
     char    reason[REMOVAL_REASON_LENGTH];
     s32     vsize;
@@ -358,6 +373,7 @@ struct MempoolRemoved {
 };
 
 struct MempoolReplaced {
+    u64     ktime;
     u8      replaced_txid[TXID_LENGHT];
     s32     replaced_vsize;
     s64     replaced_fee;
@@ -369,6 +385,7 @@ struct MempoolReplaced {
 };
 
 struct MempoolRejected {
+    u64     ktime;
     u8      txid[TXID_LENGHT];
     char    reason[REJECTION_REASON_LENGTH];
 };
@@ -376,6 +393,7 @@ struct MempoolRejected {
 SEC("usdt")
 int BPF_USDT(handle_mempool_added, void *txid, s32 vsize, s64 fee) {
     struct MempoolAdded added = {};
+    added.ktime = bpf_ktime_get_ns();
     bpf_probe_read_user(&added.txid, sizeof(added.txid), txid);
     added.vsize = vsize;
     added.fee = fee;
@@ -385,6 +403,7 @@ int BPF_USDT(handle_mempool_added, void *txid, s32 vsize, s64 fee) {
 SEC("usdt")
 int BPF_USDT(handle_mempool_removed, void *txid, void *reason, s32 vsize, s64 fee, u64 entry_time) {
     struct MempoolRemoved removed = {};
+    removed.ktime = bpf_ktime_get_ns();
     bpf_probe_read_user(&removed.txid, sizeof(removed.txid), txid);
     bpf_probe_read_user_str(&removed.reason, sizeof(removed.reason), reason);
     removed.vsize = vsize;
@@ -399,6 +418,7 @@ int BPF_USDT(handle_mempool_replaced,
     void *replacement_id, s32 replacement_vsize, s64 replacement_fee, bool replaced_by_transaction
 ) {
     struct MempoolReplaced replaced = {};
+    replaced.ktime = bpf_ktime_get_ns();
     bpf_probe_read_user(&replaced.replaced_txid, sizeof(replaced.replaced_txid), replaced_txid);
     replaced.replaced_vsize = replaced_vsize;
     replaced.replaced_fee = replaced_fee;
@@ -413,6 +433,7 @@ int BPF_USDT(handle_mempool_replaced,
 SEC("usdt")
 int BPF_USDT(handle_mempool_rejected, void *txid, void *reason) {
     struct MempoolRejected rejected = {};
+    rejected.ktime = bpf_ktime_get_ns();
     bpf_probe_read_user(&rejected.txid, sizeof(rejected.txid), txid);
     bpf_probe_read_user_str(&rejected.reason, sizeof(rejected.reason), reason);
     return bpf_ringbuf_output(&mempool_rejected, &rejected, sizeof(rejected), 0);
@@ -427,6 +448,7 @@ RINGBUFFER(validation_block_connected, VALIDATION_BLOCK_CONNECTED_PAGES)
 #define HASH_LENGHT 32
 
 struct BlockConnected {
+  u64    ktime;
   u8     hash[HASH_LENGHT];
   s32    height;
   u64    transactions;
@@ -438,6 +460,7 @@ struct BlockConnected {
 SEC("usdt")
 int BPF_USDT(handle_validation_block_connected, void *hash, s32 height, u64 transactions, s32 inputs, u64 sigops, u64 connection_time) {
     struct BlockConnected connected = {};
+    connected.ktime = bpf_ktime_get_ns();
     bpf_probe_read_user(&connected.hash, sizeof(connected.hash), hash);
     connected.height = height;
     connected.transactions = transactions;
@@ -1,4 +1,5 @@
-use shared::async_nats::{error::Error as NatsError, ConnectErrorKind};
+use shared::async_nats::{client::SubscribeError, error::Error as NatsError, ConnectErrorKind};
+use shared::labels::LabelError;
 use shared::log::SetLoggerError;
 use std::error;
 use std::fmt;
@@ -16,6 +17,8 @@ pub enum RuntimeError {
     SystemTime(SystemTimeError),
     SetLogger(SetLoggerError),
     NatsConnection(NatsError<ConnectErrorKind>),
+    NatsSubscribe(SubscribeError),
+    Labels(LabelError),
 }
 
 impl fmt::Display for RuntimeError {
@@ -33,6 +36,10 @@ impl fmt::Display for RuntimeError {
             RuntimeError::NatsConnection(e) => {
                 write!(f, "could not connect to NATS server {}", e)
             }
+            RuntimeError::NatsSubscribe(e) => {
+                write!(f, "could not subscribe to NATS subject {}", e)
+            }
+            RuntimeError::Labels(e) => write!(f, "labels error {}", e),
         }
     }
 }
@@ -46,8 +53,10 @@ impl error::Error for RuntimeError {
             RuntimeError::SystemTime(ref e) => Some(e),
             RuntimeError::SetLogger(ref e) => Some(e),
             RuntimeError::NatsConnection(ref e) => Some(e),
+            RuntimeError::NatsSubscribe(ref e) => Some(e),
             RuntimeError::NoSuchBPFMap(_) => None,
             RuntimeError::NoSuchBPFProg(_) => None,
+            RuntimeError::Labels(ref e) => Some(e),
         }
     }
 }
@@ -87,3 +96,15 @@ impl From<NatsError<ConnectErrorKind>> for RuntimeError {
         RuntimeError::NatsConnection(e)
     }
 }
+
+impl From<SubscribeError> for RuntimeError {
+    fn from(e: SubscribeError) -> Self {
+        RuntimeError::NatsSubscribe(e)
+    }
+}
+
+impl From<LabelError> for RuntimeError {
+    fn from(e: LabelError) -> Self {
+        RuntimeError::Labels(e)
+    }
+}
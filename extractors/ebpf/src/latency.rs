@@ -0,0 +1,188 @@
+use shared::protobuf::ebpf_extractor::PublishLatency;
+use std::time::{Duration, Instant};
+
+// Latencies below 2^SUB_BUCKET_BITS µs are counted exactly. Above, each
+// power of two is split into SUB_BUCKETS buckets, so a bucket is at most
+// 1/SUB_BUCKETS (6.25%) wider than its lower bound.
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+const BUCKETS: usize = 64 * SUB_BUCKETS as usize;
+
+/// Collects the latencies between the tracepoints firing and publishing the
+/// events into NATS and summarizes them into percentiles.
+///
+/// The latency starts at the kernel time (`bpf_ktime_get_ns()`) of the
+/// tracepoint, so it includes the time the event spent in the ring buffer.
+///
+/// The latencies are counted in fixed histogram buckets, so the memory used
+/// doesn't grow with the event rate. The percentiles are the upper bounds of
+/// their buckets and are at most 6.25% larger than the exact percentiles. The
+/// count and maximum are exact.
+pub struct LatencyTracker {
+    /// Number of latencies per bucket since the last summary.
+    buckets: Vec<u64>,
+    count: u64,
+    /// Maximum latency in microseconds since the last summary.
+    max: u64,
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        LatencyTracker {
+            buckets: vec![0; BUCKETS],
+            count: 0,
+            max: 0,
+        }
+    }
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        LatencyTracker::default()
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros() as u64;
+        self.buckets[bucket(micros)] += 1;
+        self.count += 1;
+        self.max = self.max.max(micros);
+    }
+
+    /// Returns a summary of the latencies recorded since the last call and
+    /// resets them. Returns None if nothing was recorded.
+    pub fn take_summary(&mut self) -> Option<PublishLatency> {
+        if self.count == 0 {
+            return None;
+        }
+        let summary = PublishLatency {
+            count: self.count,
+            p50: self.percentile(50),
+            p90: self.percentile(90),
+            p99: self.percentile(99),
+            max: self.max,
+        };
+        self.buckets.fill(0);
+        self.count = 0;
+        self.max = 0;
+        Some(summary)
+    }
+
+    /// Nearest-rank percentile: the upper bound of the bucket containing it,
+    /// but not more than the maximum.
+    fn percentile(&self, percent: u64) -> u64 {
+        let rank = (self.count * percent).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return upper_bound(index).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+/// The index of the bucket counting a latency of `micros`.
+fn bucket(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let shift = (63 - micros.leading_zeros()) - SUB_BUCKET_BITS;
+    let sub_bucket = (micros >> shift) - SUB_BUCKETS;
+    ((shift as u64 + 1) * SUB_BUCKETS + sub_bucket) as usize
+}
+
+/// The largest latency in microseconds counted in the bucket.
+fn upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let lower = (SUB_BUCKETS + index % SUB_BUCKETS) << shift;
+    lower + ((1 << shift) - 1)
+}
+
+/// The current kernel time in nanoseconds, on the clock of
+/// `bpf_ktime_get_ns()` in the tracepoints (CLOCK_MONOTONIC).
+pub fn ktime_now() -> u64 {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `now` is a valid timespec for clock_gettime to write to.
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+    now.tv_sec as u64 * 1_000_000_000 + now.tv_nsec as u64
+}
+
+/// Returns the instant the tracepoint with the kernel time `ktime` fired,
+/// given the kernel time `now_ktime` at the instant `now`. Events from the
+/// future (e.g. in tests) fired `now`.
+pub fn ktime_instant(ktime: u64, now: Instant, now_ktime: u64) -> Instant {
+    now.checked_sub(Duration::from_nanos(now_ktime.saturating_sub(ktime)))
+        .unwrap_or(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_summary() {
+        let mut tracker = LatencyTracker::new();
+        assert_eq!(tracker.take_summary(), None);
+
+        for micros in (1..=100).rev() {
+            tracker.record(Duration::from_micros(micros));
+        }
+        // the percentiles are the upper bounds of their buckets
+        assert_eq!(
+            tracker.take_summary(),
+            Some(PublishLatency {
+                count: 100,
+                p50: 51,
+                p90: 91,
+                p99: 99,
+                max: 100,
+            })
+        );
+        // the samples are reset after taking the summary
+        assert_eq!(tracker.take_summary(), None);
+
+        tracker.record(Duration::from_millis(3));
+        assert_eq!(
+            tracker.take_summary(),
+            Some(PublishLatency {
+                count: 1,
+                p50: 3000,
+                p90: 3000,
+                p99: 3000,
+                max: 3000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_latency_buckets() {
+        for micros in [0, 1, 15, 16, 17, 31, 32, 33, 1000, 3000, 1 << 40, u64::MAX] {
+            let index = bucket(micros);
+            assert!(index < BUCKETS, "{}", micros);
+            let upper = upper_bound(index);
+            assert!(upper >= micros, "{}", micros);
+            // at most 6.25% larger
+            assert!(upper - micros <= micros / SUB_BUCKETS, "{}", micros);
+            if index > 0 {
+                assert!(upper_bound(index - 1) < micros, "{}", micros);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ktime_instant() {
+        let now = Instant::now();
+        let traced = ktime_instant(1_000_000, now, 3_000_000);
+        assert_eq!(now.duration_since(traced), Duration::from_millis(2));
+        assert_eq!(ktime_instant(5_000_000, now, 3_000_000), now);
+        assert!(ktime_now() > 0);
+    }
+}
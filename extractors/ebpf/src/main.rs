@@ -1,10 +1,21 @@
 #![cfg_attr(feature = "strict", deny(warnings))]
 
+use batch::Batcher;
 use error::RuntimeError;
 use libbpf_rs::skel::{OpenSkel, Skel, SkelBuilder};
 use libbpf_rs::{Map, MapCore, Object, ProgramMut, RingBufferBuilder};
+use publish_queue::PublishQueue;
+use sampling::NetMsgSampler;
+use shared::args::{CommonArgs, ExtractorArgs};
 use shared::clap::Parser;
+use shared::config;
+use shared::debug_buffer::{self, DebugBuffer};
+use shared::extractor_metrics::{self, ExtractorMetricsRecorder};
+use shared::healthserver::{self, Health};
+use shared::heartbeat::{self, Heartbeats};
 use shared::log::{self, error};
+use shared::logging;
+use shared::nats::{self, TappedClient};
 use shared::nats_subjects::Subject;
 use shared::prost::Message;
 use shared::protobuf::ebpf_extractor::ctypes::{
@@ -13,19 +24,25 @@ use shared::protobuf::ebpf_extractor::ctypes::{
     P2PMessage, ValidationBlockConnected,
 };
 use shared::protobuf::ebpf_extractor::{
-    addrman, connection, ebpf, mempool, message, validation, Ebpf,
+    addrman, connection, ebpf, mempool, message, validation, DroppedEvents, Ebpf,
 };
 use shared::protobuf::event::event::PeerObserverEvent;
-use shared::protobuf::event::Event;
-use shared::simple_logger;
-use shared::{async_nats, clap, tokio};
+use shared::protobuf::event::{Event, Extractor};
+use shared::sink::MultiSink;
+use shared::{clap, tokio};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::mem::MaybeUninit;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::SystemTime;
 
+mod batch;
 mod error;
+mod latency;
+mod publish_queue;
+mod reorder;
+mod sampling;
 #[path = "tracing.gen.rs"]
 mod tracing;
 
@@ -33,9 +50,14 @@ const RINGBUFF_CALLBACK_OK: i32 = 0;
 const RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR: i32 = -5;
 const RINGBUFF_CALLBACK_UNABLE_TO_PARSE_P2P_MSG: i32 = -20;
 
+const RINGBUFF_POLL_TIMEOUT: Duration = Duration::from_secs(1);
+
 const NO_EVENTS_ERROR_DURATION: Duration = Duration::from_secs(60 * 3);
 const NO_EVENTS_WARN_DURATION: Duration = Duration::from_secs(60);
 
+const DROPPED_EVENTS_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+const PUBLISH_LATENCY_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
 struct Tracepoint<'a> {
     pub context: &'a str,
     pub name: &'a str,
@@ -141,9 +163,8 @@ const TRACEPOINTS_VALIDATION: [Tracepoint; 1] = [Tracepoint {
 ))]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Address of the NATS server where the extractor will publish messages to.
-    #[arg(short, long, default_value = "127.0.0.1:4222")]
-    nats_address: String,
+    #[command(flatten)]
+    common: CommonArgs,
 
     /// Path to the Bitcoin Core (bitcoind) binary that should be hooked into.
     #[arg(short, long)]
@@ -163,6 +184,11 @@ struct Args {
     /// Controls if the p2p message tracepoints should be hooked into.
     #[arg(long)]
     no_p2pmsg_tracepoints: bool,
+    /// Only publish every N-th P2P message event per message command and direction.
+    /// The sample rate is included in the events so consumers can extrapolate.
+    /// Other tracepoints (e.g. connection, validation) aren't sampled.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    sample_rate: u32,
     /// Controls if the connection tracepoints should be hooked into.
     #[arg(long)]
     no_connection_tracepoints: bool,
@@ -179,11 +205,6 @@ struct Args {
     #[arg(long)]
     addrman_tracepoints: bool,
 
-    /// The log level the extractor should run with. Valid log levels are "trace",
-    /// "debug", "info", "warn", "error". See https://docs.rs/log/latest/log/enum.Level.html
-    #[arg(short, long, default_value_t = log::Level::Debug)]
-    log_level: log::Level,
-
     /// If used, libbpf will print debug information about the BPF maps,
     /// programs, and tracepoints during extractor startup. This can be
     /// useful during debugging.
@@ -195,6 +216,55 @@ struct Args {
     /// about inactivity. This can be useful during debugging.
     #[arg(short = 'i', long)]
     no_idle_exit: bool,
+
+    /// Maximum number of events buffered between reading them from the ring
+    /// buffers and publishing them into NATS. If publishing can't keep up, the
+    /// oldest buffered events are dropped and reported in a DroppedEvents event.
+    #[arg(
+        long,
+        default_value_t = 100_000,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    publish_buffer_size: usize,
+
+    /// If set, buffered events older than this many milliseconds are dropped
+    /// instead of published and reported in a DroppedEvents event. This
+    /// favors freshness over completeness for real-time consumers when the
+    /// extractor falls behind. Disabled by default.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    max_event_age_ms: Option<u64>,
+
+    /// Publish up to N events per subject in a single NATS message on the
+    /// `<subject>.batch` subject (e.g. `netmsg.batch`). This reduces the
+    /// per-message overhead at high event rates. Consumers need to decode the
+    /// batches. Events are published individually by default.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    batch_size: u32,
+
+    /// Publish a batch at the latest this many milliseconds after its first
+    /// event, even if it isn't full. Only used with --batch-size.
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+    batch_timeout_ms: u64,
+
+    /// If set, events are held back for this many milliseconds and published
+    /// in the order of the kernel timestamps of their tracepoints, e.g. a
+    /// connection before the first message received on it. This adds up to
+    /// twice the window to the publish latency. Disabled by default.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    reorder_window_ms: Option<u64>,
+
+    /// Address of an optional HTTP health endpoint for liveness and readiness
+    /// probes, e.g. 127.0.0.1:8080. It answers with 200 if the extractor is
+    /// connected to NATS and extracted events in the last 180 seconds, and
+    /// with 503 otherwise.
+    #[arg(long)]
+    health_addr: Option<String>,
+}
+
+impl ExtractorArgs for Args {
+    fn common(&self) -> &CommonArgs {
+        &self.common
+    }
 }
 
 /// Find the BPF program with the given name
@@ -254,11 +324,15 @@ async fn main() {
 }
 
 async fn run() -> Result<(), RuntimeError> {
-    let args = Args::parse();
+    let args: Args = config::parse_args();
 
-    simple_logger::init_with_level(args.log_level)?;
+    logging::init(args.common())?;
 
     let pid = bitcoind_pid(&args)?;
+    let labels = args.common.labels()?;
+    if !labels.is_empty() {
+        log::info!("Attaching labels to all events: {:?}", labels);
+    }
 
     let mut skel_builder = tracing::TracingSkelBuilder::default();
     skel_builder.obj_builder.debug(args.libbpf_debug);
@@ -270,9 +344,81 @@ async fn run() -> Result<(), RuntimeError> {
     let skel: tracing::TracingSkel = open_skel.load()?;
     let obj = skel.object();
 
-    log::debug!("Connecting to NATS server at {}..", args.nats_address);
-    let nc = async_nats::connect(&args.nats_address).await?;
-    log::info!("Connected to NATS server at {}", &args.nats_address);
+    let health = Health::new(NO_EVENTS_ERROR_DURATION);
+    if let Some(address) = &args.health_addr {
+        healthserver::start(address, health.clone())?;
+    }
+
+    log::debug!(
+        "Connecting to NATS server at {}..",
+        args.common.nats_address
+    );
+    let nc = nats::connect(&args.common.nats_address).await?;
+    log::info!("Connected to NATS server at {}", &args.common.nats_address);
+    health.set_nats_client(nc.clone());
+    let nc = TappedClient::new(
+        nc,
+        DebugBuffer::new(args.common.debug_buffer_size),
+        MultiSink::open(&args.common.outputs)?,
+    )
+    .with_max_payload(args.common.max_nats_payload)
+    .with_subject_prefix(args.common.nats_subject_prefix.clone());
+    debug_buffer::serve(&nc, &Subject::EbpfExtractor.to_string()).await?;
+
+    let queue = Arc::new(
+        PublishQueue::new(
+            args.publish_buffer_size,
+            args.max_event_age_ms.map(Duration::from_millis),
+            labels,
+        )
+        .with_reorder_window(args.reorder_window_ms.map(Duration::from_millis)),
+    );
+    if let Some(window) = queue.reorder_window() {
+        log::info!(
+            "Publishing events ordered by their kernel timestamps within a {:?} window",
+            window
+        );
+    }
+    let batcher = if args.batch_size > 1 {
+        log::info!(
+            "Publishing events in batches of up to {} events or {} ms",
+            args.batch_size,
+            args.batch_timeout_ms
+        );
+        // Batches are completed before they exceed the maximum payload the
+        // NATS server accepts.
+        Some(
+            Batcher::new(
+                args.batch_size as usize,
+                Duration::from_millis(args.batch_timeout_ms),
+            )
+            .with_max_bytes(nc.max_payload()),
+        )
+    } else {
+        None
+    };
+    let publisher_queue = queue.clone();
+    let publisher_nc = nc.clone();
+    tokio::spawn(async move { publisher_queue.publish(publisher_nc, batcher).await });
+    tokio::spawn(report_dropped_events(queue.clone(), nc.clone()));
+    tokio::spawn(report_publish_latency(queue.clone(), nc.clone()));
+    let extractor_metrics = ExtractorMetricsRecorder::new(Extractor::EbpfExtractor);
+    if let Some(window) = args.common.extractor_metrics_window() {
+        tokio::spawn(report_extractor_metrics(
+            extractor_metrics.clone(),
+            queue.clone(),
+            nc.clone(),
+            window,
+        ));
+    }
+    if let Some(interval) = args.common.heartbeat_interval() {
+        tokio::spawn(report_heartbeats(
+            Heartbeats::new(Extractor::EbpfExtractor, interval),
+            queue.clone(),
+            nc.clone(),
+            interval,
+        ));
+    }
 
     // Update the ebpf-extractor docs in the README.md when editing the active_tracepoints.
     let mut active_tracepoints = vec![];
@@ -283,14 +429,18 @@ async fn run() -> Result<(), RuntimeError> {
     let map_net_msg_medium = find_map(obj, "net_msg_medium")?;
     let map_net_msg_large = find_map(obj, "net_msg_large")?;
     let map_net_msg_huge = find_map(obj, "net_msg_huge")?;
+    let sampler = NetMsgSampler::new(args.sample_rate);
     if !args.no_p2pmsg_tracepoints {
         active_tracepoints.extend(&TRACEPOINTS_NET_MESSAGE);
+        if let Some(rate) = sampler.sample_rate() {
+            log::info!("Sampling P2P message events: publishing 1 in {} messages per command and direction", rate);
+        }
         #[rustfmt::skip]
         ringbuff_builder
-            .add(&map_net_msg_small,    |data| { handle_net_message(data, &nc) })?
-            .add(&map_net_msg_medium,   |data| { handle_net_message(data, &nc) })?
-            .add(&map_net_msg_large,    |data| { handle_net_message(data, &nc) })?
-            .add(&map_net_msg_huge,     |data| { handle_net_message(data, &nc) })?;
+            .add(&map_net_msg_small,    |data| { handle_net_message(data, &queue, &sampler) })?
+            .add(&map_net_msg_medium,   |data| { handle_net_message(data, &queue, &sampler) })?
+            .add(&map_net_msg_large,    |data| { handle_net_message(data, &queue, &sampler) })?
+            .add(&map_net_msg_huge,     |data| { handle_net_message(data, &queue, &sampler) })?;
     }
 
     // P2P connection tracepoints
@@ -303,11 +453,11 @@ async fn run() -> Result<(), RuntimeError> {
         active_tracepoints.extend(&TRACEPOINTS_NET_CONN);
         #[rustfmt::skip]
         ringbuff_builder
-            .add(&map_net_conn_inbound,         |data| { handle_net_conn_inbound(data, &nc) })?
-            .add(&map_net_conn_outbound,        |data| { handle_net_conn_outbound(data, &nc) })?
-            .add(&map_net_conn_closed,          |data| { handle_net_conn_closed(data, &nc) })?
-            .add(&map_net_conn_inbound_evicted, |data| { handle_net_conn_inbound_evicted(data, &nc) })?
-            .add(&map_net_conn_misbehaving,     |data| { handle_net_conn_misbehaving(data, &nc) })?;
+            .add(&map_net_conn_inbound,         |data| { handle_net_conn_inbound(data, &queue) })?
+            .add(&map_net_conn_outbound,        |data| { handle_net_conn_outbound(data, &queue) })?
+            .add(&map_net_conn_closed,          |data| { handle_net_conn_closed(data, &queue) })?
+            .add(&map_net_conn_inbound_evicted, |data| { handle_net_conn_inbound_evicted(data, &queue) })?
+            .add(&map_net_conn_misbehaving,     |data| { handle_net_conn_misbehaving(data, &queue) })?;
     }
 
     // validation tracepoints
@@ -315,7 +465,7 @@ async fn run() -> Result<(), RuntimeError> {
     if !args.no_validation_tracepoints {
         active_tracepoints.extend(&TRACEPOINTS_VALIDATION);
         ringbuff_builder.add(&map_validation_block_connected, |data| {
-            handle_validation_block_connected(data, &nc)
+            handle_validation_block_connected(data, &queue)
         })?;
     }
 
@@ -328,10 +478,10 @@ async fn run() -> Result<(), RuntimeError> {
         active_tracepoints.extend(&TRACEPOINTS_MEMPOOL);
         #[rustfmt::skip]
         ringbuff_builder
-            .add(&map_mempool_added,    |data| { handle_mempool_added(data, &nc) })?
-            .add(&map_mempool_removed,  |data| { handle_mempool_removed(data, &nc) })?
-            .add(&map_mempool_rejected, |data| { handle_mempool_rejected(data, &nc) })?
-            .add(&map_mempool_replaced, |data| { handle_mempool_replaced(data, &nc) })?;
+            .add(&map_mempool_added,    |data| { handle_mempool_added(data, &queue) })?
+            .add(&map_mempool_removed,  |data| { handle_mempool_removed(data, &queue) })?
+            .add(&map_mempool_rejected, |data| { handle_mempool_rejected(data, &queue) })?
+            .add(&map_mempool_replaced, |data| { handle_mempool_replaced(data, &queue) })?;
     }
 
     // addrman tracepoints
@@ -341,8 +491,8 @@ async fn run() -> Result<(), RuntimeError> {
         active_tracepoints.extend(&TRACEPOINTS_ADDRMAN);
         #[rustfmt::skip]
         ringbuff_builder
-            .add(&map_addrman_insert_new, |data| { handle_addrman_new(data, &nc) })?
-            .add(&map_addrman_insert_tried, |data| { handle_addrman_tried(data, &nc) })?;
+            .add(&map_addrman_insert_new, |data| { handle_addrman_new(data, &queue) })?
+            .add(&map_addrman_insert_tried, |data| { handle_addrman_tried(data, &queue) })?;
     }
 
     if active_tracepoints.is_empty() {
@@ -375,10 +525,23 @@ async fn run() -> Result<(), RuntimeError> {
         "Startup successful. Starting to extract events from '{}'..",
         args.bitcoind_path
     );
+    // With a reorder window, poll at least once per window to release the
+    // held back events in time.
+    let poll_timeout = queue
+        .reorder_window()
+        .map_or(RINGBUFF_POLL_TIMEOUT, |window| {
+            window.min(RINGBUFF_POLL_TIMEOUT)
+        });
     let mut last_event_timestamp = SystemTime::now();
     let mut has_warned_about_no_events = false;
     loop {
-        match ring_buffers.poll_raw(Duration::from_secs(1)) {
+        let polled = ring_buffers.poll_raw(poll_timeout);
+        queue.release_reordered();
+        let late = queue.take_late();
+        if late > 0 {
+            extractor_metrics.inc_by("events_late", late);
+        }
+        match polled {
             RINGBUFF_CALLBACK_OK => (),
             RINGBUFF_CALLBACK_UNABLE_TO_PARSE_P2P_MSG => log::warn!("Could not parse P2P message."),
             RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR => log::warn!("SystemTimeError"),
@@ -389,6 +552,8 @@ async fn run() -> Result<(), RuntimeError> {
                 } else {
                     last_event_timestamp = SystemTime::now();
                     has_warned_about_no_events = false;
+                    health.tick();
+                    extractor_metrics.inc_by("events_extracted", _other as u64);
                     log::trace!(
                         "Extracted {} event{} from ring buffers and tried to publish {}",
                         _other,
@@ -423,8 +588,127 @@ async fn run() -> Result<(), RuntimeError> {
     }
 }
 
-fn handle_net_conn_closed(data: &[u8], nc: &async_nats::Client) -> i32 {
+/// Periodically checks if events were dropped because the NATS publisher
+/// couldn't keep up or they were older than the maximum event age, and
+/// reports them in a DroppedEvents event.
+async fn report_dropped_events(queue: Arc<PublishQueue>, nc: TappedClient) {
+    let mut interval = tokio::time::interval(DROPPED_EVENTS_REPORT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let dropped = queue.take_dropped();
+        let stale = queue.take_stale();
+        if dropped == 0 && stale == 0 {
+            continue;
+        }
+        if dropped > 0 {
+            log::warn!(
+                "Dropped {} events in the last {:?} as publishing into NATS couldn't keep up (buffer size: {}).",
+                dropped,
+                DROPPED_EVENTS_REPORT_INTERVAL,
+                queue.capacity()
+            );
+        }
+        if stale > 0 {
+            log::warn!(
+                "Dropped {} events in the last {:?} as they were older than {:?}.",
+                stale,
+                DROPPED_EVENTS_REPORT_INTERVAL,
+                queue.max_event_age().unwrap_or_default()
+            );
+        }
+        // Publish directly and not via the (full) queue.
+        publish_directly(
+            &queue,
+            &nc,
+            ebpf::EbpfEvent::DroppedEvents(DroppedEvents {
+                dropped,
+                buffer_size: queue.capacity() as u64,
+                stale: queue.max_event_age().map(|_| stale),
+            }),
+        )
+        .await;
+    }
+}
+
+/// Periodically reports the latency between the tracepoints firing and
+/// publishing the events into NATS in a PublishLatency event.
+async fn report_publish_latency(queue: Arc<PublishQueue>, nc: TappedClient) {
+    let mut interval = tokio::time::interval(PUBLISH_LATENCY_REPORT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(latency) = queue.take_latency() else {
+            continue;
+        };
+        log::debug!(
+            "Publish latency in the last {:?}: {}",
+            PUBLISH_LATENCY_REPORT_INTERVAL,
+            latency
+        );
+        // Publish directly so the summary isn't delayed by the queued events
+        // it's about.
+        publish_directly(&queue, &nc, ebpf::EbpfEvent::PublishLatency(latency)).await;
+    }
+}
+
+/// Periodically publishes the counters and gauges recorded about the
+/// ebpf-extractor's own operation (`--extractor-metrics-interval`).
+async fn report_extractor_metrics(
+    recorder: ExtractorMetricsRecorder,
+    queue: Arc<PublishQueue>,
+    nc: TappedClient,
+    window: Duration,
+) {
+    let mut interval = extractor_metrics::interval(Some(window));
+    loop {
+        interval.tick().await;
+        // Published directly and not via the queue, like the other reports.
+        recorder.publish(&nc, queue.labels(), window).await;
+    }
+}
+
+/// Periodically publishes a heartbeat (`--heartbeat-secs`), also when no
+/// tracepoints fire.
+async fn report_heartbeats(
+    mut heartbeats: Heartbeats,
+    queue: Arc<PublishQueue>,
+    nc: TappedClient,
+    interval: Duration,
+) {
+    let mut interval = heartbeat::interval(Some(interval));
+    loop {
+        interval.tick().await;
+        heartbeats.publish(&nc, queue.labels()).await;
+    }
+}
+
+/// Publishes an event about the ebpf-extractor itself into NATS without
+/// queueing it.
+async fn publish_directly(queue: &PublishQueue, nc: &TappedClient, ebpf_event: ebpf::EbpfEvent) {
+    let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
+        ebpf_event: Some(ebpf_event),
+    }))
+    .map(|event| event.with_labels(queue.labels()))
+    {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Could not create new Event due to SystemTimeError: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = nc
+        .publish(
+            Subject::EbpfExtractor.to_string(),
+            proto.encode_to_vec().into(),
+        )
+        .await
+    {
+        error!("could not publish ebpf-extractor event: {}", e);
+    }
+}
+
+fn handle_net_conn_closed(data: &[u8], queue: &PublishQueue) -> i32 {
     let closed = ClosedConnection::from_bytes(data);
+    let ktime = closed.connection.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
             event: Some(connection::connection_event::Event::Closed(closed.into())),
@@ -436,23 +720,13 @@ fn handle_net_conn_closed(data: &[u8], nc: &async_nats::Client) -> i32 {
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!(
-                "could not publish message in 'handle_net_conn_closed': {}",
-                e
-            );
-        }
-    });
+    queue.push(Subject::NetConn, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_conn_outbound(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_conn_outbound(data: &[u8], queue: &PublishQueue) -> i32 {
     let outbound = OutboundConnection::from_bytes(data);
+    let ktime = outbound.connection.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
             event: Some(connection::connection_event::Event::Outbound(
@@ -466,23 +740,13 @@ fn handle_net_conn_outbound(data: &[u8], nc: &async_nats::Client) -> i32 {
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!(
-                "could not publish message in 'handle_net_conn_outbound': {}",
-                e
-            );
-        }
-    });
+    queue.push(Subject::NetConn, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_conn_inbound(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_conn_inbound(data: &[u8], queue: &PublishQueue) -> i32 {
     let inbound = InboundConnection::from_bytes(data);
+    let ktime = inbound.connection.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
             event: Some(connection::connection_event::Event::Inbound(inbound.into())),
@@ -495,23 +759,13 @@ fn handle_net_conn_inbound(data: &[u8], nc: &async_nats::Client) -> i32 {
         }
     };
 
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!(
-                "could not publish message in 'handle_net_conn_inbound': {}",
-                e
-            );
-        }
-    });
+    queue.push(Subject::NetConn, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_conn_inbound_evicted(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_conn_inbound_evicted(data: &[u8], queue: &PublishQueue) -> i32 {
     let evicted = ClosedConnection::from_bytes(data);
+    let ktime = evicted.connection.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
             event: Some(connection::connection_event::Event::InboundEvicted(
@@ -526,23 +780,13 @@ fn handle_net_conn_inbound_evicted(data: &[u8], nc: &async_nats::Client) -> i32
         }
     };
 
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!(
-                "could not publish message in 'handle_net_conn_inbound_evicted': {}",
-                e
-            );
-        }
-    });
+    queue.push(Subject::NetConn, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_conn_misbehaving(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_conn_misbehaving(data: &[u8], queue: &PublishQueue) -> i32 {
     let misbehaving = MisbehavingConnection::from_bytes(data);
+    let ktime = misbehaving.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
             event: Some(connection::connection_event::Event::Misbehaving(
@@ -557,23 +801,15 @@ fn handle_net_conn_misbehaving(data: &[u8], nc: &async_nats::Client) -> i32 {
         }
     };
 
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!(
-                "could not publish message in 'handle_net_conn_misbehaving': {}",
-                e
-            );
-        }
-    });
+    queue.push(Subject::NetConn, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_message(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_message(data: &[u8], queue: &PublishQueue, sampler: &NetMsgSampler) -> i32 {
     let message = P2PMessage::from_bytes(data);
+    if !sampler.sample(&message.meta) {
+        return RINGBUFF_CALLBACK_OK;
+    }
     let protobuf_message = match message.decode_to_protobuf_network_message() {
         Ok(msg) => msg,
         Err(e) => {
@@ -583,7 +819,10 @@ fn handle_net_message(data: &[u8], nc: &async_nats::Client) -> i32 {
     };
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Message(message::MessageEvent {
-            meta: message.meta.create_protobuf_metadata(),
+            meta: message::Metadata {
+                sample_rate: sampler.sample_rate(),
+                ..message.meta.create_protobuf_metadata()
+            },
             msg: Some(protobuf_message),
         })),
     })) {
@@ -593,20 +832,13 @@ fn handle_net_message(data: &[u8], nc: &async_nats::Client) -> i32 {
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::NetMsg.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!("could not publish message in 'handle_net_message': {}", e);
-        }
-    });
+    queue.push(Subject::NetMsg, proto, message.meta.ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_addrman_new(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_addrman_new(data: &[u8], queue: &PublishQueue) -> i32 {
     let new = AddrmanInsertNew::from_bytes(data);
+    let ktime = new.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Addrman(addrman::AddrmanEvent {
             event: Some(addrman::addrman_event::Event::New(new.into())),
@@ -618,20 +850,13 @@ fn handle_addrman_new(data: &[u8], nc: &async_nats::Client) -> i32 {
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::Addrman.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!("could not publish message in 'handle_addrman_new': {}", e);
-        }
-    });
+    queue.push(Subject::Addrman, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_addrman_tried(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_addrman_tried(data: &[u8], queue: &PublishQueue) -> i32 {
     let tried = AddrmanInsertTried::from_bytes(data);
+    let ktime = tried.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Addrman(addrman::AddrmanEvent {
             event: Some(addrman::addrman_event::Event::Tried(tried.into())),
@@ -643,20 +868,13 @@ fn handle_addrman_tried(data: &[u8], nc: &async_nats::Client) -> i32 {
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::Addrman.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!("could not publish message in 'handle_addrman_tried': {}", e);
-        }
-    });
+    queue.push(Subject::Addrman, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_mempool_added(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_mempool_added(data: &[u8], queue: &PublishQueue) -> i32 {
     let added = MempoolAdded::from_bytes(data);
+    let ktime = added.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Mempool(mempool::MempoolEvent {
             event: Some(mempool::mempool_event::Event::Added(added.into())),
@@ -668,20 +886,13 @@ fn handle_mempool_added(data: &[u8], nc: &async_nats::Client) -> i32 {
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::Mempool.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!("could not publish message in 'handle_mempool_added': {}", e);
-        }
-    });
+    queue.push(Subject::Mempool, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_mempool_removed(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_mempool_removed(data: &[u8], queue: &PublishQueue) -> i32 {
     let removed = MempoolRemoved::from_bytes(data);
+    let ktime = removed.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Mempool(mempool::MempoolEvent {
             event: Some(mempool::mempool_event::Event::Removed(removed.into())),
@@ -693,23 +904,13 @@ fn handle_mempool_removed(data: &[u8], nc: &async_nats::Client) -> i32 {
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::Mempool.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!(
-                "could not publish message in 'handle_mempool_removed': {}",
-                e
-            );
-        }
-    });
+    queue.push(Subject::Mempool, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_mempool_replaced(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_mempool_replaced(data: &[u8], queue: &PublishQueue) -> i32 {
     let replaced = MempoolReplaced::from_bytes(data);
+    let ktime = replaced.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Mempool(mempool::MempoolEvent {
             event: Some(mempool::mempool_event::Event::Replaced(replaced.into())),
@@ -721,23 +922,13 @@ fn handle_mempool_replaced(data: &[u8], nc: &async_nats::Client) -> i32 {
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::Mempool.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!(
-                "could not publish message in 'handle_mempool_replaced': {}",
-                e
-            );
-        }
-    });
+    queue.push(Subject::Mempool, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_mempool_rejected(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_mempool_rejected(data: &[u8], queue: &PublishQueue) -> i32 {
     let rejected = MempoolRejected::from_bytes(data);
+    let ktime = rejected.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Mempool(mempool::MempoolEvent {
             event: Some(mempool::mempool_event::Event::Rejected(rejected.into())),
@@ -749,23 +940,13 @@ fn handle_mempool_rejected(data: &[u8], nc: &async_nats::Client) -> i32 {
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(Subject::Mempool.to_string(), proto.encode_to_vec().into())
-            .await
-        {
-            error!(
-                "could not publish message in 'handle_mempool_rejected': {}",
-                e
-            );
-        }
-    });
+    queue.push(Subject::Mempool, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_validation_block_connected(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_validation_block_connected(data: &[u8], queue: &PublishQueue) -> i32 {
     let connected = ValidationBlockConnected::from_bytes(data);
+    let ktime = connected.ktime;
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Validation(validation::ValidationEvent {
             event: Some(validation::validation_event::Event::BlockConnected(
@@ -779,20 +960,6 @@ fn handle_validation_block_connected(data: &[u8], nc: &async_nats::Client) -> i3
             return RINGBUFF_CALLBACK_SYSTEM_TIME_ERROR;
         }
     };
-    let nc = nc.clone();
-    tokio::spawn(async move {
-        if let Err(e) = nc
-            .publish(
-                Subject::Validation.to_string(),
-                proto.encode_to_vec().into(),
-            )
-            .await
-        {
-            error!(
-                "could not publish message in 'handle_validation_block_connected': {}",
-                e
-            );
-        }
-    });
+    queue.push(Subject::Validation, proto, ktime);
     RINGBUFF_CALLBACK_OK
 }
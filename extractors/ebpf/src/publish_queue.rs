@@ -0,0 +1,353 @@
+use crate::batch::{Batch, Batcher, Ready};
+use crate::latency::{self, LatencyTracker};
+use crate::reorder::Reorderer;
+use shared::labels::Labels;
+use shared::log;
+use shared::nats::TappedClient;
+use shared::nats_subjects::Subject;
+use shared::prost::Message;
+use shared::protobuf::ebpf_extractor::PublishLatency;
+use shared::protobuf::event::Event;
+use shared::tokio::sync::Notify;
+use shared::tokio::time;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A bounded buffer between the ring buffer callbacks and the NATS publisher.
+///
+/// The ring buffer callbacks must not block, otherwise the kernel ring buffers
+/// overflow and events are lost without us noticing. When the NATS publisher
+/// can't keep up and the queue is full, the oldest queued event is dropped and
+/// counted instead.
+///
+/// The labels are attached to the events when publishing them. The time
+/// between the tracepoint firing (its kernel time) and publishing an event is
+/// recorded as its latency.
+///
+/// Optionally, events older than a maximum age are dropped and counted when
+/// publishing them. This favors freshness over completeness, e.g. for
+/// real-time dashboards when the extractor fell behind.
+///
+/// Optionally, events are published in batches (see [Batcher]) to reduce the
+/// per-message overhead at high event rates.
+///
+/// Optionally, events are held back for a short window to queue them in the
+/// order of their kernel timestamps (see [Reorderer]).
+pub struct PublishQueue {
+    events: Mutex<VecDeque<(String, Event, Instant)>>,
+    notify: Notify,
+    capacity: usize,
+    dropped: AtomicU64,
+    max_event_age: Option<Duration>,
+    stale: AtomicU64,
+    latency: Mutex<LatencyTracker>,
+    labels: Labels,
+    reorderer: Option<Mutex<Reorderer>>,
+    late: AtomicU64,
+}
+
+impl PublishQueue {
+    pub fn new(capacity: usize, max_event_age: Option<Duration>, labels: Labels) -> Self {
+        PublishQueue {
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+            notify: Notify::new(),
+            capacity,
+            dropped: AtomicU64::new(0),
+            max_event_age,
+            stale: AtomicU64::new(0),
+            latency: Mutex::new(LatencyTracker::new()),
+            labels,
+            reorderer: None,
+            late: AtomicU64::new(0),
+        }
+    }
+
+    /// Holds the pushed events back for the window to queue them in the
+    /// order of their kernel timestamps.
+    pub fn with_reorder_window(mut self, window: Option<Duration>) -> Self {
+        self.reorderer = window.map(|window| Mutex::new(Reorderer::new(window)));
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn labels(&self) -> &Labels {
+        &self.labels
+    }
+
+    pub fn max_event_age(&self) -> Option<Duration> {
+        self.max_event_age
+    }
+
+    pub fn reorder_window(&self) -> Option<Duration> {
+        self.reorderer
+            .as_ref()
+            .map(|reorderer| reorderer.lock().expect("reorderer lock poisoned").window())
+    }
+
+    /// Queues an event with the kernel timestamp of its tracepoint for
+    /// publishing. This never blocks. If the queue is full, the oldest event
+    /// is dropped. With a reorder window, the event is only queued once
+    /// released by [PublishQueue::release_reordered].
+    pub fn push(&self, subject: Subject, event: Event, ktime: u64) {
+        let queued = Instant::now();
+        let traced = latency::ktime_instant(ktime, queued, latency::ktime_now());
+        let Some(reorderer) = &self.reorderer else {
+            self.enqueue((subject.to_string(), event, traced));
+            return;
+        };
+        let late = reorderer.lock().expect("reorderer lock poisoned").push(
+            ktime,
+            subject.to_string(),
+            event,
+            traced,
+            queued,
+        );
+        if let Some(late) = late {
+            self.late.fetch_add(1, Ordering::Relaxed);
+            self.enqueue(late);
+        }
+    }
+
+    /// Queues the events held back for the reorder window, in the order of
+    /// their kernel timestamps.
+    pub fn release_reordered(&self) {
+        let Some(reorderer) = &self.reorderer else {
+            return;
+        };
+        let ready = reorderer
+            .lock()
+            .expect("reorderer lock poisoned")
+            .take_ready(Instant::now());
+        for event in ready {
+            self.enqueue(event);
+        }
+    }
+
+    fn enqueue(&self, event: (String, Event, Instant)) {
+        {
+            let mut events = self.events.lock().expect("publish queue lock poisoned");
+            if events.len() >= self.capacity {
+                events.pop_front();
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            events.push_back(event);
+        }
+        self.notify.notify_one();
+    }
+
+    fn pop(&self) -> Option<(String, Event, Instant)> {
+        self.events
+            .lock()
+            .expect("publish queue lock poisoned")
+            .pop_front()
+    }
+
+    /// Returns the number of events dropped since the last call and resets it.
+    pub fn take_dropped(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    /// Returns the number of events dropped for being older than the maximum
+    /// event age since the last call and resets it.
+    pub fn take_stale(&self) -> u64 {
+        self.stale.swap(0, Ordering::Relaxed)
+    }
+
+    /// Returns the number of events that arrived too late to be reordered
+    /// since the last call and resets it.
+    pub fn take_late(&self) -> u64 {
+        self.late.swap(0, Ordering::Relaxed)
+    }
+
+    /// Returns true if the event's timestamp is older than the maximum event
+    /// age at `now_millis` (milliseconds since UNIX epoch).
+    fn is_stale(&self, event: &Event, now_millis: u64) -> bool {
+        match self.max_event_age {
+            Some(max_age) => {
+                now_millis.saturating_sub(event.timestamp) > max_age.as_millis() as u64
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a summary of the publish latencies since the last call and
+    /// resets them. Returns None if no events were published.
+    pub fn take_latency(&self) -> Option<PublishLatency> {
+        self.latency
+            .lock()
+            .expect("latency tracker lock poisoned")
+            .take_summary()
+    }
+
+    /// Publishes the queued events into NATS, in batches if a batcher is
+    /// passed. Runs until the task is dropped.
+    pub async fn publish(&self, nc: TappedClient, mut batcher: Option<Batcher>) {
+        loop {
+            while let Some((subject, event, traced)) = self.pop() {
+                if self.is_stale(&event, now_millis()) {
+                    self.stale.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                let event = event.with_labels(&self.labels);
+                match batcher.as_mut() {
+                    Some(batcher) => {
+                        for ready in batcher.push(&subject, event, traced, Instant::now()) {
+                            match ready {
+                                Ready::Batch(batch) => self.publish_batch(&nc, batch).await,
+                                Ready::Single {
+                                    subject,
+                                    event,
+                                    traced,
+                                } => {
+                                    self.send(&nc, subject, event.encode_to_vec(), &[traced])
+                                        .await
+                                }
+                            }
+                        }
+                    }
+                    None => {
+                        self.send(&nc, subject, event.encode_to_vec(), &[traced])
+                            .await
+                    }
+                }
+            }
+            let Some(batcher) = batcher.as_mut() else {
+                self.notify.notified().await;
+                continue;
+            };
+            for batch in batcher.take_expired(Instant::now()) {
+                self.publish_batch(&nc, batch).await;
+            }
+            match batcher.next_deadline() {
+                Some(deadline) => {
+                    let deadline = time::Instant::from_std(deadline);
+                    let _ = time::timeout_at(deadline, self.notify.notified()).await;
+                }
+                None => self.notify.notified().await,
+            }
+        }
+    }
+
+    async fn publish_batch(&self, nc: &TappedClient, batch: Batch) {
+        self.send(
+            nc,
+            batch.subject,
+            batch.batch.encode_to_vec(),
+            &batch.traced,
+        )
+        .await
+    }
+
+    /// Publishes the payload and records the latency of the events in it.
+    async fn send(&self, nc: &TappedClient, subject: String, payload: Vec<u8>, traced: &[Instant]) {
+        match nc.publish(subject.clone(), payload.into()).await {
+            Ok(()) => {
+                let mut latency = self.latency.lock().expect("latency tracker lock poisoned");
+                for traced in traced {
+                    latency.record(traced.elapsed());
+                }
+            }
+            Err(e) => {
+                log::error!("could not publish message to '{}': {}", subject, e);
+            }
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::protobuf::event::event::PeerObserverEvent;
+    use shared::protobuf::p2p_extractor::{p2p::P2pEvent, P2p, PingDuration};
+
+    fn ping_event(duration: u64) -> Event {
+        Event::new(PeerObserverEvent::P2pExtractor(P2p {
+            p2p_event: Some(P2pEvent::PingDuration(PingDuration { duration })),
+        }))
+        .unwrap()
+    }
+
+    fn ping_duration(event: Event) -> u64 {
+        match event.peer_observer_event {
+            Some(PeerObserverEvent::P2pExtractor(P2p {
+                p2p_event: Some(P2pEvent::PingDuration(ping)),
+            })) => ping.duration,
+            _ => panic!("unexpected event"),
+        }
+    }
+
+    #[test]
+    fn test_publish_queue_drops_oldest() {
+        let queue = PublishQueue::new(2, None, Labels::new());
+        queue.push(Subject::NetMsg, ping_event(1), 1);
+        queue.push(Subject::NetMsg, ping_event(2), 2);
+        assert_eq!(queue.take_dropped(), 0);
+
+        queue.push(Subject::NetConn, ping_event(3), 3);
+        assert_eq!(queue.take_dropped(), 1);
+        // the dropped count is reset after taking it
+        assert_eq!(queue.take_dropped(), 0);
+
+        let (subject, event, _) = queue.pop().unwrap();
+        assert_eq!(subject, Subject::NetMsg.to_string());
+        assert_eq!(ping_duration(event), 2);
+        let (subject, event, _) = queue.pop().unwrap();
+        assert_eq!(subject, Subject::NetConn.to_string());
+        assert_eq!(ping_duration(event), 3);
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn test_publish_queue_reorder_window() {
+        let queue = PublishQueue::new(10, None, Labels::new())
+            .with_reorder_window(Some(Duration::from_secs(60)));
+        queue.push(Subject::NetMsg, ping_event(2), 200);
+        queue.push(Subject::NetConn, ping_event(1), 100);
+        // held back for the window
+        queue.release_reordered();
+        assert!(queue.pop().is_none());
+
+        let queue =
+            PublishQueue::new(10, None, Labels::new()).with_reorder_window(Some(Duration::ZERO));
+        queue.push(Subject::NetMsg, ping_event(2), 200);
+        queue.push(Subject::NetConn, ping_event(1), 100);
+        queue.release_reordered();
+        let (subject, event, _) = queue.pop().unwrap();
+        assert_eq!(subject, Subject::NetConn.to_string());
+        assert_eq!(ping_duration(event), 1);
+        assert_eq!(ping_duration(queue.pop().unwrap().1), 2);
+
+        // too late to be reordered, queued right away
+        queue.push(Subject::NetConn, ping_event(3), 150);
+        assert_eq!(queue.take_late(), 1);
+        assert_eq!(ping_duration(queue.pop().unwrap().1), 3);
+    }
+
+    #[test]
+    fn test_publish_queue_stale_events() {
+        let mut event = ping_event(1);
+        event.timestamp = 10_000;
+
+        let queue = PublishQueue::new(2, None, Labels::new());
+        assert!(!queue.is_stale(&event, 1_000_000));
+
+        let queue = PublishQueue::new(2, Some(Duration::from_millis(500)), Labels::new());
+        assert!(!queue.is_stale(&event, 10_000));
+        assert!(!queue.is_stale(&event, 10_500));
+        assert!(queue.is_stale(&event, 10_501));
+        // events from the future (e.g. clock adjustments) aren't stale
+        assert!(!queue.is_stale(&event, 9_000));
+    }
+}
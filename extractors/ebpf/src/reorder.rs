@@ -0,0 +1,188 @@
+use shared::protobuf::event::Event;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+/// Releases the events read from the different ring buffers in the order of
+/// their kernel timestamps (`ktime`).
+///
+/// The ring buffers are read one after another, so e.g. a P2P message can be
+/// read before the inbound connection it was received on. Each event is held
+/// back for `window` after it was queued, and the held events are released
+/// ordered by their kernel timestamp. Events arriving after an event with a
+/// later kernel timestamp was already released can't be ordered anymore and
+/// are released right away.
+///
+/// This adds up to twice the window to the publish latency of the events.
+pub struct Reorderer {
+    window: Duration,
+    held: BinaryHeap<Reverse<Held>>,
+    sequence: u64,
+    released_ktime: u64,
+}
+
+/// An event to be released, with the subject to publish it on and when its
+/// tracepoint fired.
+pub type Released = (String, Event, Instant);
+
+struct Held {
+    ktime: u64,
+    // Keeps events with the same kernel timestamp in the order they were added.
+    sequence: u64,
+    queued: Instant,
+    event: Released,
+}
+
+impl PartialEq for Held {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Held {}
+
+impl PartialOrd for Held {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Held {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.ktime, self.sequence).cmp(&(other.ktime, other.sequence))
+    }
+}
+
+impl Reorderer {
+    pub fn new(window: Duration) -> Self {
+        Reorderer {
+            window,
+            held: BinaryHeap::new(),
+            sequence: 0,
+            released_ktime: 0,
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Adds an event with the kernel timestamp of its tracepoint, which fired
+    /// at `traced`. The event is held back for the window after `queued`.
+    /// Returns the event right away if an event with a later kernel timestamp
+    /// was already released, i.e. it's too late to be ordered.
+    pub fn push(
+        &mut self,
+        ktime: u64,
+        subject: String,
+        event: Event,
+        traced: Instant,
+        queued: Instant,
+    ) -> Option<Released> {
+        if ktime < self.released_ktime {
+            return Some((subject, event, traced));
+        }
+        self.held.push(Reverse(Held {
+            ktime,
+            sequence: self.sequence,
+            queued,
+            event: (subject, event, traced),
+        }));
+        self.sequence += 1;
+        None
+    }
+
+    /// Returns the events that were held back for the window at `now`, ordered
+    /// by their kernel timestamps.
+    pub fn take_ready(&mut self, now: Instant) -> Vec<Released> {
+        let mut ready = vec![];
+        while let Some(Reverse(next)) = self.held.peek() {
+            if now.duration_since(next.queued) < self.window {
+                break;
+            }
+            let Reverse(next) = self.held.pop().expect("peeked event exists");
+            self.released_ktime = next.ktime;
+            ready.push(next.event);
+        }
+        ready
+    }
+
+    /// The number of events currently held back.
+    pub fn held(&self) -> usize {
+        self.held.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::testing::events::event;
+
+    fn timestamps(released: &[Released]) -> Vec<u64> {
+        released.iter().map(|(_, e, _)| e.timestamp).collect()
+    }
+
+    #[test]
+    fn test_reorderer_orders_by_ktime() {
+        let window = Duration::from_millis(10);
+        let mut reorderer = Reorderer::new(window);
+        let start = Instant::now();
+        // the P2P message is read before the connection it was received on
+        assert!(reorderer
+            .push(200, "netmsg".to_string(), event(2), start, start)
+            .is_none());
+        assert!(reorderer
+            .push(100, "netconn".to_string(), event(1), start, start)
+            .is_none());
+        assert!(reorderer
+            .push(
+                200,
+                "netmsg".to_string(),
+                event(3),
+                start + window / 2,
+                start + window / 2
+            )
+            .is_none());
+
+        assert!(reorderer.take_ready(start + window / 2).is_empty());
+        let ready = reorderer.take_ready(start + window);
+        assert_eq!(timestamps(&ready), vec![1, 2]);
+        assert_eq!(ready[0].0, "netconn");
+        assert_eq!(reorderer.held(), 1);
+        assert_eq!(
+            timestamps(&reorderer.take_ready(start + window * 2)),
+            vec![3]
+        );
+        assert_eq!(reorderer.held(), 0);
+    }
+
+    #[test]
+    fn test_reorderer_late_event() {
+        let window = Duration::from_millis(10);
+        let mut reorderer = Reorderer::new(window);
+        let start = Instant::now();
+        reorderer.push(200, "netmsg".to_string(), event(2), start, start);
+        assert_eq!(timestamps(&reorderer.take_ready(start + window)), vec![2]);
+
+        // arrives after an event with a later kernel timestamp was released
+        let late = reorderer.push(
+            100,
+            "netconn".to_string(),
+            event(1),
+            start + window,
+            start + window,
+        );
+        assert_eq!(late.map(|(_, e, _)| e.timestamp), Some(1));
+        assert_eq!(reorderer.held(), 0);
+    }
+
+    #[test]
+    fn test_reorderer_keeps_order_of_equal_ktimes() {
+        let mut reorderer = Reorderer::new(Duration::ZERO);
+        let start = Instant::now();
+        for timestamp in 1..=3 {
+            reorderer.push(100, "mempool".to_string(), event(timestamp), start, start);
+        }
+        assert_eq!(timestamps(&reorderer.take_ready(start)), vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,89 @@
+use shared::p2p_commands;
+use shared::protobuf::ebpf_extractor::ctypes::P2PMessageMetadata;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Samples P2P message events: only every N-th message of each command and
+/// direction is published. Sampling per command and direction makes sure
+/// rare messages (e.g. `version`) are still seen when frequent messages
+/// (e.g. `inv`) are sampled. Peers can send messages with any command, so
+/// the commands unknown to Bitcoin Core are sampled together.
+pub struct NetMsgSampler {
+    rate: u32,
+    counters: Mutex<HashMap<(&'static str, bool), u64>>,
+}
+
+impl NetMsgSampler {
+    pub fn new(rate: u32) -> Self {
+        NetMsgSampler {
+            rate: rate.max(1),
+            counters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The sample rate to include in the published events. None if all
+    /// messages are published.
+    pub fn sample_rate(&self) -> Option<u32> {
+        if self.rate > 1 {
+            Some(self.rate)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the message should be published.
+    pub fn sample(&self, meta: &P2PMessageMetadata) -> bool {
+        self.sample_command(meta.msg_type(), meta.msg_inbound)
+    }
+
+    fn sample_command(&self, command: String, inbound: bool) -> bool {
+        if self.rate == 1 {
+            return true;
+        }
+        let command = p2p_commands::known_or_other(&command);
+        let mut counters = self.counters.lock().unwrap();
+        let counter = counters.entry((command, inbound)).or_insert(0);
+        let sampled = *counter % self.rate as u64 == 0;
+        *counter += 1;
+        sampled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_net_msg_sampler() {
+        let sampler = NetMsgSampler::new(3);
+        assert_eq!(sampler.sample_rate(), Some(3));
+
+        let sampled: Vec<bool> = (0..6)
+            .map(|_| sampler.sample_command("inv".to_string(), true))
+            .collect();
+        assert_eq!(sampled, vec![true, false, false, true, false, false]);
+
+        // commands and directions are sampled independently
+        assert!(sampler.sample_command("version".to_string(), true));
+        assert!(sampler.sample_command("inv".to_string(), false));
+    }
+
+    #[test]
+    fn test_net_msg_sampler_unknown_commands() {
+        let sampler = NetMsgSampler::new(2);
+        // unknown commands share one counter
+        assert!(sampler.sample_command("foo".to_string(), true));
+        assert!(!sampler.sample_command("bar".to_string(), true));
+        assert!(sampler.sample_command("baz".to_string(), true));
+        assert_eq!(sampler.counters.lock().unwrap().len(), 1);
+        // known commands are still sampled independently
+        assert!(sampler.sample_command("inv".to_string(), true));
+    }
+
+    #[test]
+    fn test_net_msg_sampler_disabled() {
+        let sampler = NetMsgSampler::new(1);
+        assert_eq!(sampler.sample_rate(), None);
+        assert!((0..10).all(|_| sampler.sample_command("inv".to_string(), true)));
+    }
+}
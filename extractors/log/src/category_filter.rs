@@ -0,0 +1,79 @@
+use shared::protobuf::log_extractor::{Log, LogDebugCategory};
+use std::collections::HashSet;
+
+/// Only lets log events of the allowed debug categories through, e.g. to
+/// only publish `[validation]` and `[net]` events for focused monitoring.
+///
+/// Log lines without a (known) debug category are parsed with the unknown
+/// category. Some of them are interesting (e.g. the header sync progress or
+/// leaving the initial block download), so whether they are let through is
+/// configured separately.
+pub struct LogCategoryFilter {
+    categories: HashSet<LogDebugCategory>,
+    include_unknown: bool,
+}
+
+impl LogCategoryFilter {
+    pub fn new(categories: &[LogDebugCategory], include_unknown: bool) -> Self {
+        LogCategoryFilter {
+            categories: categories.iter().copied().collect(),
+            include_unknown,
+        }
+    }
+
+    /// Returns true if the log event should be published.
+    pub fn allows(&self, log: &Log) -> bool {
+        match log.category() {
+            LogDebugCategory::Unknown => self.include_unknown,
+            category => self.categories.contains(&category),
+        }
+    }
+}
+
+/// Parses a `--log-categories` value: a Bitcoin Core debug category, e.g.
+/// `validation` or `net`, as passed to `-debug=`.
+pub fn parse_log_category(s: &str) -> Result<LogDebugCategory, String> {
+    match LogDebugCategory::from_str_name(&s.to_uppercase()) {
+        Some(LogDebugCategory::Unknown) => Err(
+            "'unknown' is not a debug category: use --include-unknown-category to publish log lines without one"
+                .to_string(),
+        ),
+        Some(category) => Ok(category),
+        None => Err(format!("unknown debug category '{}'", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::log_matchers::parse_log_event;
+
+    const VALIDATION_LINE: &str = "2025-09-27T01:52:01Z [validation] BlockConnected: block hash=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b block height=437";
+    const NET_LINE: &str = "2025-10-30T12:21:40Z [net] Disconnecting and discouraging peer 12!";
+    const UNCATEGORIZED_LINE: &str =
+        "2025-11-05T14:02:33Z Leaving InitialBlockDownload (latching to false)";
+
+    #[test]
+    fn test_log_category_filter() {
+        let filter = LogCategoryFilter::new(&[LogDebugCategory::Validation], false);
+        assert!(filter.allows(&parse_log_event(VALIDATION_LINE)));
+        assert!(!filter.allows(&parse_log_event(NET_LINE)));
+        assert!(!filter.allows(&parse_log_event(UNCATEGORIZED_LINE)));
+
+        let filter = LogCategoryFilter::new(&[LogDebugCategory::Net], true);
+        assert!(!filter.allows(&parse_log_event(VALIDATION_LINE)));
+        assert!(filter.allows(&parse_log_event(NET_LINE)));
+        assert!(filter.allows(&parse_log_event(UNCATEGORIZED_LINE)));
+    }
+
+    #[test]
+    fn test_parse_log_category() {
+        assert_eq!(
+            parse_log_category("validation"),
+            Ok(LogDebugCategory::Validation)
+        );
+        assert_eq!(parse_log_category("NET"), Ok(LogDebugCategory::Net));
+        assert!(parse_log_category("unknown").is_err());
+        assert!(parse_log_category("foo").is_err());
+    }
+}
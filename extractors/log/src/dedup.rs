@@ -0,0 +1,348 @@
+use shared::prost::Message;
+use shared::protobuf::log_extractor::Log;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Collapses identical log events within a window. Bitcoin Core can log the
+/// same line over and over again, e.g. when repeatedly failing to connect to
+/// a peer.
+///
+/// The first occurrence of a log event is published right away. Identical
+/// log events (same category and content, ignoring the timestamp) seen
+/// during the following window are counted instead of published. When the
+/// window ends, a single log event with the number of suppressed occurrences
+/// is published.
+///
+/// Unlike syslog's "last message repeated N times", the identical log events
+/// don't need to be consecutive: they are collapsed even if other log events
+/// are logged in between. E.g. failing connections to a few peers in turn
+/// are collapsed into one event per peer.
+///
+/// The most recently seen log events are tracked in a small LRU cache. When
+/// it's full, the least recently seen log event is evicted.
+pub struct LogDeduplicator {
+    window: Duration,
+    capacity: usize,
+    entries: HashMap<Vec<u8>, Entry>,
+    /// The keys of the entries by the sequence number of when they were
+    /// seen, least recently seen first. A key seen again is appended again,
+    /// and its older positions are skipped when evicting. This finds the
+    /// least recently seen entry in amortized constant time.
+    recency: VecDeque<(u64, Vec<u8>)>,
+    /// The sequence number of the last processed log event.
+    sequence: u64,
+}
+
+struct Entry {
+    window_start: Instant,
+    /// The sequence number of when the log event was last seen.
+    last_seen: u64,
+    /// The last suppressed log event and how many were suppressed in the
+    /// current window.
+    suppressed: Option<(Log, u32)>,
+}
+
+impl Entry {
+    /// Returns the log event to publish for the suppressed occurrences, if any.
+    fn take_suppressed(&mut self) -> Option<Log> {
+        self.suppressed.take().map(|(log, count)| Log {
+            occurrences: Some(count),
+            ..log
+        })
+    }
+}
+
+impl LogDeduplicator {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        LogDeduplicator {
+            window,
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            recency: VecDeque::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Processes a log event and returns the log events to publish.
+    pub fn process(&mut self, log: Log, now: Instant) -> Vec<Log> {
+        let key = Self::key(&log);
+        let mut publish = Vec::new();
+        self.sequence += 1;
+
+        match self.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.last_seen = self.sequence;
+                if now.duration_since(entry.window_start) < self.window {
+                    let count = entry.suppressed.as_ref().map_or(0, |(_, count)| *count);
+                    entry.suppressed = Some((log, count + 1));
+                } else {
+                    // The window ended: publish the suppressed occurrences and
+                    // start a new window with this log event.
+                    publish.extend(entry.take_suppressed());
+                    entry.window_start = now;
+                    publish.push(log);
+                }
+            }
+            None => {
+                if self.entries.len() >= self.capacity {
+                    publish.extend(self.evict_least_recently_seen());
+                }
+                self.entries.insert(
+                    key.clone(),
+                    Entry {
+                        window_start: now,
+                        last_seen: self.sequence,
+                        suppressed: None,
+                    },
+                );
+                publish.push(log);
+            }
+        }
+        self.recency.push_back((self.sequence, key));
+        self.compact_recency();
+        publish
+    }
+
+    /// Returns the log events for the suppressed occurrences of all windows
+    /// that ended before `now`.
+    pub fn flush_expired(&mut self, now: Instant) -> Vec<Log> {
+        let window = self.window;
+        let mut publish = Vec::new();
+        self.entries.retain(|_, entry| {
+            if now.duration_since(entry.window_start) < window {
+                return true;
+            }
+            publish.extend(entry.take_suppressed());
+            false
+        });
+        publish
+    }
+
+    /// Returns the log events for all suppressed occurrences, e.g. on shutdown.
+    pub fn flush_all(&mut self) -> Vec<Log> {
+        self.recency.clear();
+        self.entries
+            .drain()
+            .filter_map(|(_, mut entry)| entry.take_suppressed())
+            .collect()
+    }
+
+    fn evict_least_recently_seen(&mut self) -> Option<Log> {
+        while let Some((sequence, key)) = self.recency.pop_front() {
+            // skip the positions of keys seen again or already flushed
+            if self
+                .entries
+                .get(&key)
+                .is_some_and(|entry| entry.last_seen == sequence)
+            {
+                return self.entries.remove(&key)?.take_suppressed();
+            }
+        }
+        None
+    }
+
+    /// Drops the skipped positions from the recency queue once it got twice
+    /// as long as needed, so it doesn't grow without evictions.
+    fn compact_recency(&mut self) {
+        if self.recency.len() > 2 * self.capacity {
+            let entries = &self.entries;
+            self.recency.retain(|(sequence, key)| {
+                entries
+                    .get(key)
+                    .is_some_and(|entry| entry.last_seen == *sequence)
+            });
+        }
+    }
+
+    /// Log events are identical if they only differ in their timestamp. Their
+    /// raw log lines (--include-raw-lines) differ in the timestamp too.
+    fn key(log: &Log) -> Vec<u8> {
+        Log {
+            log_timestamp: 0,
+            occurrences: None,
+            raw_line: None,
+            ..log.clone()
+        }
+        .encode_to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::protobuf::log_extractor::{LogDebugCategory, UnknownLogMessage, log::LogEvent};
+
+    fn log(timestamp: u64, message: &str) -> Log {
+        Log {
+            occurrences: None,
+            raw_line: None,
+            log_timestamp: timestamp,
+            category: LogDebugCategory::Net.into(),
+            log_event: Some(LogEvent::UnknownLogMessage(UnknownLogMessage {
+                raw_message: message.to_string(),
+            })),
+        }
+    }
+
+    #[test]
+    fn test_dedup_window() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut dedup = LogDeduplicator::new(Duration::from_secs(10), 16);
+
+        assert_eq!(dedup.process(log(1, "a"), at(0)), vec![log(1, "a")]);
+        // identical log events in the window are suppressed
+        assert!(dedup.process(log(2, "a"), at(1)).is_empty());
+        assert!(dedup.process(log(3, "a"), at(2)).is_empty());
+        // different log events aren't
+        assert_eq!(dedup.process(log(4, "b"), at(3)), vec![log(4, "b")]);
+
+        // nothing to flush before the window ended
+        assert!(dedup.flush_expired(at(5)).is_empty());
+        // the suppressed occurrences are published as one event with the last
+        // timestamp once the window ended
+        let flushed = dedup.flush_expired(at(10));
+        assert_eq!(
+            flushed,
+            vec![Log {
+                occurrences: Some(2),
+                ..log(3, "a")
+            }]
+        );
+        assert!(dedup.flush_expired(at(20)).is_empty());
+    }
+
+    #[test]
+    fn test_dedup_new_window() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut dedup = LogDeduplicator::new(Duration::from_secs(10), 16);
+
+        dedup.process(log(1, "a"), at(0));
+        assert!(dedup.process(log(2, "a"), at(1)).is_empty());
+        // after the window, the suppressed occurrences and the new log event
+        // are published
+        assert_eq!(
+            dedup.process(log(3, "a"), at(11)),
+            vec![
+                Log {
+                    occurrences: Some(1),
+                    ..log(2, "a")
+                },
+                log(3, "a")
+            ]
+        );
+        assert!(dedup.flush_all().is_empty());
+    }
+
+    #[test]
+    fn test_dedup_eviction() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut dedup = LogDeduplicator::new(Duration::from_secs(10), 2);
+
+        dedup.process(log(1, "a"), at(0));
+        dedup.process(log(2, "a"), at(0));
+        dedup.process(log(3, "b"), at(1));
+        // "a" was seen least recently and is evicted to make room for "c"
+        assert_eq!(
+            dedup.process(log(4, "c"), at(2)),
+            vec![
+                Log {
+                    occurrences: Some(1),
+                    ..log(2, "a")
+                },
+                log(4, "c")
+            ]
+        );
+
+        // "b" was seen again since "c", so "c" is evicted next
+        assert!(dedup.process(log(5, "b"), at(3)).is_empty());
+        assert_eq!(dedup.process(log(6, "d"), at(4)), vec![log(6, "d")]);
+        assert_eq!(
+            dedup.flush_all(),
+            vec![Log {
+                occurrences: Some(1),
+                ..log(5, "b")
+            }]
+        );
+    }
+
+    #[test]
+    fn test_dedup_recency_compaction() {
+        let start = Instant::now();
+        let mut dedup = LogDeduplicator::new(Duration::from_secs(10), 2);
+
+        // the recency queue doesn't grow without bounds without evictions
+        for i in 0..100 {
+            dedup.process(log(i, "a"), start);
+        }
+        assert!(dedup.recency.len() <= 4);
+        dedup.process(log(100, "b"), start);
+        assert_eq!(
+            dedup.process(log(101, "c"), start),
+            vec![
+                Log {
+                    occurrences: Some(99),
+                    ..log(99, "a")
+                },
+                log(101, "c")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedup_not_consecutive() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut dedup = LogDeduplicator::new(Duration::from_secs(10), 16);
+
+        // identical log events are collapsed even with others in between
+        assert_eq!(dedup.process(log(1, "a"), at(0)), vec![log(1, "a")]);
+        assert_eq!(dedup.process(log(2, "b"), at(1)), vec![log(2, "b")]);
+        assert!(dedup.process(log(3, "a"), at(2)).is_empty());
+        assert!(dedup.process(log(4, "b"), at(3)).is_empty());
+        let mut flushed = dedup.flush_all();
+        flushed.sort_by_key(|log| log.log_timestamp);
+        assert_eq!(
+            flushed,
+            vec![
+                Log {
+                    occurrences: Some(1),
+                    ..log(3, "a")
+                },
+                Log {
+                    occurrences: Some(1),
+                    ..log(4, "b")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedup_raw_lines() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut dedup = LogDeduplicator::new(Duration::from_secs(10), 16);
+        let log = |timestamp, raw_line: &str| Log {
+            raw_line: Some(raw_line.to_string()),
+            ..log(timestamp, "a")
+        };
+
+        dedup.process(log(1, "2025-11-05T14:02:01Z [net] a"), at(0));
+        // the raw log lines only differ in their timestamp
+        assert!(
+            dedup
+                .process(log(2, "2025-11-05T14:02:02Z [net] a"), at(1))
+                .is_empty()
+        );
+        // the raw log line of the last occurrence is kept
+        assert_eq!(
+            dedup.flush_expired(at(10)),
+            vec![Log {
+                occurrences: Some(1),
+                ..log(2, "2025-11-05T14:02:02Z [net] a")
+            }]
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use shared::async_nats::ConnectErrorKind;
+use shared::labels::LabelError;
 use shared::log::SetLoggerError;
+use shared::log_matchers::UnknownLogMatcherError;
 use std::error;
 use std::fmt;
 use std::io;
@@ -9,6 +11,10 @@ pub enum RuntimeError {
     SetLogger(SetLoggerError),
     Io(io::Error),
     NatsConnect(shared::async_nats::error::Error<ConnectErrorKind>),
+    NatsSubscribe(shared::async_nats::client::SubscribeError),
+    LogMatcher(UnknownLogMatcherError),
+    Labels(LabelError),
+    InvalidArgs(String),
 }
 
 impl fmt::Display for RuntimeError {
@@ -17,6 +23,10 @@ impl fmt::Display for RuntimeError {
             RuntimeError::SetLogger(e) => write!(f, "set logger error {}", e),
             RuntimeError::Io(e) => write!(f, "IO error {}", e),
             RuntimeError::NatsConnect(e) => write!(f, "NATS connection error {}", e),
+            RuntimeError::NatsSubscribe(e) => write!(f, "NATS subscribe error {}", e),
+            RuntimeError::LogMatcher(e) => write!(f, "log matcher error {}", e),
+            RuntimeError::Labels(e) => write!(f, "labels error {}", e),
+            RuntimeError::InvalidArgs(e) => write!(f, "invalid arguments: {}", e),
         }
     }
 }
@@ -27,6 +37,10 @@ impl error::Error for RuntimeError {
             RuntimeError::SetLogger(ref e) => Some(e),
             RuntimeError::Io(ref e) => Some(e),
             RuntimeError::NatsConnect(ref e) => Some(e),
+            RuntimeError::NatsSubscribe(ref e) => Some(e),
+            RuntimeError::LogMatcher(ref e) => Some(e),
+            RuntimeError::Labels(ref e) => Some(e),
+            RuntimeError::InvalidArgs(_) => None,
         }
     }
 }
@@ -48,3 +62,21 @@ impl From<shared::async_nats::error::Error<ConnectErrorKind>> for RuntimeError {
         RuntimeError::NatsConnect(e)
     }
 }
+
+impl From<shared::async_nats::client::SubscribeError> for RuntimeError {
+    fn from(e: shared::async_nats::client::SubscribeError) -> Self {
+        RuntimeError::NatsSubscribe(e)
+    }
+}
+
+impl From<UnknownLogMatcherError> for RuntimeError {
+    fn from(e: UnknownLogMatcherError) -> Self {
+        RuntimeError::LogMatcher(e)
+    }
+}
+
+impl From<LabelError> for RuntimeError {
+    fn from(e: LabelError) -> Self {
+        RuntimeError::Labels(e)
+    }
+}
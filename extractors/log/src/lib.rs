@@ -1,99 +1,344 @@
+use category_filter::{LogCategoryFilter, parse_log_category};
 use error::RuntimeError;
-use shared::async_nats::{self};
+use shared::args::{CommonArgs, ExtractorArgs};
 use shared::clap;
 use shared::clap::Parser;
+use shared::debug_buffer::{self, DebugBuffer};
+use shared::extractor_metrics::{self, ExtractorMetricsRecorder};
+use shared::healthserver::{self, Health};
+use shared::heartbeat::{self, Heartbeats};
+use shared::labels::Labels;
 use shared::log;
-use shared::log_matchers::parse_log_event;
+use shared::log_matchers::{LogMatcherRegistry, MINING_LOG_MATCHERS, known_log_matchers};
+use shared::nats::{self, TappedClient};
 use shared::nats_subjects::Subject;
 use shared::prost::Message;
-use shared::protobuf::event::Event;
 use shared::protobuf::event::event::PeerObserverEvent;
-use shared::tokio::{
-    self,
-    fs::{File, OpenOptions},
-    io::{AsyncBufReadExt, BufReader},
-    sync::watch,
-    time,
-};
+use shared::protobuf::event::{Event, Extractor};
+use shared::protobuf::log_extractor::{Log, LogDebugCategory, UnknownLogSummary, log::LogEvent};
+use shared::sink::MultiSink;
+use shared::tokio::{self, sync::watch, time};
+use source::{LogSource, LogSourceKind, ReadLine, StdinSource};
+use tail::{TailFrom, parse_tail_from};
 
+mod category_filter;
+mod dedup;
 mod error;
+pub mod source;
+pub mod tail;
+
+use dedup::LogDeduplicator;
 
 // from libc crate
 pub const O_NONBLOCK: i32 = 2048;
 
+/// Number of distinct log events tracked for deduplication.
+const DEDUP_CAPACITY: usize = 1024;
+
 /// The peer-observer log-extractor reads lines from a pipe to a Bitcoin node
 /// debug.log pipe (named pipe / FIFO) and publishes parsed lines as events
 /// into a NATS pub-sub queue.
 #[derive(Parser, Debug)]
-#[clap(group(
-    clap::ArgGroup::new("pipe")
-        .required(true)
-        .multiple(false)
-        .args(&["bitcoind_pipe"]),
-))]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// Address of the NATS server where the extractor will publish messages to.
-    #[arg(short, long, default_value = "127.0.0.1:4222")]
-    pub nats_address: String,
-
-    /// Path to the bitcoind log pipe (named pipe / FIFO).
-    #[arg(short, long)]
-    pub bitcoind_pipe: String,
-
-    /// The log level the extractor should run with. Valid log levels are "trace",
-    /// "debug", "info", "warn", "error". See https://docs.rs/log/latest/log/enum.Level.html.
-    #[arg(short, long, default_value_t = log::Level::Debug)]
-    pub log_level: log::Level,
+    #[command(flatten)]
+    pub common: CommonArgs,
+
+    /// Path to the bitcoind log pipe (named pipe / FIFO). A regular debug.log
+    /// file can be used too: it's followed and reopened when rotated or truncated.
+    /// Not needed when reading from stdin.
+    #[arg(
+        short,
+        long,
+        required_unless_present = "log_source",
+        required_if_eq_any([("log_source", "file"), ("log_source", "fifo")])
+    )]
+    pub bitcoind_pipe: Option<String>,
+
+    /// Where to read the log lines from. Defaults to "fifo" if --bitcoind-pipe
+    /// is a named pipe and to "file" otherwise.
+    #[arg(long, value_enum)]
+    pub log_source: Option<LogSourceKind>,
+
+    /// Length of the window in seconds over which the ratio of unknown
+    /// (unparseable) log lines is calculated. A summary event is published
+    /// after each window.
+    #[arg(long, default_value_t = 600, value_parser = clap::value_parser!(u64).range(1..))]
+    pub unknown_log_window_seconds: u64,
+
+    /// Warn when the ratio of unknown (unparseable) log lines in a window
+    /// exceeds this threshold (0.0 to 1.0). This usually means that the log
+    /// format changed and the log matchers need to be updated.
+    #[arg(long, default_value_t = 0.99, value_parser = parse_ratio)]
+    pub unknown_log_ratio_threshold: f64,
+
+    /// Disable a log matcher. Log lines that would have been matched by it
+    /// are published as unknown log messages. Can be passed multiple times.
+    #[arg(
+        long = "disable-log-matcher",
+        value_name = "NAME",
+        value_parser = clap::builder::PossibleValuesParser::new(
+            known_log_matchers().map(|(name, _)| *name)
+        )
+    )]
+    pub disable_log_matchers: Vec<String>,
+
+    /// Enable the log matchers for block template creation lines
+    /// (block_template, block_template_timing). These are only logged by
+    /// nodes creating block templates, e.g. for a mining pool.
+    #[arg(long, default_value_t = false)]
+    pub enable_mining_log_matchers: bool,
+
+    /// Where to start reading a regular log file on startup: "start" reads
+    /// the whole file, "end" only reads newly written lines, and "offset:N"
+    /// continues at byte offset N (e.g. read from --offset-state-file).
+    #[arg(
+        long,
+        value_name = "start|end|offset:N",
+        default_value = "start",
+        value_parser = parse_tail_from
+    )]
+    pub tail_from: TailFrom,
+
+    /// Write the byte offset of the last processed line of a regular log
+    /// file to this file on shutdown, as "offset:N". Pass its content to
+    /// --tail-from to resume after a restart.
+    #[arg(long, value_name = "PATH")]
+    pub offset_state_file: Option<String>,
+
+    /// Collapse identical log events (e.g. repeated connection failures)
+    /// within this many seconds into a single event with an occurrence
+    /// count, even if other log events are logged in between. 0 disables
+    /// the deduplication.
+    #[arg(long, default_value_t = 0)]
+    pub dedup_window_seconds: u64,
+
+    /// Only publish log events of these Bitcoin Core debug categories, e.g.
+    /// "validation,net". Events of other categories are dropped before
+    /// publishing. All log events are published if not set.
+    #[arg(
+        long,
+        value_name = "CATEGORY",
+        value_delimiter = ',',
+        value_parser = parse_log_category
+    )]
+    pub log_categories: Vec<LogDebugCategory>,
+
+    /// With --log-categories, also publish log events without a debug
+    /// category, e.g. the header sync progress or leaving the initial block
+    /// download.
+    #[arg(long, default_value_t = false, requires = "log_categories")]
+    pub include_unknown_category: bool,
+
+    /// Include the original log line in the published log events, e.g. to
+    /// verify the log matchers without access to the log file. This about
+    /// doubles the size of the events.
+    #[arg(long, default_value_t = false)]
+    pub include_raw_lines: bool,
+
+    /// Address of an optional HTTP health endpoint for liveness and readiness
+    /// probes, e.g. 127.0.0.1:8080. It answers with 200 if the extractor is
+    /// connected to NATS and read a log line within the last
+    /// --health-max-line-age-secs, and with 503 otherwise.
+    #[arg(long)]
+    pub health_addr: Option<String>,
+
+    /// With --health-addr, report the extractor unhealthy if it didn't read a
+    /// log line for this many seconds, e.g. because no writer is connected to
+    /// the named pipe. Without debug categories, Bitcoin Core can be quiet
+    /// for longer than the time between two blocks.
+    #[arg(long, value_name = "SECONDS", default_value_t = 3600, value_parser = clap::value_parser!(u64).range(1..))]
+    pub health_max_line_age_secs: u64,
 }
 
 impl Args {
     pub fn new(nats_address: String, bitcoind_pipe: String, log_level: log::Level) -> Args {
         Self {
-            nats_address,
-            bitcoind_pipe,
-            log_level,
+            common: CommonArgs::new(nats_address, log_level),
+            bitcoind_pipe: Some(bitcoind_pipe),
+            log_source: None,
+            unknown_log_window_seconds: 600,
+            unknown_log_ratio_threshold: 0.99,
+            disable_log_matchers: vec![],
+            enable_mining_log_matchers: false,
+            tail_from: TailFrom::Start,
+            offset_state_file: None,
+            dedup_window_seconds: 0,
+            log_categories: vec![],
+            include_unknown_category: false,
+            include_raw_lines: false,
+            health_addr: None,
+            health_max_line_age_secs: 3600,
         }
     }
 }
 
+impl ExtractorArgs for Args {
+    fn common(&self) -> &CommonArgs {
+        &self.common
+    }
+}
+
+/// Parses a ratio between 0.0 and 1.0 (inclusive).
+fn parse_ratio(s: &str) -> Result<f64, String> {
+    let ratio: f64 = s
+        .parse()
+        .map_err(|e| format!("invalid ratio '{}': {}", s, e))?;
+    if !(0.0..=1.0).contains(&ratio) {
+        return Err(format!("ratio '{}' is not between 0.0 and 1.0", s));
+    }
+    Ok(ratio)
+}
+
 pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(), RuntimeError> {
-    log::debug!("Connecting to NATS server at {}...", &args.nats_address);
-    let nats_client = async_nats::connect(&args.nats_address).await?;
-    log::info!("Connected to NATS server at {}", &args.nats_address);
-
-    log::info!("Opening bitcoind log pipe at {}...", &args.bitcoind_pipe);
-    let file = open_pipe(&args.bitcoind_pipe, shutdown_rx.clone()).await?;
-    log::info!("Opened bitcoind log pipe at {}", &args.bitcoind_pipe);
-    let reader = BufReader::new(file);
-    let mut lines = reader.lines();
-
-    log::info!(
-        "Started reading lines from bitcoind log pipe at {}",
-        &args.bitcoind_pipe
+    let log_matchers = log_matcher_registry(&args)?;
+    log::info!("Using log matchers: {}", log_matchers.names().join(", "));
+    let labels = args.common.labels()?;
+    if !labels.is_empty() {
+        log::info!("Attaching labels to all events: {:?}", labels);
+    }
+
+    let health = Health::new(time::Duration::from_secs(args.health_max_line_age_secs));
+    if let Some(address) = &args.health_addr {
+        healthserver::start(address, health.clone())?;
+    }
+
+    log::debug!(
+        "Connecting to NATS server at {}...",
+        &args.common.nats_address
     );
+    let nats_client = nats::connect(&args.common.nats_address).await?;
+    log::info!("Connected to NATS server at {}", &args.common.nats_address);
+    health.set_nats_client(nats_client.clone());
+    let nats_client = TappedClient::new(
+        nats_client,
+        DebugBuffer::new(args.common.debug_buffer_size),
+        MultiSink::open(&args.common.outputs)?,
+    )
+    .with_max_payload(args.common.max_nats_payload)
+    .with_subject_prefix(args.common.nats_subject_prefix.clone());
+    debug_buffer::serve(&nats_client, &Subject::LogExtractor.to_string()).await?;
+
+    let mut source = open_log_source(&args, shutdown_rx.clone()).await?;
+
+    let mut unknown_log_stats = UnknownLogStats::default();
+    let window = time::Duration::from_secs(args.unknown_log_window_seconds);
+    let mut summary_interval = time::interval_at(time::Instant::now() + window, window);
+
+    let mut dedup = (args.dedup_window_seconds > 0).then(|| {
+        log::info!(
+            "Deduplicating identical log events within {}s",
+            args.dedup_window_seconds
+        );
+        LogDeduplicator::new(
+            time::Duration::from_secs(args.dedup_window_seconds),
+            DEDUP_CAPACITY,
+        )
+    });
+    let mut dedup_interval = time::interval(time::Duration::from_secs(1));
+
+    let category_filter = (!args.log_categories.is_empty()).then(|| {
+        log::info!(
+            "Only publishing log events of the categories {:?}{}",
+            args.log_categories,
+            if args.include_unknown_category {
+                " and without a category"
+            } else {
+                ""
+            }
+        );
+        LogCategoryFilter::new(&args.log_categories, args.include_unknown_category)
+    });
+
+    let extractor_metrics = ExtractorMetricsRecorder::new(Extractor::LogExtractor);
+    let metrics_window = args.common.extractor_metrics_window();
+    let mut metrics_interval = extractor_metrics::interval(metrics_window);
+    let mut heartbeats = args
+        .common
+        .heartbeat_interval()
+        .map(|interval| Heartbeats::new(Extractor::LogExtractor, interval));
+    let mut heartbeat_interval = heartbeat::interval(args.common.heartbeat_interval());
+
+    log::info!("Started reading log lines");
     loop {
         tokio::select! {
-            line = lines.next_line() => {
+            line = source.next_line() => {
                 match line {
-                    Ok(Some(line)) => process_log(&nats_client, &line).await,
-                    Ok(None) => {
-                        // Since we use O_NONBLOCK, we need to wait here for a
-                        // bit to avoid spinning here if we don't have anything
-                        // to read.
-                        time::sleep(time::Duration::from_millis(25)).await;
-                    },
-                    Err(e) => {
-                        if e.kind() == std::io::ErrorKind::WouldBlock {
-                            // Non-blocking read with no data available: briefly
-                            // sleep to avoid spinning here and then continue.
-                            time::sleep(time::Duration::from_millis(25)).await;
+                    Ok(ReadLine::Line(line)) => {
+                        health.tick();
+                        log::trace!("Read log line: {}", line);
+                        let mut log = log_matchers.parse(&line);
+                        if args.include_raw_lines {
+                            log.raw_line = Some(line);
+                        }
+                        unknown_log_stats.record(&log);
+                        extractor_metrics.inc("lines_read");
+                        if matches!(log.log_event, Some(LogEvent::UnknownLogMessage(_))) {
+                            extractor_metrics.inc("lines_unknown");
+                        }
+                        // The dropped lines are still counted above, so the
+                        // unknown log ratio covers all read lines.
+                        if category_filter.as_ref().is_some_and(|filter| !filter.allows(&log)) {
+                            extractor_metrics.inc("lines_filtered");
                             continue;
                         }
-                        return Err(e.into());
+                        match &mut dedup {
+                            Some(dedup) => {
+                                for log in dedup.process(log, std::time::Instant::now()) {
+                                    publish_log(&nats_client, &labels, log).await;
+                                }
+                            }
+                            None => publish_log(&nats_client, &labels, log).await,
+                        }
+                    },
+                    Ok(ReadLine::Pending) => {
+                        // Files and pipes are read with O_NONBLOCK, so we need
+                        // to wait here for a bit to avoid spinning here if we
+                        // don't have anything to read.
+                        time::sleep(time::Duration::from_millis(25)).await;
+                    },
+                    Ok(ReadLine::Closed) => {
+                        log::info!("The log source was closed. Shutting down.");
+                        break;
+                    },
+                    Err(e) => return Err(e.into()),
+                }
+            },
+            _ = dedup_interval.tick(), if dedup.is_some() => {
+                if let Some(dedup) = &mut dedup {
+                    for log in dedup.flush_expired(std::time::Instant::now()) {
+                        publish_log(&nats_client, &labels, log).await;
                     }
                 }
             },
+            _ = metrics_interval.tick(), if metrics_window.is_some() => {
+                extractor_metrics.publish(&nats_client, &labels, metrics_interval.period()).await;
+            },
+            _ = heartbeat_interval.tick(), if heartbeats.is_some() => {
+                if let Some(heartbeats) = &mut heartbeats {
+                    heartbeats.publish(&nats_client, &labels).await;
+                }
+            },
+            _ = summary_interval.tick() => {
+                let summary = unknown_log_stats.take_summary(args.unknown_log_window_seconds);
+                if summary.total_lines > 0
+                    && summary.unknown_ratio() > args.unknown_log_ratio_threshold
+                {
+                    log::warn!(
+                        "{:.2}% of the {} log lines read in the last {}s did not match any known log format. The log format might have changed and the log matchers need to be updated.",
+                        summary.unknown_ratio() * 100.0,
+                        summary.total_lines,
+                        summary.window_seconds,
+                    );
+                }
+                publish_log(&nats_client, &labels, Log {
+                    occurrences: None,
+                    raw_line: None,
+                    log_timestamp: now_micros(),
+                    category: LogDebugCategory::Unknown.into(),
+                    log_event: Some(LogEvent::UnknownLogSummary(summary)),
+                }).await;
+            },
             res = shutdown_rx.changed() => {
                 match res {
                     Ok(_) => {
@@ -112,12 +357,108 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
         }
     }
 
+    // Don't lose the counts of suppressed log events on shutdown.
+    if let Some(dedup) = &mut dedup {
+        for log in dedup.flush_all() {
+            publish_log(&nats_client, &labels, log).await;
+        }
+    }
+
+    if let Some(path) = &args.offset_state_file {
+        if let Some(offset) = source.offset() {
+            tail::write_offset_state(std::path::Path::new(path), offset).await?;
+            log::info!("Wrote byte offset {} to offset state file {}", offset, path);
+        } else {
+            log::warn!(
+                "Not writing offset state file {}: the log lines are not read from a regular file.",
+                path
+            );
+        }
+    }
+
     Ok(())
 }
 
-async fn process_log(nats_client: &async_nats::Client, line: &str) {
-    log::trace!("Read log line: {}", line);
-    match Event::new(PeerObserverEvent::LogExtractor(parse_log_event(line))) {
+/// Opens the log source selected with --log-source, or the file or named pipe
+/// at --bitcoind-pipe.
+async fn open_log_source(
+    args: &Args,
+    shutdown_rx: watch::Receiver<bool>,
+) -> Result<Box<dyn LogSource>, RuntimeError> {
+    if args.log_source == Some(LogSourceKind::Stdin) {
+        if args.tail_from != TailFrom::Start {
+            log::warn!(
+                "Ignoring --tail-from {}: stdin can't be seeked.",
+                args.tail_from
+            );
+        }
+        log::info!("Reading log lines from stdin");
+        return Ok(Box::new(StdinSource::spawn()));
+    }
+    let Some(path) = &args.bitcoind_pipe else {
+        return Err(RuntimeError::InvalidArgs(
+            "--bitcoind-pipe is required unless the log lines are read from --log-source stdin"
+                .to_string(),
+        ));
+    };
+    log::info!("Opening bitcoind log pipe at {}...", path);
+    let source = source::open(args.log_source, path, args.tail_from, shutdown_rx).await?;
+    log::info!("Opened bitcoind log pipe at {}", path);
+    Ok(source)
+}
+
+/// Builds the log matcher registry from the built-in log matchers, the
+/// mining log matchers if enabled, and the log matchers disabled in the args.
+fn log_matcher_registry(args: &Args) -> Result<LogMatcherRegistry, RuntimeError> {
+    let mut registry = LogMatcherRegistry::default();
+    if args.enable_mining_log_matchers {
+        for (name, _) in MINING_LOG_MATCHERS {
+            registry = registry.enable(name)?;
+        }
+    }
+    for name in &args.disable_log_matchers {
+        registry = registry.disable(name)?;
+    }
+    Ok(registry)
+}
+
+/// Counts the read log lines and the log lines that did not match any known
+/// log format.
+#[derive(Default)]
+struct UnknownLogStats {
+    total_lines: u64,
+    unknown_lines: u64,
+}
+
+impl UnknownLogStats {
+    fn record(&mut self, log: &Log) {
+        self.total_lines += 1;
+        if matches!(log.log_event, Some(LogEvent::UnknownLogMessage(_))) {
+            self.unknown_lines += 1;
+        }
+    }
+
+    /// Returns a summary of the current window and resets the counts.
+    fn take_summary(&mut self, window_seconds: u64) -> UnknownLogSummary {
+        let summary = UnknownLogSummary {
+            total_lines: self.total_lines,
+            unknown_lines: self.unknown_lines,
+            window_seconds,
+        };
+        *self = Self::default();
+        summary
+    }
+}
+
+fn now_micros() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+async fn publish_log(nats_client: &TappedClient, labels: &Labels, log: Log) {
+    match Event::new(PeerObserverEvent::LogExtractor(log)).map(|event| event.with_labels(labels)) {
         Ok(proto) => {
             if let Err(e) = nats_client
                 .publish(
@@ -137,38 +478,146 @@ async fn process_log(nats_client: &async_nats::Client, line: &str) {
     };
 }
 
-async fn open_pipe(path: &str, shutdown_rx: watch::Receiver<bool>) -> Result<File, std::io::Error> {
-    // Fail after MAX_RETRIES if the pipe doesn't exist yet.
-    const MAX_RETRIES: i32 = 30;
-    for retries in 0..=MAX_RETRIES {
-        if *shutdown_rx.borrow() {
-            log::info!("open_pipe received shutdown signal.");
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Interrupted,
-                "shutdown signal received",
-            ));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if !std::path::Path::new(path).exists() {
-            log::warn!(
-                "Pipe {} does not exist yet, retrying in 1s (retry: {}/{})",
-                path,
-                retries,
-                MAX_RETRIES
-            );
-            time::sleep(time::Duration::from_secs(1)).await;
-        } else {
-            break;
-        }
+    #[test]
+    fn test_unknown_log_stats() {
+        let log_matchers = LogMatcherRegistry::default();
+        let mut stats = UnknownLogStats::default();
+        stats.record(&log_matchers.parse("2025-01-01T00:00:00Z Random message"));
+        stats.record(&log_matchers.parse(
+            "2025-09-27T01:52:01Z [validation] Enqueuing BlockConnected: block hash=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b block height=437",
+        ));
+        stats.record(&log_matchers.parse("2025-01-01T00:00:01Z Another random message"));
+
+        let summary = stats.take_summary(60);
+        assert_eq!(summary.total_lines, 3);
+        assert_eq!(summary.unknown_lines, 2);
+        assert_eq!(summary.window_seconds, 60);
+        assert!((summary.unknown_ratio() - 2.0 / 3.0).abs() < f64::EPSILON);
+
+        // the counts are reset after taking a summary
+        let summary = stats.take_summary(60);
+        assert_eq!(summary.total_lines, 0);
+        assert_eq!(summary.unknown_lines, 0);
+        assert_eq!(summary.unknown_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_log_matcher_registry_from_args() {
+        let mut args = Args::new(
+            "127.0.0.1:4222".to_string(),
+            "/tmp/bitcoind-pipe".to_string(),
+            log::Level::Info,
+        );
+        args.disable_log_matchers = vec!["block_connected".to_string()];
+
+        let registry = log_matcher_registry(&args).unwrap();
+        assert!(!registry.is_enabled("block_connected"));
+        assert!(registry.is_enabled("block_checked"));
+
+        args.disable_log_matchers = vec!["foo".to_string()];
+        assert!(log_matcher_registry(&args).is_err());
+
+        args.disable_log_matchers = vec!["block_template_timing".to_string()];
+        assert!(
+            !log_matcher_registry(&args)
+                .unwrap()
+                .is_enabled("block_template")
+        );
+        args.enable_mining_log_matchers = true;
+        let registry = log_matcher_registry(&args).unwrap();
+        assert!(registry.is_enabled("block_template"));
+        assert!(!registry.is_enabled("block_template_timing"));
+    }
+
+    #[test]
+    fn test_log_source_args() {
+        let parse = |args: &[&str]| {
+            Args::try_parse_from([&["log-extractor"][..], args].concat())
+                .map(|args| (args.log_source, args.bitcoind_pipe))
+        };
+        assert_eq!(
+            parse(&["--bitcoind-pipe", "/tmp/pipe"]).unwrap(),
+            (None, Some("/tmp/pipe".to_string()))
+        );
+        assert_eq!(
+            parse(&["--log-source", "stdin"]).unwrap(),
+            (Some(LogSourceKind::Stdin), None)
+        );
+        // files and named pipes need a path
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--log-source", "file"]).is_err());
+        assert!(parse(&["--log-source", "fifo"]).is_err());
+        assert!(parse(&["--log-source", "journald", "-b", "/tmp/pipe"]).is_err());
+    }
+
+    #[test]
+    fn test_log_categories_args() {
+        let parse = |args: &[&str]| {
+            Args::try_parse_from([&["log-extractor", "-b", "/tmp/pipe"][..], args].concat())
+                .map(|args| (args.log_categories, args.include_unknown_category))
+        };
+        assert_eq!(parse(&[]).unwrap(), (vec![], false));
+        assert_eq!(
+            parse(&["--log-categories", "validation,net"]).unwrap(),
+            (
+                vec![LogDebugCategory::Validation, LogDebugCategory::Net],
+                false
+            )
+        );
+        assert_eq!(
+            parse(&[
+                "--log-categories",
+                "validation",
+                "--include-unknown-category"
+            ])
+            .unwrap(),
+            (vec![LogDebugCategory::Validation], true)
+        );
+        assert!(parse(&["--log-categories", "foo"]).is_err());
+        assert!(parse(&["--include-unknown-category"]).is_err());
     }
 
-    OpenOptions::new()
-        .read(true)
-        .write(false)
-        // We need to use O_NONBLOCK here, otherwise a pipe without a writer
-        // will block the tokio async routine on next_line() and we can't
-        // e.g. CTRL+C anymore.
-        .custom_flags(O_NONBLOCK)
-        .open(path)
-        .await
+    #[test]
+    fn test_include_raw_lines_args() {
+        let parse = |args: &[&str]| {
+            Args::try_parse_from([&["log-extractor", "-b", "/tmp/pipe"][..], args].concat())
+                .map(|args| args.include_raw_lines)
+        };
+        assert!(!parse(&[]).unwrap());
+        assert!(parse(&["--include-raw-lines"]).unwrap());
+    }
+
+    #[test]
+    fn test_health_max_line_age_args() {
+        let parse = |args: &[&str]| {
+            Args::try_parse_from([&["log-extractor", "-b", "/tmp/pipe"][..], args].concat())
+                .map(|args| args.health_max_line_age_secs)
+        };
+        assert_eq!(parse(&[]).unwrap(), 3600);
+        assert_eq!(parse(&["--health-max-line-age-secs", "60"]).unwrap(), 60);
+        assert!(parse(&["--health-max-line-age-secs", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_log_ratio_threshold_args() {
+        let parse = |threshold: &str| {
+            Args::try_parse_from([
+                "log-extractor",
+                "-b",
+                "/tmp/pipe",
+                &format!("--unknown-log-ratio-threshold={}", threshold),
+            ])
+            .map(|args| args.unknown_log_ratio_threshold)
+        };
+        assert_eq!(parse("0").unwrap(), 0.0);
+        assert_eq!(parse("0.5").unwrap(), 0.5);
+        assert_eq!(parse("1.0").unwrap(), 1.0);
+        for invalid in ["-0.1", "1.01", "NaN", "inf", "half"] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+    }
 }
@@ -1,29 +1,33 @@
 use log_extractor::Args;
+use shared::args::ExtractorArgs;
+use shared::config;
 use shared::log;
+use shared::logging;
 use shared::tokio::{self, signal, sync::watch};
-use shared::{clap::Parser, simple_logger};
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
+    let args: Args = config::parse_args();
 
-    if let Err(e) = simple_logger::init_with_level(args.log_level) {
+    if let Err(e) = logging::init(args.common()) {
         eprintln!("log extractor error: {}", e);
     }
 
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
-    let log_handle = tokio::spawn(log_extractor::run(args, shutdown_rx));
+    let mut log_handle = tokio::spawn(log_extractor::run(args, shutdown_rx));
 
-    tokio::select! {
+    let result = tokio::select! {
         _ = signal::ctrl_c() => {
             log::info!("Received Ctrl+C. Stopping...");
             let _ = shutdown_tx.send(true);
+            // Wait for the log_extractor to shut down, as it might still need
+            // to write the offset state file.
+            log_handle.await
         }
-        result = log_handle => {
-            match result.unwrap() {
-                Ok(_) => log::info!("log_extractor task completed."),
-                Err(e) => log::error!("log_extractor task failed: {e}"),
-            }
-        }
+        result = &mut log_handle => result,
+    };
+    match result.unwrap() {
+        Ok(_) => log::info!("log_extractor task completed."),
+        Err(e) => log::error!("log_extractor task failed: {e}"),
     }
 }
@@ -0,0 +1,472 @@
+use crate::O_NONBLOCK;
+use crate::tail::{self, TailFrom};
+use shared::clap::ValueEnum;
+use shared::futures::{FutureExt, future::BoxFuture};
+use shared::log;
+use shared::tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncBufReadExt, BufReader, Lines},
+    sync::{mpsc, watch},
+    time,
+};
+use std::io::{self, BufRead};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::thread;
+
+/// Number of lines read from stdin that are buffered until they're processed.
+const STDIN_BUFFER_LINES: usize = 1024;
+
+/// Where the log lines are read from (`--log-source`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogSourceKind {
+    /// A debug.log file at the --bitcoind-pipe path. It's followed and
+    /// reopened when rotated or truncated.
+    File,
+    /// A named pipe (FIFO) at the --bitcoind-pipe path. It's reopened when
+    /// the writer disconnects.
+    Fifo,
+    /// The standard input, e.g. `bitcoind -printtoconsole | log-extractor`.
+    /// The extractor shuts down once stdin is closed.
+    Stdin,
+}
+
+/// The result of reading from a [LogSource].
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReadLine {
+    /// A complete line without the trailing newline.
+    Line(String),
+    /// No line is available right now. Reading should be retried later.
+    Pending,
+    /// The source is closed and won't yield any more lines.
+    Closed,
+}
+
+/// An input the log lines are read from. Reading a line must be cancel safe,
+/// as it's raced against other events in the main loop.
+pub trait LogSource: Send {
+    /// Reads the next line. The source is reopened when needed, e.g. after
+    /// a log file was rotated.
+    fn next_line(&mut self) -> BoxFuture<'_, io::Result<ReadLine>>;
+
+    /// The byte offset after the last line read, if the source can be
+    /// resumed at it with `--tail-from offset:N`. Only regular files can.
+    fn offset(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Opens the file or named pipe at the path. Without a kind, a named pipe is
+/// read as [LogSourceKind::Fifo] and anything else as [LogSourceKind::File].
+/// Fails if the path is of a different kind than requested. Stdin isn't
+/// opened from a path, see [StdinSource::spawn].
+pub async fn open(
+    kind: Option<LogSourceKind>,
+    path: &str,
+    tail_from: TailFrom,
+    shutdown_rx: watch::Receiver<bool>,
+) -> io::Result<Box<dyn LogSource>> {
+    let file = open_path(path, shutdown_rx.clone()).await?;
+    let is_fifo = file.metadata().await?.file_type().is_fifo();
+    match (kind, is_fifo) {
+        (None | Some(LogSourceKind::Fifo), true) => {
+            if tail_from != TailFrom::Start {
+                log::warn!(
+                    "Ignoring --tail-from {}: {} is a named pipe and can't be seeked.",
+                    tail_from,
+                    path
+                );
+            }
+            log::debug!("Reading from named pipe {}", path);
+            Ok(Box::new(FifoSource {
+                path: path.to_string(),
+                lines: BufReader::new(file).lines(),
+                has_writer: false,
+                shutdown_rx,
+            }))
+        }
+        (None | Some(LogSourceKind::File), false) => Ok(Box::new(
+            FileSource::new(path, file, tail_from, shutdown_rx).await?,
+        )),
+        (Some(LogSourceKind::Fifo), false) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a named pipe (--log-source fifo)", path),
+        )),
+        (Some(LogSourceKind::File), true) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is a named pipe, not a file (--log-source file)", path),
+        )),
+        (Some(LogSourceKind::Stdin), _) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} can't be read as stdin (--log-source stdin)", path),
+        )),
+    }
+}
+
+/// A log file, followed like with `tail -F`. Files that aren't regular files,
+/// e.g. character devices, can't be rotated and are read until EOF and
+/// retried.
+pub struct FileSource {
+    path: String,
+    reader: BufReader<File>,
+    /// The bytes read of the current line, including its line ending. Kept
+    /// across reads, as the last line of the file might not be completely
+    /// written yet and reading it might be cancelled.
+    line: Vec<u8>,
+    /// The inode of a regular file, to detect rotation. None for other files.
+    inode: Option<u64>,
+    /// Number of bytes of the complete lines read from the current file,
+    /// including their line endings. Used to detect truncation and persisted
+    /// to the offset state file on shutdown.
+    offset: u64,
+    shutdown_rx: watch::Receiver<bool>,
+}
+
+impl FileSource {
+    async fn new(
+        path: &str,
+        mut file: File,
+        tail_from: TailFrom,
+        shutdown_rx: watch::Receiver<bool>,
+    ) -> io::Result<Self> {
+        let inode = regular_file_inode(&file).await?;
+        let mut offset = 0;
+        if inode.is_some() {
+            offset = tail::seek(&mut file, tail_from).await?;
+            log::info!(
+                "Reading log file {} from byte offset {} (--tail-from {})",
+                path,
+                offset,
+                tail_from
+            );
+        } else if tail_from != TailFrom::Start {
+            log::warn!(
+                "Ignoring --tail-from {}: {} is not a regular file and can't be seeked.",
+                tail_from,
+                path
+            );
+        }
+        Ok(FileSource {
+            path: path.to_string(),
+            reader: BufReader::new(file),
+            line: vec![],
+            inode,
+            offset,
+            shutdown_rx,
+        })
+    }
+
+    async fn read(&mut self) -> io::Result<ReadLine> {
+        // read_until() is cancel safe: the bytes read so far stay in
+        // self.line and the next read continues the line.
+        match self.reader.read_until(b'\n', &mut self.line).await {
+            Ok(_) if self.line.ends_with(b"\n") => {
+                // Count the bytes actually read, e.g. also the `\r` of a
+                // `\r\n` line ending.
+                self.offset += self.line.len() as u64;
+                self.take_line().map(ReadLine::Line)
+            }
+            Ok(_) => {
+                // We reached the end of the current file, possibly in the
+                // middle of a line that's still being written. If the file
+                // was rotated or truncated in the meantime, reopen it and
+                // continue reading from the start.
+                let Some(inode) = self.inode else {
+                    return Ok(ReadLine::Pending);
+                };
+                if file_rotated(&self.path, inode, self.offset).await {
+                    log::info!(
+                        "Log file {} was rotated or truncated. Reopening it..",
+                        self.path
+                    );
+                    let file = open_path(&self.path, self.shutdown_rx.clone()).await?;
+                    self.inode = regular_file_inode(&file).await?;
+                    self.offset = 0;
+                    self.reader = BufReader::new(file);
+                    // The last line of the old file won't be completed.
+                    if !self.line.is_empty() {
+                        return self.take_line().map(ReadLine::Line);
+                    }
+                }
+                Ok(ReadLine::Pending)
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(ReadLine::Pending),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Takes the current line without its `\n` or `\r\n` line ending.
+    fn take_line(&mut self) -> io::Result<String> {
+        let mut line = std::mem::take(&mut self.line);
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl LogSource for FileSource {
+    fn next_line(&mut self) -> BoxFuture<'_, io::Result<ReadLine>> {
+        self.read().boxed()
+    }
+
+    fn offset(&self) -> Option<u64> {
+        self.inode.map(|_| self.offset)
+    }
+}
+
+/// A named pipe (FIFO). EOF means that the writer disconnected, and the pipe
+/// is reopened to wait for the next writer with a fresh buffer.
+pub struct FifoSource {
+    path: String,
+    lines: Lines<BufReader<File>>,
+    /// Set once a line was read from the current writer.
+    has_writer: bool,
+    shutdown_rx: watch::Receiver<bool>,
+}
+
+impl FifoSource {
+    async fn read(&mut self) -> io::Result<ReadLine> {
+        match self.lines.next_line().await {
+            Ok(Some(line)) => {
+                self.has_writer = true;
+                Ok(ReadLine::Line(line))
+            }
+            Ok(None) => {
+                // Only log the disconnect once and not while we are waiting
+                // for a writer to connect.
+                if self.has_writer {
+                    log::info!("Writer of pipe {} disconnected. Reopening it..", self.path);
+                }
+                let file = open_path(&self.path, self.shutdown_rx.clone()).await?;
+                self.has_writer = false;
+                self.lines = BufReader::new(file).lines();
+                Ok(ReadLine::Pending)
+            }
+            // While a writer is connected, reads without data available
+            // return WouldBlock.
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(ReadLine::Pending),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl LogSource for FifoSource {
+    fn next_line(&mut self) -> BoxFuture<'_, io::Result<ReadLine>> {
+        self.read().boxed()
+    }
+}
+
+/// The standard input of the extractor. It's read with blocking reads on a
+/// separate thread, as these can't be interrupted on shutdown.
+pub struct StdinSource {
+    lines: mpsc::Receiver<io::Result<String>>,
+}
+
+impl StdinSource {
+    /// Starts reading stdin on a separate thread.
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel(STDIN_BUFFER_LINES);
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let failed = line.is_err();
+                if tx.blocking_send(line).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        StdinSource { lines: rx }
+    }
+}
+
+impl LogSource for StdinSource {
+    fn next_line(&mut self) -> BoxFuture<'_, io::Result<ReadLine>> {
+        async move {
+            match self.lines.recv().await {
+                Some(line) => line.map(ReadLine::Line),
+                None => Ok(ReadLine::Closed),
+            }
+        }
+        .boxed()
+    }
+}
+
+async fn regular_file_inode(file: &File) -> io::Result<Option<u64>> {
+    let metadata = file.metadata().await?;
+    Ok(metadata.is_file().then(|| metadata.ino()))
+}
+
+/// Checks if the file at `path` was rotated (it now has a different inode than
+/// the file we are reading from) or truncated (it's shorter than what we
+/// already read). If there is currently no file at `path`, e.g. during a
+/// rotation, this returns false and we check again later.
+async fn file_rotated(path: &str, inode: u64, offset: u64) -> bool {
+    match shared::tokio::fs::metadata(path).await {
+        Ok(metadata) => metadata.ino() != inode || metadata.len() < offset,
+        Err(_) => false,
+    }
+}
+
+async fn open_path(path: &str, shutdown_rx: watch::Receiver<bool>) -> io::Result<File> {
+    // Fail after MAX_RETRIES if the pipe doesn't exist yet.
+    const MAX_RETRIES: i32 = 30;
+    for retries in 0..=MAX_RETRIES {
+        if *shutdown_rx.borrow() {
+            log::info!("open_path received shutdown signal.");
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "shutdown signal received",
+            ));
+        }
+
+        if !std::path::Path::new(path).exists() {
+            log::warn!(
+                "Pipe {} does not exist yet, retrying in 1s (retry: {}/{})",
+                path,
+                retries,
+                MAX_RETRIES
+            );
+            time::sleep(time::Duration::from_secs(1)).await;
+        } else {
+            break;
+        }
+    }
+
+    OpenOptions::new()
+        .read(true)
+        .write(false)
+        // We need to use O_NONBLOCK here, otherwise a pipe without a writer
+        // will block the tokio async routine on next_line() and we can't
+        // e.g. CTRL+C anymore.
+        .custom_flags(O_NONBLOCK)
+        .open(path)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::tokio;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "peer-observer-log-source-test-{}-{}.log",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn test_file_source() {
+        let path = temp_path("file");
+        fs::write(&path, "first\nsecond\n").unwrap();
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut source = open(None, path.to_str().unwrap(), TailFrom::Start, shutdown_rx)
+            .await
+            .unwrap();
+
+        let line = |s: &str| ReadLine::Line(s.to_string());
+        assert_eq!(source.next_line().await.unwrap(), line("first"));
+        assert_eq!(source.next_line().await.unwrap(), line("second"));
+        assert_eq!(source.offset(), Some(13));
+        assert_eq!(source.next_line().await.unwrap(), ReadLine::Pending);
+
+        // a truncated file is reopened and read from the start
+        fs::write(&path, "third\n").unwrap();
+        assert_eq!(source.next_line().await.unwrap(), ReadLine::Pending);
+        assert_eq!(source.next_line().await.unwrap(), line("third"));
+        assert_eq!(source.offset(), Some(6));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_source_line_endings() {
+        let path = temp_path("line-endings");
+        fs::write(&path, "first\r\nsecond\nthi").unwrap();
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut source = open(None, path.to_str().unwrap(), TailFrom::Start, shutdown_rx)
+            .await
+            .unwrap();
+
+        let line = |s: &str| ReadLine::Line(s.to_string());
+        assert_eq!(source.next_line().await.unwrap(), line("first"));
+        // the `\r` is counted, too
+        assert_eq!(source.offset(), Some(7));
+        assert_eq!(source.next_line().await.unwrap(), line("second"));
+        assert_eq!(source.offset(), Some(14));
+        // an incomplete line isn't returned or counted until it's completed
+        assert_eq!(source.next_line().await.unwrap(), ReadLine::Pending);
+        assert_eq!(source.offset(), Some(14));
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, b"rd\r\n").unwrap();
+        assert_eq!(source.next_line().await.unwrap(), line("third"));
+        assert_eq!(source.offset(), Some(21));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_source_resume() {
+        let path = temp_path("resume");
+        let state = temp_path("resume-state");
+        fs::write(&path, "first\nsecond\n").unwrap();
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let line = |s: &str| ReadLine::Line(s.to_string());
+
+        let mut source = open(
+            None,
+            path.to_str().unwrap(),
+            TailFrom::Start,
+            shutdown_rx.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(source.next_line().await.unwrap(), line("first"));
+        // stopped after the first line
+        tail::write_offset_state(&state, source.offset().unwrap())
+            .await
+            .unwrap();
+        drop(source);
+
+        // a line is written while the extractor isn't running
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, b"third\n").unwrap();
+
+        // restarted with --tail-from "$(cat state)"
+        let tail_from =
+            tail::parse_tail_from(fs::read_to_string(&state).unwrap().trim_end()).unwrap();
+        let mut source = open(None, path.to_str().unwrap(), tail_from, shutdown_rx)
+            .await
+            .unwrap();
+        assert_eq!(source.next_line().await.unwrap(), line("second"));
+        assert_eq!(source.next_line().await.unwrap(), line("third"));
+        assert_eq!(source.next_line().await.unwrap(), ReadLine::Pending);
+        assert_eq!(source.offset(), Some(19));
+        fs::remove_file(path).unwrap();
+        fs::remove_file(state).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_open_kind_mismatch() {
+        let path = temp_path("mismatch");
+        fs::write(&path, "").unwrap();
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let result = open(
+            Some(LogSourceKind::Fifo),
+            path.to_str().unwrap(),
+            TailFrom::Start,
+            shutdown_rx,
+        )
+        .await;
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
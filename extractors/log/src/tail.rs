@@ -0,0 +1,155 @@
+use shared::log;
+use shared::tokio::{
+    fs::File,
+    io::{AsyncSeekExt, SeekFrom},
+};
+use std::fmt;
+use std::path::Path;
+
+/// Where to start reading the log file on startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TailFrom {
+    /// Read the whole file from the start.
+    Start,
+    /// Only read lines written after the extractor started.
+    End,
+    /// Continue reading at a byte offset, e.g. one persisted with
+    /// `--offset-state-file` before a restart.
+    Offset(u64),
+}
+
+impl fmt::Display for TailFrom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TailFrom::Start => write!(f, "start"),
+            TailFrom::End => write!(f, "end"),
+            TailFrom::Offset(offset) => write!(f, "offset:{}", offset),
+        }
+    }
+}
+
+/// Parses a `--tail-from` argument: `start`, `end`, or `offset:N`.
+pub fn parse_tail_from(s: &str) -> Result<TailFrom, String> {
+    match s {
+        "start" => Ok(TailFrom::Start),
+        "end" => Ok(TailFrom::End),
+        _ => match s.strip_prefix("offset:") {
+            Some(offset) => offset
+                .parse::<u64>()
+                .map(TailFrom::Offset)
+                .map_err(|e| format!("invalid byte offset '{}': {}", offset, e)),
+            None => Err(format!(
+                "invalid value '{}': expected 'start', 'end', or 'offset:N'",
+                s
+            )),
+        },
+    }
+}
+
+/// Seeks a regular file to the position given by `tail_from` and returns the
+/// new offset. If the offset is past the end of the file, the file was likely
+/// rotated or truncated since the offset was persisted and we read it from
+/// the start instead.
+pub async fn seek(file: &mut File, tail_from: TailFrom) -> Result<u64, std::io::Error> {
+    let len = file.metadata().await?.len();
+    let offset = match tail_from {
+        TailFrom::Start => 0,
+        TailFrom::End => len,
+        TailFrom::Offset(offset) if offset > len => {
+            log::warn!(
+                "Byte offset {} is past the end of the log file ({} bytes). Reading it from the start.",
+                offset,
+                len
+            );
+            0
+        }
+        TailFrom::Offset(offset) => offset,
+    };
+    file.seek(SeekFrom::Start(offset)).await
+}
+
+/// Persists the byte offset of the last processed line to the state file as
+/// `offset:N`, the format `--tail-from` accepts. The offset is written to a
+/// temporary file first and then moved into place to not leave a partially
+/// written state file behind.
+pub async fn write_offset_state(path: &Path, offset: u64) -> Result<(), std::io::Error> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    shared::tokio::fs::write(&tmp, format!("{}\n", TailFrom::Offset(offset))).await?;
+    shared::tokio::fs::rename(&tmp, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::tokio;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "peer-observer-log-tail-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn test_seek() {
+        let path = temp_path("seek");
+        std::fs::write(&path, "first\nsecond\n").unwrap();
+        for (tail_from, expected) in [
+            (TailFrom::Start, 0),
+            (TailFrom::End, 13),
+            (TailFrom::Offset(6), 6),
+            (TailFrom::Offset(13), 13),
+            // past the end, e.g. after a rotation
+            (TailFrom::Offset(14), 0),
+        ] {
+            let mut file = File::open(&path).await.unwrap();
+            assert_eq!(seek(&mut file, tail_from).await.unwrap(), expected);
+            assert_eq!(file.stream_position().await.unwrap(), expected);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_offset_state_round_trip() {
+        let path = temp_path("state");
+        write_offset_state(&path, 123456).await.unwrap();
+        let state = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(state, "offset:123456\n");
+        // e.g. passed as --tail-from "$(cat state)"
+        assert_eq!(
+            parse_tail_from(state.trim_end()),
+            Ok(TailFrom::Offset(123456))
+        );
+
+        // the state is replaced, and no temporary file is left behind
+        write_offset_state(&path, 7).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "offset:7\n");
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        assert!(!Path::new(&tmp).exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_tail_from() {
+        assert_eq!(parse_tail_from("start"), Ok(TailFrom::Start));
+        assert_eq!(parse_tail_from("end"), Ok(TailFrom::End));
+        assert_eq!(parse_tail_from("offset:0"), Ok(TailFrom::Offset(0)));
+        assert_eq!(
+            parse_tail_from("offset:123456"),
+            Ok(TailFrom::Offset(123456))
+        );
+        assert!(parse_tail_from("offset:").is_err());
+        assert!(parse_tail_from("offset:-1").is_err());
+        assert!(parse_tail_from("middle").is_err());
+
+        for tail_from in [TailFrom::Start, TailFrom::End, TailFrom::Offset(42)] {
+            assert_eq!(parse_tail_from(&tail_from.to_string()), Ok(tail_from));
+        }
+    }
+}
@@ -451,3 +451,124 @@ async fn test_integration_logextractor_mutated_block_bad_txnmrklroot() {
     )
     .await;
 }
+
+#[tokio::test]
+async fn test_integration_logextractor_log_rotation() {
+    println!("test that we keep receiving log events after the log file is rotated");
+    setup();
+
+    let dir =
+        std::env::temp_dir().join(format!("peer-observer-log-rotation-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let log_path = dir.join("debug.log");
+    std::fs::write(
+        &log_path,
+        "2025-01-01T00:00:00Z line written before rotation\n",
+    )
+    .unwrap();
+
+    let nats_server = NatsServerForTesting::new().await;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let nc = async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+        .await
+        .unwrap();
+    let mut sub = nc.subscribe("*").await.unwrap();
+
+    let args = make_test_args(nats_server.port, log_path.display().to_string());
+    let log_extractor_handle = tokio::spawn(async move {
+        log_extractor::run(args, shutdown_rx.clone())
+            .await
+            .expect("log extractor failed");
+    });
+
+    let mut rotated = false;
+    while let Some(msg) = sub.next().await {
+        let unwrapped = Event::decode(msg.payload).unwrap();
+        if let Some(PeerObserverEvent::LogExtractor(r)) = unwrapped.peer_observer_event {
+            if let Some(log::LogEvent::UnknownLogMessage(unknown)) = r.log_event {
+                if unknown.raw_message.contains("before rotation") && !rotated {
+                    // Simulate a rotation: move the old file away and replace
+                    // it with a new file.
+                    std::fs::rename(&log_path, dir.join("debug.log.1")).unwrap();
+                    std::fs::write(
+                        &log_path,
+                        "2025-01-01T00:00:01Z line written after rotation\n",
+                    )
+                    .unwrap();
+                    rotated = true;
+                } else if unknown.raw_message.contains("after rotation") {
+                    assert!(rotated);
+                    break;
+                }
+            }
+        }
+    }
+
+    shutdown_tx.send(true).unwrap();
+    log_extractor_handle.await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_integration_logextractor_pipe_writer_reconnect() {
+    println!("test that we keep receiving log events after the pipe writer reconnects");
+    setup();
+
+    let dir = std::env::temp_dir().join(format!(
+        "peer-observer-pipe-reconnect-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pipe_path = dir.join("bitcoind_pipe");
+    std::process::Command::new("mkfifo")
+        .arg(&pipe_path)
+        .status()
+        .expect("Failed to create named pipe");
+
+    let nats_server = NatsServerForTesting::new().await;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let nc = async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+        .await
+        .unwrap();
+    let mut sub = nc.subscribe("*").await.unwrap();
+
+    let args = make_test_args(nats_server.port, pipe_path.display().to_string());
+    let log_extractor_handle = tokio::spawn(async move {
+        log_extractor::run(args, shutdown_rx.clone())
+            .await
+            .expect("log extractor failed");
+    });
+
+    // Each writer writes a line and disconnects again.
+    let write_line = |line: &str| {
+        tokio::process::Command::new("bash")
+            .arg("-c")
+            .arg(format!("echo '{}' > {}", line, pipe_path.display()))
+            .spawn()
+            .expect("Failed to spawn writer");
+    };
+    write_line("2025-01-01T00:00:00Z line from first writer");
+
+    let mut reconnected = false;
+    while let Some(msg) = sub.next().await {
+        let unwrapped = Event::decode(msg.payload).unwrap();
+        if let Some(PeerObserverEvent::LogExtractor(r)) = unwrapped.peer_observer_event {
+            if let Some(log::LogEvent::UnknownLogMessage(unknown)) = r.log_event {
+                if unknown.raw_message.contains("first writer") && !reconnected {
+                    sleep(Duration::from_millis(100)).await;
+                    write_line("2025-01-01T00:00:01Z line from second writer");
+                    reconnected = true;
+                } else if unknown.raw_message.contains("second writer") {
+                    assert!(reconnected);
+                    break;
+                }
+            }
+        }
+    }
+
+    shutdown_tx.send(true).unwrap();
+    log_extractor_handle.await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
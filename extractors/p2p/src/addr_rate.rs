@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The window over which the announced addresses are counted.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Tracks how many addresses a peer announced in addr and addrv2 messages over
+/// the last minute. Address announcement spam is a known DoS vector.
+///
+/// An alert is raised when the number of addresses announced in the last
+/// minute exceeds the threshold. To not raise an alert for every following
+/// announcement, the next alert is only raised once the rate dropped to or
+/// below the threshold and exceeds it again.
+pub struct AddrRateTracker {
+    threshold: u64,
+    /// The announcements in the last minute: when, and how many addresses.
+    announcements: VecDeque<(Instant, u64)>,
+    addresses: u64,
+    alerting: bool,
+}
+
+impl AddrRateTracker {
+    pub fn new(threshold: u64) -> Self {
+        AddrRateTracker {
+            threshold,
+            announcements: VecDeque::new(),
+            addresses: 0,
+            alerting: false,
+        }
+    }
+
+    pub fn threshold(&self) -> u64 {
+        self.threshold
+    }
+
+    /// Records an announcement of `addresses` addresses at `now`. Returns the
+    /// number of addresses announced in the last minute if an alert should be
+    /// raised.
+    pub fn record(&mut self, addresses: u64, now: Instant) -> Option<u64> {
+        while let Some((at, count)) = self.announcements.front() {
+            if now.duration_since(*at) < WINDOW {
+                break;
+            }
+            self.addresses -= count;
+            self.announcements.pop_front();
+        }
+        self.announcements.push_back((now, addresses));
+        self.addresses += addresses;
+
+        if self.addresses <= self.threshold {
+            self.alerting = false;
+            return None;
+        }
+        if self.alerting {
+            return None;
+        }
+        self.alerting = true;
+        Some(self.addresses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addr_rate_alert() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut tracker = AddrRateTracker::new(1000);
+
+        assert_eq!(tracker.record(600, at(0)), None);
+        assert_eq!(tracker.record(400, at(10)), None);
+        // exceeds the threshold within a minute
+        assert_eq!(tracker.record(10, at(20)), Some(1010));
+        // no repeated alert while above the threshold
+        assert_eq!(tracker.record(10, at(30)), None);
+    }
+
+    #[test]
+    fn test_addr_rate_window() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut tracker = AddrRateTracker::new(1000);
+
+        assert_eq!(tracker.record(1001, at(0)), Some(1001));
+        // the first announcement left the window: back below the threshold
+        assert_eq!(tracker.record(500, at(60)), None);
+        // and exceeding it again raises a new alert
+        assert_eq!(tracker.record(501, at(70)), Some(1001));
+    }
+}
@@ -1,4 +1,5 @@
 use shared::async_nats::ConnectErrorKind;
+use shared::labels::LabelError;
 use shared::log::SetLoggerError;
 use std::error;
 use std::fmt;
@@ -9,6 +10,8 @@ pub enum RuntimeError {
     SetLogger(SetLoggerError),
     Io(io::Error),
     NatsConnect(shared::async_nats::error::Error<ConnectErrorKind>),
+    NatsSubscribe(shared::async_nats::client::SubscribeError),
+    Labels(LabelError),
 }
 
 impl fmt::Display for RuntimeError {
@@ -17,6 +20,8 @@ impl fmt::Display for RuntimeError {
             RuntimeError::SetLogger(e) => write!(f, "set logger error {}", e),
             RuntimeError::Io(e) => write!(f, "IO error {}", e),
             RuntimeError::NatsConnect(e) => write!(f, "NATS connection error {}", e),
+            RuntimeError::NatsSubscribe(e) => write!(f, "NATS subscribe error {}", e),
+            RuntimeError::Labels(e) => write!(f, "labels error {}", e),
         }
     }
 }
@@ -27,6 +32,8 @@ impl error::Error for RuntimeError {
             RuntimeError::SetLogger(ref e) => Some(e),
             RuntimeError::Io(ref e) => Some(e),
             RuntimeError::NatsConnect(ref e) => Some(e),
+            RuntimeError::NatsSubscribe(ref e) => Some(e),
+            RuntimeError::Labels(ref e) => Some(e),
         }
     }
 }
@@ -49,12 +56,22 @@ impl From<shared::async_nats::error::Error<ConnectErrorKind>> for RuntimeError {
     }
 }
 
+impl From<shared::async_nats::client::SubscribeError> for RuntimeError {
+    fn from(e: shared::async_nats::client::SubscribeError) -> Self {
+        RuntimeError::NatsSubscribe(e)
+    }
+}
+
 #[derive(Debug)]
 pub enum BitcoinMsgDecodeError {
     HeaderReadError(shared::tokio::io::Error),
     PayloadReadError(shared::tokio::io::Error),
     InvalidLengthBytes(std::array::TryFromSliceError),
-    DecodeError(shared::bitcoin::consensus::encode::Error),
+    DecodeError {
+        command: String,
+        length: u32,
+        error: shared::bitcoin::consensus::encode::Error,
+    },
     MagicError([u8; 4], [u8; 4]),
 }
 
@@ -65,7 +82,15 @@ impl fmt::Display for BitcoinMsgDecodeError {
             HeaderReadError(e) => write!(f, "Failed to read message header: {}", e),
             PayloadReadError(e) => write!(f, "Failed to read message payload: {}", e),
             InvalidLengthBytes(e) => write!(f, "Invalid length bytes in header: {}", e),
-            DecodeError(e) => write!(f, "Consensus decode error: {}", e),
+            DecodeError {
+                command,
+                length,
+                error,
+            } => write!(
+                f,
+                "Consensus decode error in '{}' message ({} bytes): {}",
+                command, length, error
+            ),
             MagicError(expected, got) => write!(
                 f,
                 "Network magic mismatch: got={}, expected={}",
@@ -81,14 +106,14 @@ impl fmt::Display for BitcoinMsgDecodeError {
 
 impl std::error::Error for BitcoinMsgDecodeError {}
 
-impl From<shared::bitcoin::consensus::encode::Error> for BitcoinMsgDecodeError {
-    fn from(e: shared::bitcoin::consensus::encode::Error) -> Self {
-        BitcoinMsgDecodeError::DecodeError(e)
-    }
-}
-
 impl From<std::array::TryFromSliceError> for BitcoinMsgDecodeError {
     fn from(e: std::array::TryFromSliceError) -> Self {
         BitcoinMsgDecodeError::InvalidLengthBytes(e)
     }
 }
+
+impl From<LabelError> for RuntimeError {
+    fn from(e: LabelError) -> Self {
+        RuntimeError::Labels(e)
+    }
+}
@@ -1,29 +1,38 @@
 use shared::{
-    async_nats,
+    args::{CommonArgs, ExtractorArgs},
     bitcoin::{
         Network as BitcoinNetwork,
         consensus::{Decodable, Encodable},
+        hashes::Hash,
         io::Cursor as BitcoinCursor,
         p2p::{
             ServiceFlags, address,
             message::{self, NetworkMessage, RawNetworkMessage},
-            message_network,
+            message_blockdata::{GetBlocksMessage, GetHeadersMessage, Inventory},
+            message_compact_blocks, message_network,
         },
     },
     clap::{self, Parser, ValueEnum},
+    debug_buffer::{self, DebugBuffer},
+    extractor_metrics::{self, ExtractorMetricsRecorder},
+    healthserver::{self, Health},
+    heartbeat::{self, Heartbeats},
+    labels::Labels,
     log,
+    nats::{self, TappedClient},
     nats_subjects::Subject,
     prost::Message,
     protobuf::{
         bitcoin_primitives,
-        event::{Event, event::PeerObserverEvent},
+        event::{Event, Extractor, event::PeerObserverEvent},
         p2p_extractor,
     },
     rand::{self, Rng},
+    sink::MultiSink,
     tokio::{
         io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader},
         net::{TcpListener, TcpStream, tcp::WriteHalf},
-        sync::watch,
+        sync::{Semaphore, watch},
         time::{self, Duration},
     },
     util,
@@ -31,15 +40,26 @@ use shared::{
 
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
-    time::{SystemTime, UNIX_EPOCH},
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
+mod addr_rate;
 mod error;
+mod parse_error;
+mod service_mismatch;
 
+use addr_rate::AddrRateTracker;
 use error::{BitcoinMsgDecodeError, RuntimeError};
+use parse_error::ParseErrorLimiter;
+use service_mismatch::ServiceMismatchDetector;
 
 const USER_AGENT: &str = "/p2p-extractor:0.1/";
 
+/// Bitcoin Core pings its peers every two minutes, so we receive at least one
+/// message from a connected node in this interval.
+const NODE_PING_INTERVAL: Duration = Duration::from_secs(2 * 60);
+
 /// Enum of all possible networks. These determine the network magic.
 #[derive(Debug, Clone, ValueEnum)]
 pub enum Network {
@@ -81,21 +101,24 @@ impl From<Network> for BitcoinNetwork {
 #[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// Address of the NATS server where the extractor will publish messages to.
-    #[arg(short, long, default_value = "127.0.0.1:4222")]
-    pub nats_address: String,
-
-    /// The log level the extractor should run with. Valid log levels are "trace",
-    /// "debug", "info", "warn", "error". See https://docs.rs/log/latest/log/enum.Level.html.
-    #[arg(short, long, default_value_t = log::Level::Debug)]
-    pub log_level: log::Level,
+    #[command(flatten)]
+    pub common: CommonArgs,
 
     /// Address of the P2P interface the P2P extractor will listen on.
     /// On the Bitcoin node side, the connection needs to be established
     /// with -addnode=<p2p_address>.
-    #[arg(long, default_value = "127.0.0.1:9333")]
+    #[arg(
+        long,
+        visible_alias = "p2p-listen-addr",
+        default_value = "127.0.0.1:9333"
+    )]
     pub p2p_address: String,
 
+    /// Maximum number of concurrent inbound connections. Further connections
+    /// are closed right after accepting them.
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_inbound_connections: u32,
+
     /// Network (P2P) the Bitcoin node is on. This determines the network magic.
     /// The network magic of the p2p-extractor and the Bitcoin node must match.
     #[arg(long, default_value_t = Network::Mainnet)]
@@ -116,6 +139,12 @@ pub struct Args {
     #[arg(long, default_value_t = false)]
     pub disable_addrv2: bool,
 
+    /// Publish an alert event when the node announces more than this many
+    /// addresses per minute to the p2p_extractor (in addr and addrv2 messages).
+    /// Address announcement spam is a known DoS vector. Disabled by default.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub addr_rate_alert_threshold: Option<u64>,
+
     /// The p2p_extractor publishes events for invs the node annouces to us.
     /// This allows disabling the inv annoucement events.
     #[arg(long, default_value_t = false)]
@@ -125,6 +154,64 @@ pub struct Args {
     /// This allows disabling the feefilter annoucement events.
     #[arg(long, default_value_t = false)]
     pub disable_feefilter: bool,
+
+    /// The p2p_extractor publishes which of the sendaddrv2, wtxidrelay, and sendheaders
+    /// messages the node sent us. This allows disabling the feature negotiation events.
+    #[arg(long, default_value_t = false)]
+    pub disable_feature_negotiation: bool,
+
+    /// The p2p_extractor publishes events for getheaders and getblocks messages the node
+    /// sends us. This allows disabling the header request events.
+    #[arg(long, default_value_t = false)]
+    pub disable_header_requests: bool,
+
+    /// The p2p_extractor publishes events for messages from the node it can't parse
+    /// (malformed messages or unknown commands). This allows disabling the parse error events.
+    #[arg(long, default_value_t = false)]
+    pub disable_parse_errors: bool,
+
+    /// Maximum number of parse error events published per connection and minute.
+    /// Further parse errors in the minute are only counted.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub parse_error_rate_limit: u64,
+
+    /// The p2p_extractor publishes events for messages from the node that contradict the
+    /// protocol version or services the node advertised in its version message. This allows
+    /// disabling the service mismatch events.
+    #[arg(long, default_value_t = false)]
+    pub disable_service_mismatches: bool,
+
+    /// The p2p_extractor publishes events for sendcmpct and cmpctblock messages the node sends us.
+    /// This allows disabling the compact block (BIP152) events.
+    #[arg(long, default_value_t = false)]
+    pub disable_compactblocks: bool,
+
+    /// The p2p_extractor requests announced transactions from the node and publishes events
+    /// for the tx messages the node sends us. This allows disabling the transaction relay events.
+    #[arg(long, default_value_t = false)]
+    pub disable_tx: bool,
+
+    /// Include the full serialized transaction in the transaction relay events. This
+    /// significantly increases the size of the events and the load on the NATS server.
+    #[arg(long, default_value_t = false)]
+    pub include_raw_tx: bool,
+
+    /// The p2p_extractor requests announced blocks from the node and publishes events
+    /// for the block messages the node sends us. This allows disabling the block relay events.
+    #[arg(long, default_value_t = false)]
+    pub disable_blocks: bool,
+
+    /// Include the full serialized block in the block relay events. This
+    /// significantly increases the size of the events and the load on the NATS server.
+    #[arg(long, default_value_t = false)]
+    pub include_raw_block: bool,
+
+    /// Address of an optional HTTP health endpoint for liveness and readiness
+    /// probes, e.g. 127.0.0.1:8080. It answers with 200 if the extractor is
+    /// connected to NATS and recently received a message from the node, and
+    /// with 503 otherwise.
+    #[arg(long)]
+    pub health_addr: Option<String>,
 }
 
 impl Args {
@@ -139,22 +226,43 @@ impl Args {
         disable_addrv2: bool,
         disable_invs: bool,
         disable_feefilter: bool,
+        disable_compactblocks: bool,
+        disable_tx: bool,
+        disable_blocks: bool,
     ) -> Args {
         Self {
-            nats_address,
-            log_level,
+            common: CommonArgs::new(nats_address, log_level),
             p2p_address,
+            max_inbound_connections: 8,
             p2p_network,
             ping_interval,
             disable_ping,
             disable_addrv2,
+            addr_rate_alert_threshold: None,
             disable_invs,
             disable_feefilter,
+            disable_feature_negotiation: false,
+            disable_header_requests: false,
+            disable_parse_errors: false,
+            parse_error_rate_limit: 10,
+            disable_service_mismatches: false,
+            disable_compactblocks,
+            disable_tx,
+            include_raw_tx: false,
+            disable_blocks,
+            include_raw_block: false,
+            health_addr: None,
             // when adding more disable_* args, make sure to update the disable_all below
         }
     }
 }
 
+impl ExtractorArgs for Args {
+    fn common(&self) -> &CommonArgs {
+        &self.common
+    }
+}
+
 pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(), RuntimeError> {
     log::info!("Using network magic for: {}", args.p2p_network);
     let network: BitcoinNetwork = args.p2p_network.clone().into();
@@ -163,35 +271,146 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
         log::info!("Ping measurements interval: {}s", args.ping_interval);
     }
     log::info!("Addrv2 events enabled: {}", !args.disable_addrv2);
+    if let Some(threshold) = args.addr_rate_alert_threshold {
+        log::info!("Addr rate alert threshold: {} addresses/min", threshold);
+    }
     log::info!("Invs events enabled: {}", !args.disable_invs);
+    log::info!("Feefilter events enabled: {}", !args.disable_feefilter);
+    log::info!(
+        "Feature negotiation events enabled: {}",
+        !args.disable_feature_negotiation
+    );
+    log::info!(
+        "Header request events enabled: {}",
+        !args.disable_header_requests
+    );
+    log::info!("Parse error events enabled: {}", !args.disable_parse_errors);
+    if !args.disable_parse_errors {
+        log::info!(
+            "Parse error events rate limit: {}/min per connection",
+            args.parse_error_rate_limit
+        );
+    }
+    log::info!(
+        "Service mismatch events enabled: {}",
+        !args.disable_service_mismatches
+    );
+    log::info!(
+        "Compact block events enabled: {}",
+        !args.disable_compactblocks
+    );
+    log::info!("Transaction relay events enabled: {}", !args.disable_tx);
+    if !args.disable_tx {
+        log::info!(
+            "Including raw transactions in transaction relay events: {}",
+            args.include_raw_tx
+        );
+    }
+    log::info!("Block relay events enabled: {}", !args.disable_blocks);
+    if !args.disable_blocks {
+        log::info!(
+            "Including raw blocks in block relay events: {}",
+            args.include_raw_block
+        );
+    }
     // check if at least one P2P measurement is enabled
-    let disable_all = args.disable_ping && args.disable_addrv2 && args.disable_invs;
+    let disable_all = args.disable_ping
+        && args.disable_addrv2
+        && args.disable_invs
+        && args.disable_feefilter
+        && args.disable_feature_negotiation
+        && args.disable_header_requests
+        && args.disable_parse_errors
+        && args.disable_service_mismatches
+        && args.disable_compactblocks
+        && args.disable_tx
+        && args.disable_blocks;
     if disable_all {
         log::warn!("No P2P measurement enabled!");
     }
 
-    log::debug!("Connecting to NATS server at {}..", args.nats_address);
-    let nats_client = async_nats::connect(&args.nats_address).await?;
-    log::info!("Connected to NATS server at {}", &args.nats_address);
+    let labels = args.common.labels()?;
+    if !labels.is_empty() {
+        log::info!("Attaching labels to all events: {:?}", labels);
+    }
+
+    let health = Health::new(health_max_tick_age(&args));
+    if let Some(address) = &args.health_addr {
+        healthserver::start(address, health.clone())?;
+    }
+
+    log::debug!(
+        "Connecting to NATS server at {}..",
+        args.common.nats_address
+    );
+    let nats_client = nats::connect(&args.common.nats_address).await?;
+    log::info!("Connected to NATS server at {}", &args.common.nats_address);
+    health.set_nats_client(nats_client.clone());
+    let nats_client = TappedClient::new(
+        nats_client,
+        DebugBuffer::new(args.common.debug_buffer_size),
+        MultiSink::open(&args.common.outputs)?,
+    )
+    .with_max_payload(args.common.max_nats_payload)
+    .with_subject_prefix(args.common.nats_subject_prefix.clone());
+    debug_buffer::serve(&nats_client, &Subject::P2PExtractor.to_string()).await?;
 
     log::debug!("Starting TCP listener on {}..", args.p2p_address);
     let listener = TcpListener::bind(args.p2p_address.clone()).await?;
     let local_addr = listener.local_addr()?;
-    log::info!("P2P-extractor listening on {}", local_addr);
+    log::info!(
+        "P2P-extractor listening on {} for up to {} inbound connections",
+        local_addr,
+        args.max_inbound_connections
+    );
+    let connection_slots = Arc::new(Semaphore::new(args.max_inbound_connections as usize));
+
+    let extractor_metrics = ExtractorMetricsRecorder::new(Extractor::P2pExtractor);
+    let metrics_window = args.common.extractor_metrics_window();
+    let mut metrics_interval = extractor_metrics::interval(metrics_window);
+    let mut heartbeats = args
+        .common
+        .heartbeat_interval()
+        .map(|interval| Heartbeats::new(Extractor::P2pExtractor, interval));
+    let mut heartbeat_interval = heartbeat::interval(args.common.heartbeat_interval());
 
     loop {
         shared::tokio::select! {
             res = listener.accept() => {
                 if let Ok(connection) = res {
                     let (socket, addr) = connection;
+                    let Ok(slot) = connection_slots.clone().try_acquire_owned() else {
+                        log::warn!(
+                            "Closing the connection from {}: already {} inbound connections (--max-inbound-connections)",
+                            addr,
+                            args.max_inbound_connections
+                        );
+                        extractor_metrics.inc("connections_rejected");
+                        continue;
+                    };
                     log::info!("accepted a new connection from: {}", addr);
+                    extractor_metrics.inc("connections_accepted");
                     let nats_client_clone = nats_client.clone();
-                    shared::tokio::task::spawn(handle_connection(socket, network, args.clone(), nats_client_clone));
+                    let connection = handle_connection(socket, network, args.clone(), nats_client_clone, labels.clone(), health.clone());
+                    shared::tokio::task::spawn(async move {
+                        connection.await;
+                        // the slot is freed once the connection is closed
+                        drop(slot);
+                    });
 
                 } else {
                     log::warn!("Could not accept connection on socket: {:?}", res);
+                    extractor_metrics.inc("accept_errors");
                 }
             },
+            _ = metrics_interval.tick(), if metrics_window.is_some() => {
+                extractor_metrics.publish(&nats_client, &labels, metrics_interval.period()).await;
+            }
+            _ = heartbeat_interval.tick(), if heartbeats.is_some() => {
+                if let Some(heartbeats) = &mut heartbeats {
+                    heartbeats.publish(&nats_client, &labels).await;
+                }
+            }
             res = shutdown_rx.changed() => {
                 match res {
                     Ok(_) => {
@@ -212,11 +431,24 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
     Ok(())
 }
 
+/// The extractor is reported unhealthy if it didn't receive a message from the
+/// node for this long: three of our ping intervals, but at most two of the
+/// node's.
+fn health_max_tick_age(args: &Args) -> Duration {
+    let max_tick_age = NODE_PING_INTERVAL * 2;
+    if args.disable_ping {
+        return max_tick_age;
+    }
+    max_tick_age.min(Duration::from_secs(args.ping_interval * 3))
+}
+
 async fn handle_connection(
     mut stream: TcpStream,
     network: BitcoinNetwork,
     args: Args,
-    nats_client: async_nats::Client,
+    nats_client: TappedClient,
+    labels: Labels,
+    health: Health,
 ) {
     let addr: &str = match stream.peer_addr() {
         Ok(addr) => &addr.to_string(),
@@ -228,7 +460,14 @@ async fn handle_connection(
     let (read_half, mut write_half) = stream.split();
     let mut reader = BufReader::new(read_half);
     let mut ping_interval = time::interval(Duration::from_secs(args.ping_interval));
+    let mut version_received = false;
     let mut verack_done = false;
+    let mut addr_rate = args.addr_rate_alert_threshold.map(AddrRateTracker::new);
+    let mut parse_errors =
+        (!args.disable_parse_errors).then(|| ParseErrorLimiter::new(args.parse_error_rate_limit));
+    let mut features = p2p_extractor::FeatureNegotiation::default();
+    // set once the node's version message is received
+    let mut service_mismatches: Option<ServiceMismatchDetector> = None;
 
     async fn send_message(
         msg: message::NetworkMessage,
@@ -259,19 +498,67 @@ async fn handle_connection(
             result = read_and_decode_message(&mut reader, network, addr) => {
                 match result {
                     Ok(raw_msg) => {
+                        health.tick();
                         log::trace!(target: addr, "received message: {:?}", raw_msg.payload());
+                        if let Some(mismatch) = service_mismatches.as_mut().and_then(|d| d.check(raw_msg.payload())) {
+                            log::warn!(target: addr, "node contradicts its version message: {}", mismatch);
+                            publish_service_mismatch_event(mismatch, &nats_client, &labels).await;
+                        }
                         match raw_msg.payload() {
-                            NetworkMessage::Version(_) => {
+                            NetworkMessage::Version(_) if version_received => {
+                                log::warn!(target: addr, "ignoring a duplicate version message");
+                            }
+                            NetworkMessage::Version(version) => {
+                                version_received = true;
+                                if !args.disable_service_mismatches {
+                                    service_mismatches = Some(ServiceMismatchDetector::new(addr, version));
+                                }
                                 send_message(build_version_message(), network, &mut write_half, addr).await;
                                 // indicate support for addrv2 during version handshake
                                 send_message(NetworkMessage::SendAddrV2, network, &mut write_half, addr).await;
                                 // indicate that we want to receive wtxids in invs (see BIP339)
                                 send_message(NetworkMessage::WtxidRelay, network, &mut write_half, addr).await;
+                                // the feature negotiation messages must be sent before the verack
+                                send_message(NetworkMessage::Verack, network, &mut write_half, addr).await;
+                            }
+                            NetworkMessage::Verack if verack_done => {
+                                log::warn!(target: addr, "ignoring a duplicate verack message");
                             }
                             NetworkMessage::Verack => {
-                                send_message(NetworkMessage::Verack, network, &mut write_half, addr).await;
+                                if !args.disable_feature_negotiation {
+                                    publish_feature_negotiation_event(features.clone(), &nats_client, &labels).await;
+                                }
+                                if !args.disable_compactblocks {
+                                    // request high-bandwidth compact block relay (see BIP152) so the
+                                    // node announces new blocks to us with cmpctblock messages
+                                    send_message(
+                                        NetworkMessage::SendCmpct(message_compact_blocks::SendCmpct {
+                                            send_compact: true,
+                                            version: 2,
+                                        }),
+                                        network,
+                                        &mut write_half,
+                                        addr,
+                                    ).await;
+                                }
                                 verack_done = true;
                             }
+                            NetworkMessage::SendAddrV2 => {
+                                log::debug!(target: addr, "received sendaddrv2");
+                                features.send_addr_v2 = true;
+                            }
+                            NetworkMessage::WtxidRelay => {
+                                log::debug!(target: addr, "received wtxidrelay");
+                                features.wtxid_relay = true;
+                            }
+                            NetworkMessage::SendHeaders => {
+                                log::debug!(target: addr, "received sendheaders");
+                                features.send_headers = true;
+                                // the node usually sends sendheaders after the handshake
+                                if verack_done && !args.disable_feature_negotiation {
+                                    publish_feature_negotiation_event(features.clone(), &nats_client, &labels).await;
+                                }
+                            }
                             NetworkMessage::Ping(nonce) => {
                                 send_message(NetworkMessage::Pong(*nonce), network, &mut write_half, addr).await;
                             }
@@ -283,7 +570,7 @@ async fn handle_connection(
                                     .as_nanos() as u64;
                                 let duration = now - nonce;
                                 log::debug!(target: addr, "processing the ping message took: {}ns", now - nonce);
-                                publish_ping_measurement_event(duration, &nats_client).await;
+                                publish_ping_measurement_event(duration, &nats_client, &labels).await;
                             }
                             NetworkMessage::AddrV2(addrs) => {
                                 log::debug!(target: addr, "received addrv2: {:?}", addrs);
@@ -291,7 +578,16 @@ async fn handle_connection(
                                     .iter()
                                     .map(|addr_entry| addr_entry.clone().into())
                                     .collect();
-                                publish_addr_announcement_event(addresses, &nats_client).await;
+                                publish_addr_announcement_event(addresses, &nats_client, &labels).await;
+                                if let Some(alert) = check_addr_rate(&mut addr_rate, addrs.len(), addr) {
+                                    publish_addr_rate_alert_event(alert, &nats_client, &labels).await;
+                                }
+                            }
+                            NetworkMessage::Addr(addrs) => {
+                                log::debug!(target: addr, "received addr: {:?}", addrs);
+                                if let Some(alert) = check_addr_rate(&mut addr_rate, addrs.len(), addr) {
+                                    publish_addr_rate_alert_event(alert, &nats_client, &labels).await;
+                                }
                             }
                             NetworkMessage::Inv(inventory) => {
                                 log::debug!(target: addr, "received inv: {:?}", inventory);
@@ -300,13 +596,138 @@ async fn handle_connection(
                                         .iter()
                                         .map(|i| (*i).into())
                                         .collect();
-                                    publish_inventory_announcement_event(items, &nats_client).await;
+                                    publish_inventory_announcement_event(items, &nats_client, &labels).await;
+                                }
+                                if !args.disable_tx {
+                                    // request the announced transactions to receive tx messages
+                                    let tx_requests: Vec<Inventory> = inventory
+                                        .iter()
+                                        .filter(|i| matches!(i, Inventory::Transaction(_) | Inventory::WTx(_)))
+                                        .cloned()
+                                        .collect();
+                                    if !tx_requests.is_empty() {
+                                        send_message(NetworkMessage::GetData(tx_requests), network, &mut write_half, addr).await;
+                                    }
+                                }
+                                if !args.disable_blocks {
+                                    // request the announced blocks to receive block messages
+                                    let block_requests: Vec<Inventory> = inventory
+                                        .iter()
+                                        .filter_map(|i| match i {
+                                            Inventory::Block(hash) | Inventory::WitnessBlock(hash) | Inventory::CompactBlock(hash) => Some(Inventory::WitnessBlock(*hash)),
+                                            _ => None,
+                                        })
+                                        .collect();
+                                    if !block_requests.is_empty() {
+                                        send_message(NetworkMessage::GetData(block_requests), network, &mut write_half, addr).await;
+                                    }
+                                }
+                            }
+                            NetworkMessage::Tx(tx) => {
+                                log::debug!(target: addr, "received tx: {}", tx.compute_txid());
+                                if !args.disable_tx {
+                                    let raw_tx = shared::bitcoin::consensus::serialize(tx);
+                                    publish_transaction_relay_event(
+                                        p2p_extractor::TransactionRelay {
+                                            txid: tx.compute_txid().as_byte_array().to_vec(),
+                                            wtxid: tx.compute_wtxid().as_byte_array().to_vec(),
+                                            size: raw_tx.len() as u32,
+                                            raw_tx: args.include_raw_tx.then_some(raw_tx),
+                                        },
+                                        &nats_client,
+                                        &labels,
+                                    ).await;
                                 }
                             }
                             NetworkMessage::FeeFilter(feefilter) => {
                                 log::debug!(target: addr, "received feefilter: {}", feefilter);
                                 if !args.disable_feefilter {
-                                    publish_feefilter_announcement_event(*feefilter, &nats_client).await;
+                                    publish_feefilter_announcement_event(*feefilter, &nats_client, &labels).await;
+                                }
+                            }
+                            NetworkMessage::SendCmpct(sendcmpct) => {
+                                log::debug!(target: addr, "received sendcmpct: {:?}", sendcmpct);
+                                if !args.disable_compactblocks {
+                                    publish_sendcmpct_event(
+                                        p2p_extractor::SendCompact {
+                                            high_bandwidth: sendcmpct.send_compact,
+                                            version: sendcmpct.version,
+                                        },
+                                        &nats_client,
+                                        &labels,
+                                    ).await;
+                                }
+                            }
+                            NetworkMessage::CmpctBlock(cmpctblock) => {
+                                log::debug!(target: addr, "received cmpctblock: {}", cmpctblock.compact_block.header.block_hash());
+                                if !args.disable_compactblocks {
+                                    let compact_block = &cmpctblock.compact_block;
+                                    publish_compact_block_announcement_event(
+                                        p2p_extractor::CompactBlockAnnouncement {
+                                            block_hash: compact_block.header.block_hash().as_byte_array().to_vec(),
+                                            short_ids: compact_block.short_ids.len() as u32,
+                                            prefilled_transactions: compact_block.prefilled_txs.len() as u32,
+                                        },
+                                        &nats_client,
+                                        &labels,
+                                    ).await;
+                                }
+                                if !args.disable_blocks {
+                                    // request the full block to receive a block message
+                                    let hash = cmpctblock.compact_block.header.block_hash();
+                                    send_message(NetworkMessage::GetData(vec![Inventory::WitnessBlock(hash)]), network, &mut write_half, addr).await;
+                                }
+                            }
+                            NetworkMessage::Block(block) => {
+                                log::debug!(target: addr, "received block: {}", block.block_hash());
+                                if !args.disable_blocks {
+                                    let raw_block = shared::bitcoin::consensus::serialize(block);
+                                    publish_block_relay_event(
+                                        p2p_extractor::BlockRelay {
+                                            header: block.header.into(),
+                                            transactions: block.txdata.len() as u32,
+                                            size: raw_block.len() as u32,
+                                            raw_block: args.include_raw_block.then_some(raw_block),
+                                        },
+                                        &nats_client,
+                                        &labels,
+                                    ).await;
+                                }
+                            }
+                            NetworkMessage::GetHeaders(GetHeadersMessage { version, locator_hashes, stop_hash }) => {
+                                log::debug!(target: addr, "received getheaders with {} locator hashes", locator_hashes.len());
+                                if !args.disable_header_requests {
+                                    publish_header_request_event(
+                                        p2p_extractor::HeaderRequest::new(
+                                            p2p_extractor::HeaderRequestCommand::Getheaders,
+                                            *version,
+                                            locator_hashes,
+                                            *stop_hash,
+                                        ),
+                                        &nats_client,
+                                        &labels,
+                                    ).await;
+                                }
+                            }
+                            NetworkMessage::GetBlocks(GetBlocksMessage { version, locator_hashes, stop_hash }) => {
+                                log::debug!(target: addr, "received getblocks with {} locator hashes", locator_hashes.len());
+                                if !args.disable_header_requests {
+                                    publish_header_request_event(
+                                        p2p_extractor::HeaderRequest::new(
+                                            p2p_extractor::HeaderRequestCommand::Getblocks,
+                                            *version,
+                                            locator_hashes,
+                                            *stop_hash,
+                                        ),
+                                        &nats_client,
+                                        &labels,
+                                    ).await;
+                                }
+                            }
+                            NetworkMessage::Unknown { command, payload } => {
+                                log::debug!(target: addr, "received unknown message: {}", command);
+                                if let Some(parse_error) = check_parse_error(&mut parse_errors, addr, command.to_string(), payload.len() as u32, "unknown command".to_string()) {
+                                    publish_parse_error_event(parse_error, &nats_client, &labels).await;
                                 }
                             }
                             NetworkMessage::Alert(_) => {
@@ -318,6 +739,13 @@ async fn handle_connection(
                             }
                         }
                     }
+                    Err(BitcoinMsgDecodeError::DecodeError { command, length, error }) => {
+                        // the whole message was read, so the next one can still be parsed
+                        log::warn!(target: addr, "could not parse '{}' message ({} bytes): {}", command, length, error);
+                        if let Some(parse_error) = check_parse_error(&mut parse_errors, addr, command, length, error.to_string()) {
+                            publish_parse_error_event(parse_error, &nats_client, &labels).await;
+                        }
+                    }
                     Err(e) => {
                         log::warn!(target: addr, "error decoding message: {}", e);
                         break;
@@ -332,13 +760,15 @@ async fn handle_connection(
 
 async fn publish_addr_announcement_event(
     addresses: Vec<bitcoin_primitives::Address>,
-    nats_client: &async_nats::Client,
+    nats_client: &TappedClient,
+    labels: &Labels,
 ) {
     let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
         p2p_event: Some(p2p_extractor::p2p::P2pEvent::AddressAnnouncement(
             p2p_extractor::AddressAnnouncement { addresses },
         )),
-    }));
+    }))
+    .map(|event| event.with_labels(labels));
 
     match proto_result {
         Ok(proto) => {
@@ -360,15 +790,209 @@ async fn publish_addr_announcement_event(
     }
 }
 
+/// Records announced addresses with the tracker, if enabled, and returns an
+/// alert if the node exceeded the address rate threshold.
+fn check_addr_rate(
+    tracker: &mut Option<AddrRateTracker>,
+    addresses: usize,
+    addr: &str,
+) -> Option<p2p_extractor::AddrRateAlert> {
+    let tracker = tracker.as_mut()?;
+    let rate = tracker.record(addresses as u64, Instant::now())?;
+    log::warn!(
+        target: addr,
+        "node announced {} addresses in the last minute (threshold: {})",
+        rate,
+        tracker.threshold()
+    );
+    Some(p2p_extractor::AddrRateAlert {
+        peer: addr.to_string(),
+        addresses: rate,
+        threshold: tracker.threshold(),
+    })
+}
+
+/// Records a parse error with the limiter, if enabled, and returns the event
+/// to publish unless the peer exceeded the parse error rate limit.
+fn check_parse_error(
+    limiter: &mut Option<ParseErrorLimiter>,
+    addr: &str,
+    command: String,
+    length: u32,
+    reason: String,
+) -> Option<p2p_extractor::ParseError> {
+    let limiter = limiter.as_mut()?;
+    let suppressed = limiter.record(Instant::now())?;
+    Some(p2p_extractor::ParseError {
+        peer: addr.to_string(),
+        command,
+        length,
+        reason,
+        suppressed,
+    })
+}
+
+async fn publish_parse_error_event(
+    parse_error: p2p_extractor::ParseError,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
+    let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+        p2p_event: Some(p2p_extractor::p2p::P2pEvent::ParseError(parse_error)),
+    }))
+    .map(|event| event.with_labels(labels));
+
+    match proto_result {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(
+                    Subject::P2PExtractor.to_string(),
+                    proto.encode_to_vec().into(),
+                )
+                .await
+            {
+                log::error!("could not publish parse error into NATS: {}", e);
+            } else {
+                log::trace!("published parse error into NATS: {:?}", proto);
+            }
+        }
+        Err(e) => {
+            log::error!("could not create parse error protobuf: {}", e);
+        }
+    }
+}
+
+async fn publish_service_mismatch_event(
+    mismatch: p2p_extractor::ServiceMismatch,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
+    let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+        p2p_event: Some(p2p_extractor::p2p::P2pEvent::ServiceMismatch(mismatch)),
+    }))
+    .map(|event| event.with_labels(labels));
+
+    match proto_result {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(
+                    Subject::P2PExtractor.to_string(),
+                    proto.encode_to_vec().into(),
+                )
+                .await
+            {
+                log::error!("could not publish service mismatch into NATS: {}", e);
+            } else {
+                log::trace!("published service mismatch into NATS: {:?}", proto);
+            }
+        }
+        Err(e) => {
+            log::error!("could not create service mismatch protobuf: {}", e);
+        }
+    }
+}
+
+async fn publish_addr_rate_alert_event(
+    alert: p2p_extractor::AddrRateAlert,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
+    let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+        p2p_event: Some(p2p_extractor::p2p::P2pEvent::AddrRateAlert(alert)),
+    }))
+    .map(|event| event.with_labels(labels));
+
+    match proto_result {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(
+                    Subject::P2PExtractor.to_string(),
+                    proto.encode_to_vec().into(),
+                )
+                .await
+            {
+                log::error!("could not publish addr rate alert into NATS: {}", e);
+            } else {
+                log::trace!("published addr rate alert into NATS: {:?}", proto);
+            }
+        }
+        Err(e) => {
+            log::error!("could not create addr rate alert protobuf: {}", e);
+        }
+    }
+}
+
+async fn publish_feature_negotiation_event(
+    features: p2p_extractor::FeatureNegotiation,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
+    let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+        p2p_event: Some(p2p_extractor::p2p::P2pEvent::FeatureNegotiation(features)),
+    }))
+    .map(|event| event.with_labels(labels));
+
+    match proto_result {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(
+                    Subject::P2PExtractor.to_string(),
+                    proto.encode_to_vec().into(),
+                )
+                .await
+            {
+                log::error!("could not publish feature negotiation into NATS: {}", e);
+            } else {
+                log::trace!("published feature negotiation into NATS: {:?}", proto);
+            }
+        }
+        Err(e) => {
+            log::error!("could not create feature negotiation protobuf: {}", e);
+        }
+    }
+}
+
+async fn publish_header_request_event(
+    request: p2p_extractor::HeaderRequest,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
+    let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+        p2p_event: Some(p2p_extractor::p2p::P2pEvent::HeaderRequest(request)),
+    }))
+    .map(|event| event.with_labels(labels));
+
+    match proto_result {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(
+                    Subject::P2PExtractor.to_string(),
+                    proto.encode_to_vec().into(),
+                )
+                .await
+            {
+                log::error!("could not publish header request into NATS: {}", e);
+            } else {
+                log::trace!("published header request into NATS: {:?}", proto);
+            }
+        }
+        Err(e) => {
+            log::error!("could not create header request protobuf: {}", e);
+        }
+    }
+}
+
 async fn publish_inventory_announcement_event(
     inventory: Vec<bitcoin_primitives::InventoryItem>,
-    nats_client: &async_nats::Client,
+    nats_client: &TappedClient,
+    labels: &Labels,
 ) {
     let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
         p2p_event: Some(p2p_extractor::p2p::P2pEvent::InventoryAnnouncement(
             p2p_extractor::InventoryAnnouncement { inventory },
         )),
-    }));
+    }))
+    .map(|event| event.with_labels(labels));
 
     match proto_result {
         Ok(proto) => {
@@ -390,12 +1014,17 @@ async fn publish_inventory_announcement_event(
     }
 }
 
-async fn publish_feefilter_announcement_event(feefilter: i64, nats_client: &async_nats::Client) {
+async fn publish_feefilter_announcement_event(
+    feefilter: i64,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
     let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
         p2p_event: Some(p2p_extractor::p2p::P2pEvent::FeefilterAnnouncement(
-            feefilter,
+            p2p_extractor::FeefilterAnnouncement::new(feefilter),
         )),
-    }));
+    }))
+    .map(|event| event.with_labels(labels));
 
     match proto_result {
         Ok(proto) => {
@@ -417,12 +1046,148 @@ async fn publish_feefilter_announcement_event(feefilter: i64, nats_client: &asyn
     }
 }
 
-async fn publish_ping_measurement_event(duration: u64, nats_client: &async_nats::Client) {
+async fn publish_sendcmpct_event(
+    sendcmpct: p2p_extractor::SendCompact,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
+    let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+        p2p_event: Some(p2p_extractor::p2p::P2pEvent::SendCompact(sendcmpct)),
+    }))
+    .map(|event| event.with_labels(labels));
+
+    match proto_result {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(
+                    Subject::P2PExtractor.to_string(),
+                    proto.encode_to_vec().into(),
+                )
+                .await
+            {
+                log::error!("could not publish sendcmpct into NATS: {}", e);
+            } else {
+                log::trace!("published sendcmpct into NATS: {:?}", proto);
+            }
+        }
+        Err(e) => {
+            log::error!("could not create sendcmpct protobuf: {}", e);
+        }
+    }
+}
+
+async fn publish_compact_block_announcement_event(
+    announcement: p2p_extractor::CompactBlockAnnouncement,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
+    let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+        p2p_event: Some(p2p_extractor::p2p::P2pEvent::CompactBlockAnnouncement(
+            announcement,
+        )),
+    }))
+    .map(|event| event.with_labels(labels));
+
+    match proto_result {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(
+                    Subject::P2PExtractor.to_string(),
+                    proto.encode_to_vec().into(),
+                )
+                .await
+            {
+                log::error!(
+                    "could not publish compact block announcement into NATS: {}",
+                    e
+                );
+            } else {
+                log::trace!(
+                    "published compact block announcement into NATS: {:?}",
+                    proto
+                );
+            }
+        }
+        Err(e) => {
+            log::error!(
+                "could not create compact block announcement protobuf: {}",
+                e
+            );
+        }
+    }
+}
+
+async fn publish_transaction_relay_event(
+    tx: p2p_extractor::TransactionRelay,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
+    let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+        p2p_event: Some(p2p_extractor::p2p::P2pEvent::TransactionRelay(tx)),
+    }))
+    .map(|event| event.with_labels(labels));
+
+    match proto_result {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(
+                    Subject::P2PExtractor.to_string(),
+                    proto.encode_to_vec().into(),
+                )
+                .await
+            {
+                log::error!("could not publish transaction relay into NATS: {}", e);
+            } else {
+                log::trace!("published transaction relay into NATS: {:?}", proto);
+            }
+        }
+        Err(e) => {
+            log::error!("could not create transaction relay protobuf: {}", e);
+        }
+    }
+}
+
+async fn publish_block_relay_event(
+    block: p2p_extractor::BlockRelay,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
+    let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+        p2p_event: Some(p2p_extractor::p2p::P2pEvent::BlockRelay(block)),
+    }))
+    .map(|event| event.with_labels(labels));
+
+    match proto_result {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(
+                    Subject::P2PExtractor.to_string(),
+                    proto.encode_to_vec().into(),
+                )
+                .await
+            {
+                log::error!("could not publish block relay into NATS: {}", e);
+            } else {
+                log::trace!("published block relay into NATS: {:?}", proto);
+            }
+        }
+        Err(e) => {
+            log::error!("could not create block relay protobuf: {}", e);
+        }
+    }
+}
+
+async fn publish_ping_measurement_event(
+    duration: u64,
+    nats_client: &TappedClient,
+    labels: &Labels,
+) {
     let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
         p2p_event: Some(p2p_extractor::p2p::P2pEvent::PingDuration(
             p2p_extractor::PingDuration { duration },
         )),
-    }));
+    }))
+    .map(|event| event.with_labels(labels));
 
     match proto_result {
         Ok(proto) => {
@@ -487,11 +1252,24 @@ async fn read_and_decode_message<R: AsyncRead + Unpin>(
     full_msg_bytes.extend_from_slice(&payload);
 
     let mut cursor = BitcoinCursor::new(full_msg_bytes);
-    let msg = RawNetworkMessage::consensus_decode(&mut cursor)?;
+    let msg = RawNetworkMessage::consensus_decode(&mut cursor).map_err(|error| {
+        BitcoinMsgDecodeError::DecodeError {
+            command: header_command(&header),
+            length: payload_length,
+            error,
+        }
+    })?;
 
     Ok(msg)
 }
 
+/// The command in a P2P message header, without the NUL padding.
+fn header_command(header: &[u8; 24]) -> String {
+    String::from_utf8_lossy(&header[4..16])
+        .trim_end_matches('\0')
+        .to_string()
+}
+
 fn build_raw_network_message(
     payload: message::NetworkMessage,
     network: BitcoinNetwork,
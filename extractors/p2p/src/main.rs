@@ -1,13 +1,15 @@
 use p2p_extractor::Args;
+use shared::args::ExtractorArgs;
+use shared::config;
 use shared::log;
+use shared::logging;
 use shared::tokio::{self, signal, sync::watch};
-use shared::{clap::Parser, simple_logger};
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
+    let args: Args = config::parse_args();
 
-    if let Err(e) = simple_logger::init_with_level(args.log_level) {
+    if let Err(e) = logging::init(args.common()) {
         eprintln!("p2p extractor error: {}", e);
     }
 
@@ -0,0 +1,67 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The window over which the published parse errors are counted.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits how many parse error events are published for a peer per minute,
+/// so that a peer sending malformed messages can't flood the NATS stream.
+///
+/// Parse errors over the limit are suppressed and only counted. The number of
+/// suppressed errors is reported with the next published one.
+pub struct ParseErrorLimiter {
+    limit: u64,
+    /// When the parse errors in the last minute were published.
+    published: VecDeque<Instant>,
+    suppressed: u64,
+}
+
+impl ParseErrorLimiter {
+    pub fn new(limit: u64) -> Self {
+        ParseErrorLimiter {
+            limit,
+            published: VecDeque::new(),
+            suppressed: 0,
+        }
+    }
+
+    /// Records a parse error at `now`. Returns the number of parse errors
+    /// suppressed since the last published one if this one should be
+    /// published.
+    pub fn record(&mut self, now: Instant) -> Option<u64> {
+        while let Some(at) = self.published.front() {
+            if now.duration_since(*at) < WINDOW {
+                break;
+            }
+            self.published.pop_front();
+        }
+        if self.published.len() as u64 >= self.limit {
+            self.suppressed += 1;
+            return None;
+        }
+        self.published.push_back(now);
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_limit() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut limiter = ParseErrorLimiter::new(2);
+
+        assert_eq!(limiter.record(at(0)), Some(0));
+        assert_eq!(limiter.record(at(10)), Some(0));
+        // over the limit within a minute
+        assert_eq!(limiter.record(at(20)), None);
+        assert_eq!(limiter.record(at(30)), None);
+        // the first one left the window, the suppressed ones are reported
+        assert_eq!(limiter.record(at(60)), Some(2));
+        assert_eq!(limiter.record(at(61)), None);
+        assert_eq!(limiter.record(at(70)), Some(1));
+    }
+}
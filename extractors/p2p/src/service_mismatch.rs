@@ -0,0 +1,227 @@
+use shared::bitcoin::{
+    Block,
+    p2p::{ServiceFlags, message::NetworkMessage, message_network::VersionMessage},
+};
+use shared::protobuf::p2p_extractor::{ServiceMismatch, ServiceMismatchKind};
+use std::collections::HashSet;
+
+/// The protocol versions the feature negotiation messages were introduced
+/// with (see Bitcoin Core's `src/node/protocol_version.h`).
+const SENDHEADERS_VERSION: u32 = 70012;
+const FEEFILTER_VERSION: u32 = 70013;
+const SHORT_IDS_BLOCKS_VERSION: u32 = 70014;
+const WTXID_RELAY_VERSION: u32 = 70016;
+
+/// The start of the witness commitment output of a coinbase: OP_RETURN, a
+/// push of 36 bytes, and the commitment header `aa21a9ed` (see BIP141).
+const WITNESS_COMMITMENT_PREFIX: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// Detects messages from the node that contradict the protocol version and
+/// the services it advertised in its version message.
+///
+/// The detection is conservative to avoid false positives. Only these
+/// mismatches are reported:
+/// - [ServiceMismatchKind::MessageAboveVersion]: the node sent a sendheaders,
+///   feefilter, sendcmpct, or wtxidrelay message, but advertised a protocol
+///   version lower than the one the message was introduced with.
+/// - [ServiceMismatchKind::WitnessStripped]: the node advertised NODE_WITNESS,
+///   but sent a block with a witness commitment and without the witness
+///   reserved value of the coinbase. The p2p-extractor requests blocks with
+///   witnesses, so the node stripped the witness data.
+///
+/// Each mismatch is only reported once per connection and command.
+pub struct ServiceMismatchDetector {
+    peer: String,
+    version: u32,
+    services: ServiceFlags,
+    reported: HashSet<(ServiceMismatchKind, &'static str)>,
+}
+
+impl ServiceMismatchDetector {
+    /// Creates a detector for the version message the node sent.
+    pub fn new(peer: &str, version: &VersionMessage) -> Self {
+        ServiceMismatchDetector {
+            peer: peer.to_string(),
+            version: version.version,
+            services: version.services,
+            reported: HashSet::new(),
+        }
+    }
+
+    /// Returns the mismatch if the message contradicts the advertised
+    /// protocol version or services and wasn't reported before.
+    pub fn check(&mut self, msg: &NetworkMessage) -> Option<ServiceMismatch> {
+        let (kind, command, required_version) = match msg {
+            NetworkMessage::SendHeaders => {
+                self.above_version("sendheaders", SENDHEADERS_VERSION)?
+            }
+            NetworkMessage::FeeFilter(_) => self.above_version("feefilter", FEEFILTER_VERSION)?,
+            NetworkMessage::SendCmpct(_) => {
+                self.above_version("sendcmpct", SHORT_IDS_BLOCKS_VERSION)?
+            }
+            NetworkMessage::WtxidRelay => self.above_version("wtxidrelay", WTXID_RELAY_VERSION)?,
+            NetworkMessage::Block(block)
+                if self.services.has(ServiceFlags::WITNESS) && is_witness_stripped(block) =>
+            {
+                (ServiceMismatchKind::WitnessStripped, "block", None)
+            }
+            _ => return None,
+        };
+        if !self.reported.insert((kind, command)) {
+            return None;
+        }
+        Some(ServiceMismatch {
+            peer: self.peer.clone(),
+            kind: kind.into(),
+            command: command.to_string(),
+            version: self.version,
+            services: self.services.to_u64(),
+            required_version,
+        })
+    }
+
+    fn above_version(
+        &self,
+        command: &'static str,
+        required_version: u32,
+    ) -> Option<(ServiceMismatchKind, &'static str, Option<u32>)> {
+        (self.version < required_version).then_some((
+            ServiceMismatchKind::MessageAboveVersion,
+            command,
+            Some(required_version),
+        ))
+    }
+}
+
+/// True if the block commits to witnesses, but the coinbase lacks the
+/// witness reserved value. Such a block can only be valid with the witness
+/// data, so it was sent without it.
+fn is_witness_stripped(block: &Block) -> bool {
+    let Some(coinbase) = block.txdata.first() else {
+        return false;
+    };
+    let has_commitment = coinbase.output.iter().any(|output| {
+        output
+            .script_pubkey
+            .as_bytes()
+            .starts_with(&WITNESS_COMMITMENT_PREFIX)
+    });
+    has_commitment && coinbase.input.iter().all(|input| input.witness.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::bitcoin::{
+        Amount, BlockHash, CompactTarget, ScriptBuf, Transaction, TxIn, TxMerkleNode, TxOut,
+        Witness, absolute::LockTime, block, hashes::Hash, p2p::message_network, transaction,
+    };
+
+    fn version(version: u32, services: ServiceFlags) -> VersionMessage {
+        let address = shared::bitcoin::p2p::Address {
+            services: ServiceFlags::NONE,
+            address: [0; 8],
+            port: 0,
+        };
+        message_network::VersionMessage {
+            version,
+            services,
+            timestamp: 0,
+            receiver: address.clone(),
+            sender: address,
+            nonce: 0,
+            user_agent: "/test/".to_string(),
+            start_height: 0,
+            relay: true,
+        }
+    }
+
+    fn block(commitment: bool, witness: bool) -> Block {
+        let mut script = WITNESS_COMMITMENT_PREFIX.to_vec();
+        script.extend([0; 32]);
+        let coinbase = Transaction {
+            version: transaction::Version::ONE,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                witness: if witness {
+                    Witness::from_slice(&[[0u8; 32]])
+                } else {
+                    Witness::new()
+                },
+                ..Default::default()
+            }],
+            output: commitment
+                .then(|| TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: ScriptBuf::from_bytes(script),
+                })
+                .into_iter()
+                .collect(),
+        };
+        Block {
+            header: block::Header {
+                version: block::Version::ONE,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: 0,
+                bits: CompactTarget::from_consensus(0),
+                nonce: 0,
+            },
+            txdata: vec![coinbase],
+        }
+    }
+
+    #[test]
+    fn test_message_above_version() {
+        let mut detector =
+            ServiceMismatchDetector::new("127.0.0.1:8333", &version(70013, ServiceFlags::NETWORK));
+
+        // feefilter was introduced with 70013
+        assert_eq!(detector.check(&NetworkMessage::FeeFilter(1000)), None);
+        assert_eq!(detector.check(&NetworkMessage::SendHeaders), None);
+
+        let mismatch = detector.check(&NetworkMessage::WtxidRelay).unwrap();
+        assert_eq!(mismatch.kind(), ServiceMismatchKind::MessageAboveVersion);
+        assert_eq!(mismatch.command, "wtxidrelay");
+        assert_eq!(mismatch.version, 70013);
+        assert_eq!(mismatch.services, ServiceFlags::NETWORK.to_u64());
+        assert_eq!(mismatch.required_version, Some(WTXID_RELAY_VERSION));
+        // only reported once per connection
+        assert_eq!(detector.check(&NetworkMessage::WtxidRelay), None);
+    }
+
+    #[test]
+    fn test_witness_stripped() {
+        let witness = ServiceFlags::NETWORK | ServiceFlags::WITNESS;
+        let mut detector = ServiceMismatchDetector::new("127.0.0.1:8333", &version(70016, witness));
+
+        // with witness data, and blocks without a witness commitment
+        assert_eq!(
+            detector.check(&NetworkMessage::Block(block(true, true))),
+            None
+        );
+        assert_eq!(
+            detector.check(&NetworkMessage::Block(block(false, false))),
+            None
+        );
+
+        let mismatch = detector
+            .check(&NetworkMessage::Block(block(true, false)))
+            .unwrap();
+        assert_eq!(mismatch.kind(), ServiceMismatchKind::WitnessStripped);
+        assert_eq!(mismatch.command, "block");
+        assert_eq!(mismatch.required_version, None);
+        assert_eq!(
+            detector.check(&NetworkMessage::Block(block(true, false))),
+            None
+        );
+
+        // nodes not advertising NODE_WITNESS don't promise witness data
+        let mut detector =
+            ServiceMismatchDetector::new("127.0.0.1:8333", &version(70016, ServiceFlags::NETWORK));
+        assert_eq!(
+            detector.check(&NetworkMessage::Block(block(true, false))),
+            None
+        );
+    }
+}
@@ -12,7 +12,8 @@ use shared::{
         bitcoin_primitives::inventory_item::Item,
         event::{Event, event::PeerObserverEvent},
         p2p_extractor::p2p::P2pEvent::{
-            AddressAnnouncement, FeefilterAnnouncement, InventoryAnnouncement, PingDuration,
+            AddressAnnouncement, BlockRelay, FeatureNegotiation, FeefilterAnnouncement,
+            InventoryAnnouncement, PingDuration, SendCompact, TransactionRelay,
         },
     },
     rand::{self, Rng},
@@ -68,6 +69,9 @@ fn make_test_args(
     disable_addrv2: bool,
     disable_invs: bool,
     disable_feefilter: bool,
+    disable_compactblocks: bool,
+    disable_tx: bool,
+    disable_blocks: bool,
 ) -> Args {
     Args::new(
         format!("127.0.0.1:{}", nats_port),
@@ -79,6 +83,9 @@ fn make_test_args(
         disable_addrv2,
         disable_invs,
         disable_feefilter,
+        disable_compactblocks,
+        disable_tx,
+        disable_blocks,
     )
 }
 
@@ -121,6 +128,9 @@ async fn check(
     disable_addrv2: bool,
     disable_invs: bool,
     disable_feefilter: bool,
+    disable_compactblocks: bool,
+    disable_tx: bool,
+    disable_blocks: bool,
     test_setup: fn(&corepc_node::Node),
     check_expected: fn(PeerObserverEvent) -> bool,
 ) {
@@ -136,6 +146,9 @@ async fn check(
             disable_addrv2,
             disable_invs,
             disable_feefilter,
+            disable_compactblocks,
+            disable_tx,
+            disable_blocks,
         );
         p2p_extractor::run(args, shutdown_rx.clone())
             .await
@@ -208,6 +221,9 @@ async fn test_integration_p2pextractor_ping_measurements() {
         true,
         true,
         true,
+        true,
+        true,
+        true,
         |_| (),
         |event| {
             match event {
@@ -243,6 +259,9 @@ async fn test_integration_p2pextractor_addr_annoucement() {
         false,
         true,
         true,
+        true,
+        true,
+        true,
         |node| {
             // To self-announce our address, we need to be out ouf initial block download
             // Mine a block to get out of initial block download
@@ -307,6 +326,9 @@ async fn test_integration_p2pextractor_inv_annoucement() {
         true,
         false,
         true,
+        true,
+        true,
+        true,
         |node| {
             let address = node
                 .client
@@ -362,6 +384,9 @@ async fn test_integration_p2pextractor_feefilter_annoucement() {
         true,
         true,
         false,
+        true,
+        true,
+        true,
         |_node| {
             // No setup required as the node should automatically send a
             // feefilter message to us right after connecting.
@@ -387,6 +412,276 @@ async fn test_integration_p2pextractor_feefilter_annoucement() {
     .await;
 }
 
+#[tokio::test]
+async fn test_integration_p2pextractor_sendcmpct() {
+    println!("test that we receive SendCompact P2P-extractor events");
+
+    check(
+        true,
+        true,
+        true,
+        true,
+        false,
+        true,
+        true,
+        |_node| {
+            // No setup required as the node should automatically send a
+            // sendcmpct message to us right after connecting.
+        },
+        |event| {
+            match event {
+                PeerObserverEvent::P2pExtractor(p) => {
+                    if let Some(ref e) = p.p2p_event {
+                        match e {
+                            SendCompact(sendcmpct) => {
+                                log::info!("{}", sendcmpct);
+                                assert_eq!(sendcmpct.version, 2);
+                                return true;
+                            }
+                            _ => log::info!("unhandled P2P extractor event {:?}", p.p2p_event),
+                        }
+                    }
+                }
+                _ => panic!("unexpected event {:?}", event),
+            }
+            return false;
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_p2pextractor_feature_negotiation() {
+    println!("test that we receive FeatureNegotiation P2P-extractor events");
+
+    check(
+        true,
+        true,
+        true,
+        true,
+        true,
+        true,
+        true,
+        |_node| {
+            // No setup required as the node sends sendaddrv2 and wtxidrelay
+            // during the handshake.
+        },
+        |event| {
+            match event {
+                PeerObserverEvent::P2pExtractor(p) => {
+                    if let Some(ref e) = p.p2p_event {
+                        match e {
+                            FeatureNegotiation(features) => {
+                                log::info!("{}", features);
+                                assert!(features.send_addr_v2);
+                                assert!(features.wtxid_relay);
+                                return true;
+                            }
+                            _ => log::info!("unhandled P2P extractor event {:?}", p.p2p_event),
+                        }
+                    }
+                }
+                _ => panic!("unexpected event {:?}", event),
+            }
+            return false;
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_p2pextractor_tx_relay() {
+    println!("test that we receive TransactionRelay P2P-extractor events");
+
+    check(
+        true,
+        true,
+        true,
+        true,
+        true,
+        false,
+        true,
+        |node| {
+            let address = node
+                .client
+                .get_new_address(None, None)
+                .unwrap()
+                .address()
+                .unwrap()
+                .require_network(bitcoin::Network::Regtest)
+                .unwrap();
+            node.client.generate_to_address(110, &address).unwrap();
+            node.client
+                .send_to_address(&address, Amount::from_sat(10000))
+                .unwrap();
+        },
+        |event| {
+            match event {
+                PeerObserverEvent::P2pExtractor(p) => {
+                    if let Some(ref e) = p.p2p_event {
+                        match e {
+                            TransactionRelay(tx) => {
+                                log::info!("{}", tx);
+                                assert_eq!(tx.txid.len(), 32);
+                                assert_eq!(tx.wtxid.len(), 32);
+                                assert!(tx.size > 0);
+                                // raw transactions aren't included by default
+                                assert!(tx.raw_tx.is_none());
+                                return true;
+                            }
+                            _ => log::info!("unhandled P2P extractor event {:?}", p.p2p_event),
+                        }
+                    }
+                }
+                _ => panic!("unexpected event {:?}", event),
+            }
+            return false;
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_p2pextractor_block_relay() {
+    println!("test that we receive BlockRelay P2P-extractor events");
+
+    check(
+        true,
+        true,
+        true,
+        true,
+        true,
+        true,
+        false,
+        |node| {
+            let address: bitcoin::address::Address =
+                bitcoin::address::Address::from_str("bcrt1qs758ursh4q9z627kt3pp5yysm78ddny6txaqgw")
+                    .unwrap()
+                    .require_network(bitcoin::Network::Regtest)
+                    .unwrap();
+            node.client.generate_to_address(1, &address).unwrap();
+        },
+        |event| {
+            match event {
+                PeerObserverEvent::P2pExtractor(p) => {
+                    if let Some(ref e) = p.p2p_event {
+                        match e {
+                            BlockRelay(block) => {
+                                log::info!("{}", block);
+                                assert_eq!(block.header.hash.len(), 32);
+                                // only the coinbase transaction
+                                assert_eq!(block.transactions, 1);
+                                assert!(block.size > 80);
+                                // raw blocks aren't included by default
+                                assert!(block.raw_block.is_none());
+                                return true;
+                            }
+                            _ => log::info!("unhandled P2P extractor event {:?}", p.p2p_event),
+                        }
+                    }
+                }
+                _ => panic!("unexpected event {:?}", event),
+            }
+            return false;
+        },
+    )
+    .await;
+}
+
+/// Starts a p2p-extractor without a node connecting to it and returns its P2P
+/// port. The tests act as the node.
+async fn start_p2p_extractor(
+    nats_server: &NatsServerForTesting,
+    max_inbound_connections: u32,
+) -> (u16, watch::Sender<bool>, tokio::task::JoinHandle<()>) {
+    let p2p_extractor_port = setup();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut args = make_test_args(
+        nats_server.port,
+        format!("127.0.0.1:{}", p2p_extractor_port),
+        true,
+        true,
+        true,
+        true,
+        true,
+        true,
+        true,
+    );
+    args.max_inbound_connections = max_inbound_connections;
+    let p2p_extractor_handle = tokio::spawn(async move {
+        p2p_extractor::run(args, shutdown_rx)
+            .await
+            .expect("p2p-extractor failed");
+    });
+    // allow the p2p-extractor to start
+    sleep(Duration::from_secs(2)).await;
+    (p2p_extractor_port, shutdown_tx, p2p_extractor_handle)
+}
+
+#[tokio::test]
+async fn test_integration_p2pextractor_handshake() {
+    println!("test that the p2p-extractor answers a version message with its version and a verack");
+
+    let nats_server = NatsServerForTesting::new().await;
+    let (port, shutdown_tx, handle) = start_p2p_extractor(&nats_server, 8).await;
+
+    let (_stream, received) =
+        tokio::task::spawn_blocking(move || p2p_client::connect_and_send_version(port))
+            .await
+            .unwrap();
+    // the feature negotiation messages are sent before the verack
+    assert_eq!(
+        received,
+        Some(vec![
+            "version".to_string(),
+            "sendaddrv2".to_string(),
+            "wtxidrelay".to_string(),
+            "verack".to_string(),
+        ])
+    );
+
+    shutdown_tx.send(true).unwrap();
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_integration_p2pextractor_max_inbound_connections() {
+    println!("test that the p2p-extractor closes connections above --max-inbound-connections");
+
+    let nats_server = NatsServerForTesting::new().await;
+    let (port, shutdown_tx, handle) = start_p2p_extractor(&nats_server, 1).await;
+
+    let (first, received) =
+        tokio::task::spawn_blocking(move || p2p_client::connect_and_send_version(port))
+            .await
+            .unwrap();
+    assert!(
+        received.is_some(),
+        "the first connection should be accepted"
+    );
+
+    let (_second, received) =
+        tokio::task::spawn_blocking(move || p2p_client::connect_and_send_version(port))
+            .await
+            .unwrap();
+    assert_eq!(received, None, "the second connection should be closed");
+
+    // the slot of the first connection is freed once it's closed
+    drop(first);
+    sleep(Duration::from_secs(1)).await;
+    let (_third, received) =
+        tokio::task::spawn_blocking(move || p2p_client::connect_and_send_version(port))
+            .await
+            .unwrap();
+    assert!(
+        received.is_some(),
+        "a connection should be accepted after the first one disconnected"
+    );
+
+    shutdown_tx.send(true).unwrap();
+    handle.await.unwrap();
+}
+
 mod p2p_client {
     use shared::bitcoin::{
         Network,
@@ -411,9 +706,8 @@ mod p2p_client {
         message::RawNetworkMessage::new(Network::Regtest.magic(), payload)
     }
 
-    // ONLY for the test_integration_p2pextractor_addr_annoucement test
-    pub fn open_connection_and_send_addr(port: u16) {
-        let version_msg = message::NetworkMessage::Version(VersionMessage::new(
+    fn build_version_message() -> message::NetworkMessage {
+        message::NetworkMessage::Version(VersionMessage::new(
             ServiceFlags::NETWORK_LIMITED,
             util::current_timestamp() as i64,
             address::Address::new(
@@ -427,7 +721,40 @@ mod p2p_client {
             rand::rng().random(),
             String::from(USER_AGENT),
             0,
-        ));
+        ))
+    }
+
+    /// Opens a connection to the p2p-extractor like a node would, sends a
+    /// version message, and returns the commands of the messages received
+    /// until the verack. Returns None if the connection is closed before.
+    pub fn connect_and_send_version(port: u16) -> (TcpStream, Option<Vec<String>>) {
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+        stream
+            .set_read_timeout(Some(time::Duration::from_secs(10)))
+            .unwrap();
+        // the write can fail if the p2p-extractor already closed the connection
+        let sent = stream
+            .write_all(
+                encode::serialize(&build_raw_network_message(build_version_message())).as_slice(),
+            )
+            .is_ok();
+        let mut stream_reader = BufReader::new(stream.try_clone().unwrap());
+        let mut received = vec![];
+        while sent {
+            let Ok(reply) = message::RawNetworkMessage::consensus_decode(&mut stream_reader) else {
+                return (stream, None);
+            };
+            received.push(reply.payload().cmd().to_string());
+            if *reply.payload() == message::NetworkMessage::Verack {
+                return (stream, Some(received));
+            }
+        }
+        (stream, None)
+    }
+
+    // ONLY for the test_integration_p2pextractor_addr_annoucement test
+    pub fn open_connection_and_send_addr(port: u16) {
+        let version_msg = build_version_message();
 
         if let Ok(mut stream) = TcpStream::connect(format!("127.0.0.1:{}", port)) {
             log::info!("Connection to node opened");
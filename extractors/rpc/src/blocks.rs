@@ -0,0 +1,117 @@
+use shared::hash::Hash256;
+use shared::nats_subjects::Subject;
+use shared::protobuf::ebpf_extractor::{ebpf, validation};
+use shared::protobuf::event::{Event, event::PeerObserverEvent};
+use shared::protobuf::{log_extractor, p2p_extractor};
+use std::collections::VecDeque;
+
+/// The subjects of the extractors reporting new blocks: connected blocks
+/// (ebpf-extractor and log-extractor) and relayed blocks (p2p-extractor).
+pub const BLOCK_SUBJECTS: [Subject; 3] = [
+    Subject::Validation,
+    Subject::LogExtractor,
+    Subject::P2PExtractor,
+];
+
+/// How many of the recently seen blocks are remembered.
+const RECENT_BLOCKS: usize = 16;
+
+/// Returns the hash of the block if the event reports a new block. None for
+/// other events.
+pub fn new_block_hash(event: &Event) -> Option<Hash256> {
+    match event.peer_observer_event.as_ref()? {
+        PeerObserverEvent::EbpfExtractor(e) => match e.ebpf_event.as_ref()? {
+            ebpf::EbpfEvent::Validation(v) => match v.event.as_ref()? {
+                validation::validation_event::Event::BlockConnected(connected) => {
+                    connected.hash256().ok()
+                }
+            },
+            _ => None,
+        },
+        PeerObserverEvent::LogExtractor(l) => match l.log_event.as_ref()? {
+            log_extractor::log::LogEvent::BlockConnectedLog(connected) => connected.hash256().ok(),
+            _ => None,
+        },
+        PeerObserverEvent::P2pExtractor(p) => match p.p2p_event.as_ref()? {
+            p2p_extractor::p2p::P2pEvent::BlockRelay(block) => {
+                Hash256::from_internal_bytes(&block.header.hash).ok()
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Remembers the recently seen blocks, so that a block reported by multiple
+/// extractors only triggers one fetch.
+#[derive(Default)]
+pub struct RecentBlocks {
+    hashes: VecDeque<Hash256>,
+}
+
+impl RecentBlocks {
+    /// Remembers the block. Returns true if it wasn't seen recently.
+    pub fn insert(&mut self, hash: Hash256) -> bool {
+        if self.hashes.contains(&hash) {
+            return false;
+        }
+        if self.hashes.len() == RECENT_BLOCKS {
+            self.hashes.pop_front();
+        }
+        self.hashes.push_back(hash);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_HASH: &str = "41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b";
+
+    fn log_event(log_event: log_extractor::log::LogEvent) -> Event {
+        Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+            occurrences: None,
+            raw_line: None,
+            log_timestamp: 0,
+            category: 0,
+            log_event: Some(log_event),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_new_block_hash() {
+        let connected = log_event(log_extractor::log::LogEvent::BlockConnectedLog(
+            log_extractor::BlockConnectedLog {
+                block_hash: BLOCK_HASH.to_string(),
+                block_height: 437,
+            },
+        ));
+        assert_eq!(
+            new_block_hash(&connected).map(|hash| hash.to_string()),
+            Some(BLOCK_HASH.to_string())
+        );
+
+        let unknown = log_event(log_extractor::log::LogEvent::UnknownLogMessage(
+            log_extractor::UnknownLogMessage {
+                raw_message: BLOCK_HASH.to_string(),
+            },
+        ));
+        assert_eq!(new_block_hash(&unknown), None);
+    }
+
+    #[test]
+    fn test_recent_blocks() {
+        let hash = |i: u8| Hash256::from_internal_bytes(&[i; 32]).unwrap();
+        let mut recent = RecentBlocks::default();
+        assert!(recent.insert(hash(0)));
+        assert!(!recent.insert(hash(0)));
+        for i in 1..=RECENT_BLOCKS as u8 {
+            assert!(recent.insert(hash(i)));
+        }
+        // the oldest block was forgotten
+        assert!(recent.insert(hash(0)));
+        assert!(!recent.insert(hash(RECENT_BLOCKS as u8)));
+    }
+}
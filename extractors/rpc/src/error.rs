@@ -1,6 +1,7 @@
 use shared::async_nats;
 use shared::async_nats::ConnectErrorKind;
 use shared::corepc_client::client_sync::Error as RPCError;
+use shared::labels::LabelError;
 use shared::log::SetLoggerError;
 use std::error;
 use std::fmt;
@@ -10,14 +11,16 @@ use std::time::SystemTimeError;
 #[derive(Debug)]
 pub enum FetchOrPublishError {
     Rpc(RPCError),
+    Json(shared::serde_json::Error),
     SystemTime(SystemTimeError),
-    NatsPublish(async_nats::error::Error<async_nats::client::PublishErrorKind>),
+    NatsPublish(shared::nats::PublishError),
 }
 
 impl fmt::Display for FetchOrPublishError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             FetchOrPublishError::Rpc(e) => write!(f, "RPC error: {}", e),
+            FetchOrPublishError::Json(e) => write!(f, "JSON error: {}", e),
             FetchOrPublishError::SystemTime(e) => write!(f, "system time error {}", e),
             FetchOrPublishError::NatsPublish(e) => write!(f, "NATS publish error {}", e),
         }
@@ -28,6 +31,7 @@ impl error::Error for FetchOrPublishError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             FetchOrPublishError::Rpc(ref e) => Some(e),
+            FetchOrPublishError::Json(ref e) => Some(e),
             FetchOrPublishError::SystemTime(ref e) => Some(e),
             FetchOrPublishError::NatsPublish(ref e) => Some(e),
         }
@@ -40,14 +44,20 @@ impl From<RPCError> for FetchOrPublishError {
     }
 }
 
+impl From<shared::serde_json::Error> for FetchOrPublishError {
+    fn from(e: shared::serde_json::Error) -> Self {
+        FetchOrPublishError::Json(e)
+    }
+}
+
 impl From<SystemTimeError> for FetchOrPublishError {
     fn from(e: SystemTimeError) -> Self {
         FetchOrPublishError::SystemTime(e)
     }
 }
 
-impl From<async_nats::error::Error<async_nats::client::PublishErrorKind>> for FetchOrPublishError {
-    fn from(e: async_nats::error::Error<async_nats::client::PublishErrorKind>) -> Self {
+impl From<shared::nats::PublishError> for FetchOrPublishError {
+    fn from(e: shared::nats::PublishError) -> Self {
         FetchOrPublishError::NatsPublish(e)
     }
 }
@@ -58,6 +68,11 @@ pub enum RuntimeError {
     Io(io::Error),
     Corepc(shared::corepc_client::client_sync::Error),
     NatsConnect(shared::async_nats::error::Error<ConnectErrorKind>),
+    NatsSubscribe(async_nats::client::SubscribeError),
+    NatsFlush(async_nats::client::FlushError),
+    AllQueriesFailed(usize),
+    InvalidArgs(String),
+    Labels(LabelError),
 }
 
 impl fmt::Display for RuntimeError {
@@ -67,6 +82,11 @@ impl fmt::Display for RuntimeError {
             RuntimeError::Io(e) => write!(f, "IO error {}", e),
             RuntimeError::Corepc(e) => write!(f, "RPC client error {}", e),
             RuntimeError::NatsConnect(e) => write!(f, "NATS connection error {}", e),
+            RuntimeError::NatsSubscribe(e) => write!(f, "NATS subscribe error {}", e),
+            RuntimeError::NatsFlush(e) => write!(f, "NATS flush error {}", e),
+            RuntimeError::AllQueriesFailed(n) => write!(f, "all {} queried RPCs failed", n),
+            RuntimeError::InvalidArgs(e) => write!(f, "invalid arguments: {}", e),
+            RuntimeError::Labels(e) => write!(f, "labels error {}", e),
         }
     }
 }
@@ -78,6 +98,11 @@ impl error::Error for RuntimeError {
             RuntimeError::Io(ref e) => Some(e),
             RuntimeError::Corepc(ref e) => Some(e),
             RuntimeError::NatsConnect(ref e) => Some(e),
+            RuntimeError::NatsSubscribe(ref e) => Some(e),
+            RuntimeError::NatsFlush(ref e) => Some(e),
+            RuntimeError::AllQueriesFailed(_) => None,
+            RuntimeError::InvalidArgs(_) => None,
+            RuntimeError::Labels(ref e) => Some(e),
         }
     }
 }
@@ -105,3 +130,21 @@ impl From<shared::async_nats::error::Error<ConnectErrorKind>> for RuntimeError {
         RuntimeError::NatsConnect(e)
     }
 }
+
+impl From<async_nats::client::SubscribeError> for RuntimeError {
+    fn from(e: async_nats::client::SubscribeError) -> Self {
+        RuntimeError::NatsSubscribe(e)
+    }
+}
+
+impl From<async_nats::client::FlushError> for RuntimeError {
+    fn from(e: async_nats::client::FlushError) -> Self {
+        RuntimeError::NatsFlush(e)
+    }
+}
+
+impl From<LabelError> for RuntimeError {
+    fn from(e: LabelError) -> Self {
+        RuntimeError::Labels(e)
+    }
+}
@@ -1,18 +1,127 @@
-use shared::clap::{ArgGroup, Parser};
+use shared::args::{CommonArgs, ExtractorArgs};
+use shared::clap::{ArgGroup, Parser, ValueEnum};
 use shared::corepc_client::client_sync::Auth;
 use shared::corepc_client::client_sync::v29::Client;
+use shared::corepc_client::types::v17::{
+    EstimateSmartFee as RPCEstimateSmartFee, GetMemoryInfoStats as RPCGetMemoryInfoStats,
+    GetNetTotals as RPCGetNetTotals,
+};
+use shared::corepc_client::types::v26::{
+    GetAddrManInfo as RPCGetAddrManInfo, GetMempoolInfo as RPCGetMempoolInfo,
+    GetPeerInfo as RPCGetPeerInfo,
+};
+use shared::corepc_client::types::v29::GetBlockchainInfo as RPCGetBlockchainInfo;
+use shared::debug_buffer::{self, DebugBuffer};
+use shared::event_builder::EventBuilder;
+use shared::extractor_metrics::{self, ExtractorMetricsRecorder};
+use shared::futures::StreamExt;
+use shared::futures::stream::select_all;
+use shared::healthserver::{self, Health};
+use shared::heartbeat::{self, Heartbeats};
+use shared::labels::{Labels, NETWORK_LABEL, NODE_LABEL, add_instance_label, random_instance_id};
 use shared::log;
-use shared::nats_subjects::Subject;
+use shared::nats::{self, TappedClient};
+use shared::nats_subjects::{Subject, peer_info_subject, request_subject, snapshot_subject};
 use shared::prost::Message;
-use shared::protobuf::event::{Event, event::PeerObserverEvent};
+use shared::protobuf::event::{
+    ErrorEvent, ErrorSeverity, Event, Extractor, event::PeerObserverEvent,
+};
 use shared::protobuf::rpc_extractor;
+use shared::serde::de::DeserializeOwned;
+use shared::sink::MultiSink;
 use shared::tokio::sync::watch;
+use shared::tokio::task::JoinHandle;
 use shared::tokio::time::{self, Duration};
-use shared::{async_nats, clap};
+use shared::util::current_timestamp;
+use shared::{clap, rand, serde_json};
+use std::collections::BTreeMap;
+use std::future::Future;
 
+mod blocks;
 mod error;
+#[cfg(feature = "pushgateway")]
+mod pushgateway;
+mod request;
+mod scan;
+mod snapshot;
+mod source;
 
+use blocks::{BLOCK_SUBJECTS, RecentBlocks};
 use error::{FetchOrPublishError, RuntimeError};
+use request::{REQUEST_METHODS, RateLimiter, RequestNode};
+use scan::Scanner;
+use snapshot::Snapshots;
+use source::RpcSource;
+
+/// The extractor is reported unhealthy if not all RPCs succeeded in this many
+/// query intervals.
+const HEALTH_MAX_MISSED_INTERVALS: u32 = 3;
+
+/// How long a sweep in progress may take to finish after a shutdown signal.
+pub const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// The fee estimate modes of `estimatesmartfee`.
+const FEE_ESTIMATE_MODES: &[&str] = &["economical", "conservative"];
+
+/// How the getpeerinfo results are published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PeerInfoPublishMode {
+    /// One PeerInfos event with all peers.
+    Aggregate,
+    /// One PeerInfo event per peer on the `<subject>.peerinfo.<id>` subject.
+    PerPeer,
+    /// Both the aggregate and the per-peer events.
+    Both,
+}
+
+/// When the per-message byte counters of the getpeerinfo results are
+/// published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PeerInfoPerMessage {
+    /// With every sample.
+    Always,
+    /// Only when they changed since they were last published for the peer.
+    Changed,
+    /// Only with every N-th sample (`--peerinfo-per-message-every`).
+    Every,
+}
+
+/// Omits the per-message byte counters of the getpeerinfo samples according
+/// to `--peerinfo-per-message`. Keeps the state of one node.
+struct PerMessageFilter {
+    mode: PeerInfoPerMessage,
+    every: u64,
+    /// The number of getpeerinfo samples so far.
+    samples: u64,
+    /// The per-message byte counters last published per peer.
+    published: BTreeMap<u32, rpc_extractor::PerMessageBytes>,
+}
+
+impl PerMessageFilter {
+    fn new(mode: PeerInfoPerMessage, every: u64) -> Self {
+        PerMessageFilter {
+            mode,
+            every: every.max(1),
+            samples: 0,
+            published: BTreeMap::new(),
+        }
+    }
+
+    fn apply(&mut self, peer_infos: &mut rpc_extractor::PeerInfos) {
+        match self.mode {
+            PeerInfoPerMessage::Always => (),
+            PeerInfoPerMessage::Changed => {
+                peer_infos.omit_unchanged_per_message(&mut self.published)
+            }
+            PeerInfoPerMessage::Every => {
+                if self.samples % self.every != 0 {
+                    peer_infos.omit_per_message();
+                }
+            }
+        }
+        self.samples += 1;
+    }
+}
 
 /// The peer-observer rpc-extractor periodically queries data from the
 /// Bitcoin Core RPC endpoint and publishes the results as events into
@@ -25,44 +134,114 @@ use error::{FetchOrPublishError, RuntimeError};
         .args(&["rpc_cookie_file", "rpc_user"])
 ))]
 #[command(version, about, long_about = None)]
+// the rpc-extractor determines the network itself if --network isn't set
+#[command(mut_arg("network", |arg| arg.help(
+    "The network of the node (\"main\", \"test\", \"testnet4\", \"signet\", or \"regtest\"). \
+     Attached to every published event as \"network\" label. If not set, it's determined with \
+     getblockchaininfo for each node"
+)))]
 pub struct Args {
-    /// Address of the NATS server where the extractor will publish messages to.
-    #[arg(short, long, default_value = "127.0.0.1:4222")]
-    pub nats_address: String,
-
-    /// The log level the extractor should run with. Valid log levels are "trace",
-    /// "debug", "info", "warn", "error". See https://docs.rs/log/latest/log/enum.Level.html.
-    #[arg(short, long, default_value_t = log::Level::Debug)]
-    pub log_level: log::Level,
+    #[command(flatten)]
+    pub common: CommonArgs,
 
     /// Address of the Bitcoin Core RPC endpoint the RPC extractor will query.
+    /// Can be specified multiple times to query multiple nodes. With multiple
+    /// nodes, events are published on per-node subjects (e.g. `rpc.127_0_0_1_8332`).
     #[arg(long, default_value = "127.0.0.1:8332")]
-    pub rpc_host: String,
+    pub rpc_host: Vec<String>,
 
     /// RPC username for authentication with the Bitcoin Core RPC endpoint.
+    /// Specify once to use it for all nodes, or once per --rpc-host (in the same order).
     #[arg(long)]
-    pub rpc_user: Option<String>,
+    pub rpc_user: Vec<String>,
 
     /// RPC password for authentication with the Bitcoin Core RPC endpoint.
+    /// Specify once to use it for all nodes, or once per --rpc-host (in the same order).
     #[arg(requires = "rpc_user", long)]
-    pub rpc_password: Option<String>,
+    pub rpc_password: Vec<String>,
 
     /// An RPC cookie file for authentication with the Bitcoin Core RPC endpoint.
+    /// Specify once to use it for all nodes, or once per --rpc-host (in the same order).
     #[arg(long)]
-    pub rpc_cookie_file: Option<String>,
+    pub rpc_cookie_file: Vec<String>,
 
     /// Interval (in seconds) in which to query from the Bitcoin Core RPC endpoint.
-    #[arg(long, default_value_t = 10)]
+    /// Must be at least 1.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
     pub query_interval: u64,
 
     /// Disable quering and publishing of `getpeerinfo` data.
     #[arg(long, default_value_t = false)]
     pub disable_getpeerinfo: bool,
 
+    /// Additionally publish a summary of the `getpeerinfo` data with peer counts
+    /// by direction, network, and connection type.
+    #[arg(long, default_value_t = false)]
+    pub peerinfo_summary: bool,
+
+    /// Additionally publish the peer counts and bytes sent and received per
+    /// network (e.g. ipv4, onion, i2p, cjdns) of the `getpeerinfo` data.
+    #[arg(long, default_value_t = false)]
+    pub peerinfo_network_stats: bool,
+
+    /// How to publish the `getpeerinfo` data: "aggregate" publishes one event
+    /// with all peers, "per-peer" publishes one event per peer on the
+    /// `rpc.peerinfo.<id>` subject (`rpc.<node>.peerinfo.<id>` with multiple
+    /// nodes), and "both" does both. The metrics tool only uses the aggregate
+    /// event.
+    #[arg(long, value_enum, default_value_t = PeerInfoPublishMode::Aggregate)]
+    pub peerinfo_publish_mode: PeerInfoPublishMode,
+
+    /// Publish the aggregate `getpeerinfo` event in a compact form with only
+    /// the fields needed for long-term archival (id, address, direction,
+    /// services, bytes sent and received, connection time, and subversion)
+    /// instead of the full form. The metrics tool needs the full form.
+    #[arg(long, default_value_t = false)]
+    pub peerinfo_compact: bool,
+
+    /// Additionally publish the peers that connected and disconnected since
+    /// the previous `getpeerinfo` sample, with their session durations.
+    #[arg(long, default_value_t = false)]
+    pub peerinfo_churn: bool,
+
+    /// Split the aggregate `getpeerinfo` event into multiple chunks if it's
+    /// larger than this many bytes, to stay below the maximum NATS message
+    /// size on nodes with many peers. Consumers reassemble the chunks.
+    #[arg(long, default_value_t = 512 * 1024)]
+    pub peerinfo_max_bytes: usize,
+
+    /// When to publish the `bytes_sent_per_message` and
+    /// `bytes_received_per_message` maps of the `getpeerinfo` data, which
+    /// make up a large part of it: "always", "changed" only when they
+    /// changed since they were last published for the peer, or "every" only
+    /// with every `--peerinfo-per-message-every` sample. Omitted maps are
+    /// left empty and `per_message_omitted` is set. The metrics tool only
+    /// updates its sub-1 sat/vbyte relay peers metric with complete samples.
+    #[arg(long, value_enum, default_value_t = PeerInfoPerMessage::Always)]
+    pub peerinfo_per_message: PeerInfoPerMessage,
+
+    /// With `--peerinfo-per-message every`, publish the per-message maps
+    /// with every this many `getpeerinfo` samples, starting with the first.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub peerinfo_per_message_every: u64,
+
     /// Disable quering and publishing of `getmempoolinfo` data.
     #[arg(long, default_value_t = false)]
     pub disable_getmempoolinfo: bool,
 
+    /// Enable quering and publishing of `getrawmempool` data: the txids of
+    /// all mempool transactions. getrawmempool is queried right after
+    /// getmempoolinfo, and both events carry the same correlation id so
+    /// consumers can join them. The mempool might still change between the
+    /// two RPCs. It's skipped if getmempoolinfo failed. Responses larger than
+    /// the maximum NATS payload are published in chunks.
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with = "disable_getmempoolinfo"
+    )]
+    pub enable_getrawmempool: bool,
+
     /// Disable quering and publishing of `uptime` data.
     #[arg(long, default_value_t = false)]
     pub disable_uptime: bool,
@@ -78,6 +257,149 @@ pub struct Args {
     /// Disable quering and publishing of `getaddrmaninfo` data.
     #[arg(long, default_value_t = false)]
     pub disable_getaddrmaninfo: bool,
+
+    /// Disable quering and publishing of `getblockchaininfo` data.
+    #[arg(long, default_value_t = false)]
+    pub disable_getblockchaininfo: bool,
+
+    /// Disable quering and publishing of `estimatesmartfee` data.
+    #[arg(long, default_value_t = false)]
+    pub disable_estimatesmartfee: bool,
+
+    /// Disable quering and publishing of `getnodeaddresses` data.
+    #[arg(long, default_value_t = false)]
+    pub disable_getnodeaddresses: bool,
+
+    /// The number of addresses to query with `getnodeaddresses`. The node
+    /// returns a random selection of the addresses it would gossip.
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+    pub getnodeaddresses_count: u32,
+
+    /// Additionally query and publish `getblockchaininfo` right away when
+    /// another extractor reports a new block: a connected block on the
+    /// `validation` (ebpf-extractor) or `log-extractor` subject, or a relayed
+    /// block on the `p2p-extractor` subject. Aligns the chain snapshots to
+    /// block arrivals instead of the query interval.
+    #[arg(long, default_value_t = false)]
+    pub fetch_on_new_block: bool,
+
+    /// The confirmation targets (in blocks) to query `estimatesmartfee` for,
+    /// as comma-separated list.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "1,3,6,144",
+        value_parser = clap::value_parser!(u32).range(1..=1008)
+    )]
+    pub feeestimate_targets: Vec<u32>,
+
+    /// The fee estimate mode passed to `estimatesmartfee`.
+    #[arg(
+        long,
+        default_value = "economical",
+        value_parser = clap::builder::PossibleValuesParser::new(FEE_ESTIMATE_MODES)
+    )]
+    pub feeestimate_mode: String,
+
+    /// Enable quering and publishing of `getorphantxs` data. getorphantxs is a
+    /// hidden RPC, e.g. for research nodes. If the node doesn't know it,
+    /// querying it is disabled for this node.
+    #[cfg(feature = "getorphantxs")]
+    #[arg(long, default_value_t = false)]
+    pub enable_getorphantxs: bool,
+
+    /// Enable quering and publishing of `getrawaddrman` data: all entries of
+    /// the address manager. getrawaddrman is a hidden RPC, e.g. for research
+    /// nodes. Its events can exceed the default NATS maximum payload of 1 MB
+    /// with a full address manager. If the node doesn't know it, querying it
+    /// is disabled for this node.
+    #[arg(long, default_value_t = false)]
+    pub enable_getrawaddrman: bool,
+
+    /// Answer one-off requests for this RPC method, sent with NATS
+    /// request-reply on the `rpc.request` subject (`rpc.<node>.request` with
+    /// multiple nodes). Can be specified multiple times. No requests are
+    /// answered by default. scantxoutset is expensive: it starts a scan of
+    /// the UTXO set whose progress is published as ScanProgress events.
+    #[arg(
+        long = "allow-rpc-request",
+        value_name = "METHOD",
+        value_parser = clap::builder::PossibleValuesParser::new(REQUEST_METHODS)
+    )]
+    pub allow_rpc_requests: Vec<String>,
+
+    /// Maximum number of answered requests per second per node. Requests over
+    /// the limit are answered with an error.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub rpc_request_rate_limit: u32,
+
+    /// Keep the events of the last successful fetch of each RPC method and
+    /// answer NATS requests for them on the `rpc.snapshot` subject
+    /// (`rpc.<node>.snapshot` with multiple nodes). The reply is an
+    /// EventBatch with the events of all methods, or, if the request names
+    /// an RPC method (e.g. "getpeerinfo"), only with its event. Events that
+    /// would make the reply larger than the maximum NATS payload are left
+    /// out. Lets consumers joining mid-stream see the current state right
+    /// away.
+    #[arg(long, default_value_t = false)]
+    pub serve_snapshots: bool,
+
+    /// Attach the JSON result of the RPC to each published RPC event. Useful
+    /// to debug the conversion of RPC results into events, e.g. with new
+    /// Bitcoin Core versions.
+    #[arg(long, default_value_t = false)]
+    pub include_raw_rpc: bool,
+
+    /// Don't attach the JSON result if it's larger than this many bytes, to
+    /// stay below the maximum NATS message size.
+    #[arg(long, default_value_t = 256 * 1024)]
+    pub raw_rpc_max_bytes: usize,
+
+    /// An identifier of this extractor instance, attached to every published
+    /// event as "instance" label. Events of two extractors accidentally
+    /// querying the same node can be told apart and deduplicated by it. A
+    /// random identifier is generated on startup if not set.
+    #[arg(long, value_name = "ID")]
+    pub instance_id: Option<String>,
+
+    /// Address of an optional HTTP health endpoint for liveness and readiness
+    /// probes, e.g. 127.0.0.1:8080. It answers with 200 if the extractor is
+    /// connected to NATS and all RPCs succeeded in one of the last three
+    /// query intervals, and with 503 otherwise.
+    #[arg(long)]
+    pub health_addr: Option<String>,
+
+    /// Query the enabled RPCs of all nodes once, publish the results and
+    /// exit instead of querying them every query interval. Exits with a
+    /// nonzero status if all queried RPCs failed. Useful for cron jobs and
+    /// for testing a setup.
+    #[arg(long, default_value_t = false)]
+    pub once: bool,
+
+    /// Keep running even if all RPCs are disabled, e.g. to only answer
+    /// requests (--allow-rpc-request). Without it, the extractor exits with an
+    /// error if there's nothing to query. While idle, the health endpoint
+    /// reports the extractor as unhealthy, as nothing is queried.
+    #[arg(long, default_value_t = false)]
+    pub allow_empty: bool,
+
+    /// Push the extractor metrics to the Prometheus pushgateway at this URL,
+    /// e.g. http://127.0.0.1:9091, every --extractor-metrics-interval and
+    /// before exiting with --once. Only plain HTTP is supported.
+    #[cfg(feature = "pushgateway")]
+    #[arg(long, value_name = "URL")]
+    pub pushgateway_url: Option<String>,
+
+    /// The job label of the metrics pushed to the pushgateway.
+    #[cfg(feature = "pushgateway")]
+    #[arg(long, default_value = "rpc-extractor")]
+    pub pushgateway_job: String,
+
+    /// The instance label of the metrics pushed to the pushgateway. Defaults
+    /// to the "node" label, if any.
+    #[cfg(feature = "pushgateway")]
+    #[arg(long)]
+    pub pushgateway_instance: Option<String>,
 }
 
 impl Args {
@@ -94,41 +416,280 @@ impl Args {
         disable_getnettotals: bool,
         disable_getmemoryinfo: bool,
         disable_getaddrmaninfo: bool,
+        disable_getblockchaininfo: bool,
+        disable_estimatesmartfee: bool,
+        disable_getnodeaddresses: bool,
     ) -> Args {
         Self {
-            nats_address,
-            log_level,
-            rpc_host,
-            rpc_password: None,
-            rpc_user: None,
-            rpc_cookie_file: Some(rpc_cookie_file),
+            common: CommonArgs::new(nats_address, log_level),
+            rpc_host: vec![rpc_host],
+            rpc_password: vec![],
+            rpc_user: vec![],
+            rpc_cookie_file: vec![rpc_cookie_file],
             query_interval,
             disable_getpeerinfo,
+            peerinfo_summary: false,
+            peerinfo_network_stats: false,
+            peerinfo_publish_mode: PeerInfoPublishMode::Aggregate,
+            peerinfo_compact: false,
+            peerinfo_churn: false,
+            peerinfo_max_bytes: 512 * 1024,
+            peerinfo_per_message: PeerInfoPerMessage::Always,
+            peerinfo_per_message_every: 10,
             disable_getmempoolinfo,
+            enable_getrawmempool: false,
             disable_uptime,
             disable_getnettotals,
             disable_getmemoryinfo,
             disable_getaddrmaninfo,
-            // when adding more disable_* args, make sure to update the disable_all below
+            disable_getblockchaininfo,
+            disable_estimatesmartfee,
+            disable_getnodeaddresses,
+            getnodeaddresses_count: 100,
+            fetch_on_new_block: false,
+            feeestimate_targets: vec![1, 3, 6, 144],
+            feeestimate_mode: "economical".to_string(),
+            #[cfg(feature = "getorphantxs")]
+            enable_getorphantxs: false,
+            enable_getrawaddrman: false,
+            allow_rpc_requests: vec![],
+            rpc_request_rate_limit: 10,
+            serve_snapshots: false,
+            include_raw_rpc: false,
+            raw_rpc_max_bytes: 256 * 1024,
+            instance_id: None,
+            health_addr: None,
+            once: false,
+            allow_empty: false,
+            #[cfg(feature = "pushgateway")]
+            pushgateway_url: None,
+            #[cfg(feature = "pushgateway")]
+            pushgateway_job: "rpc-extractor".to_string(),
+            #[cfg(feature = "pushgateway")]
+            pushgateway_instance: None,
+            // when adding more disable_* args, make sure to update disables_all_rpcs() above
         }
     }
+
+    /// True if all RPCs are disabled and there's nothing to query.
+    fn disables_all_rpcs(&self) -> bool {
+        self.disable_getpeerinfo
+            && self.disable_getmempoolinfo
+            && self.disable_uptime
+            && self.disable_getnettotals
+            && self.disable_getmemoryinfo
+            && self.disable_getaddrmaninfo
+            && self.disable_getblockchaininfo
+            && self.disable_estimatesmartfee
+            && self.disable_getnodeaddresses
+            && !self.enables_getorphantxs()
+            && !self.enable_getrawaddrman
+    }
+
+    /// True if getorphantxs is queried.
+    #[cfg(feature = "getorphantxs")]
+    fn enables_getorphantxs(&self) -> bool {
+        self.enable_getorphantxs
+    }
+
+    /// Without the getorphantxs feature, getorphantxs is never queried.
+    #[cfg(not(feature = "getorphantxs"))]
+    fn enables_getorphantxs(&self) -> bool {
+        false
+    }
 }
 
-pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(), RuntimeError> {
-    let auth: Auth = match args.rpc_cookie_file {
-        Some(path) => Auth::CookieFile(path.into()),
-        None => Auth::UserPass(
-            args.rpc_user.expect("need an RPC user"),
-            args.rpc_password.expect("need an RPC password"),
-        ),
-    };
-    let rpc_client = Client::new_with_auth(&format!("http://{}", args.rpc_host), auth)?;
+/// Fails if all RPCs are disabled, unless idling is allowed with --allow-empty.
+fn check_rpcs_enabled(args: &Args) -> Result<(), RuntimeError> {
+    if args.disables_all_rpcs() && !args.allow_empty {
+        return Err(RuntimeError::InvalidArgs(
+            "all RPCs are disabled, so there is nothing to query. Enable at least one RPC, or pass --allow-empty to keep running anyway"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl ExtractorArgs for Args {
+    fn common(&self) -> &CommonArgs {
+        &self.common
+    }
+}
+
+/// A Bitcoin Core node queried by the rpc-extractor through `client`.
+struct Node<C = Client> {
+    host: String,
+    client: C,
+    /// The NATS subject events fetched from this node are published on.
+    subject: String,
+    /// The NATS subject errors querying this node are published on.
+    errors_subject: String,
+    /// The previous getnettotals sample, used to derive the bytes per second.
+    previous_net_totals: Option<rpc_extractor::NetTotals>,
+    /// The previous getmempoolinfo sample, used to derive the mempool minimum
+    /// fee trend. Reset when the query fails, e.g. while the node restarts.
+    previous_mempool_info: Option<rpc_extractor::MempoolInfo>,
+    /// The peers of the previous getpeerinfo sample, used to derive the
+    /// connection churn.
+    previous_peers: Option<BTreeMap<u32, rpc_extractor::ChurnPeer>>,
+    /// Decides which per-message byte counters of the getpeerinfo samples
+    /// are published.
+    per_message: PerMessageFilter,
+    /// The labels attached to events fetched from this node. Includes the
+    /// network label once the network of the node is known.
+    labels: Labels,
+    /// Set while the node label isn't explicitly configured, multiple nodes
+    /// are queried, and the node hasn't been asked for its identity yet.
+    detect_node_id: bool,
+    /// Limits the requests answered by this node.
+    rate_limiter: RateLimiter,
+    /// Set to false once the node answered getorphantxs with "method not
+    /// found".
+    #[cfg(feature = "getorphantxs")]
+    getorphantxs_supported: bool,
+    /// Set to false once the node answered getrawaddrman with "method not
+    /// found".
+    getrawaddrman_supported: bool,
+    /// Runs the scantxoutset scans requested for this node.
+    scanner: Scanner,
+    /// The events of the last successful fetches, with --serve-snapshots.
+    snapshots: Snapshots,
+}
+
+impl<C: RpcSource> Node<C> {
+    fn new(
+        args: &Args,
+        host: &str,
+        client: C,
+        scanner: Scanner,
+        subject: String,
+        errors_subject: String,
+        labels: Labels,
+    ) -> Self {
+        Node {
+            host: host.to_string(),
+            client,
+            scanner,
+            subject,
+            errors_subject,
+            previous_net_totals: None,
+            previous_mempool_info: None,
+            previous_peers: None,
+            per_message: PerMessageFilter::new(
+                args.peerinfo_per_message,
+                args.peerinfo_per_message_every,
+            ),
+            labels,
+            // A single node keeps the hostname like the other extractors.
+            detect_node_id: args.rpc_host.len() > 1 && !args.common.has_explicit_node(),
+            rate_limiter: RateLimiter::new(args.rpc_request_rate_limit),
+            #[cfg(feature = "getorphantxs")]
+            getorphantxs_supported: true,
+            getrawaddrman_supported: true,
+            snapshots: Snapshots::new(args.serve_snapshots),
+        }
+    }
+}
+
+/// Picks the i-th value of a repeatable per-node argument. A single value is
+/// used for all nodes.
+fn per_node_arg<'a>(
+    values: &'a [String],
+    name: &str,
+    num_nodes: usize,
+    i: usize,
+) -> Result<&'a str, RuntimeError> {
+    match values.len() {
+        1 => Ok(&values[0]),
+        n if n == num_nodes => Ok(&values[i]),
+        n => Err(RuntimeError::InvalidArgs(format!(
+            "expected --{} once or once per --rpc-host ({} times), but got it {} times",
+            name, num_nodes, n
+        ))),
+    }
+}
+
+fn setup_nodes(args: &Args, labels: &Labels) -> Result<Vec<Node>, RuntimeError> {
+    let num_nodes = args.rpc_host.len();
+    let mut nodes = Vec::with_capacity(num_nodes);
+    for (i, host) in args.rpc_host.iter().enumerate() {
+        let auth: Auth = if !args.rpc_cookie_file.is_empty() {
+            let path = per_node_arg(&args.rpc_cookie_file, "rpc-cookie-file", num_nodes, i)?;
+            Auth::CookieFile(path.into())
+        } else {
+            Auth::UserPass(
+                per_node_arg(&args.rpc_user, "rpc-user", num_nodes, i)?.to_string(),
+                per_node_arg(&args.rpc_password, "rpc-password", num_nodes, i)?.to_string(),
+            )
+        };
+        // With a single node, keep publishing on the plain subject so existing
+        // consumers don't need to change.
+        let (subject, errors_subject) = if num_nodes == 1 {
+            (Subject::Rpc.to_string(), Subject::Errors.to_string())
+        } else {
+            (Subject::Rpc.for_node(host), Subject::Errors.for_node(host))
+        };
+        log::info!("Querying node {} and publishing to '{}'", host, subject);
+        let mut labels = labels.clone();
+        // With multiple nodes, the hostname doesn't tell them apart. Use the
+        // RPC host until the node reports its identity.
+        if num_nodes > 1 && !args.common.has_explicit_node() {
+            labels.insert(NODE_LABEL.to_string(), host.clone());
+        }
+        let url = format!("http://{}", host);
+        nodes.push(Node::new(
+            args,
+            host,
+            Client::new_with_auth(&url, auth.clone())?,
+            Scanner::new(url, auth),
+            subject,
+            errors_subject,
+            labels,
+        ));
+    }
+    Ok(nodes)
+}
 
-    log::debug!("Connecting to NATS server at {}..", args.nats_address);
-    let nats_client = async_nats::connect(&args.nats_address).await?;
-    log::info!("Connected to NATS server at {}", &args.nats_address);
+pub async fn run(args: Args, shutdown_rx: watch::Receiver<bool>) -> Result<(), RuntimeError> {
+    check_rpcs_enabled(&args)?;
+    let mut labels = args.common.labels()?;
+    let instance_id = args.instance_id.clone().unwrap_or_else(random_instance_id);
+    log::info!("Running as instance {}", instance_id);
+    add_instance_label(&mut labels, &instance_id)?;
+    log::info!("Attaching labels to all events: {:?}", labels);
+    let nodes = setup_nodes(&args, &labels)?;
+    run_nodes(args, labels, nodes, shutdown_rx).await
+}
 
+/// Runs the extractor like [run] for the already set up `nodes`.
+async fn run_nodes<C: RpcSource>(
+    args: Args,
+    labels: Labels,
+    mut nodes: Vec<Node<C>>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> Result<(), RuntimeError> {
     let duration_sec = Duration::from_secs(args.query_interval);
+    let health = Health::new(duration_sec * HEALTH_MAX_MISSED_INTERVALS);
+    if let Some(address) = &args.health_addr {
+        healthserver::start(address, health.clone())?;
+    }
+
+    log::debug!(
+        "Connecting to NATS server at {}..",
+        args.common.nats_address
+    );
+    let nats_client = nats::connect(&args.common.nats_address).await?;
+    log::info!("Connected to NATS server at {}", &args.common.nats_address);
+    health.set_nats_client(nats_client.clone());
+    let nats_client = TappedClient::new(
+        nats_client,
+        DebugBuffer::new(args.common.debug_buffer_size),
+        MultiSink::open(&args.common.outputs)?,
+    )
+    .with_max_payload(args.common.max_nats_payload)
+    .with_subject_prefix(args.common.nats_subject_prefix.clone());
+    debug_buffer::serve(&nats_client, &Subject::Rpc.to_string()).await?;
+
     let mut interval = time::interval(duration_sec);
     log::info!(
         "Querying the Bitcoin Core RPC interface every {:?}.",
@@ -139,10 +700,27 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
         "Querying getpeerinfo enabled:    {}",
         !args.disable_getpeerinfo
     );
+    if !args.disable_getpeerinfo {
+        log::info!("Publishing getpeerinfo summary:  {}", args.peerinfo_summary);
+        log::info!(
+            "Publishing network peer stats:   {}",
+            args.peerinfo_network_stats
+        );
+        log::info!(
+            "Publishing getpeerinfo mode:     {:?}",
+            args.peerinfo_publish_mode
+        );
+        log::info!("Publishing compact getpeerinfo:  {}", args.peerinfo_compact);
+        log::info!("Publishing connection churn:     {}", args.peerinfo_churn);
+    }
     log::info!(
         "Querying getmempoolinfo enabled: {}",
         !args.disable_getmempoolinfo
     );
+    log::info!(
+        "Querying getrawmempool enabled:  {}",
+        args.enable_getrawmempool
+    );
     log::info!("Querying uptime enabled:         {}", !args.disable_uptime);
     log::info!(
         "Querying getnettotals enabled:   {}",
@@ -156,44 +734,246 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
         "Querying getaddrmaninfo enabled: {}",
         !args.disable_getaddrmaninfo
     );
-    // check if we have at least one RPC to query
-    let disable_all = args.disable_getpeerinfo
-        && args.disable_getmempoolinfo
-        && args.disable_uptime
-        && args.disable_getnettotals
-        && args.disable_getmemoryinfo
-        && args.disable_getaddrmaninfo;
+    log::info!(
+        "Querying getblockchaininfo enabled: {}",
+        !args.disable_getblockchaininfo
+    );
+    log::info!(
+        "Fetching on new blocks enabled:  {}",
+        args.fetch_on_new_block
+    );
+    if args.fetch_on_new_block && args.disable_getblockchaininfo {
+        log::warn!("--fetch-on-new-block has no effect with --disable-getblockchaininfo");
+    }
+    log::info!(
+        "Querying estimatesmartfee enabled: {}",
+        !args.disable_estimatesmartfee
+    );
+    if !args.disable_estimatesmartfee {
+        log::info!(
+            "Estimating fees for targets {:?} in {} mode",
+            args.feeestimate_targets,
+            args.feeestimate_mode
+        );
+    }
+    log::info!(
+        "Querying getnodeaddresses enabled: {}",
+        !args.disable_getnodeaddresses
+    );
+    if !args.disable_getnodeaddresses {
+        log::info!(
+            "Querying up to {} addresses with getnodeaddresses",
+            args.getnodeaddresses_count
+        );
+    }
+    #[cfg(feature = "getorphantxs")]
+    log::info!(
+        "Querying getorphantxs enabled:   {}",
+        args.enable_getorphantxs
+    );
+    log::info!(
+        "Querying getrawaddrman enabled:  {}",
+        args.enable_getrawaddrman
+    );
+    // only possible with --allow-empty, see check_rpcs_enabled()
+    let disable_all = args.disables_all_rpcs();
     if disable_all {
-        log::warn!("No RPC configured to be queried!");
+        log::warn!("No RPC configured to be queried! Idling as --allow-empty is set.");
+    }
+
+    let extractor_metrics = ExtractorMetricsRecorder::new(Extractor::RpcExtractor);
+    extractor_metrics.set("nodes", nodes.len() as f64);
+    let metrics_window = args.common.extractor_metrics_window();
+
+    #[cfg(feature = "pushgateway")]
+    let pushgateway = match &args.pushgateway_url {
+        Some(url) => {
+            let instance = args
+                .pushgateway_instance
+                .as_deref()
+                .or(labels.get(NODE_LABEL).map(String::as_str));
+            let pushgateway = pushgateway::Pushgateway::new(url, &args.pushgateway_job, instance)
+                .map_err(RuntimeError::InvalidArgs)?;
+            log::info!(
+                "Pushing the extractor metrics to the pushgateway at {}",
+                url
+            );
+            if metrics_window.is_none() && !args.once {
+                log::warn!(
+                    "--pushgateway-url has no effect without --extractor-metrics-interval or --once"
+                );
+            }
+            Some(pushgateway)
+        }
+        None => None,
+    };
+
+    if args.once {
+        #[cfg(feature = "pushgateway")]
+        let started = time::Instant::now();
+        let mut outcome = QueryOutcome::default();
+        for node in nodes.iter_mut() {
+            outcome += query_node(node, &args, &nats_client).await;
+        }
+        // make sure everything is published before exiting
+        nats_client.flush().await?;
+        log::info!(
+            "Queried {} RPCs once, {} of them failed.",
+            outcome.queried,
+            outcome.failed
+        );
+        extractor_metrics.inc_by("rpcs_queried", outcome.queried as u64);
+        extractor_metrics.inc_by("rpcs_failed", outcome.failed as u64);
+        #[cfg(feature = "pushgateway")]
+        if let Some(pushgateway) = &pushgateway {
+            pushgateway
+                .push(&extractor_metrics.take(started.elapsed()))
+                .await;
+        }
+        if outcome.all_failed() {
+            return Err(RuntimeError::AllQueriesFailed(outcome.queried));
+        }
+        return Ok(());
+    }
+
+    // Subscribe to the request subjects of all nodes, if requests are allowed.
+    // Requests are tagged with the index of the node they are sent to.
+    let mut requests = select_all(Vec::new());
+    if !args.allow_rpc_requests.is_empty() {
+        for (i, node) in nodes.iter().enumerate() {
+            let subject = request_subject(&node.subject);
+            log::info!(
+                "Answering {} requests for node {} on '{}'",
+                args.allow_rpc_requests.join(", "),
+                node.host,
+                subject
+            );
+            let subscriber = nats_client.subscribe(subject).await?;
+            requests.push(subscriber.map(move |msg| (i, msg)));
+        }
+    }
+
+    // Subscribe to the snapshot subjects of all nodes, if enabled. Tagged like
+    // the requests.
+    let mut snapshot_requests = select_all(Vec::new());
+    if args.serve_snapshots {
+        for (i, node) in nodes.iter().enumerate() {
+            let subject = snapshot_subject(&node.subject);
+            log::info!(
+                "Answering snapshot requests for node {} on '{}'",
+                node.host,
+                subject
+            );
+            let subscriber = nats_client.subscribe(subject).await?;
+            snapshot_requests.push(subscriber.map(move |msg| (i, msg)));
+        }
     }
 
+    // Subscribe to the block events of the other extractors, if enabled. The
+    // extractors might publish on per-node and batch subjects too.
+    let mut block_events = select_all(Vec::new());
+    let mut recent_blocks = RecentBlocks::default();
+    if args.fetch_on_new_block && !args.disable_getblockchaininfo {
+        for subject in BLOCK_SUBJECTS {
+            for subject in [subject.to_string(), format!("{}.>", subject)] {
+                log::info!(
+                    "Fetching getblockchaininfo on new blocks reported on '{}'",
+                    subject
+                );
+                block_events.push(nats_client.subscribe(subject).await?);
+            }
+        }
+    }
+
+    let mut metrics_interval = extractor_metrics::interval(metrics_window);
+    let mut heartbeats = args
+        .common
+        .heartbeat_interval()
+        .map(|interval| Heartbeats::new(Extractor::RpcExtractor, interval));
+    let mut heartbeat_interval = heartbeat::interval(args.common.heartbeat_interval());
+
     loop {
         shared::tokio::select! {
-            _ = interval.tick() => {
-                if !args.disable_getpeerinfo
-                    && let Err(e) = getpeerinfo(&rpc_client, &nats_client).await {
-                        log::error!("Could not fetch and publish 'getpeerinfo': {}", e)
-                    }
-                if !args.disable_getmempoolinfo
-                    && let Err(e) = getmempoolinfo(&rpc_client, &nats_client).await {
-                        log::error!("Could not fetch and publish 'getmempoolinfo': {}", e)
-                    }
-                if !args.disable_uptime
-                    && let Err(e) = uptime(&rpc_client, &nats_client).await {
-                        log::error!("Could not fetch and publish 'uptime': {}", e)
-                    }
-                if !args.disable_getnettotals
-                    && let Err(e) = getnettotals(&rpc_client, &nats_client).await {
-                        log::error!("Could not fetch and publish 'getnettotals': {}", e)
-                    }
-                if !args.disable_getmemoryinfo
-                    && let Err(e) = getmemoryinfo(&rpc_client, &nats_client).await {
-                        log::error!("Could not fetch and publish 'getmemoryinfo': {}", e)
+            // No new sweep is started once the shutdown was requested.
+            _ = interval.tick(), if !disable_all && !*shutdown_rx.borrow() => {
+                let mut outcome = QueryOutcome::default();
+                for node in nodes.iter_mut() {
+                    outcome += query_node(node, &args, &nats_client).await;
+                }
+                extractor_metrics.inc_by("rpcs_queried", outcome.queried as u64);
+                extractor_metrics.inc_by("rpcs_failed", outcome.failed as u64);
+                if outcome.failed == 0 {
+                    health.tick();
+                }
+            }
+            _ = metrics_interval.tick(), if metrics_window.is_some() => {
+                #[cfg_attr(not(feature = "pushgateway"), allow(unused_variables))]
+                let metrics = extractor_metrics
+                    .publish(&nats_client, &labels, metrics_interval.period())
+                    .await;
+                #[cfg(feature = "pushgateway")]
+                if let Some(pushgateway) = &pushgateway {
+                    pushgateway.push(&metrics).await;
+                }
+            }
+            _ = heartbeat_interval.tick(), if heartbeats.is_some() => {
+                if let Some(heartbeats) = &mut heartbeats {
+                    heartbeats.publish(&nats_client, &labels).await;
+                }
+            }
+            Some((i, msg)) = requests.next(), if !requests.is_empty() => {
+                let node = &mut nodes[i];
+                let Some(reply) = msg.reply else {
+                    log::warn!("Ignoring request for node {} without a reply subject", node.host);
+                    continue;
+                };
+                let request_node = RequestNode {
+                    rpc_client: &node.client,
+                    scanner: &node.scanner,
+                    subject: &node.subject,
+                    labels: &node.labels,
+                };
+                let response = request::handle_request(
+                    request_node,
+                    &nats_client,
+                    &msg.payload,
+                    &args.allow_rpc_requests,
+                    &mut node.rate_limiter,
+                );
+                if let Err(e) = nats_client.publish(reply, response.into()).await {
+                    log::error!("Could not reply to request for node {}: {}", node.host, e);
+                }
+            }
+            Some(msg) = block_events.next(), if !block_events.is_empty() => {
+                let events = match nats::decode_events(&msg) {
+                    Ok(events) => events,
+                    Err(e) => {
+                        log::warn!("Could not decode the events on '{}': {}", msg.subject, e);
+                        continue;
                     }
-                if !args.disable_getaddrmaninfo
-                    && let Err(e) = getaddrmaninfo(&rpc_client, &nats_client).await {
-                        log::error!("Could not fetch and publish 'getaddrmaninfo': {}", e)
+                };
+                let new_blocks: Vec<_> = events
+                    .iter()
+                    .filter_map(blocks::new_block_hash)
+                    .filter(|hash| recent_blocks.insert(*hash))
+                    .collect();
+                if let Some(hash) = new_blocks.last() {
+                    log::debug!("New block {} reported on '{}'", hash, msg.subject);
+                    for node in nodes.iter_mut() {
+                        fetch_on_new_block(node, &args, &nats_client).await;
                     }
+                }
+            }
+            Some((i, msg)) = snapshot_requests.next(), if !snapshot_requests.is_empty() => {
+                let node = &nodes[i];
+                let Some(reply) = msg.reply else {
+                    log::warn!("Ignoring snapshot request for node {} without a reply subject", node.host);
+                    continue;
+                };
+                let batch = node.snapshots.reply(&msg.payload, nats_client.max_payload());
+                if let Err(e) = nats_client.publish(reply, batch.encode_to_vec().into()).await {
+                    log::error!("Could not reply to snapshot request for node {}: {}", node.host, e);
+                }
             }
             res = shutdown_rx.changed() => {
                 match res {
@@ -212,105 +992,1257 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
             }
         }
     }
+    // make sure the events of the last sweep are published before exiting
+    nats_client.flush().await?;
     Ok(())
 }
 
+/// Runs the extractor `task` (see [run]) until it completes or the `signal`
+/// fires. On the signal, the task is asked to shut down through
+/// `shutdown_tx`. A sweep in progress isn't dropped mid-fetch: the task gets
+/// `grace_period` to finish it and publish its events. Returns None if the
+/// task didn't finish in time.
+pub async fn run_until_signal(
+    mut task: JoinHandle<Result<(), RuntimeError>>,
+    signal: impl Future<Output = ()>,
+    shutdown_tx: watch::Sender<bool>,
+    grace_period: Duration,
+) -> Option<Result<(), RuntimeError>> {
+    shared::tokio::select! {
+        result = &mut task => return Some(result.unwrap()),
+        _ = signal => {}
+    }
+    let _ = shutdown_tx.send(true);
+    match time::timeout(grace_period, &mut task).await {
+        Ok(result) => Some(result.unwrap()),
+        Err(_) => {
+            task.abort();
+            None
+        }
+    }
+}
+
+/// How many RPCs were queried and how many of them failed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct QueryOutcome {
+    queried: usize,
+    failed: usize,
+}
+
+impl QueryOutcome {
+    /// The outcome of querying a single RPC.
+    fn single(failed: bool) -> Self {
+        QueryOutcome {
+            queried: 1,
+            failed: failed as usize,
+        }
+    }
+
+    /// True if at least one RPC was queried and all of them failed.
+    fn all_failed(&self) -> bool {
+        self.queried > 0 && self.failed == self.queried
+    }
+}
+
+impl std::ops::AddAssign for QueryOutcome {
+    fn add_assign(&mut self, other: Self) {
+        self.queried += other.queried;
+        self.failed += other.failed;
+    }
+}
+
+/// Queries the enabled RPCs of the node and returns how many of them failed.
+async fn query_node<C: RpcSource>(
+    node: &mut Node<C>,
+    args: &Args,
+    nats_client: &TappedClient,
+) -> QueryOutcome {
+    if !node.labels.contains_key(NETWORK_LABEL) {
+        detect_network(node);
+    }
+    if node.detect_node_id {
+        detect_node_id(node);
+    }
+    let events = &EventBuilder::new(node.labels.clone());
+    let raw_max_bytes = args.include_raw_rpc.then_some(args.raw_rpc_max_bytes);
+    let mut outcome = QueryOutcome::default();
+    if !args.disable_getpeerinfo {
+        let result = getpeerinfo(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+            args.peerinfo_summary,
+            args.peerinfo_network_stats,
+            args.peerinfo_publish_mode,
+            args.peerinfo_compact,
+            args.peerinfo_max_bytes,
+            args.peerinfo_churn.then_some(&mut node.previous_peers),
+            &mut node.per_message,
+        )
+        .await;
+        outcome += finish_query(nats_client, node, "getpeerinfo", result).await;
+    }
+    if !args.disable_getmempoolinfo {
+        // getrawmempool is queried right after getmempoolinfo, with a shared
+        // correlation id for consumers joining the two events
+        let correlation_id = args.enable_getrawmempool.then(rand::random::<u64>);
+        let result = getmempoolinfo(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+            &mut node.previous_mempool_info,
+            correlation_id,
+        )
+        .await;
+        let mempool_info_failed = result.is_err();
+        if mempool_info_failed {
+            node.previous_mempool_info = None;
+        }
+        outcome += finish_query(nats_client, node, "getmempoolinfo", result).await;
+        // Without the getmempoolinfo event, there is nothing to correlate the
+        // txids with.
+        if let (Some(correlation_id), false) = (correlation_id, mempool_info_failed) {
+            let result = getrawmempool(
+                &node.client,
+                nats_client,
+                &node.subject,
+                events,
+                raw_max_bytes,
+                correlation_id,
+            )
+            .await;
+            outcome += finish_query(nats_client, node, "getrawmempool", result).await;
+        }
+    }
+    if !args.disable_uptime {
+        let result = uptime(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+        )
+        .await;
+        outcome += finish_query(nats_client, node, "uptime", result).await;
+    }
+    if !args.disable_getnettotals {
+        let result = getnettotals(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+            &mut node.previous_net_totals,
+        )
+        .await;
+        outcome += finish_query(nats_client, node, "getnettotals", result).await;
+    }
+    if !args.disable_getmemoryinfo {
+        let result = getmemoryinfo(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+        )
+        .await;
+        outcome += finish_query(nats_client, node, "getmemoryinfo", result).await;
+    }
+    if !args.disable_getaddrmaninfo {
+        let result = getaddrmaninfo(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+        )
+        .await;
+        outcome += finish_query(nats_client, node, "getaddrmaninfo", result).await;
+    }
+    if !args.disable_getblockchaininfo {
+        let result = getblockchaininfo(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+        )
+        .await;
+        outcome += finish_query(nats_client, node, "getblockchaininfo", result).await;
+    }
+    if !args.disable_estimatesmartfee {
+        let result = estimatesmartfee(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+            &args.feeestimate_targets,
+            &args.feeestimate_mode,
+        )
+        .await;
+        outcome += finish_query(nats_client, node, "estimatesmartfee", result).await;
+    }
+    if !args.disable_getnodeaddresses {
+        let result = getnodeaddresses(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+            args.getnodeaddresses_count,
+        )
+        .await;
+        outcome += finish_query(nats_client, node, "getnodeaddresses", result).await;
+    }
+    #[cfg(feature = "getorphantxs")]
+    if args.enable_getorphantxs && node.getorphantxs_supported {
+        let result = getorphantxs(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+        )
+        .await;
+        outcome += match result {
+            Err(e) if is_method_not_found(&e) => {
+                log::warn!(
+                    "Node {} doesn't support getorphantxs. Not querying it anymore: {}",
+                    node.host,
+                    e
+                );
+                node.getorphantxs_supported = false;
+                QueryOutcome::single(false)
+            }
+            result => finish_query(nats_client, node, "getorphantxs", result).await,
+        };
+    }
+    if args.enable_getrawaddrman && node.getrawaddrman_supported {
+        let result = getrawaddrman(
+            &node.client,
+            nats_client,
+            &node.subject,
+            events,
+            raw_max_bytes,
+        )
+        .await;
+        outcome += match result {
+            Err(e) if is_method_not_found(&e) => {
+                log::warn!(
+                    "Node {} doesn't support getrawaddrman. Not querying it anymore: {}",
+                    node.host,
+                    e
+                );
+                node.getrawaddrman_supported = false;
+                QueryOutcome::single(false)
+            }
+            result => finish_query(nats_client, node, "getrawaddrman", result).await,
+        };
+    }
+    outcome
+}
+
+/// Keeps the event of a successful query of `method` for the snapshots, or
+/// reports the error. Returns the outcome of the query.
+async fn finish_query<C>(
+    nats_client: &TappedClient,
+    node: &mut Node<C>,
+    method: &'static str,
+    result: Result<Event, FetchOrPublishError>,
+) -> QueryOutcome {
+    match result {
+        Ok(event) => {
+            node.snapshots.update(method, event);
+            QueryOutcome::single(false)
+        }
+        Err(e) => {
+            report_error(nats_client, node, method, e).await;
+            QueryOutcome::single(true)
+        }
+    }
+}
+
+/// Queries and publishes getblockchaininfo of the node after another extractor
+/// reported a new block.
+async fn fetch_on_new_block<C: RpcSource>(
+    node: &mut Node<C>,
+    args: &Args,
+    nats_client: &TappedClient,
+) {
+    let raw_max_bytes = args.include_raw_rpc.then_some(args.raw_rpc_max_bytes);
+    let result = getblockchaininfo(
+        &node.client,
+        nats_client,
+        &node.subject,
+        &EventBuilder::new(node.labels.clone()),
+        raw_max_bytes,
+    )
+    .await;
+    finish_query(nats_client, node, "getblockchaininfo", result).await;
+}
+
+/// Bitcoin Core's JSON-RPC error code for an unknown RPC method.
+const RPC_METHOD_NOT_FOUND: i32 = -32601;
+
+/// Returns true if the RPC call failed because the node doesn't know the
+/// method. The RPC client doesn't expose the JSON-RPC error code, so this
+/// checks the error message.
+fn is_method_not_found(error: &FetchOrPublishError) -> bool {
+    match error {
+        FetchOrPublishError::Rpc(e) => {
+            let message = e.to_string();
+            message.contains(&RPC_METHOD_NOT_FOUND.to_string())
+                || message.contains("Method not found")
+        }
+        _ => false,
+    }
+}
+
+/// Calls the RPC method of the source and deserializes the result. If `raw_max_bytes` is set
+/// (with --include-raw-rpc), the JSON result is returned as well, unless it's
+/// larger than `raw_max_bytes`.
+fn call<T: DeserializeOwned>(
+    rpc_client: &impl RpcSource,
+    method: &str,
+    raw_max_bytes: Option<usize>,
+) -> Result<(T, Option<String>), FetchOrPublishError> {
+    call_with_args(rpc_client, method, &[], raw_max_bytes)
+}
+
+/// Like call(), but passes the arguments to the RPC method.
+fn call_with_args<T: DeserializeOwned>(
+    rpc_client: &impl RpcSource,
+    method: &str,
+    args: &[serde_json::Value],
+    raw_max_bytes: Option<usize>,
+) -> Result<(T, Option<String>), FetchOrPublishError> {
+    let Some(raw_max_bytes) = raw_max_bytes else {
+        return Ok((rpc_client.call(method, args)?, None));
+    };
+    let value: serde_json::Value = rpc_client.call(method, args)?;
+    let raw_json = value.to_string();
+    let result = serde_json::from_value(value)?;
+    if raw_json.len() > raw_max_bytes {
+        log::warn!(
+            "Not including the raw JSON of '{}': it's {} bytes, more than --raw-rpc-max-bytes ({})",
+            method,
+            raw_json.len(),
+            raw_max_bytes
+        );
+        return Ok((result, None));
+    }
+    Ok((result, Some(raw_json)))
+}
+
+/// Fetches and publishes the getpeerinfo data. With `previous_peers`, the
+/// connection churn since the previous sample is published too. The
+/// per-message byte counters are omitted as decided by `per_message`. An
+/// aggregate event larger than `max_bytes` is published in chunks. Returns the
+/// aggregate event for the snapshots, also if only per-peer events or chunks
+/// are published.
+#[allow(clippy::too_many_arguments)]
 async fn getpeerinfo(
-    rpc_client: &Client,
-    nats_client: &async_nats::Client,
-) -> Result<(), FetchOrPublishError> {
-    let peer_info = rpc_client.get_peer_info()?;
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+    peerinfo_summary: bool,
+    network_stats: bool,
+    publish_mode: PeerInfoPublishMode,
+    compact: bool,
+    max_bytes: usize,
+    previous_peers: Option<&mut Option<BTreeMap<u32, rpc_extractor::ChurnPeer>>>,
+    per_message: &mut PerMessageFilter,
+) -> Result<Event, FetchOrPublishError> {
+    let (peer_infos, raw_json): (RPCGetPeerInfo, _) =
+        call(rpc_client, "getpeerinfo", raw_max_bytes)?;
+    let mut peer_infos: rpc_extractor::PeerInfos = peer_infos.into();
+    per_message.apply(&mut peer_infos);
+    let summary = peerinfo_summary.then(|| peer_infos.summary());
+    let network_stats = network_stats.then(|| peer_infos.network_stats());
+    let churn = match previous_peers {
+        Some(previous_peers) => {
+            let current = peer_infos.churn_peers();
+            // The first sample has nothing to compare to.
+            let churn = previous_peers.as_ref().map(|previous| {
+                rpc_extractor::ConnectionChurn::between(previous, &current, current_timestamp())
+            });
+            *previous_peers = Some(current);
+            churn
+        }
+        None => None,
+    };
+
+    if publish_mode != PeerInfoPublishMode::Aggregate {
+        // The raw JSON is only attached to the aggregate event.
+        for peer_info in peer_infos.infos.iter() {
+            let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfo(peer_info.clone())),
+                raw_json: None,
+                correlation_id: None,
+            }))?;
 
-    let proto = Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
-        rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(peer_info.into())),
+            nats_client
+                .publish(
+                    peer_info_subject(subject, peer_info.id),
+                    proto.encode_to_vec().into(),
+                )
+                .await?;
+        }
+    }
+
+    // The compact form is small enough to not need chunks.
+    let chunks = (!compact && peer_infos.encoded_len() > max_bytes)
+        .then(|| peer_infos.clone().into_chunks(max_bytes));
+    let rpc_event = if compact {
+        rpc_extractor::rpc::RpcEvent::CompactPeerInfos(peer_infos.into())
+    } else {
+        rpc_extractor::rpc::RpcEvent::PeerInfos(peer_infos)
+    };
+    let aggregate = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        rpc_event: Some(rpc_event),
+        raw_json: raw_json.clone(),
+        correlation_id: None,
     }))?;
+    if publish_mode != PeerInfoPublishMode::PerPeer {
+        match chunks {
+            Some(chunks) => {
+                log::debug!(
+                    "Publishing the getpeerinfo response in {} chunks: it's larger than --peerinfo-max-bytes ({})",
+                    chunks.len(),
+                    max_bytes
+                );
+                // The raw JSON is only attached to the first chunk.
+                let mut raw_json = raw_json;
+                for chunk in chunks {
+                    let proto =
+                        events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                            rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(chunk)),
+                            raw_json: raw_json.take(),
+                            correlation_id: None,
+                        }))?;
 
-    nats_client
-        .publish(Subject::Rpc.to_string(), proto.encode_to_vec().into())
-        .await?;
-    Ok(())
+                    nats_client
+                        .publish(subject.to_string(), proto.encode_to_vec().into())
+                        .await?;
+                }
+            }
+            None => {
+                nats_client
+                    .publish(subject.to_string(), aggregate.encode_to_vec().into())
+                    .await?;
+            }
+        }
+    }
+
+    if let Some(summary) = summary {
+        let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+            rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfoSummary(summary)),
+            raw_json: None,
+            correlation_id: None,
+        }))?;
+
+        nats_client
+            .publish(subject.to_string(), proto.encode_to_vec().into())
+            .await?;
+    }
+
+    if let Some(network_stats) = network_stats {
+        let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+            rpc_event: Some(rpc_extractor::rpc::RpcEvent::NetworkPeerStats(
+                network_stats,
+            )),
+            raw_json: None,
+            correlation_id: None,
+        }))?;
+
+        nats_client
+            .publish(subject.to_string(), proto.encode_to_vec().into())
+            .await?;
+    }
+
+    if let Some(churn) = churn {
+        let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+            rpc_event: Some(rpc_extractor::rpc::RpcEvent::ConnectionChurn(churn)),
+            raw_json: None,
+            correlation_id: None,
+        }))?;
+
+        nats_client
+            .publish(subject.to_string(), proto.encode_to_vec().into())
+            .await?;
+    }
+    Ok(aggregate)
 }
 
 async fn getmempoolinfo(
-    rpc_client: &Client,
-    nats_client: &async_nats::Client,
-) -> Result<(), FetchOrPublishError> {
-    let mempool_info = rpc_client.get_mempool_info()?;
-
-    let proto = Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
-        rpc_event: Some(rpc_extractor::rpc::RpcEvent::MempoolInfo(
-            mempool_info.into(),
-        )),
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+    previous_mempool_info: &mut Option<rpc_extractor::MempoolInfo>,
+    correlation_id: Option<u64>,
+) -> Result<Event, FetchOrPublishError> {
+    let (mempool_info, raw_json): (RPCGetMempoolInfo, _) =
+        call(rpc_client, "getmempoolinfo", raw_max_bytes)?;
+    let mut mempool_info: rpc_extractor::MempoolInfo = mempool_info.into();
+    if let Some(previous) = previous_mempool_info {
+        mempool_info.set_trend_since(previous);
+    }
+    *previous_mempool_info = Some(mempool_info.clone());
+
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        rpc_event: Some(rpc_extractor::rpc::RpcEvent::MempoolInfo(mempool_info)),
+        raw_json,
+        correlation_id,
     }))?;
 
     nats_client
-        .publish(Subject::Rpc.to_string(), proto.encode_to_vec().into())
+        .publish(subject.to_string(), proto.encode_to_vec().into())
         .await?;
-    Ok(())
+    Ok(proto)
+}
+
+/// Fetches and publishes the txids of the mempool, with the correlation id of
+/// the getmempoolinfo event fetched right before. A response larger than the
+/// maximum NATS payload is published in chunks. Returns the unsplit event for
+/// the snapshots.
+async fn getrawmempool(
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+    correlation_id: u64,
+) -> Result<Event, FetchOrPublishError> {
+    let (txids, raw_json): (Vec<String>, _) = call(rpc_client, "getrawmempool", raw_max_bytes)?;
+    let build = |mempool, raw_json| {
+        events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+            rpc_event: Some(rpc_extractor::rpc::RpcEvent::RawMempool(mempool)),
+            raw_json,
+            correlation_id: Some(correlation_id),
+        }))
+    };
+
+    let mempool = rpc_extractor::RawMempool::new(txids);
+    let proto = build(mempool.clone(), raw_json.clone())?;
+    let max_payload = nats_client
+        .max_payload()
+        .filter(|max_payload| proto.encoded_len() > *max_payload);
+    let Some(max_payload) = max_payload else {
+        nats_client
+            .publish(subject.to_string(), proto.encode_to_vec().into())
+            .await?;
+        return Ok(proto);
+    };
+
+    // The chunks are at most max_payload minus the size of the event around
+    // them, which grows by a few bytes with its content.
+    let envelope_len = build(rpc_extractor::RawMempool::default(), None)?.encoded_len();
+    let chunks = mempool.into_chunks(max_payload.saturating_sub(envelope_len + 16));
+    log::debug!(
+        "Publishing the getrawmempool response in {} chunks: it's larger than the maximum NATS payload ({})",
+        chunks.len(),
+        max_payload
+    );
+    if raw_json.is_some() {
+        log::warn!(
+            "Not including the raw JSON of 'getrawmempool': the response is split into chunks"
+        );
+    }
+    for chunk in chunks {
+        nats_client
+            .publish(
+                subject.to_string(),
+                build(chunk, None)?.encode_to_vec().into(),
+            )
+            .await?;
+    }
+    Ok(proto)
 }
 
 async fn uptime(
-    rpc_client: &Client,
-    nats_client: &async_nats::Client,
-) -> Result<(), FetchOrPublishError> {
-    let uptime_seconds = rpc_client.uptime()?;
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+) -> Result<Event, FetchOrPublishError> {
+    let (uptime_seconds, raw_json) = call(rpc_client, "uptime", raw_max_bytes)?;
 
-    let proto = Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
         rpc_event: Some(rpc_extractor::rpc::RpcEvent::Uptime(uptime_seconds)),
+        raw_json,
+        correlation_id: None,
     }))?;
 
     nats_client
-        .publish(Subject::Rpc.to_string(), proto.encode_to_vec().into())
+        .publish(subject.to_string(), proto.encode_to_vec().into())
         .await?;
-    Ok(())
+    Ok(proto)
 }
 
 async fn getnettotals(
-    rpc_client: &Client,
-    nats_client: &async_nats::Client,
-) -> Result<(), FetchOrPublishError> {
-    let net_totals = rpc_client.get_net_totals()?;
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+    previous_net_totals: &mut Option<rpc_extractor::NetTotals>,
+) -> Result<Event, FetchOrPublishError> {
+    let (net_totals, raw_json): (RPCGetNetTotals, _) =
+        call(rpc_client, "getnettotals", raw_max_bytes)?;
+    let mut net_totals: rpc_extractor::NetTotals = net_totals.into();
+    if let Some(previous) = previous_net_totals {
+        net_totals.set_rates_since(previous);
+    }
+    *previous_net_totals = Some(net_totals.clone());
 
-    let proto = Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
-        rpc_event: Some(rpc_extractor::rpc::RpcEvent::NetTotals(net_totals.into())),
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        rpc_event: Some(rpc_extractor::rpc::RpcEvent::NetTotals(net_totals)),
+        raw_json,
+        correlation_id: None,
     }))?;
 
     nats_client
-        .publish(Subject::Rpc.to_string(), proto.encode_to_vec().into())
+        .publish(subject.to_string(), proto.encode_to_vec().into())
         .await?;
-    Ok(())
+    Ok(proto)
 }
 
 async fn getmemoryinfo(
-    rpc_client: &Client,
-    nats_client: &async_nats::Client,
-) -> Result<(), FetchOrPublishError> {
-    let memory_info = rpc_client.get_memory_info()?;
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+) -> Result<Event, FetchOrPublishError> {
+    let (memory_info, raw_json): (RPCGetMemoryInfoStats, _) =
+        call(rpc_client, "getmemoryinfo", raw_max_bytes)?;
 
-    let proto = Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
         rpc_event: Some(rpc_extractor::rpc::RpcEvent::MemoryInfo(memory_info.into())),
+        raw_json,
+        correlation_id: None,
     }))?;
 
     nats_client
-        .publish(Subject::Rpc.to_string(), proto.encode_to_vec().into())
+        .publish(subject.to_string(), proto.encode_to_vec().into())
         .await?;
-    Ok(())
+    Ok(proto)
 }
 
 async fn getaddrmaninfo(
-    rpc_client: &Client,
-    nats_client: &async_nats::Client,
-) -> Result<(), FetchOrPublishError> {
-    let addrman_info = rpc_client.get_addr_man_info()?;
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+) -> Result<Event, FetchOrPublishError> {
+    let (addrman_info, raw_json): (RPCGetAddrManInfo, _) =
+        call(rpc_client, "getaddrmaninfo", raw_max_bytes)?;
 
-    let proto = Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
         rpc_event: Some(rpc_extractor::rpc::RpcEvent::AddrmanInfo(
             addrman_info.into(),
         )),
+        raw_json,
+        correlation_id: None,
     }))?;
 
     nats_client
-        .publish(Subject::Rpc.to_string(), proto.encode_to_vec().into())
+        .publish(subject.to_string(), proto.encode_to_vec().into())
         .await?;
-    Ok(())
+    Ok(proto)
+}
+
+/// Logs an error that occurred while querying a node and publishes it as
+/// [ErrorEvent] so it can be monitored.
+async fn report_error<C>(
+    nats_client: &TappedClient,
+    node: &Node<C>,
+    operation: &str,
+    error: FetchOrPublishError,
+) {
+    log::error!(
+        "Could not fetch and publish '{}' from {}: {}",
+        operation,
+        node.host,
+        error
+    );
+    let event = ErrorEvent::new(
+        Extractor::RpcExtractor,
+        operation,
+        error.to_string(),
+        ErrorSeverity::Error,
+    );
+    match EventBuilder::new(node.labels.clone()).build(PeerObserverEvent::ErrorEvent(event)) {
+        Ok(proto) => {
+            if let Err(e) = nats_client
+                .publish(node.errors_subject.clone(), proto.encode_to_vec().into())
+                .await
+            {
+                log::error!("Could not publish error event: {}", e);
+            }
+        }
+        Err(e) => log::error!("Could not create error event: {}", e),
+    }
+}
+
+/// Determines the network of the node with getblockchaininfo and adds it as
+/// network label. If the node can't be reached, we try again on the next
+/// query.
+fn detect_network<C: RpcSource>(node: &mut Node<C>) {
+    match node
+        .client
+        .call::<RPCGetBlockchainInfo>("getblockchaininfo", &[])
+    {
+        Ok(info) => {
+            log::info!("Node {} is on network '{}'", node.host, info.chain);
+            node.labels.insert(NETWORK_LABEL.to_string(), info.chain);
+        }
+        Err(e) => log::warn!(
+            "Could not determine the network of node {}: {}",
+            node.host,
+            e
+        ),
+    }
+}
+
+/// With multiple nodes and no explicit `--node`, uses the address the node
+/// advertises to its peers, the first local address of getnetworkinfo, as node
+/// label (see [shared::labels::node_id]). Nodes without local addresses, e.g.
+/// not listening or behind NAT, keep the RPC host. If the node can't be
+/// reached, we try again on the next query.
+fn detect_node_id<C: RpcSource>(node: &mut Node<C>) {
+    match node.client.call::<serde_json::Value>("getnetworkinfo", &[]) {
+        Ok(info) => {
+            node.detect_node_id = false;
+            if let Some(id) = local_address(&info) {
+                log::info!("Node {} identifies as '{}'", node.host, id);
+                node.labels.insert(NODE_LABEL.to_string(), id);
+            }
+        }
+        Err(e) => log::warn!(
+            "Could not determine the identity of node {}: {}",
+            node.host,
+            e
+        ),
+    }
+}
+
+/// The first local address ("address:port") of a getnetworkinfo response.
+fn local_address(network_info: &serde_json::Value) -> Option<String> {
+    let local = network_info["localaddresses"].as_array()?.first()?;
+    Some(format!(
+        "{}:{}",
+        local["address"].as_str()?,
+        local["port"].as_u64()?
+    ))
+}
+
+async fn getblockchaininfo(
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+) -> Result<Event, FetchOrPublishError> {
+    let (blockchain_info, raw_json): (RPCGetBlockchainInfo, _) =
+        call(rpc_client, "getblockchaininfo", raw_max_bytes)?;
+
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        rpc_event: Some(rpc_extractor::rpc::RpcEvent::BlockchainInfo(
+            blockchain_info.into(),
+        )),
+        raw_json,
+        correlation_id: None,
+    }))?;
+
+    nats_client
+        .publish(subject.to_string(), proto.encode_to_vec().into())
+        .await?;
+    Ok(proto)
+}
+
+async fn estimatesmartfee(
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+    targets: &[u32],
+    estimate_mode: &str,
+) -> Result<Event, FetchOrPublishError> {
+    let mut responses = Vec::with_capacity(targets.len());
+    let mut raw_jsons = Vec::with_capacity(targets.len());
+    for target in targets {
+        let (response, raw_json): (RPCEstimateSmartFee, _) = call_with_args(
+            rpc_client,
+            "estimatesmartfee",
+            &[(*target).into(), estimate_mode.into()],
+            raw_max_bytes,
+        )?;
+        responses.push((*target, response));
+        raw_jsons.extend(raw_json);
+    }
+    // With --include-raw-rpc, the JSON results of all targets are included as
+    // an array in the order of the targets.
+    let raw_json = (raw_jsons.len() == targets.len() && raw_max_bytes.is_some())
+        .then(|| format!("[{}]", raw_jsons.join(",")));
+
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        rpc_event: Some(rpc_extractor::rpc::RpcEvent::FeeEstimates(
+            rpc_extractor::FeeEstimates::new(estimate_mode.to_string(), responses),
+        )),
+        raw_json,
+        correlation_id: None,
+    }))?;
+
+    nats_client
+        .publish(subject.to_string(), proto.encode_to_vec().into())
+        .await?;
+    Ok(proto)
+}
+
+async fn getnodeaddresses(
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+    count: u32,
+) -> Result<Event, FetchOrPublishError> {
+    let (addresses, raw_json): (Vec<rpc_extractor::RPCNodeAddress>, _) = call_with_args(
+        rpc_client,
+        "getnodeaddresses",
+        &[count.into()],
+        raw_max_bytes,
+    )?;
+
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        rpc_event: Some(rpc_extractor::rpc::RpcEvent::NodeAddresses(
+            addresses.into(),
+        )),
+        raw_json,
+        correlation_id: None,
+    }))?;
+
+    nats_client
+        .publish(subject.to_string(), proto.encode_to_vec().into())
+        .await?;
+    Ok(proto)
+}
+
+#[cfg(feature = "getorphantxs")]
+async fn getorphantxs(
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+) -> Result<Event, FetchOrPublishError> {
+    // verbosity 1 includes the peers that announced the orphans
+    let (orphans, raw_json): (Vec<rpc_extractor::RPCOrphanTx>, _) =
+        call_with_args(rpc_client, "getorphantxs", &[1.into()], raw_max_bytes)?;
+
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        rpc_event: Some(rpc_extractor::rpc::RpcEvent::OrphanTxs(orphans.into())),
+        raw_json,
+        correlation_id: None,
+    }))?;
+
+    nats_client
+        .publish(subject.to_string(), proto.encode_to_vec().into())
+        .await?;
+    Ok(proto)
+}
+
+async fn getrawaddrman(
+    rpc_client: &impl RpcSource,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    raw_max_bytes: Option<usize>,
+) -> Result<Event, FetchOrPublishError> {
+    let (addrman, raw_json): (rpc_extractor::RPCRawAddrMan, _) =
+        call_with_args(rpc_client, "getrawaddrman", &[], raw_max_bytes)?;
+
+    let proto = events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        rpc_event: Some(rpc_extractor::rpc::RpcEvent::RawAddrman(addrman.into())),
+        raw_json,
+        correlation_id: None,
+    }))?;
+
+    nats_client
+        .publish(subject.to_string(), proto.encode_to_vec().into())
+        .await?;
+    Ok(proto)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::tokio;
+    use shared::tokio::sync::oneshot;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_per_message_filter() {
+        let peer_infos = || {
+            rpc_extractor::PeerInfos::new(vec![rpc_extractor::PeerInfo {
+                id: 1,
+                bytes_sent_per_message: [("ping".to_string(), 32)].into(),
+                ..Default::default()
+            }])
+        };
+        let omitted = |filter: &mut PerMessageFilter| {
+            let mut infos = peer_infos();
+            filter.apply(&mut infos);
+            infos.infos[0].per_message_omitted == Some(true)
+        };
+
+        let mut always = PerMessageFilter::new(PeerInfoPerMessage::Always, 10);
+        assert!(!omitted(&mut always));
+        assert!(!omitted(&mut always));
+
+        let mut changed = PerMessageFilter::new(PeerInfoPerMessage::Changed, 10);
+        assert!(!omitted(&mut changed));
+        assert!(omitted(&mut changed));
+
+        let mut every = PerMessageFilter::new(PeerInfoPerMessage::Every, 3);
+        let samples: Vec<bool> = (0..7).map(|_| omitted(&mut every)).collect();
+        assert_eq!(samples, vec![false, true, true, false, true, true, false]);
+    }
+
+    /// Spawns a fake extractor task with a sweep of `sweep` in progress when
+    /// the signal fires. It only exits once asked to shut down after the sweep.
+    fn slow_sweep(
+        sweep: Duration,
+    ) -> (
+        JoinHandle<Result<(), RuntimeError>>,
+        impl Future<Output = ()>,
+        watch::Sender<bool>,
+        Arc<AtomicBool>,
+    ) {
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+        let (started_tx, started_rx) = oneshot::channel();
+        let published = Arc::new(AtomicBool::new(false));
+        let task_published = published.clone();
+        let task = tokio::spawn(async move {
+            let _ = started_tx.send(());
+            time::sleep(sweep).await;
+            task_published.store(true, Ordering::SeqCst);
+            while !*shutdown_rx.borrow() {
+                let _ = shutdown_rx.changed().await;
+            }
+            Ok(())
+        });
+        let signal = async {
+            let _ = started_rx.await;
+        };
+        (task, signal, shutdown_tx, published)
+    }
+
+    #[cfg(feature = "nats_integration_tests")]
+    #[tokio::test]
+    async fn test_getrawmempool_chunks() {
+        use shared::testing::nats_server::NatsServerForTesting;
+
+        const MAX_PAYLOAD: usize = 64 * 1024;
+        let nats_server = NatsServerForTesting::new().await;
+        let client = shared::async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+            .await
+            .unwrap();
+        let mut sub = client.subscribe(Subject::Rpc.to_string()).await.unwrap();
+        client.flush().await.unwrap();
+        let nats_client =
+            TappedClient::new(client, DebugBuffer::new(0), MultiSink::open(&[]).unwrap())
+                .with_max_payload(Some(MAX_PAYLOAD));
+
+        // about 330 kB of txids, like a small mainnet mempool
+        let txids: Vec<String> = (0..5000).map(|i| format!("{:064x}", i)).collect();
+        let rpc = source::MockRpc::default().with("getrawmempool", serde_json::json!(txids));
+        let events = EventBuilder::new(Labels::new());
+        let aggregate = getrawmempool(
+            &rpc,
+            &nats_client,
+            &Subject::Rpc.to_string(),
+            &events,
+            None,
+            7,
+        )
+        .await
+        .unwrap();
+        assert!(aggregate.encoded_len() > MAX_PAYLOAD);
+
+        let mut received = vec![];
+        loop {
+            let msg = time::timeout(Duration::from_secs(5), sub.next())
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(msg.payload.len() <= MAX_PAYLOAD);
+            let Some(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::RawMempool(chunk)),
+                correlation_id,
+                ..
+            })) = Event::decode(msg.payload).unwrap().peer_observer_event
+            else {
+                panic!("expected a RawMempool event");
+            };
+            assert_eq!(correlation_id, Some(7));
+            assert_eq!(chunk.chunk_index, Some(received.len() as u32));
+            received.push(chunk);
+            if received.len() as u32 == received[0].chunk_total.unwrap() {
+                break;
+            }
+        }
+        assert!(received.len() > 1);
+        assert_eq!(
+            received
+                .into_iter()
+                .flat_map(|c| c.txids)
+                .collect::<Vec<_>>(),
+            txids
+        );
+    }
+
+    #[cfg(feature = "nats_integration_tests")]
+    #[tokio::test]
+    async fn test_shutdown_during_slow_fetch() {
+        use shared::testing::nats_server::NatsServerForTesting;
+
+        let nats_server = NatsServerForTesting::new().await;
+        let nats_client = shared::async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+            .await
+            .unwrap();
+        let mut sub = nats_client
+            .subscribe(Subject::Rpc.to_string())
+            .await
+            .unwrap();
+        nats_client.flush().await.unwrap();
+
+        // Only uptime is queried, but detecting the network calls the node
+        // too. The sweep of two slow calls takes longer than the query
+        // interval of one second.
+        let args = Args::new(
+            format!("127.0.0.1:{}", nats_server.port),
+            log::Level::Info,
+            "127.0.0.1:8332".to_string(),
+            "cookie".to_string(),
+            1,
+            true,
+            true,
+            false,
+            true,
+            true,
+            true,
+            true,
+            true,
+            true,
+        );
+        let rpc = source::MockRpc::default()
+            .with("uptime", serde_json::json!(42))
+            .with_delay(Duration::from_millis(600));
+        let node = Node::new(
+            &args,
+            "127.0.0.1:8332",
+            rpc,
+            Scanner::new("http://127.0.0.1:8332".to_string(), Auth::None),
+            Subject::Rpc.to_string(),
+            Subject::Errors.to_string(),
+            Labels::new(),
+        );
+
+        // The fetches block the runtime, so the shutdown is requested from
+        // another thread while the first sweep is in progress.
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let requester = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            shutdown_tx.send(true).unwrap();
+        });
+        let result = time::timeout(
+            Duration::from_secs(10),
+            run_nodes(args, Labels::new(), vec![node], shutdown_rx),
+        )
+        .await;
+        requester.join().unwrap();
+        assert!(matches!(result, Ok(Ok(()))));
+
+        // the sweep wasn't dropped mid-fetch and its event was published
+        let msg = time::timeout(Duration::from_secs(5), sub.next())
+            .await
+            .unwrap()
+            .unwrap();
+        let event = Event::decode(msg.payload).unwrap();
+        assert!(matches!(
+            event.peer_observer_event,
+            Some(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::Uptime(42)),
+                ..
+            }))
+        ));
+        // no new sweep was started after the shutdown was requested
+        assert!(
+            time::timeout(Duration::from_millis(500), sub.next())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_grace_period_exceeded() {
+        let (task, signal, shutdown_tx, published) = slow_sweep(Duration::from_secs(60));
+        let result = run_until_signal(task, signal, shutdown_tx, Duration::from_millis(100)).await;
+        assert!(result.is_none());
+        assert!(!published.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_query_interval_not_zero() {
+        let parse = |interval: &str| {
+            Args::try_parse_from([
+                "rpc-extractor",
+                "--rpc-cookie-file",
+                "cookie",
+                "--query-interval",
+                interval,
+            ])
+        };
+        assert_eq!(parse("1").unwrap().query_interval, 1);
+        assert_eq!(
+            parse("0").unwrap_err().kind(),
+            clap::error::ErrorKind::ValueValidation
+        );
+    }
+
+    #[test]
+    fn test_query_outcome_all_failed() {
+        let outcome = |queried, failed| QueryOutcome { queried, failed };
+        assert!(outcome(3, 3).all_failed());
+        assert!(!outcome(3, 2).all_failed());
+        // nothing queried isn't a failure
+        assert!(!outcome(0, 0).all_failed());
+
+        let mut sum = outcome(2, 2);
+        sum += outcome(2, 0);
+        assert_eq!(sum, outcome(4, 2));
+        assert!(!sum.all_failed());
+
+        sum += QueryOutcome::single(true);
+        sum += QueryOutcome::single(false);
+        assert_eq!(sum, outcome(6, 3));
+    }
+
+    #[test]
+    fn test_check_rpcs_enabled() {
+        let mut args = Args::new(
+            "127.0.0.1:4222".to_string(),
+            log::Level::Info,
+            "127.0.0.1:8332".to_string(),
+            "cookie".to_string(),
+            10,
+            true,
+            true,
+            true,
+            true,
+            true,
+            true,
+            true,
+            true,
+            true,
+        );
+        assert!(matches!(
+            check_rpcs_enabled(&args),
+            Err(RuntimeError::InvalidArgs(_))
+        ));
+        args.allow_empty = true;
+        assert!(check_rpcs_enabled(&args).is_ok());
+
+        args.allow_empty = false;
+        #[cfg(feature = "getorphantxs")]
+        {
+            args.enable_getorphantxs = true;
+            assert!(check_rpcs_enabled(&args).is_ok());
+            args.enable_getorphantxs = false;
+        }
+        args.disable_uptime = false;
+        assert!(check_rpcs_enabled(&args).is_ok());
+    }
+
+    #[test]
+    fn test_call_raw_json() {
+        let rpc = source::MockRpc::default().with("uptime", serde_json::json!(42));
+        let (uptime, raw_json): (u64, _) = call(&rpc, "uptime", None).unwrap();
+        assert_eq!((uptime, raw_json), (42, None));
+        let (uptime, raw_json): (u64, _) = call(&rpc, "uptime", Some(100)).unwrap();
+        assert_eq!((uptime, raw_json.as_deref()), (42, Some("42")));
+        // larger than --raw-rpc-max-bytes
+        let (uptime, raw_json): (u64, _) = call(&rpc, "uptime", Some(1)).unwrap();
+        assert_eq!((uptime, raw_json), (42, None));
+        assert_eq!(rpc.calls().len(), 3);
+    }
+
+    #[test]
+    fn test_call_errors() {
+        let rpc = source::MockRpc::default().with("uptime", serde_json::json!("a while"));
+        let result: Result<(u64, _), _> = call(&rpc, "uptime", None);
+        assert!(matches!(
+            result,
+            Err(FetchOrPublishError::Rpc(
+                shared::corepc_client::client_sync::Error::Json(_)
+            ))
+        ));
+        let result: Result<(u64, _), _> = call(&rpc, "uptime", Some(100));
+        assert!(matches!(result, Err(FetchOrPublishError::Json(_))));
+
+        let result: Result<(u64, _), _> = call(&rpc, "getorphantxs", None);
+        assert!(is_method_not_found(&result.unwrap_err()));
+    }
+
+    #[test]
+    fn test_call_with_args() {
+        let rpc = source::MockRpc::default().with(
+            "getnodeaddresses",
+            serde_json::json!([{
+                "time": 1700000000,
+                "services": 1033,
+                "address": "203.0.113.5",
+                "port": 8333,
+                "network": "ipv4"
+            }]),
+        );
+        let (addresses, _): (Vec<rpc_extractor::RPCNodeAddress>, _) =
+            call_with_args(&rpc, "getnodeaddresses", &[100.into()], None).unwrap();
+        let addresses: rpc_extractor::NodeAddresses = addresses.into();
+        assert_eq!(addresses.addresses.len(), 1);
+        assert_eq!(addresses.addresses[0].address, "203.0.113.5");
+        assert_eq!(
+            rpc.calls(),
+            vec![("getnodeaddresses".to_string(), vec![100.into()])]
+        );
+    }
+
+    #[test]
+    fn test_local_address() {
+        let info = serde_json::json!({
+            "localaddresses": [
+                {"address": "203.0.113.5", "port": 8333, "score": 4},
+                {"address": "2001:db8::1", "port": 8333, "score": 1}
+            ]
+        });
+        assert_eq!(local_address(&info), Some("203.0.113.5:8333".to_string()));
+        let info = serde_json::json!({"localaddresses": []});
+        assert_eq!(local_address(&info), None);
+        assert_eq!(local_address(&serde_json::json!({})), None);
+    }
 }
@@ -1,29 +1,36 @@
-use rpc_extractor::Args;
+use rpc_extractor::{Args, SHUTDOWN_GRACE_PERIOD};
+use shared::args::ExtractorArgs;
+use shared::config;
 use shared::log;
+use shared::logging;
 use shared::tokio::{self, signal, sync::watch};
-use shared::{clap::Parser, simple_logger};
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
+    let args: Args = config::parse_args();
 
-    if let Err(e) = simple_logger::init_with_level(args.log_level) {
+    if let Err(e) = logging::init(args.common()) {
         eprintln!("rpc extractor error: {}", e);
     }
 
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
     let rpc_handle = tokio::spawn(rpc_extractor::run(args, shutdown_rx));
+    let ctrl_c = async {
+        let _ = signal::ctrl_c().await;
+        log::info!("Received Ctrl+C. Stopping after the current sweep...");
+    };
 
-    tokio::select! {
-        _ = signal::ctrl_c() => {
-            log::info!("Received Ctrl+C. Stopping...");
-            let _ = shutdown_tx.send(true);
-        }
-        result = rpc_handle => {
-            match result.unwrap() {
-                Ok(_) => log::info!("rpc-extractor task completed."),
-                Err(e) => log::error!("rpc-extractor task failed: {e}"),
-            }
+    match rpc_extractor::run_until_signal(rpc_handle, ctrl_c, shutdown_tx, SHUTDOWN_GRACE_PERIOD)
+        .await
+    {
+        Some(Ok(_)) => log::info!("rpc-extractor task completed."),
+        Some(Err(e)) => {
+            log::error!("rpc-extractor task failed: {e}");
+            std::process::exit(1);
         }
+        None => log::warn!(
+            "The current sweep didn't finish within {:?}. Stopped anyway.",
+            SHUTDOWN_GRACE_PERIOD
+        ),
     }
 }
@@ -0,0 +1,309 @@
+use shared::log;
+use shared::prometheus::{Encoder, GaugeVec, IntCounterVec, Opts, Registry, TextEncoder};
+use shared::protobuf::event::{Extractor, ExtractorMetrics};
+use shared::tokio::task;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// The same names as the extractor metrics of the metrics tool, so that
+/// dashboards work with both.
+const NAMESPACE: &str = "peerobserver";
+const LABEL_EXTRACTOR: &str = "extractor";
+const LABEL_NAME: &str = "name";
+
+/// The timeout for connecting to, writing to, and reading from the
+/// pushgateway.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pushes the extractor metrics to a Prometheus pushgateway
+/// (`--pushgateway-url`), for deployments where the metrics tool can't
+/// scrape, e.g. cron jobs with `--once`.
+///
+/// The counts of the windows are summed up into counters, and each push
+/// replaces the metrics of the job and instance on the pushgateway.
+pub struct Pushgateway {
+    /// `host:port` of the pushgateway.
+    address: String,
+    /// `/metrics/job/<job>[/instance/<instance>]`, below the path of the URL.
+    path: String,
+    registry: Registry,
+    counters: IntCounterVec,
+    gauges: GaugeVec,
+}
+
+impl Pushgateway {
+    /// Creates a pushgateway sink for the URL, e.g. `http://127.0.0.1:9091`.
+    /// Only plain HTTP is supported. The job and the instance, if any, must
+    /// only contain alphanumeric characters and `-_.:`.
+    pub fn new(url: &str, job: &str, instance: Option<&str>) -> Result<Self, String> {
+        let (address, base_path) = parse_url(url)?;
+        let mut path = format!("{}/metrics/job/{}", base_path, grouping_label("job", job)?);
+        if let Some(instance) = instance {
+            path.push_str(&format!(
+                "/instance/{}",
+                grouping_label("instance", instance)?
+            ));
+        }
+
+        let registry =
+            Registry::new_custom(Some(NAMESPACE.to_string()), None).map_err(|e| e.to_string())?;
+        let counters = IntCounterVec::new(
+            Opts::new(
+                "extractor_counter",
+                "Counters reported by the extractors about their own operation, by extractor and counter name.",
+            ),
+            &[LABEL_EXTRACTOR, LABEL_NAME],
+        )
+        .map_err(|e| e.to_string())?;
+        let gauges = GaugeVec::new(
+            Opts::new(
+                "extractor_gauge",
+                "Gauges reported by the extractors about their own operation, by extractor and gauge name.",
+            ),
+            &[LABEL_EXTRACTOR, LABEL_NAME],
+        )
+        .map_err(|e| e.to_string())?;
+        registry
+            .register(Box::new(counters.clone()))
+            .map_err(|e| e.to_string())?;
+        registry
+            .register(Box::new(gauges.clone()))
+            .map_err(|e| e.to_string())?;
+
+        Ok(Pushgateway {
+            address,
+            path,
+            registry,
+            counters,
+            gauges,
+        })
+    }
+
+    /// Adds the counts of the window to the counters and sets the gauges.
+    fn record(&self, metrics: &ExtractorMetrics) {
+        let extractor = metrics.extractor().to_string();
+        for (name, count) in metrics.counters.iter() {
+            self.counters
+                .with_label_values(&[extractor.as_str(), name.as_str()])
+                .inc_by(*count);
+        }
+        for (name, value) in metrics.gauges.iter() {
+            self.gauges
+                .with_label_values(&[extractor.as_str(), name.as_str()])
+                .set(*value);
+        }
+    }
+
+    /// The recorded metrics in the Prometheus text format.
+    fn body(&self) -> Result<Vec<u8>, shared::prometheus::Error> {
+        let mut body = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut body)?;
+        Ok(body)
+    }
+
+    /// Records the metrics of the window and pushes all recorded metrics.
+    /// Failures are only logged, the metrics are pushed again with the next
+    /// window.
+    pub async fn push(&self, metrics: &ExtractorMetrics) {
+        self.record(metrics);
+        let body = match self.body() {
+            Ok(body) => body,
+            Err(e) => {
+                log::error!("Could not encode the metrics for the pushgateway: {}", e);
+                return;
+            }
+        };
+        let address = self.address.clone();
+        let path = self.path.clone();
+        // The pushes are rare, a blocking connection is good enough.
+        match task::spawn_blocking(move || put(&address, &path, &body)).await {
+            Ok(Ok(())) => log::debug!(
+                "Pushed the extractor metrics to {}{}",
+                self.address,
+                self.path
+            ),
+            Ok(Err(e)) => log::error!(
+                "Could not push the extractor metrics to {}{}: {}",
+                self.address,
+                self.path,
+                e
+            ),
+            Err(e) => log::error!("Could not push the extractor metrics: {}", e),
+        }
+    }
+}
+
+/// Splits a `http://host[:port][/path]` URL into `host:port` and the path
+/// without a trailing slash. The port defaults to 80.
+fn parse_url(url: &str) -> Result<(String, String), String> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(format!(
+            "invalid pushgateway URL '{}': expected 'http://host:port'",
+            url
+        ));
+    };
+    let (host, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    if host.is_empty() {
+        return Err(format!("missing host in pushgateway URL '{}'", url));
+    }
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    Ok((address, path.trim_end_matches('/').to_string()))
+}
+
+/// Checks that the value of a grouping label can be used as is in the path.
+fn grouping_label<'a>(label: &str, value: &'a str) -> Result<&'a str, String> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || "-_.:".contains(c);
+    if value.is_empty() || !value.chars().all(valid) {
+        return Err(format!(
+            "invalid pushgateway {} '{}': only alphanumeric characters and '-_.:' are allowed",
+            label, value
+        ));
+    }
+    Ok(value)
+}
+
+/// Sends the metrics with a PUT request, replacing the metrics of the group.
+fn put(address: &str, path: &str, body: &[u8]) -> io::Result<()> {
+    let addr = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::other(format!("could not resolve '{}'", address)))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let header = format!(
+        "PUT {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        path,
+        address,
+        body.len()
+    );
+    stream.write_all(header.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+
+    // The body isn't necessarily UTF-8, only the status line is checked.
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    check_response(&response)
+}
+
+/// Returns an error unless the response has a 2xx status.
+fn check_response(response: &[u8]) -> io::Result<()> {
+    let status_line = response.split(|b| *b == b'\n').next().unwrap_or_default();
+    let status_line = String::from_utf8_lossy(status_line);
+    let status_line = status_line.trim_end();
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(io::Error::other(format!(
+            "pushgateway answered with '{}'",
+            status_line
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn metrics(rpcs_queried: u64) -> ExtractorMetrics {
+        ExtractorMetrics {
+            extractor: Extractor::RpcExtractor.into(),
+            window: 60,
+            counters: [("rpcs_queried".to_string(), rpcs_queried)].into(),
+            gauges: [("nodes".to_string(), 2.0)].into(),
+        }
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            parse_url("http://127.0.0.1:9091"),
+            Ok(("127.0.0.1:9091".to_string(), "".to_string()))
+        );
+        assert_eq!(
+            parse_url("http://pushgateway/prefix/"),
+            Ok(("pushgateway:80".to_string(), "/prefix".to_string()))
+        );
+        assert!(parse_url("https://127.0.0.1:9091").is_err());
+        assert!(parse_url("127.0.0.1:9091").is_err());
+        assert!(parse_url("http:///metrics").is_err());
+    }
+
+    #[test]
+    fn test_new() {
+        let pushgateway =
+            Pushgateway::new("http://127.0.0.1:9091", "rpc-extractor", Some("node-1")).unwrap();
+        assert_eq!(pushgateway.address, "127.0.0.1:9091");
+        assert_eq!(
+            pushgateway.path,
+            "/metrics/job/rpc-extractor/instance/node-1"
+        );
+        assert!(Pushgateway::new("http://127.0.0.1:9091", "rpc/extractor", None).is_err());
+        assert!(Pushgateway::new("http://127.0.0.1:9091", "rpc-extractor", Some("")).is_err());
+    }
+
+    #[test]
+    fn test_body() {
+        let pushgateway = Pushgateway::new("http://127.0.0.1:9091", "rpc-extractor", None).unwrap();
+        pushgateway.record(&metrics(3));
+        pushgateway.record(&metrics(4));
+        let body = String::from_utf8(pushgateway.body().unwrap()).unwrap();
+        // the counts of the windows are summed up
+        assert!(body.contains(
+            r#"peerobserver_extractor_counter{extractor="rpc-extractor",name="rpcs_queried"} 7"#
+        ));
+        assert!(
+            body.contains(
+                r#"peerobserver_extractor_gauge{extractor="rpc-extractor",name="nodes"} 2"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_put() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 1024];
+            // the header and the body might arrive separately
+            while !request.ends_with(b"metric 1\n") {
+                let n = stream.read(&mut buffer).unwrap();
+                assert!(n > 0, "connection closed before the body was read");
+                request.extend_from_slice(&buffer[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        put(&address, "/metrics/job/rpc-extractor", b"metric 1\n").unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("PUT /metrics/job/rpc-extractor HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Length: 9\r\n"));
+        assert!(request.ends_with("\r\n\r\nmetric 1\n"));
+    }
+
+    #[test]
+    fn test_check_response() {
+        assert!(check_response(b"HTTP/1.1 200 OK\r\n\r\n").is_ok());
+        assert!(check_response(b"HTTP/1.1 202 Accepted\r\n\r\n").is_ok());
+        assert!(check_response(b"HTTP/1.1 400 Bad Request\r\n\r\n").is_err());
+        assert!(check_response(b"").is_err());
+        // a body that isn't UTF-8 doesn't matter
+        assert!(check_response(b"HTTP/1.1 200 OK\r\n\r\n\xff\xfe").is_ok());
+    }
+}
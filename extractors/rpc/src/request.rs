@@ -0,0 +1,202 @@
+use crate::scan::Scanner;
+use crate::source::RpcSource;
+use shared::bitcoin::Txid;
+use shared::corepc_client::types::v17::{GetMempoolAncestors, GetMempoolDescendants};
+use shared::labels::Labels;
+use shared::nats::TappedClient;
+use shared::prost::Message;
+use shared::protobuf::rpc_extractor::{RpcRequest, RpcResponse, rpc_request, rpc_response};
+use shared::serde_json;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// The RPC methods that can be allowed to be requested with `--allow-rpc-request`.
+pub const REQUEST_METHODS: &[&str] = &[
+    "getmempoolancestors",
+    "getmempooldescendants",
+    "scantxoutset",
+];
+
+/// Limits the number of answered requests per second. Requests over the limit
+/// are answered with an error without querying the node.
+pub struct RateLimiter {
+    max_per_second: u32,
+    window_start: Instant,
+    count: u32,
+}
+
+impl RateLimiter {
+    pub fn new(max_per_second: u32) -> Self {
+        RateLimiter {
+            max_per_second,
+            window_start: Instant::now(),
+            count: 0,
+        }
+    }
+
+    /// Returns true if another request can be answered at `now`.
+    fn allow(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.window_start) >= Duration::from_secs(1) {
+            self.window_start = now;
+            self.count = 0;
+        }
+        if self.count >= self.max_per_second {
+            return false;
+        }
+        self.count += 1;
+        true
+    }
+}
+
+/// Checks that the requested method is allowed and the rate limit isn't
+/// exceeded.
+fn check_request(
+    request: &rpc_request::Request,
+    allowed_methods: &[String],
+    rate_limiter: &mut RateLimiter,
+) -> Result<(), String> {
+    let method = request.method();
+    if !allowed_methods.iter().any(|m| m == method) {
+        return Err(format!("RPC method '{}' is not allowed", method));
+    }
+    if !rate_limiter.allow(Instant::now()) {
+        return Err(format!(
+            "rate limit of {} requests per second exceeded",
+            rate_limiter.max_per_second
+        ));
+    }
+    Ok(())
+}
+
+/// The node a request is answered for.
+pub struct RequestNode<'a, C> {
+    pub rpc_client: &'a C,
+    /// Runs the scantxoutset scans of the node.
+    pub scanner: &'a Scanner,
+    /// The subject and labels of the events published for the node, e.g. the
+    /// ScanProgress events.
+    pub subject: &'a str,
+    pub labels: &'a Labels,
+}
+
+/// Decodes a request, queries the node if it's allowed, and returns the
+/// encoded response to reply with.
+pub fn handle_request<C: RpcSource>(
+    node: RequestNode<C>,
+    nats_client: &TappedClient,
+    payload: &[u8],
+    allowed_methods: &[String],
+    rate_limiter: &mut RateLimiter,
+) -> Vec<u8> {
+    let response = match RpcRequest::decode(payload) {
+        Ok(RpcRequest {
+            request: Some(request),
+        }) => match check_request(&request, allowed_methods, rate_limiter) {
+            Ok(()) => query(node, nats_client, request),
+            Err(e) => RpcResponse::error(e),
+        },
+        Ok(RpcRequest { request: None }) => RpcResponse::error("empty request".to_string()),
+        Err(e) => RpcResponse::error(format!("could not decode request: {}", e)),
+    };
+    response.encode_to_vec()
+}
+
+fn query<C: RpcSource>(
+    node: RequestNode<C>,
+    nats_client: &TappedClient,
+    request: rpc_request::Request,
+) -> RpcResponse {
+    let rpc_client = node.rpc_client;
+    let method = request.method();
+    let result = match request {
+        rpc_request::Request::MempoolAncestors(txid) => parse_txid(&txid).and_then(|txid| {
+            rpc_client
+                .call::<GetMempoolAncestors>("getmempoolancestors", &[txid_arg(txid)])
+                .map(|a| rpc_response::Response::MempoolAncestors(a.into()))
+                .map_err(|e| e.to_string())
+        }),
+        rpc_request::Request::MempoolDescendants(txid) => parse_txid(&txid).and_then(|txid| {
+            rpc_client
+                .call::<GetMempoolDescendants>("getmempooldescendants", &[txid_arg(txid)])
+                .map(|d| rpc_response::Response::MempoolDescendants(d.into()))
+                .map_err(|e| e.to_string())
+        }),
+        rpc_request::Request::ScanTxOutSet(scan) => node
+            .scanner
+            .start(scan.descriptors, nats_client, node.subject, node.labels)
+            .map(|()| rpc_response::Response::ScanStarted(true)),
+    };
+    match result {
+        Ok(response) => RpcResponse {
+            response: Some(response),
+        },
+        Err(e) => RpcResponse::error(format!("{} failed: {}", method, e)),
+    }
+}
+
+fn parse_txid(txid: &str) -> Result<Txid, String> {
+    Txid::from_str(txid).map_err(|e| format!("invalid txid '{}': {}", txid, e))
+}
+
+/// The txid as argument of an RPC call.
+fn txid_arg(txid: Txid) -> serde_json::Value {
+    serde_json::Value::String(txid.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(2);
+        let start = limiter.window_start;
+        assert!(limiter.allow(start));
+        assert!(limiter.allow(start + Duration::from_millis(100)));
+        assert!(!limiter.allow(start + Duration::from_millis(200)));
+        // a new window starts after a second
+        assert!(limiter.allow(start + Duration::from_millis(1000)));
+        assert!(limiter.allow(start + Duration::from_millis(1500)));
+        assert!(!limiter.allow(start + Duration::from_millis(1900)));
+    }
+
+    #[test]
+    fn test_check_request_allowlist() {
+        let mut limiter = RateLimiter::new(10);
+        let ancestors = rpc_request::Request::MempoolAncestors("00".repeat(32));
+        let descendants = rpc_request::Request::MempoolDescendants("00".repeat(32));
+        let allowed = vec!["getmempoolancestors".to_string()];
+
+        assert_eq!(check_request(&ancestors, &allowed, &mut limiter), Ok(()));
+        assert_eq!(
+            check_request(&descendants, &allowed, &mut limiter),
+            Err("RPC method 'getmempooldescendants' is not allowed".to_string())
+        );
+        assert!(check_request(&ancestors, &[], &mut limiter).is_err());
+    }
+
+    #[test]
+    fn test_check_request_rate_limit() {
+        let mut limiter = RateLimiter::new(1);
+        let ancestors = rpc_request::Request::MempoolAncestors("00".repeat(32));
+        let allowed = vec!["getmempoolancestors".to_string()];
+
+        assert_eq!(check_request(&ancestors, &allowed, &mut limiter), Ok(()));
+        assert_eq!(
+            check_request(&ancestors, &allowed, &mut limiter),
+            Err("rate limit of 1 requests per second exceeded".to_string())
+        );
+    }
+
+    #[test]
+    fn test_request_methods_allowable() {
+        // every request method must be allowable with --allow-rpc-request
+        for request in [
+            rpc_request::Request::MempoolAncestors(String::new()),
+            rpc_request::Request::MempoolDescendants(String::new()),
+            rpc_request::Request::ScanTxOutSet(Default::default()),
+        ] {
+            assert!(REQUEST_METHODS.contains(&request.method()));
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use shared::corepc_client::client_sync::Auth;
+use shared::corepc_client::client_sync::v29::Client;
+use shared::event_builder::EventBuilder;
+use shared::labels::Labels;
+use shared::log;
+use shared::nats::TappedClient;
+use shared::prost::Message;
+use shared::protobuf::event::event::PeerObserverEvent;
+use shared::protobuf::rpc_extractor::{self, RPCScanTxOutSet, RPCScanTxOutSetStatus, ScanProgress};
+use shared::serde_json;
+use shared::tokio::{self, task, time};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// How often the progress of a running scan is published.
+const SCAN_PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs `scantxoutset` scans of a node in the background and publishes their
+/// progress as ScanProgress events.
+///
+/// `scantxoutset start` only returns once the scan finished, which can take
+/// minutes. The scan runs on a blocking thread with its own RPC client, while
+/// another client periodically queries `scantxoutset status`. Only one scan
+/// runs at a time: Bitcoin Core doesn't allow concurrent scans either.
+pub struct Scanner {
+    url: String,
+    auth: Auth,
+    running: Arc<AtomicBool>,
+}
+
+impl Scanner {
+    pub fn new(url: String, auth: Auth) -> Self {
+        Scanner {
+            url,
+            auth,
+            running: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Starts a scan for the descriptors and publishes its progress on the
+    /// subject. Returns an error if a scan is already running.
+    pub fn start(
+        &self,
+        descriptors: Vec<String>,
+        nats_client: &TappedClient,
+        subject: &str,
+        labels: &Labels,
+    ) -> Result<(), String> {
+        if descriptors.is_empty() {
+            return Err("no descriptors to scan for".to_string());
+        }
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err("a scan is already running".to_string());
+        }
+        let clients = Client::new_with_auth(&self.url, self.auth.clone()).and_then(|scan| {
+            Client::new_with_auth(&self.url, self.auth.clone()).map(|status| (scan, status))
+        });
+        let (scan_client, status_client) = match clients {
+            Ok(clients) => clients,
+            Err(e) => {
+                self.running.store(false, Ordering::SeqCst);
+                return Err(format!("could not create RPC client for the scan: {}", e));
+            }
+        };
+
+        let running = self.running.clone();
+        let nats_client = nats_client.clone();
+        let subject = subject.to_string();
+        let events = EventBuilder::new(labels.clone());
+        tokio::spawn(async move {
+            run_scan(
+                scan_client,
+                status_client,
+                descriptors,
+                &nats_client,
+                &subject,
+                &events,
+            )
+            .await;
+            running.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+}
+
+async fn run_scan(
+    scan_client: Client,
+    status_client: Client,
+    descriptors: Vec<String>,
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+) {
+    log::info!(
+        "Starting scantxoutset for {} descriptors",
+        descriptors.len()
+    );
+    publish_progress(nats_client, subject, events, ScanProgress::started()).await;
+
+    let mut scan = task::spawn_blocking(move || {
+        scan_client.call::<RPCScanTxOutSet>(
+            "scantxoutset",
+            &["start".into(), serde_json::Value::from(descriptors)],
+        )
+    });
+    let status_client = Arc::new(status_client);
+    let mut interval = time::interval(SCAN_PROGRESS_INTERVAL);
+    // The first tick completes immediately.
+    interval.tick().await;
+
+    let result = loop {
+        tokio::select! {
+            result = &mut scan => break result,
+            _ = interval.tick() => {
+                let client = status_client.clone();
+                let status = task::spawn_blocking(move || {
+                    client.call::<Option<RPCScanTxOutSetStatus>>(
+                        "scantxoutset",
+                        &["status".into()],
+                    )
+                })
+                .await;
+                match status {
+                    Ok(Ok(Some(status))) => {
+                        let progress = ScanProgress::running(status);
+                        publish_progress(nats_client, subject, events, progress).await;
+                    }
+                    // The scan didn't start yet or just finished.
+                    Ok(Ok(None)) => (),
+                    Ok(Err(e)) => log::warn!("Could not query the scantxoutset status: {}", e),
+                    Err(e) => log::warn!("Could not query the scantxoutset status: {}", e),
+                }
+            }
+        }
+    };
+
+    let progress = match result {
+        Ok(Ok(result)) => result.into(),
+        Ok(Err(e)) => ScanProgress::failed(e.to_string()),
+        Err(e) => ScanProgress::failed(e.to_string()),
+    };
+    log::info!("scantxoutset ended: {}", progress);
+    publish_progress(nats_client, subject, events, progress).await;
+}
+
+async fn publish_progress(
+    nats_client: &TappedClient,
+    subject: &str,
+    events: &EventBuilder,
+    progress: ScanProgress,
+) {
+    let proto = match events.build(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        rpc_event: Some(rpc_extractor::rpc::RpcEvent::ScanProgress(progress)),
+        raw_json: None,
+        correlation_id: None,
+    })) {
+        Ok(event) => event,
+        Err(e) => {
+            log::error!("Could not create new Event due to SystemTimeError: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = nats_client
+        .publish(subject.to_string(), proto.encode_to_vec().into())
+        .await
+    {
+        log::error!("Could not publish scantxoutset progress: {}", e);
+    }
+}
+
+#[cfg(all(test, feature = "nats_integration_tests"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scanner_single_scan() {
+        use shared::debug_buffer::DebugBuffer;
+        use shared::nats_subjects::Subject;
+        use shared::sink::MultiSink;
+        use shared::testing::nats_server::NatsServerForTesting;
+
+        let nats_server = NatsServerForTesting::new().await;
+        let client = shared::async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+            .await
+            .unwrap();
+        let nats_client =
+            TappedClient::new(client, DebugBuffer::new(0), MultiSink::open(&[]).unwrap());
+        let subject = Subject::Rpc.to_string();
+        let labels = Labels::new();
+        let descriptors = || vec!["addr(bcrt1qjqmxmkpmxt80xz4y3746zgt0q3u3ferr34acd5)".to_string()];
+
+        // A node that accepts the connection but never answers, so that the
+        // first scan keeps running.
+        let node = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let scanner = Scanner::new(
+            format!("http://{}", node.local_addr().unwrap()),
+            Auth::UserPass("user".to_string(), "password".to_string()),
+        );
+
+        assert!(
+            scanner
+                .start(vec![], &nats_client, &subject, &labels)
+                .is_err()
+        );
+        assert!(!scanner.running.load(Ordering::SeqCst));
+        scanner
+            .start(descriptors(), &nats_client, &subject, &labels)
+            .unwrap();
+        assert_eq!(
+            scanner.start(descriptors(), &nats_client, &subject, &labels),
+            Err("a scan is already running".to_string())
+        );
+
+        // once the first scan failed, a new one can be started
+        drop(node);
+        time::timeout(Duration::from_secs(10), async {
+            while scanner.running.load(Ordering::SeqCst) {
+                time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+        scanner
+            .start(descriptors(), &nats_client, &subject, &labels)
+            .unwrap();
+    }
+}
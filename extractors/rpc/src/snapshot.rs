@@ -0,0 +1,129 @@
+use shared::log;
+use shared::protobuf::event::{Event, EventBatch};
+use std::collections::BTreeMap;
+
+/// Caches the event of the last successful fetch of each RPC method, so that
+/// consumers joining mid-stream can ask for the current state instead of
+/// waiting for the next query interval.
+pub struct Snapshots {
+    enabled: bool,
+    events: BTreeMap<&'static str, Event>,
+}
+
+impl Snapshots {
+    /// Creates the cache. A disabled cache doesn't keep any events.
+    pub fn new(enabled: bool) -> Self {
+        Snapshots {
+            enabled,
+            events: BTreeMap::new(),
+        }
+    }
+
+    /// Replaces the cached event of the RPC method.
+    pub fn update(&mut self, method: &'static str, event: Event) {
+        if self.enabled {
+            self.events.insert(method, event);
+        }
+    }
+
+    /// Answers a snapshot request. An empty request returns the cached events
+    /// of all RPC methods, ordered by method. Otherwise, the request is the
+    /// name of an RPC method and only its event is returned, if cached. With
+    /// a maximum size, e.g. the maximum NATS payload, the events that would
+    /// make the reply larger than `max_bytes` are left out with a warning.
+    pub fn reply(&self, request: &[u8], max_bytes: Option<usize>) -> EventBatch {
+        let requested: Vec<(&str, &Event)> = match std::str::from_utf8(request).map(str::trim) {
+            Ok("") => self
+                .events
+                .iter()
+                .map(|(method, event)| (*method, event))
+                .collect(),
+            Ok(method) => self
+                .events
+                .get_key_value(method)
+                .map(|(method, event)| (*method, event))
+                .into_iter()
+                .collect(),
+            Err(_) => vec![],
+        };
+        let mut events = vec![];
+        let mut bytes = 0;
+        for (method, event) in requested {
+            let event_bytes = event.batched_len();
+            if let Some(max_bytes) = max_bytes.filter(|max| bytes + event_bytes > *max) {
+                log::warn!(
+                    "Leaving the {} event ({} bytes) out of the snapshot reply: the reply would be larger than {} bytes",
+                    method,
+                    event_bytes,
+                    max_bytes
+                );
+                continue;
+            }
+            bytes += event_bytes;
+            events.push(event.clone());
+        }
+        EventBatch { events }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::testing::events::event;
+
+    fn timestamps(batch: &EventBatch) -> Vec<u64> {
+        batch.events.iter().map(|e| e.timestamp).collect()
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let mut snapshots = Snapshots::new(true);
+        assert!(snapshots.reply(b"", None).events.is_empty());
+
+        snapshots.update("uptime", event(1));
+        snapshots.update("getmempoolinfo", event(2));
+        snapshots.update("uptime", event(3));
+        // ordered by method
+        assert_eq!(timestamps(&snapshots.reply(b"", None)), vec![2, 3]);
+        assert_eq!(timestamps(&snapshots.reply(b"uptime", None)), vec![3]);
+        assert_eq!(timestamps(&snapshots.reply(b" uptime\n", None)), vec![3]);
+        assert!(snapshots.reply(b"getpeerinfo", None).events.is_empty());
+        assert!(snapshots.reply(&[0xff], None).events.is_empty());
+    }
+
+    #[test]
+    fn test_snapshots_max_bytes() {
+        let mut snapshots = Snapshots::new(true);
+        let large = Event {
+            labels: [("node".to_string(), "x".repeat(100))].into(),
+            ..event(1)
+        };
+        snapshots.update("getpeerinfo", large.clone());
+        snapshots.update("getmempoolinfo", event(2));
+        snapshots.update("uptime", event(3));
+        let max_bytes = event(2).batched_len() * 2;
+        assert!(large.batched_len() > max_bytes);
+
+        // the events that don't fit are left out, the others still fit
+        let reply = snapshots.reply(b"", Some(max_bytes));
+        assert_eq!(timestamps(&reply), vec![2, 3]);
+        assert!(
+            snapshots
+                .reply(b"getpeerinfo", Some(max_bytes))
+                .events
+                .is_empty()
+        );
+        assert_eq!(
+            timestamps(&snapshots.reply(b"", Some(max_bytes - 1))),
+            vec![2]
+        );
+        assert_eq!(timestamps(&snapshots.reply(b"", None)), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn test_snapshots_disabled() {
+        let mut snapshots = Snapshots::new(false);
+        snapshots.update("uptime", event(1));
+        assert!(snapshots.reply(b"", None).events.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use shared::corepc_client::client_sync::Error as RPCError;
+use shared::corepc_client::client_sync::v29::Client;
+use shared::serde::de::DeserializeOwned;
+use shared::serde_json;
+
+/// The RPC interface of a node the fetchers query. Implemented by the RPC
+/// client, and by a mock in the tests, so that the conversion and publish
+/// logic can be tested without a running node.
+pub trait RpcSource {
+    /// Calls the RPC method with the arguments and deserializes the result.
+    fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        args: &[serde_json::Value],
+    ) -> Result<T, RPCError>;
+}
+
+impl RpcSource for Client {
+    fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        args: &[serde_json::Value],
+    ) -> Result<T, RPCError> {
+        Client::call(self, method, args)
+    }
+}
+
+#[cfg(test)]
+pub use mock::MockRpc;
+
+#[cfg(test)]
+mod mock {
+    use super::*;
+    use crate::RPC_METHOD_NOT_FOUND;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    /// Answers RPC calls with canned JSON results and records the calls.
+    /// Methods without a result fail like unknown methods on a node.
+    #[derive(Default)]
+    pub struct MockRpc {
+        results: BTreeMap<String, serde_json::Value>,
+        calls: RefCell<Vec<(String, Vec<serde_json::Value>)>>,
+        delay: Duration,
+    }
+
+    impl MockRpc {
+        pub fn with(mut self, method: &str, result: serde_json::Value) -> Self {
+            self.results.insert(method.to_string(), result);
+            self
+        }
+
+        /// Blocks each call for `delay`, like a slow node.
+        pub fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+
+        /// The methods called so far and their arguments, oldest first.
+        pub fn calls(&self) -> Vec<(String, Vec<serde_json::Value>)> {
+            self.calls.borrow().clone()
+        }
+    }
+
+    impl RpcSource for MockRpc {
+        fn call<T: DeserializeOwned>(
+            &self,
+            method: &str,
+            args: &[serde_json::Value],
+        ) -> Result<T, RPCError> {
+            self.calls
+                .borrow_mut()
+                .push((method.to_string(), args.to_vec()));
+            std::thread::sleep(self.delay);
+            let Some(result) = self.results.get(method) else {
+                return Err(RPCError::Returned(format!(
+                    "Method not found ({})",
+                    RPC_METHOD_NOT_FOUND
+                )));
+            };
+            // like the client, which fails with a JSON error for results it
+            // can't deserialize
+            serde_json::from_value(result.clone()).map_err(RPCError::Json)
+        }
+    }
+}
@@ -6,18 +6,22 @@ use shared::{
     futures::StreamExt,
     log::{self, info},
     prost::Message,
-    protobuf::event::{Event, event::PeerObserverEvent},
+    protobuf::event::{Event, EventBatch, event::PeerObserverEvent},
+    protobuf::rpc_extractor::PeerConnectionType,
     protobuf::rpc_extractor::rpc::RpcEvent::{
-        AddrmanInfo, MemoryInfo, MempoolInfo, NetTotals, PeerInfos, Uptime,
+        AddrmanInfo, BlockchainInfo, CompactPeerInfos, FeeEstimates, MemoryInfo, MempoolInfo,
+        NetTotals, NetworkPeerStats, NodeAddresses, PeerInfo, PeerInfoSummary, PeerInfos,
+        RawAddrman, RawMempool, Uptime,
     },
     simple_logger::SimpleLogger,
     testing::nats_server::NatsServerForTesting,
     tokio::{self, sync::watch},
 };
 
+use std::cell::Cell;
 use std::sync::Once;
 
-use rpc_extractor::Args;
+use rpc_extractor::{Args, PeerInfoPublishMode};
 
 static INIT: Once = Once::new();
 
@@ -33,29 +37,37 @@ fn setup() -> () {
     });
 }
 
-fn make_test_args(
-    nats_port: u16,
-    rpc_url: String,
-    cookie_file: String,
-    disable_getpeerinfo: bool,
-    disable_getmempoolinfo: bool,
-    disable_uptime: bool,
-    disable_getnettotals: bool,
-    disable_getmemoryinfo: bool,
-    disable_getaddrmaninfo: bool,
-) -> Args {
+/// The RPCs queried by the extractor in a test. All of them are disabled by
+/// default.
+#[derive(Default)]
+struct Rpcs {
+    getpeerinfo: bool,
+    getmempoolinfo: bool,
+    uptime: bool,
+    getnettotals: bool,
+    getmemoryinfo: bool,
+    getaddrmaninfo: bool,
+    getblockchaininfo: bool,
+    estimatesmartfee: bool,
+    getnodeaddresses: bool,
+}
+
+fn make_test_args(nats_port: u16, rpc_url: String, cookie_file: String, rpcs: Rpcs) -> Args {
     Args::new(
         format!("127.0.0.1:{}", nats_port),
         log::Level::Trace,
         rpc_url,
         cookie_file,
         QUERY_INTERVAL_SECONDS,
-        disable_getpeerinfo,
-        disable_getmempoolinfo,
-        disable_uptime,
-        disable_getnettotals,
-        disable_getmemoryinfo,
-        disable_getaddrmaninfo,
+        !rpcs.getpeerinfo,
+        !rpcs.getmempoolinfo,
+        !rpcs.uptime,
+        !rpcs.getnettotals,
+        !rpcs.getmemoryinfo,
+        !rpcs.getaddrmaninfo,
+        !rpcs.getblockchaininfo,
+        !rpcs.estimatesmartfee,
+        !rpcs.getnodeaddresses,
     )
 }
 
@@ -86,14 +98,24 @@ fn setup_two_connected_nodes() -> (corepc_node::Node, corepc_node::Node) {
     (node1, node2)
 }
 
-async fn check(
-    disable_getpeerinfo: bool,
-    disable_getmempoolinfo: bool,
-    disable_uptime: bool,
-    disable_getnettotals: bool,
-    disable_getmemoryinfo: bool,
-    disable_getaddrmaninfo: bool,
-    check_expected: fn(PeerObserverEvent) -> (),
+async fn check(rpcs: Rpcs, check_expected: fn(PeerObserverEvent) -> ()) {
+    check_with_args(
+        |_| (),
+        rpcs,
+        move |event| {
+            check_expected(event);
+            true
+        },
+    )
+    .await;
+}
+
+// Like check(), but allows modifying the Args before starting the extractor.
+// Events are passed to check_expected until it returns true.
+async fn check_with_args(
+    modify_args: fn(&mut Args),
+    rpcs: Rpcs,
+    check_expected: impl Fn(PeerObserverEvent) -> bool,
 ) {
     setup();
     let (node1, _node2) = setup_two_connected_nodes();
@@ -101,17 +123,13 @@ async fn check(
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
     let rpc_extractor_handle = tokio::spawn(async move {
-        let args = make_test_args(
+        let mut args = make_test_args(
             nats_server.port,
             node1.rpc_url().replace("http://", ""),
             node1.params.cookie_file.display().to_string(),
-            disable_getpeerinfo,
-            disable_getmempoolinfo,
-            disable_uptime,
-            disable_getnettotals,
-            disable_getmemoryinfo,
-            disable_getaddrmaninfo,
+            rpcs,
         );
+        modify_args(&mut args);
         rpc_extractor::run(args, shutdown_rx.clone())
             .await
             .expect("rpc extractor failed");
@@ -120,13 +138,15 @@ async fn check(
     let nc = async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
         .await
         .unwrap();
-    let mut sub = nc.subscribe("*").await.unwrap();
+    // per-peer getpeerinfo events are published on subjects with multiple tokens
+    let mut sub = nc.subscribe(">").await.unwrap();
 
     while let Some(msg) = sub.next().await {
         let unwrapped = Event::decode(msg.payload).unwrap();
         if let Some(event) = unwrapped.peer_observer_event {
-            check_expected(event);
-            break;
+            if check_expected(event) {
+                break;
+            }
         }
     }
 
@@ -138,26 +158,195 @@ async fn check(
 async fn test_integration_rpc_getpeerinfo() {
     println!("test that we receive getpeerinfo RPC events");
 
-    check(false, true, true, true, true, true, |event| {
-        match event {
-            PeerObserverEvent::RpcExtractor(r) => {
-                if let Some(ref e) = r.rpc_event {
-                    match e {
-                        PeerInfos(p) => {
-                            // we expect 1 peer to be connected
-                            assert_eq!(p.infos.len(), 1);
-                            let peer = p.infos.first().expect("we have expactly one peer here");
-                            assert_eq!(peer.connection_type, "inbound");
+    check(
+        Rpcs {
+            getpeerinfo: true,
+            ..Default::default()
+        },
+        |event| {
+            match event {
+                PeerObserverEvent::RpcExtractor(r) => {
+                    if let Some(ref e) = r.rpc_event {
+                        match e {
+                            PeerInfos(p) => {
+                                // we expect 1 peer to be connected
+                                assert_eq!(p.infos.len(), 1);
+                                let peer = p.infos.first().expect("we have expactly one peer here");
+                                assert_eq!(peer.connection_type, "inbound");
+                                assert_eq!(
+                                    peer.connection_type_kind(),
+                                    PeerConnectionType::Inbound
+                                );
+                                // the human-readable names of the services the peer offers
+                                assert!(peer.services_names.contains(&"WITNESS".to_string()));
+
+                                return;
+                            }
+                            _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+                        }
+                    }
+                }
+                _ => panic!("unexpected event {:?}", event),
+            }
+        },
+    )
+    .await;
+}
 
-                            return;
+#[tokio::test]
+async fn test_integration_rpc_getpeerinfo_summary() {
+    println!("test that we receive getpeerinfo summary RPC events");
+
+    check_with_args(
+        |args| args.peerinfo_summary = true,
+        Rpcs {
+            getpeerinfo: true,
+            ..Default::default()
+        },
+        |event| {
+            match event {
+                PeerObserverEvent::RpcExtractor(r) => {
+                    if let Some(ref e) = r.rpc_event {
+                        match e {
+                            PeerInfos(_) => (),
+                            PeerInfoSummary(summary) => {
+                                // we expect 1 inbound peer to be connected
+                                assert_eq!(summary.total, 1);
+                                assert_eq!(summary.buckets.len(), 1);
+                                let bucket = summary.buckets.first().unwrap();
+                                assert!(bucket.inbound);
+                                assert_eq!(bucket.connection_type, "inbound");
+                                assert_eq!(bucket.count, 1);
+                                return true;
+                            }
+                            _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+                        }
+                    }
+                }
+                _ => panic!("unexpected event {:?}", event),
+            }
+            false
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_rpc_getpeerinfo_network_stats() {
+    println!("test that we receive getpeerinfo network stats RPC events");
+
+    check_with_args(
+        |args| args.peerinfo_network_stats = true,
+        Rpcs {
+            getpeerinfo: true,
+            ..Default::default()
+        },
+        |event| {
+            match event {
+                PeerObserverEvent::RpcExtractor(r) => {
+                    if let Some(ref e) = r.rpc_event {
+                        match e {
+                            PeerInfos(_) => (),
+                            NetworkPeerStats(stats) => {
+                                // we expect 1 inbound peer to be connected
+                                assert_eq!(stats.networks.len(), 1);
+                                let network = stats.networks.first().unwrap();
+                                assert_eq!(network.peers, 1);
+                                assert_eq!(network.inbound, 1);
+                                assert_eq!(network.outbound, 0);
+                                assert!(network.bytes_sent > 0);
+                                assert!(network.bytes_received > 0);
+                                return true;
+                            }
+                            _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                         }
-                        _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                     }
                 }
+                _ => panic!("unexpected event {:?}", event),
             }
+            false
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_rpc_getpeerinfo_per_peer() {
+    println!("test that we receive per-peer getpeerinfo RPC events");
+
+    check_with_args(
+        |args| args.peerinfo_publish_mode = PeerInfoPublishMode::PerPeer,
+        Rpcs {
+            getpeerinfo: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => match r.rpc_event {
+                Some(PeerInfo(peer)) => {
+                    assert_eq!(peer.connection_type, "inbound");
+                    true
+                }
+                _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+            },
             _ => panic!("unexpected event {:?}", event),
-        }
-    })
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_rpc_getpeerinfo_compact() {
+    println!("test that we receive compact getpeerinfo RPC events");
+
+    check_with_args(
+        |args| args.peerinfo_compact = true,
+        Rpcs {
+            getpeerinfo: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => match r.rpc_event {
+                Some(CompactPeerInfos(p)) => {
+                    // we expect 1 peer to be connected
+                    assert_eq!(p.infos.len(), 1);
+                    let peer = p.infos.first().expect("we have exactly one peer here");
+                    assert!(peer.inbound);
+                    assert!(!peer.subversion.is_empty());
+                    true
+                }
+                _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+            },
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_rpc_getpeerinfo_chunks() {
+    println!("test that we receive large getpeerinfo RPC events in chunks");
+
+    check_with_args(
+        // smaller than any peer, so each peer gets a chunk
+        |args| args.peerinfo_max_bytes = 1,
+        Rpcs {
+            getpeerinfo: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => match r.rpc_event {
+                Some(PeerInfos(p)) => {
+                    // we expect 1 peer to be connected
+                    assert_eq!(p.infos.len(), 1);
+                    assert_eq!(p.chunk_index, Some(0));
+                    assert_eq!(p.chunk_total, Some(1));
+                    true
+                }
+                _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+            },
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
     .await;
 }
 
@@ -165,32 +354,72 @@ async fn test_integration_rpc_getpeerinfo() {
 async fn test_integration_rpc_getmempoolinfo() {
     println!("test that we receive getmempoolinfo RPC events");
 
-    check(true, false, true, true, true, true, |event| match event {
-        PeerObserverEvent::RpcExtractor(r) => {
-            if let Some(ref e) = r.rpc_event {
-                match e {
-                    MempoolInfo(info) => {
-                        assert_eq!(info.loaded, true);
-                        assert_eq!(info.size, 0);
-                        assert_eq!(info.usage, 0);
-                        assert_eq!(info.bytes, 0);
-                        assert_eq!(info.total_fee, 0.0);
-                        assert_eq!(info.max_mempool, 300000000);
-                        // These will change between v29 and v30, so don't hardcode something here.
-                        assert!(info.mempoolminfee > 0.0);
-                        assert!(info.minrelaytxfee > 0.0);
-                        assert!(info.incrementalrelayfee > 0.0);
-
-                        assert_eq!(info.unbroadcastcount, 0);
-                        assert_eq!(info.fullrbf, true);
-                        return;
+    check(
+        Rpcs {
+            getmempoolinfo: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => {
+                if let Some(ref e) = r.rpc_event {
+                    match e {
+                        MempoolInfo(info) => {
+                            assert_eq!(info.loaded, true);
+                            assert_eq!(info.size, 0);
+                            assert_eq!(info.usage, 0);
+                            assert_eq!(info.bytes, 0);
+                            assert_eq!(info.total_fee, 0.0);
+                            assert_eq!(info.max_mempool, 300000000);
+                            // These will change between v29 and v30, so don't hardcode something here.
+                            assert!(info.mempoolminfee > 0.0);
+                            assert!(info.minrelaytxfee > 0.0);
+                            assert!(info.incrementalrelayfee > 0.0);
+
+                            assert_eq!(info.unbroadcastcount, 0);
+                            assert_eq!(info.fullrbf, true);
+                            return;
+                        }
+                        _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                     }
-                    _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                 }
             }
-        }
-        _ => panic!("unexpected event {:?}", event),
-    })
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_rpc_getrawmempool() {
+    println!("test that we receive correlated getmempoolinfo and getrawmempool RPC events");
+
+    let mempool_info_correlation_id = Cell::new(None);
+    check_with_args(
+        |args| args.enable_getrawmempool = true,
+        Rpcs {
+            getmempoolinfo: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => match r.rpc_event {
+                Some(MempoolInfo(_)) => {
+                    assert!(r.correlation_id.is_some());
+                    mempool_info_correlation_id.set(r.correlation_id);
+                    false
+                }
+                Some(RawMempool(mempool)) => {
+                    // the getmempoolinfo event of the same sample is published first
+                    assert!(mempool_info_correlation_id.get().is_some());
+                    assert_eq!(r.correlation_id, mempool_info_correlation_id.get());
+                    // a fresh regtest node has an empty mempool
+                    assert!(mempool.txids.is_empty());
+                    true
+                }
+                _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+            },
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
     .await;
 }
 
@@ -198,21 +427,27 @@ async fn test_integration_rpc_getmempoolinfo() {
 async fn test_integration_rpc_uptime() {
     println!("test that we receive uptime RPC events");
 
-    check(true, true, false, true, true, true, |event| match event {
-        PeerObserverEvent::RpcExtractor(r) => {
-            if let Some(ref e) = r.rpc_event {
-                match e {
-                    Uptime(uptime_seconds) => {
-                        // Uptime should be a positive number
-                        assert!(*uptime_seconds > 0);
-                        return;
+    check(
+        Rpcs {
+            uptime: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => {
+                if let Some(ref e) = r.rpc_event {
+                    match e {
+                        Uptime(uptime_seconds) => {
+                            // Uptime should be a positive number
+                            assert!(*uptime_seconds > 0);
+                            return;
+                        }
+                        _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                     }
-                    _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                 }
             }
-        }
-        _ => panic!("unexpected event {:?}", event),
-    })
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
     .await;
 }
 
@@ -220,22 +455,28 @@ async fn test_integration_rpc_uptime() {
 async fn test_integration_rpc_getnettotals() {
     println!("test that we receive getnettotals RPC events");
 
-    check(true, true, true, false, true, true, |event| match event {
-        PeerObserverEvent::RpcExtractor(r) => {
-            if let Some(ref e) = r.rpc_event {
-                match e {
-                    NetTotals(net_totals) => {
-                        assert!(net_totals.time_millis > 0);
-                        assert!(net_totals.total_bytes_received > 0);
-                        assert!(net_totals.total_bytes_sent > 0);
-                        return;
+    check(
+        Rpcs {
+            getnettotals: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => {
+                if let Some(ref e) = r.rpc_event {
+                    match e {
+                        NetTotals(net_totals) => {
+                            assert!(net_totals.time_millis > 0);
+                            assert!(net_totals.total_bytes_received > 0);
+                            assert!(net_totals.total_bytes_sent > 0);
+                            return;
+                        }
+                        _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                     }
-                    _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                 }
             }
-        }
-        _ => panic!("unexpected event {:?}", event),
-    })
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
     .await;
 }
 
@@ -243,22 +484,28 @@ async fn test_integration_rpc_getnettotals() {
 async fn test_integration_rpc_getmemoryinfo() {
     println!("test that we receive getmemoryinfo RPC events");
 
-    check(true, true, true, true, false, true, |event| match event {
-        PeerObserverEvent::RpcExtractor(r) => {
-            if let Some(ref e) = r.rpc_event {
-                match e {
-                    MemoryInfo(info) => {
-                        assert!(info.total > 0);
-                        assert!(info.used <= info.total);
-                        assert!(info.locked <= info.total);
-                        return;
+    check(
+        Rpcs {
+            getmemoryinfo: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => {
+                if let Some(ref e) = r.rpc_event {
+                    match e {
+                        MemoryInfo(info) => {
+                            assert!(info.total > 0);
+                            assert!(info.used <= info.total);
+                            assert!(info.locked <= info.total);
+                            return;
+                        }
+                        _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                     }
-                    _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                 }
             }
-        }
-        _ => panic!("unexpected event {:?}", event),
-    })
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
     .await;
 }
 
@@ -266,37 +513,307 @@ async fn test_integration_rpc_getmemoryinfo() {
 async fn test_integration_rpc_getaddrmaninfo() {
     println!("test that we receive getaddrmaninfo RPC events");
 
-    check(true, true, true, true, true, false, |event| match event {
-        PeerObserverEvent::RpcExtractor(r) => {
-            if let Some(ref e) = r.rpc_event {
-                match e {
-                    AddrmanInfo(info) => {
-                        assert!(!info.networks.is_empty());
-
-                        if let Some(all_nets) = info.networks.get("all_networks") {
-                            assert_eq!(
-                                all_nets.total,
-                                all_nets.new + all_nets.tried,
-                                "all_networks: total should equal new + tried"
-                            );
-                        }
+    check(
+        Rpcs {
+            getaddrmaninfo: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => {
+                if let Some(ref e) = r.rpc_event {
+                    match e {
+                        AddrmanInfo(info) => {
+                            assert!(!info.networks.is_empty());
+
+                            if let Some(all_nets) = info.networks.get("all_networks") {
+                                assert_eq!(
+                                    all_nets.total,
+                                    all_nets.new + all_nets.tried,
+                                    "all_networks: total should equal new + tried"
+                                );
+                            }
+
+                            for (network, data) in &info.networks {
+                                assert_eq!(
+                                    data.total,
+                                    data.new + data.tried,
+                                    "Network {}: total should equal new + tried",
+                                    network
+                                );
+                            }
 
-                        for (network, data) in &info.networks {
-                            assert_eq!(
-                                data.total,
-                                data.new + data.tried,
-                                "Network {}: total should equal new + tried",
-                                network
-                            );
+                            return;
                         }
+                        _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+                    }
+                }
+            }
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
+    .await;
+}
 
-                        return;
+#[tokio::test]
+async fn test_integration_rpc_getblockchaininfo() {
+    println!("test that we receive getblockchaininfo RPC events");
+
+    check(
+        Rpcs {
+            getblockchaininfo: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => {
+                if let Some(ref e) = r.rpc_event {
+                    match e {
+                        BlockchainInfo(info) => {
+                            assert_eq!(info.chain, "regtest");
+                            assert!(info.blocks >= 0);
+                            assert!(info.headers >= info.blocks);
+                            assert_eq!(info.best_block_hash.len(), 64);
+                            assert!(!info.pruned);
+                            return;
+                        }
+                        _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                     }
-                    _ => panic!("unexpected RPC data {:?}", r.rpc_event),
                 }
             }
-        }
-        _ => panic!("unexpected event {:?}", event),
-    })
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_rpc_estimatesmartfee() {
+    println!("test that we receive estimatesmartfee RPC events");
+
+    check_with_args(
+        |args| args.feeestimate_targets = vec![2, 6],
+        Rpcs {
+            estimatesmartfee: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => match r.rpc_event {
+                Some(FeeEstimates(estimates)) => {
+                    assert_eq!(estimates.estimate_mode, "economical");
+                    assert_eq!(
+                        estimates.estimates.keys().copied().collect::<Vec<u32>>(),
+                        vec![2, 6]
+                    );
+                    // a fresh regtest node doesn't have enough data to
+                    // estimate fees
+                    for estimate in estimates.estimates.values() {
+                        assert_eq!(estimate.feerate, None);
+                        assert!(!estimate.errors.is_empty());
+                    }
+                    true
+                }
+                _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+            },
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
+    .await;
+}
+
+#[cfg(feature = "getorphantxs")]
+#[tokio::test]
+async fn test_integration_rpc_getorphantxs() {
+    println!("test that we receive getorphantxs RPC events");
+
+    check_with_args(
+        |args| args.enable_getorphantxs = true,
+        Rpcs::default(),
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => match r.rpc_event {
+                Some(shared::protobuf::rpc_extractor::rpc::RpcEvent::OrphanTxs(orphans)) => {
+                    // a fresh regtest node doesn't have orphans
+                    assert!(orphans.orphans.is_empty());
+                    true
+                }
+                _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+            },
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
     .await;
 }
+
+#[tokio::test]
+async fn test_integration_rpc_getrawaddrman() {
+    println!("test that we receive getrawaddrman RPC events");
+
+    check_with_args(
+        |args| args.enable_getrawaddrman = true,
+        Rpcs::default(),
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => match r.rpc_event {
+                Some(RawAddrman(addrman)) => {
+                    // a fresh regtest node doesn't know any addresses
+                    assert!(addrman.new_entries.is_empty());
+                    assert!(addrman.tried_entries.is_empty());
+                    true
+                }
+                _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+            },
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_rpc_getnodeaddresses() {
+    println!("test that we receive getnodeaddresses RPC events");
+
+    check(
+        Rpcs {
+            getnodeaddresses: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => match r.rpc_event {
+                Some(NodeAddresses(addresses)) => {
+                    // a fresh regtest node doesn't know any addresses
+                    assert!(addresses.addresses.is_empty());
+                }
+                _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+            },
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_rpc_include_raw_rpc() {
+    println!("test that the raw RPC JSON is attached with --include-raw-rpc");
+
+    check_with_args(
+        |args| args.include_raw_rpc = true,
+        Rpcs {
+            uptime: true,
+            ..Default::default()
+        },
+        |event| match event {
+            PeerObserverEvent::RpcExtractor(r) => match r.rpc_event {
+                Some(Uptime(uptime)) => {
+                    let raw_json = r.raw_json.expect("the raw JSON should be attached");
+                    assert_eq!(raw_json.parse::<u32>().unwrap(), uptime);
+                    true
+                }
+                _ => panic!("unexpected RPC data {:?}", r.rpc_event),
+            },
+            _ => panic!("unexpected event {:?}", event),
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_rpc_serve_snapshots() {
+    println!("test that the last known events are served with --serve-snapshots");
+
+    setup();
+    let (node1, _node2) = setup_two_connected_nodes();
+    let nats_server = NatsServerForTesting::new().await;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let nc = async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+        .await
+        .unwrap();
+    let mut sub = nc.subscribe("rpc").await.unwrap();
+
+    let rpc_extractor_handle = tokio::spawn(async move {
+        let mut args = make_test_args(
+            nats_server.port,
+            node1.rpc_url().replace("http://", ""),
+            node1.params.cookie_file.display().to_string(),
+            Rpcs {
+                getmempoolinfo: true,
+                uptime: true,
+                ..Default::default()
+            },
+        );
+        args.serve_snapshots = true;
+        rpc_extractor::run(args, shutdown_rx.clone())
+            .await
+            .expect("rpc extractor failed");
+    });
+
+    // wait until both RPCs were fetched once
+    sub.next().await.unwrap();
+    sub.next().await.unwrap();
+
+    let reply = nc.request("rpc.snapshot", "".into()).await.unwrap();
+    let batch = EventBatch::decode(reply.payload).unwrap();
+    assert_eq!(batch.events.len(), 2);
+
+    let reply = nc.request("rpc.snapshot", "uptime".into()).await.unwrap();
+    let batch = EventBatch::decode(reply.payload).unwrap();
+    assert_eq!(batch.events.len(), 1);
+    match &batch.events[0].peer_observer_event {
+        Some(PeerObserverEvent::RpcExtractor(r)) => {
+            assert!(matches!(r.rpc_event, Some(Uptime(_))))
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    shutdown_tx.send(true).unwrap();
+    rpc_extractor_handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_integration_rpc_debug_buffer() {
+    println!("test that the last published events are served with --debug-buffer-size");
+
+    setup();
+    let (node1, _node2) = setup_two_connected_nodes();
+    let nats_server = NatsServerForTesting::new().await;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let nc = async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+        .await
+        .unwrap();
+    let mut sub = nc.subscribe("rpc").await.unwrap();
+
+    let rpc_extractor_handle = tokio::spawn(async move {
+        let mut args = make_test_args(
+            nats_server.port,
+            node1.rpc_url().replace("http://", ""),
+            node1.params.cookie_file.display().to_string(),
+            Rpcs {
+                getmempoolinfo: true,
+                uptime: true,
+                ..Default::default()
+            },
+        );
+        args.common.debug_buffer_size = 2;
+        rpc_extractor::run(args, shutdown_rx.clone())
+            .await
+            .expect("rpc extractor failed");
+    });
+
+    // wait until three events were published, one more than retained
+    for _ in 0..3 {
+        sub.next().await.unwrap();
+    }
+
+    let reply = nc.request("rpc.debug", "".into()).await.unwrap();
+    let batch = EventBatch::decode(reply.payload).unwrap();
+    assert_eq!(batch.events.len(), 2);
+
+    let reply = nc.request("rpc.debug", "1".into()).await.unwrap();
+    let batch = EventBatch::decode(reply.payload).unwrap();
+    assert_eq!(batch.events.len(), 1);
+    assert!(matches!(
+        batch.events[0].peer_observer_event,
+        Some(PeerObserverEvent::RpcExtractor(_))
+    ));
+
+    shutdown_tx.send(true).unwrap();
+    rpc_extractor_handle.await.unwrap();
+}
@@ -7,6 +7,12 @@ fn main() {
     if let Err(e) = prost_build::Config::new()
         .compile_well_known_types()
         .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
+        .btree_map([
+            ".event.Event.labels",
+            ".event.ExtractorMetrics.counters",
+            ".event.ExtractorMetrics.gauges",
+            ".rpc_extractor.FeeEstimates.estimates",
+        ])
         .compile_protos(&["../protobuf/event.proto"], &["../protobuf/"])
     {
         println!("Error while compiling protos: {}", e);
@@ -0,0 +1,274 @@
+use crate::labels::{
+    add_network_label, add_node_label, labels_from_tags, node_id, parse_tag, LabelError, Labels,
+    NETWORKS, NODE_LABEL,
+};
+use crate::logging::LogFormat;
+use crate::nats::parse_nats_address;
+use crate::nats_subjects::parse_subject_prefix;
+use crate::sink::{parse_output, Output};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Command line arguments common to all extractors. Flattened into each
+/// extractor's `Args` with `#[command(flatten)]`, so cross-cutting options
+/// only need to be added here.
+#[derive(clap::Args, Debug, Clone)]
+pub struct CommonArgs {
+    /// Address of the NATS server where the extractor will publish messages to.
+    /// Multiple comma-separated addresses, e.g. of the servers in a NATS cluster, can be given.
+    #[arg(short, long, default_value = "127.0.0.1:4222", value_parser = parse_nats_address)]
+    pub nats_address: String,
+
+    /// The log level the extractor should run with. Valid log levels are "trace",
+    /// "debug", "info", "warn", "error". See https://docs.rs/log/latest/log/enum.Level.html.
+    #[arg(short, long, default_value_t = log::Level::Debug)]
+    pub log_level: log::Level,
+
+    /// The format of the extractor's own log output: "text" for human readable
+    /// lines or "json" for one JSON object per line, e.g. for log aggregators.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// A label attached to every published event, as key=value. Can be
+    /// specified multiple times with different keys.
+    #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+    pub tags: Vec<(String, String)>,
+
+    /// The network of the node ("main", "test", "testnet4", "signet", or
+    /// "regtest"). Attached to every published event as "network" label.
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(NETWORKS))]
+    pub network: Option<String>,
+
+    /// An identifier of the node, attached to every published event as
+    /// "node" label. Defaults to the hostname of the machine the extractor
+    /// runs on. A "node" tag is used as is.
+    #[arg(long, value_name = "ID")]
+    pub node: Option<String>,
+
+    /// Read options from a TOML file. The keys are the long option names,
+    /// e.g. `nats-address = "127.0.0.1:4222"`. Flags are set with
+    /// `flag = true`, and options that can be passed multiple times take an
+    /// array. Options passed on the command line take precedence over the
+    /// ones in the file.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Publish counters and gauges about the extractor's own operation, e.g.
+    /// the number of queried RPCs or read log lines, on the
+    /// `extractor-metrics` subject every this many seconds. Disabled if not
+    /// set.
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub extractor_metrics_interval: Option<u64>,
+
+    /// Publish a heartbeat event on the `heartbeat` subject every this many
+    /// seconds, also when there is nothing else to publish, so that a quiet
+    /// extractor can be told apart from a dead one. Disabled if not set.
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub heartbeat_secs: Option<u64>,
+
+    /// Retain the last this many published messages in memory and answer
+    /// NATS requests for their events on the `<subject>.debug` subject of the
+    /// extractor, e.g. `rpc.debug`. A request payload N only returns the
+    /// events of the last N messages. Disabled with 0.
+    #[arg(long, value_name = "MESSAGES", default_value_t = 0)]
+    pub debug_buffer_size: usize,
+
+    /// Additionally write every published event to this output: "stdout" or
+    /// "file:<PATH>" (appended to), as one JSON object with the subject and
+    /// the event per line. Can be specified multiple times. A failing output
+    /// doesn't stop publishing into NATS. With "stdout", the logs are written
+    /// to stderr.
+    #[arg(long = "output", value_name = "OUTPUT", value_parser = parse_output)]
+    pub outputs: Vec<Output>,
+
+    /// Reject events with an encoded size larger than this many bytes with
+    /// an error naming the event type, instead of publishing them. Defaults
+    /// to the maximum payload (`max_payload`) announced by the NATS server.
+    #[arg(long, value_name = "BYTES")]
+    pub max_nats_payload: Option<usize>,
+
+    /// Publish the events below this subject prefix, e.g. on `team-a.rpc`
+    /// instead of `rpc`, to share a NATS server with other applications.
+    /// Requests to the extractor are answered below the prefix too.
+    #[arg(long, value_name = "PREFIX", value_parser = parse_subject_prefix)]
+    pub nats_subject_prefix: Option<String>,
+}
+
+impl CommonArgs {
+    pub fn new(nats_address: String, log_level: log::Level) -> Self {
+        CommonArgs {
+            nats_address,
+            log_level,
+            log_format: LogFormat::Text,
+            tags: vec![],
+            network: None,
+            node: None,
+            config: None,
+            extractor_metrics_interval: None,
+            heartbeat_secs: None,
+            debug_buffer_size: 0,
+            outputs: vec![],
+            max_nats_payload: None,
+            nats_subject_prefix: None,
+        }
+    }
+
+    /// The window the extractor metrics are collected over, if enabled.
+    pub fn extractor_metrics_window(&self) -> Option<Duration> {
+        self.extractor_metrics_interval.map(Duration::from_secs)
+    }
+
+    /// The interval between the heartbeat events, if enabled.
+    pub fn heartbeat_interval(&self) -> Option<Duration> {
+        self.heartbeat_secs.map(Duration::from_secs)
+    }
+
+    /// The labels attached to every published event: the tags, the network,
+    /// if set, and the node identifier (see [node_id]).
+    pub fn labels(&self) -> Result<Labels, LabelError> {
+        let mut labels = labels_from_tags(&self.tags)?;
+        if let Some(network) = &self.network {
+            add_network_label(&mut labels, network)?;
+        }
+        // A "node" tag is used as is, unless --node is set too, which is an
+        // error like a duplicate tag.
+        if self.node.is_some() || !labels.contains_key(NODE_LABEL) {
+            if let Some(node) = node_id(self.node.as_deref(), None) {
+                add_node_label(&mut labels, &node)?;
+            }
+        }
+        Ok(labels)
+    }
+
+    /// True if the node identifier is set explicitly with `--node` or a
+    /// "node" tag, and shouldn't be determined automatically.
+    pub fn has_explicit_node(&self) -> bool {
+        self.node.is_some() || self.tags.iter().any(|(key, _)| key == NODE_LABEL)
+    }
+}
+
+/// Implemented by the `Args` of each extractor.
+pub trait ExtractorArgs {
+    /// The arguments common to all extractors.
+    fn common(&self) -> &CommonArgs;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct TestArgs {
+        #[command(flatten)]
+        common: CommonArgs,
+    }
+
+    #[test]
+    fn test_common_args() {
+        let args = TestArgs::try_parse_from([
+            "extractor",
+            "--nats-address",
+            "host1:4222,host2:4222",
+            "--tag",
+            "host=node1",
+            "--network",
+            "signet",
+        ])
+        .unwrap();
+        assert_eq!(args.common.nats_address, "host1:4222,host2:4222");
+        assert_eq!(args.common.log_level, log::Level::Debug);
+        assert_eq!(args.common.log_format, LogFormat::Text);
+        let labels = args.common.labels().unwrap();
+        assert_eq!(labels["host"], "node1");
+        assert_eq!(labels["network"], "signet");
+    }
+
+    #[test]
+    fn test_common_args_log_format() {
+        let args = TestArgs::try_parse_from(["extractor", "--log-format", "json"]).unwrap();
+        assert_eq!(args.common.log_format, LogFormat::Json);
+        assert!(TestArgs::try_parse_from(["extractor", "--log-format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_common_args_extractor_metrics_interval() {
+        let args = TestArgs::try_parse_from(["extractor"]).unwrap();
+        assert_eq!(args.common.extractor_metrics_window(), None);
+        let args =
+            TestArgs::try_parse_from(["extractor", "--extractor-metrics-interval", "60"]).unwrap();
+        assert_eq!(
+            args.common.extractor_metrics_window(),
+            Some(Duration::from_secs(60))
+        );
+        assert!(
+            TestArgs::try_parse_from(["extractor", "--extractor-metrics-interval", "0"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_common_args_heartbeat_secs() {
+        let args = TestArgs::try_parse_from(["extractor"]).unwrap();
+        assert_eq!(args.common.heartbeat_interval(), None);
+        let args = TestArgs::try_parse_from(["extractor", "--heartbeat-secs", "30"]).unwrap();
+        assert_eq!(
+            args.common.heartbeat_interval(),
+            Some(Duration::from_secs(30))
+        );
+        assert!(TestArgs::try_parse_from(["extractor", "--heartbeat-secs", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_common_args_outputs() {
+        let args = TestArgs::try_parse_from(["extractor"]).unwrap();
+        assert!(args.common.outputs.is_empty());
+        let args = TestArgs::try_parse_from([
+            "extractor",
+            "--output",
+            "stdout",
+            "--output",
+            "file:/tmp/events.jsonl",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.common.outputs,
+            vec![
+                Output::Stdout,
+                Output::File(PathBuf::from("/tmp/events.jsonl"))
+            ]
+        );
+        assert!(TestArgs::try_parse_from(["extractor", "--output", "nats"]).is_err());
+    }
+
+    #[test]
+    fn test_common_args_node() {
+        let args = TestArgs::try_parse_from(["extractor"]).unwrap();
+        assert!(!args.common.has_explicit_node());
+        assert_eq!(
+            args.common.labels().unwrap().get(NODE_LABEL),
+            crate::labels::hostname().as_ref()
+        );
+
+        let args = TestArgs::try_parse_from(["extractor", "--node", "node-1"]).unwrap();
+        assert!(args.common.has_explicit_node());
+        assert_eq!(args.common.labels().unwrap()[NODE_LABEL], "node-1");
+
+        // a node tag is used as is
+        let args = TestArgs::try_parse_from(["extractor", "--tag", "node=node-2"]).unwrap();
+        assert!(args.common.has_explicit_node());
+        assert_eq!(args.common.labels().unwrap()[NODE_LABEL], "node-2");
+
+        let args =
+            TestArgs::try_parse_from(["extractor", "--tag", "node=node-2", "--node", "node-1"])
+                .unwrap();
+        assert!(args.common.labels().is_err());
+    }
+
+    #[test]
+    fn test_common_args_duplicate_network() {
+        let args =
+            TestArgs::try_parse_from(["extractor", "--tag", "network=main", "--network", "signet"])
+                .unwrap();
+        assert!(args.common.labels().is_err());
+    }
+}
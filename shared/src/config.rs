@@ -0,0 +1,222 @@
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command, Parser};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The id of the `--config` argument in [crate::args::CommonArgs].
+const CONFIG_ARG: &str = "config";
+
+/// Parses the extractor's `Args` from the command line and the TOML file
+/// passed with `--config`, if any. Exits with a usage message on errors, like
+/// [Parser::parse].
+pub fn parse_args<A: Parser>() -> A {
+    try_parse_args_from(std::env::args_os()).unwrap_or_else(|e| e.exit())
+}
+
+/// Parses the extractor's `Args` from `itr` and the TOML file passed with
+/// `--config`, if any.
+///
+/// The keys of the file are the long option names, with either dashes or
+/// underscores, e.g. `nats-address = "127.0.0.1:4222"`. Flags are set with
+/// `flag = true`. Options that can be passed multiple times take an array.
+/// Options passed on the command line take precedence over the file: they
+/// replace the file's value, including all values of multi-value options.
+pub fn try_parse_args_from<A, I, T>(itr: I) -> Result<A, clap::Error>
+where
+    A: Parser,
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let args: Vec<OsString> = itr.into_iter().map(Into::into).collect();
+    let mut cmd = A::command();
+    // Options only set in the config file (e.g. required ones) are still
+    // missing here, so ignore errors and only look for `--config`.
+    let matches = cmd
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(&args)?;
+    let Some(path) = matches.get_one::<PathBuf>(CONFIG_ARG) else {
+        return A::try_parse_from(args);
+    };
+
+    let file_args = config_file_args(&cmd, &matches, path)
+        .map_err(|e| cmd.error(ErrorKind::InvalidValue, e))?;
+    let mut combined: Vec<OsString> = args.iter().take(1).cloned().collect();
+    combined.extend(file_args.into_iter().map(OsString::from));
+    combined.extend(args.into_iter().skip(1));
+    A::try_parse_from(combined)
+}
+
+/// Reads the config file and returns its options as command line arguments.
+/// Options passed on the command line are skipped.
+fn config_file_args(
+    cmd: &Command,
+    matches: &ArgMatches,
+    path: &Path,
+) -> Result<Vec<String>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("could not read config file '{}': {}", path.display(), e))?;
+    let table: toml::Table = content
+        .parse()
+        .map_err(|e| format!("could not parse config file '{}': {}", path.display(), e))?;
+
+    let mut args = Vec::new();
+    for (key, value) in table {
+        let long = key.replace('_', "-");
+        let arg = cmd
+            .get_arguments()
+            .filter(|arg| arg.get_id() != CONFIG_ARG)
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown option '{}' in config file '{}'",
+                    key,
+                    path.display()
+                )
+            })?;
+        if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                toml::Value::Boolean(set) if !arg.get_action().takes_values() => {
+                    if set {
+                        args.push(format!("--{}", long));
+                    }
+                }
+                toml::Value::String(s) => args.push(format!("--{}={}", long, s)),
+                toml::Value::Array(_) | toml::Value::Table(_) => {
+                    return Err(format!(
+                        "invalid value for '{}' in config file '{}'",
+                        key,
+                        path.display()
+                    ));
+                }
+                value => args.push(format!("--{}={}", long, value)),
+            }
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::CommonArgs;
+
+    #[derive(Parser, Debug)]
+    struct TestArgs {
+        #[command(flatten)]
+        common: CommonArgs,
+
+        #[arg(long)]
+        host: String,
+
+        #[arg(long, default_value_t = 10)]
+        query_interval: u64,
+
+        #[arg(long)]
+        no_idle_exit: bool,
+    }
+
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "peer-observer-config-test-{}-{}.toml",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_config_file() {
+        let path = write_config(
+            "file",
+            r#"
+            nats-address = "host1:4222"
+            log_level = "info"
+            tag = ["host=node1", "region=eu"]
+            host = "127.0.0.1:8332"
+            query-interval = 20
+            no-idle-exit = true
+            "#,
+        );
+        let args: TestArgs =
+            try_parse_args_from(["extractor", "--config", path.to_str().unwrap()]).unwrap();
+        assert_eq!(args.common.nats_address, "host1:4222");
+        assert_eq!(args.common.log_level, log::Level::Info);
+        assert_eq!(args.common.tags.len(), 2);
+        assert_eq!(args.host, "127.0.0.1:8332");
+        assert_eq!(args.query_interval, 20);
+        assert!(args.no_idle_exit);
+    }
+
+    #[test]
+    fn test_config_file_cli_precedence() {
+        let path = write_config(
+            "precedence",
+            r#"
+            host = "127.0.0.1:8332"
+            query_interval = 20
+            tag = ["host=node1", "region=eu"]
+            "#,
+        );
+        let args: TestArgs = try_parse_args_from([
+            "extractor",
+            "--query-interval",
+            "5",
+            "--config",
+            path.to_str().unwrap(),
+            "--tag",
+            "host=node2",
+        ])
+        .unwrap();
+        assert_eq!(args.host, "127.0.0.1:8332");
+        assert_eq!(args.query_interval, 5);
+        assert_eq!(
+            args.common.tags,
+            vec![("host".to_string(), "node2".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_config_file_errors() {
+        let path = write_config("unknown", "foo = 1\nhost = \"127.0.0.1:8332\"");
+        let err = try_parse_args_from::<TestArgs, _, _>([
+            "extractor",
+            "--config",
+            path.to_str().unwrap(),
+        ])
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidValue);
+
+        let path = write_config(
+            "invalid",
+            "host = \"127.0.0.1:8332\"\nquery_interval = \"soon\"",
+        );
+        assert!(try_parse_args_from::<TestArgs, _, _>([
+            "extractor",
+            "--config",
+            path.to_str().unwrap()
+        ])
+        .is_err());
+
+        assert!(try_parse_args_from::<TestArgs, _, _>([
+            "extractor",
+            "--config",
+            "/does/not/exist.toml"
+        ])
+        .is_err());
+
+        // without a config file, the required options must be passed
+        assert!(try_parse_args_from::<TestArgs, _, _>(["extractor"]).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::nats::{decode_payload, TappedClient};
+use crate::nats_subjects::{debug_subject, is_event_subject};
+use crate::protobuf::event::EventBatch;
+use async_nats::client::SubscribeError;
+use async_nats::Client;
+use futures::StreamExt;
+use prost::bytes::Bytes;
+use prost::Message;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Retains the last published messages (`--debug-buffer-size`), so that the
+/// recent events of an extractor can be inspected without running a full
+/// capture. A batch of events counts as one message. Clones share the same
+/// buffer.
+#[derive(Clone)]
+pub struct DebugBuffer {
+    capacity: usize,
+    messages: Arc<Mutex<VecDeque<(String, Bytes)>>>,
+}
+
+impl DebugBuffer {
+    /// Creates a buffer retaining up to `capacity` messages. A buffer with a
+    /// capacity of zero is disabled and doesn't retain anything.
+    pub fn new(capacity: usize) -> Self {
+        DebugBuffer {
+            capacity,
+            messages: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Retains a published message, dropping the oldest one if the buffer is
+    /// full. Messages on subjects that don't carry events, like replies to
+    /// requests, are ignored.
+    pub fn record(&self, subject: &str, payload: Bytes) {
+        if !self.is_enabled() || !is_event_subject(subject) {
+            return;
+        }
+        let mut messages = self.messages.lock().unwrap();
+        if messages.len() == self.capacity {
+            messages.pop_front();
+        }
+        messages.push_back((subject.to_string(), payload));
+    }
+
+    /// Answers a request for the recent events, oldest first. An empty
+    /// request returns the events of all retained messages. Otherwise, the
+    /// request is a number N and only the events of the last N messages are
+    /// returned.
+    pub fn reply(&self, request: &[u8]) -> EventBatch {
+        let messages = self.messages.lock().unwrap();
+        let count = match std::str::from_utf8(request).map(str::trim) {
+            Ok("") => messages.len(),
+            Ok(n) => match n.parse::<usize>() {
+                Ok(n) => n.min(messages.len()),
+                Err(_) => 0,
+            },
+            Err(_) => 0,
+        };
+        let events = messages
+            .iter()
+            .skip(messages.len() - count)
+            .flat_map(|(subject, payload)| {
+                decode_payload(subject, payload).unwrap_or_else(|e| {
+                    log::warn!(
+                        "Could not decode a retained message on '{}': {}",
+                        subject,
+                        e
+                    );
+                    vec![]
+                })
+            })
+            .collect();
+        EventBatch { events }
+    }
+}
+
+/// Answers requests for the recent events retained by the client's buffer on
+/// the debug subject of the extractor (e.g. `rpc.debug` for the `rpc`
+/// subject) in a spawned task. The reply is an EventBatch, see
+/// [DebugBuffer::reply]. Does nothing if the buffer is disabled.
+pub async fn serve(client: &TappedClient, subject: &str) -> Result<(), SubscribeError> {
+    let buffer = client.debug_buffer().clone();
+    if !buffer.is_enabled() {
+        return Ok(());
+    }
+    let subject = debug_subject(subject);
+    let mut requests = client.subscribe(subject.clone()).await?;
+    log::info!(
+        "Answering requests for the last {} published messages on '{}'",
+        buffer.capacity(),
+        client.prefixed(&subject)
+    );
+    // Replies are published with the wrapped client, they aren't events.
+    let client: Client = (**client).clone();
+    tokio::spawn(async move {
+        while let Some(msg) = requests.next().await {
+            let Some(reply) = msg.reply else {
+                log::warn!("Ignoring debug buffer request without a reply subject");
+                continue;
+            };
+            let batch = buffer.reply(&msg.payload);
+            if let Err(e) = client.publish(reply, batch.encode_to_vec().into()).await {
+                log::error!("Could not reply to debug buffer request: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::events::event;
+
+    fn timestamps(batch: &EventBatch) -> Vec<u64> {
+        batch.events.iter().map(|e| e.timestamp).collect()
+    }
+
+    #[test]
+    fn test_debug_buffer() {
+        let buffer = DebugBuffer::new(3);
+        assert!(buffer.reply(b"").events.is_empty());
+
+        buffer.record("rpc", event(1).encode_to_vec().into());
+        let batch = EventBatch {
+            events: vec![event(2), event(3)],
+        };
+        buffer.record("netmsg.batch", batch.encode_to_vec().into());
+        // replies to requests aren't retained
+        buffer.record("_INBOX.abc", event(0).encode_to_vec().into());
+        buffer
+            .clone()
+            .record("rpc", event(4).encode_to_vec().into());
+        assert_eq!(timestamps(&buffer.reply(b"")), vec![1, 2, 3, 4]);
+
+        // the oldest message is dropped
+        buffer.record("rpc", event(5).encode_to_vec().into());
+        assert_eq!(timestamps(&buffer.reply(b"")), vec![2, 3, 4, 5]);
+        assert_eq!(timestamps(&buffer.reply(b"2")), vec![4, 5]);
+        assert_eq!(timestamps(&buffer.reply(b" 1\n")), vec![5]);
+        assert_eq!(timestamps(&buffer.reply(b"100")), vec![2, 3, 4, 5]);
+        assert!(buffer.reply(b"last").events.is_empty());
+        assert!(buffer.reply(&[0xff]).events.is_empty());
+    }
+
+    #[test]
+    fn test_debug_buffer_disabled() {
+        let buffer = DebugBuffer::new(0);
+        assert!(!buffer.is_enabled());
+        buffer.record("rpc", event(1).encode_to_vec().into());
+        assert!(buffer.reply(b"").events.is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+use crate::labels::Labels;
+use crate::protobuf::event::{event::PeerObserverEvent, Clock, Event, SystemClock};
+use std::time::SystemTimeError;
+
+/// Creates the [Event]s of an extractor with the common metadata stamped in
+/// one place: the timestamp and the labels (e.g. the node, instance, and
+/// network labels).
+///
+/// Configured once, e.g. per node, it creates a complete event from just the
+/// inner [PeerObserverEvent], so the publish sites can't forget parts of the
+/// envelope.
+#[derive(Debug, Clone)]
+pub struct EventBuilder<C: Clock = SystemClock> {
+    labels: Labels,
+    clock: C,
+}
+
+impl EventBuilder {
+    pub fn new(labels: Labels) -> Self {
+        EventBuilder {
+            labels,
+            clock: SystemClock,
+        }
+    }
+}
+
+impl<C: Clock> EventBuilder<C> {
+    /// Takes the timestamps of the events from the clock, e.g. a
+    /// [crate::protobuf::event::FixedClock] in tests.
+    pub fn with_clock<D: Clock>(self, clock: D) -> EventBuilder<D> {
+        EventBuilder {
+            labels: self.labels,
+            clock,
+        }
+    }
+
+    pub fn labels(&self) -> &Labels {
+        &self.labels
+    }
+
+    /// Creates the event with the current timestamp and the labels.
+    pub fn build(&self, event: PeerObserverEvent) -> Result<Event, SystemTimeError> {
+        Ok(Event::new_with_clock(event, &self.clock)?.with_labels(&self.labels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::event::FixedClock;
+    use crate::protobuf::rpc_extractor::{rpc::RpcEvent, Rpc};
+    use std::time::Duration;
+
+    #[test]
+    fn test_event_builder() {
+        let labels = Labels::from([
+            ("node".to_string(), "node-a".to_string()),
+            ("network".to_string(), "main".to_string()),
+        ]);
+        let builder = EventBuilder::new(labels.clone())
+            .with_clock(FixedClock(Duration::from_millis(1_700_000_000_123)));
+        let event = builder
+            .build(PeerObserverEvent::RpcExtractor(Rpc {
+                rpc_event: Some(RpcEvent::Uptime(42)),
+                raw_json: None,
+                correlation_id: None,
+            }))
+            .unwrap();
+        assert_eq!(event.timestamp, 1_700_000_000_123);
+        assert_eq!(event.labels, labels);
+        assert_eq!(builder.labels(), &labels);
+        assert!(matches!(
+            event.peer_observer_event,
+            Some(PeerObserverEvent::RpcExtractor(Rpc {
+                rpc_event: Some(RpcEvent::Uptime(42)),
+                ..
+            }))
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::labels::Labels;
+use crate::nats::TappedClient;
+use crate::nats_subjects::Subject;
+use crate::protobuf::event::{event::PeerObserverEvent, Event, Extractor, ExtractorMetrics};
+use prost::Message;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time;
+
+/// Records counters and gauges about the extractor's own operation for the
+/// periodic [ExtractorMetrics] events. Clones record into the same metrics.
+#[derive(Clone)]
+pub struct ExtractorMetricsRecorder {
+    extractor: Extractor,
+    inner: Arc<Mutex<Recorded>>,
+}
+
+#[derive(Default)]
+struct Recorded {
+    counters: BTreeMap<String, u64>,
+    gauges: BTreeMap<String, f64>,
+}
+
+impl ExtractorMetricsRecorder {
+    pub fn new(extractor: Extractor) -> Self {
+        ExtractorMetricsRecorder {
+            extractor,
+            inner: Arc::new(Mutex::new(Recorded::default())),
+        }
+    }
+
+    /// Increments the counter by one.
+    pub fn inc(&self, counter: &str) {
+        self.inc_by(counter, 1);
+    }
+
+    /// Increments the counter by `value`.
+    pub fn inc_by(&self, counter: &str, value: u64) {
+        let mut recorded = self.inner.lock().unwrap();
+        let count = recorded.counters.entry(counter.to_string()).or_default();
+        *count = count.saturating_add(value);
+    }
+
+    /// Sets the gauge to `value`.
+    pub fn set(&self, gauge: &str, value: f64) {
+        self.inner
+            .lock()
+            .unwrap()
+            .gauges
+            .insert(gauge.to_string(), value);
+    }
+
+    /// Returns the metrics recorded in the last `window` and resets the
+    /// counters to zero. Counters stay included with zero counts in the next
+    /// windows, and the gauges keep their values.
+    pub fn take(&self, window: Duration) -> ExtractorMetrics {
+        let mut recorded = self.inner.lock().unwrap();
+        let metrics = ExtractorMetrics {
+            extractor: self.extractor.into(),
+            window: window.as_secs(),
+            counters: recorded.counters.clone(),
+            gauges: recorded.gauges.clone(),
+        };
+        recorded.counters.values_mut().for_each(|count| *count = 0);
+        metrics
+    }
+
+    /// Takes the metrics recorded in the last `window` (see
+    /// [ExtractorMetricsRecorder::take]) and publishes them on the
+    /// `extractor-metrics` subject (below the subject prefix, if any).
+    /// Returns the taken metrics, e.g. to also push them elsewhere.
+    pub async fn publish(
+        &self,
+        nats_client: &TappedClient,
+        labels: &Labels,
+        window: Duration,
+    ) -> ExtractorMetrics {
+        let metrics = self.take(window);
+        log::debug!("Publishing {}", metrics);
+        match Event::new(PeerObserverEvent::ExtractorMetrics(metrics.clone())) {
+            Ok(proto) => {
+                let proto = proto.with_labels(labels);
+                // Published with the wrapped client, the extractor metrics
+                // aren't retained in the debug buffer.
+                if let Err(e) = (**nats_client)
+                    .publish(
+                        nats_client.prefixed(&Subject::ExtractorMetrics.to_string()),
+                        proto.encode_to_vec().into(),
+                    )
+                    .await
+                {
+                    log::error!("Could not publish extractor metrics: {}", e);
+                }
+            }
+            Err(e) => log::error!("Could not create extractor metrics event: {}", e),
+        }
+        metrics
+    }
+}
+
+/// An interval ticking at the end of each extractor metrics window. Without
+/// a window, the interval must not be polled.
+pub fn interval(window: Option<Duration>) -> time::Interval {
+    let period = window.unwrap_or(Duration::from_secs(1));
+    time::interval_at(time::Instant::now() + period, period)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder() {
+        let recorder = ExtractorMetricsRecorder::new(Extractor::LogExtractor);
+        recorder.inc("lines_read");
+        recorder.clone().inc_by("lines_read", 9);
+        recorder.inc("lines_unknown");
+        recorder.set("pending", 3.0);
+
+        let metrics = recorder.take(Duration::from_secs(60));
+        assert_eq!(metrics.extractor(), Extractor::LogExtractor);
+        assert_eq!(metrics.window, 60);
+        assert_eq!(metrics.counters["lines_read"], 10);
+        assert_eq!(metrics.counters["lines_unknown"], 1);
+        assert_eq!(metrics.gauges["pending"], 3.0);
+        assert_eq!(
+            metrics.to_string(),
+            "ExtractorMetrics(extractor=log-extractor, window=60s, counters=[lines_read=10, lines_unknown=1], gauges=[pending=3])"
+        );
+
+        // the counters are reset, the gauges are kept
+        recorder.inc("lines_read");
+        let metrics = recorder.take(Duration::from_secs(60));
+        assert_eq!(metrics.counters["lines_read"], 1);
+        assert_eq!(metrics.counters["lines_unknown"], 0);
+        assert_eq!(metrics.gauges["pending"], 3.0);
+    }
+}
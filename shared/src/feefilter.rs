@@ -0,0 +1,347 @@
+use crate::nats_subjects::Subject;
+use crate::protobuf::ebpf_extractor::{connection::connection_event, ebpf, message::message_event};
+use crate::protobuf::event::{event::PeerObserverEvent, Event};
+use crate::protobuf::p2p_extractor::p2p;
+use crate::protobuf::rpc_extractor::rpc;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The subjects of the events the [FeefilterCorrelator] uses: the mempool
+/// info (rpc-extractor), feefilter messages and closed connections
+/// (ebpf-extractor), and the node's feefilter (p2p-extractor).
+pub const FEEFILTER_SUBJECTS: [Subject; 4] = [
+    Subject::Rpc,
+    Subject::NetMsg,
+    Subject::NetConn,
+    Subject::P2PExtractor,
+];
+
+/// Who announced a feefilter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FeefilterSource {
+    /// A peer of the node, by the peer id assigned by Bitcoin Core. From the
+    /// feefilter messages the ebpf-extractor saw the node receive.
+    Peer(u64),
+    /// The node itself. From the feefilter the node sent to the p2p-extractor.
+    Node,
+}
+
+impl fmt::Display for FeefilterSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeefilterSource::Peer(id) => write!(f, "peer={}", id),
+            FeefilterSource::Node => write!(f, "node"),
+        }
+    }
+}
+
+/// How a feefilter relates to the node's mempool minimum fee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeRelation {
+    /// The feefilter is below the mempool minimum fee: transactions the peer
+    /// wants announced could be rejected by our mempool.
+    Below,
+    Equal,
+    /// The feefilter is above the mempool minimum fee: the peer doesn't want
+    /// some transactions our mempool accepts.
+    Above,
+}
+
+impl fmt::Display for FeeRelation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeeRelation::Below => write!(f, "below"),
+            FeeRelation::Equal => write!(f, "equal"),
+            FeeRelation::Above => write!(f, "above"),
+        }
+    }
+}
+
+/// A feefilter compared to the node's mempool minimum fee at the time. Both
+/// fee rates are in sat/kvB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeefilterComparison {
+    pub source: FeefilterSource,
+    pub feefilter: i64,
+    pub mempool_min_fee: i64,
+}
+
+impl FeefilterComparison {
+    pub fn relation(&self) -> FeeRelation {
+        match self.feefilter.cmp(&self.mempool_min_fee) {
+            std::cmp::Ordering::Less => FeeRelation::Below,
+            std::cmp::Ordering::Equal => FeeRelation::Equal,
+            std::cmp::Ordering::Greater => FeeRelation::Above,
+        }
+    }
+
+    /// The feefilter minus the mempool minimum fee in sat/kvB.
+    pub fn difference(&self) -> i64 {
+        self.feefilter.saturating_sub(self.mempool_min_fee)
+    }
+}
+
+impl fmt::Display for FeefilterComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FeefilterComparison({}, feefilter={} sat/kvB {} mempoolminfee={} sat/kvB, difference={} sat/kvB)",
+            self.source,
+            self.feefilter,
+            self.relation(),
+            self.mempool_min_fee,
+            self.difference()
+        )
+    }
+}
+
+/// Correlates the feefilters announced to the node with the node's own
+/// mempool minimum fee, showing whether peers filter above or below our
+/// floor.
+///
+/// Consumers feed it the events of the [FEEFILTER_SUBJECTS] with
+/// [FeefilterCorrelator::observe]. It keeps the latest `mempoolminfee` of the
+/// getmempoolinfo RPC and the latest feefilter per source. A new feefilter is
+/// compared to the latest mempool minimum fee, and a changed mempool minimum
+/// fee is compared to all known feefilters. Nothing is compared until the
+/// first mempool minimum fee is known. The feefilters of disconnected peers
+/// are forgotten.
+#[derive(Debug, Default)]
+pub struct FeefilterCorrelator {
+    mempool_min_fee: Option<i64>,
+    feefilters: BTreeMap<FeefilterSource, i64>,
+}
+
+impl FeefilterCorrelator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The latest mempool minimum fee in sat/kvB, if known.
+    pub fn mempool_min_fee(&self) -> Option<i64> {
+        self.mempool_min_fee
+    }
+
+    /// The latest feefilter in sat/kvB per source.
+    pub fn feefilters(&self) -> &BTreeMap<FeefilterSource, i64> {
+        &self.feefilters
+    }
+
+    /// Records the relevant information of the event and returns the
+    /// resulting comparisons. Other events are ignored.
+    pub fn observe(&mut self, event: &Event) -> Vec<FeefilterComparison> {
+        match &event.peer_observer_event {
+            Some(PeerObserverEvent::RpcExtractor(r)) => match &r.rpc_event {
+                Some(rpc::RpcEvent::MempoolInfo(info)) => {
+                    self.set_mempool_min_fee(btc_per_kvb_to_sat(info.mempoolminfee))
+                }
+                _ => vec![],
+            },
+            Some(PeerObserverEvent::EbpfExtractor(e)) => match &e.ebpf_event {
+                Some(ebpf::EbpfEvent::Message(msg)) if msg.meta.inbound => match &msg.msg {
+                    Some(message_event::Msg::Feefilter(feefilter)) => self
+                        .set_feefilter(FeefilterSource::Peer(msg.meta.peer_id), feefilter.fee)
+                        .into_iter()
+                        .collect(),
+                    _ => vec![],
+                },
+                Some(ebpf::EbpfEvent::Connection(conn)) => {
+                    if let Some(connection_event::Event::Closed(closed)) = &conn.event {
+                        self.remove_peer(closed.conn.peer_id);
+                    }
+                    vec![]
+                }
+                _ => vec![],
+            },
+            Some(PeerObserverEvent::P2pExtractor(p)) => match &p.p2p_event {
+                Some(p2p::P2pEvent::FeefilterAnnouncement(announcement)) => self
+                    .set_feefilter(FeefilterSource::Node, announcement.fee_rate)
+                    .into_iter()
+                    .collect(),
+                _ => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
+    /// Records the mempool minimum fee in sat/kvB. If it changed, returns the
+    /// comparisons with all known feefilters.
+    pub fn set_mempool_min_fee(&mut self, mempool_min_fee: i64) -> Vec<FeefilterComparison> {
+        if self.mempool_min_fee == Some(mempool_min_fee) {
+            return vec![];
+        }
+        self.mempool_min_fee = Some(mempool_min_fee);
+        self.feefilters
+            .iter()
+            .map(|(source, feefilter)| FeefilterComparison {
+                source: *source,
+                feefilter: *feefilter,
+                mempool_min_fee,
+            })
+            .collect()
+    }
+
+    /// Records the feefilter in sat/kvB of the source. Returns the comparison
+    /// with the mempool minimum fee, if known.
+    pub fn set_feefilter(
+        &mut self,
+        source: FeefilterSource,
+        feefilter: i64,
+    ) -> Option<FeefilterComparison> {
+        self.feefilters.insert(source, feefilter);
+        Some(FeefilterComparison {
+            source,
+            feefilter,
+            mempool_min_fee: self.mempool_min_fee?,
+        })
+    }
+
+    /// Forgets the feefilter of a disconnected peer.
+    pub fn remove_peer(&mut self, peer_id: u64) {
+        self.feefilters.remove(&FeefilterSource::Peer(peer_id));
+    }
+}
+
+/// Converts a fee rate in BTC/kvB, as returned by the RPC interface, to
+/// sat/kvB, as used in the feefilter message.
+pub fn btc_per_kvb_to_sat(fee_rate: f64) -> i64 {
+    (fee_rate * 100_000_000.0).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::ebpf_extractor::{
+        connection::{ClosedConnection, Connection, ConnectionEvent},
+        message::{FeeFilter, MessageEvent, Metadata},
+        Ebpf,
+    };
+    use crate::protobuf::p2p_extractor::{FeefilterAnnouncement, P2p};
+    use crate::protobuf::rpc_extractor::{MempoolInfo, Rpc};
+
+    fn mempool_info(mempoolminfee: f64) -> Event {
+        Event::new(PeerObserverEvent::RpcExtractor(Rpc {
+            rpc_event: Some(rpc::RpcEvent::MempoolInfo(MempoolInfo {
+                loaded: true,
+                size: 1,
+                bytes: 1,
+                usage: 1,
+                total_fee: 0.0,
+                max_mempool: 300_000_000,
+                mempoolminfee,
+                minrelaytxfee: 0.000001,
+                incrementalrelayfee: 0.000001,
+                unbroadcastcount: 0,
+                fullrbf: true,
+                mempoolminfee_delta: None,
+                mempoolminfee_trend: None,
+            })),
+            raw_json: None,
+            correlation_id: None,
+        }))
+        .unwrap()
+    }
+
+    fn feefilter(peer_id: u64, inbound: bool, fee: i64) -> Event {
+        Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
+            ebpf_event: Some(ebpf::EbpfEvent::Message(MessageEvent {
+                meta: Metadata {
+                    peer_id,
+                    addr: "127.0.0.1:8333".to_string(),
+                    conn_type: 1,
+                    command: "feefilter".to_string(),
+                    inbound,
+                    size: 8,
+                    sample_rate: None,
+                    payload_bytes: None,
+                    total_bytes: None,
+                },
+                msg: Some(message_event::Msg::Feefilter(FeeFilter { fee })),
+            })),
+        }))
+        .unwrap()
+    }
+
+    fn closed(peer_id: u64) -> Event {
+        Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
+            ebpf_event: Some(ebpf::EbpfEvent::Connection(ConnectionEvent {
+                event: Some(connection_event::Event::Closed(ClosedConnection {
+                    conn: Connection {
+                        peer_id,
+                        addr: "127.0.0.1:8333".to_string(),
+                        conn_type: 1,
+                        network: 1,
+                    },
+                    time_established: 0,
+                })),
+            })),
+        }))
+        .unwrap()
+    }
+
+    fn announcement(fee_rate: i64) -> Event {
+        Event::new(PeerObserverEvent::P2pExtractor(P2p {
+            p2p_event: Some(p2p::P2pEvent::FeefilterAnnouncement(
+                FeefilterAnnouncement::new(fee_rate),
+            )),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_btc_per_kvb_to_sat() {
+        assert_eq!(btc_per_kvb_to_sat(0.00001), 1000);
+        assert_eq!(btc_per_kvb_to_sat(0.000001), 100);
+        assert_eq!(btc_per_kvb_to_sat(0.00002345), 2345);
+    }
+
+    #[test]
+    fn test_feefilter_correlator() {
+        let mut correlator = FeefilterCorrelator::new();
+
+        // nothing to compare to without a mempool minimum fee
+        assert!(correlator.observe(&feefilter(1, true, 1000)).is_empty());
+        // feefilters we send aren't recorded
+        assert!(correlator.observe(&feefilter(2, false, 5000)).is_empty());
+        assert_eq!(correlator.feefilters().len(), 1);
+
+        let comparisons = correlator.observe(&mempool_info(0.00001));
+        assert_eq!(correlator.mempool_min_fee(), Some(1000));
+        assert_eq!(
+            comparisons,
+            vec![FeefilterComparison {
+                source: FeefilterSource::Peer(1),
+                feefilter: 1000,
+                mempool_min_fee: 1000,
+            }]
+        );
+        assert_eq!(comparisons[0].relation(), FeeRelation::Equal);
+        // an unchanged mempool minimum fee isn't compared again
+        assert!(correlator.observe(&mempool_info(0.00001)).is_empty());
+
+        let comparisons = correlator.observe(&feefilter(3, true, 100));
+        assert_eq!(comparisons[0].relation(), FeeRelation::Below);
+        assert_eq!(comparisons[0].difference(), -900);
+        let comparisons = correlator.observe(&announcement(2500));
+        assert_eq!(comparisons[0].source, FeefilterSource::Node);
+        assert_eq!(comparisons[0].relation(), FeeRelation::Above);
+        assert_eq!(
+            comparisons[0].to_string(),
+            "FeefilterComparison(node, feefilter=2500 sat/kvB above mempoolminfee=1000 sat/kvB, difference=1500 sat/kvB)"
+        );
+
+        // the feefilters of disconnected peers are forgotten
+        assert!(correlator.observe(&closed(3)).is_empty());
+        let comparisons = correlator.observe(&mempool_info(0.00002));
+        assert_eq!(
+            comparisons
+                .iter()
+                .map(|c| (c.source, c.relation()))
+                .collect::<Vec<_>>(),
+            vec![
+                (FeefilterSource::Peer(1), FeeRelation::Below),
+                (FeefilterSource::Node, FeeRelation::Above),
+            ]
+        );
+    }
+}
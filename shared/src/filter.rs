@@ -0,0 +1,33 @@
+use crate::protobuf::event::{Event, Extractor};
+
+/// Returns a predicate matching events from one of the given extractors,
+/// e.g. to be used with `Iterator::filter` or `StreamExt::filter`.
+pub fn by_extractor(kinds: &[Extractor]) -> impl Fn(&Event) -> bool {
+    let kinds = kinds.to_vec();
+    move |event: &Event| {
+        event
+            .extractor_kind()
+            .is_some_and(|kind| kinds.contains(&kind))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::event::event::PeerObserverEvent;
+    use crate::protobuf::p2p_extractor::{p2p::P2pEvent, P2p, PingDuration};
+
+    #[test]
+    fn test_by_extractor() {
+        let event = Event::new(PeerObserverEvent::P2pExtractor(P2p {
+            p2p_event: Some(P2pEvent::PingDuration(PingDuration { duration: 1 })),
+        }))
+        .unwrap();
+
+        assert!(by_extractor(&[Extractor::P2pExtractor])(&event));
+        let log_or_p2p = by_extractor(&[Extractor::LogExtractor, Extractor::P2pExtractor]);
+        assert!(log_or_p2p(&event));
+        assert!(!by_extractor(&[Extractor::EbpfExtractor])(&event));
+        assert!(!by_extractor(&[])(&event));
+    }
+}
@@ -0,0 +1,225 @@
+use bitcoin::hashes::Hash;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+const HASH256_LENGTH: usize = 32;
+
+/// A 256-bit hash such as a block hash, txid, or wtxid.
+///
+/// Bitcoin stores these hashes in internal byte order, but displays them
+/// in reversed byte order (big-endian). The extractors publish hashes as
+/// bytes in internal byte order (ebpf-extractor, p2p-extractor) or as
+/// display hex strings (log-extractor). A `Hash256` can be created from
+/// both and is always displayed as big-endian hex, as Bitcoin Core does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Hash256([u8; HASH256_LENGTH]);
+
+impl Hash256 {
+    /// Creates a hash from bytes in internal byte order, as published by
+    /// the ebpf-extractor and p2p-extractor.
+    pub fn from_internal_bytes(bytes: &[u8]) -> Result<Self, HashError> {
+        let bytes: [u8; HASH256_LENGTH] = bytes
+            .try_into()
+            .map_err(|_| HashError::InvalidLength(bytes.len()))?;
+        Ok(Hash256(bytes))
+    }
+
+    /// Creates a hash from a hex string in display (big-endian) byte order,
+    /// as logged by Bitcoin Core and published by the log-extractor.
+    pub fn from_display_hex(hex: &str) -> Result<Self, HashError> {
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(HashError::InvalidHex(hex.to_string()));
+        }
+        if hex.len() != HASH256_LENGTH * 2 {
+            return Err(HashError::InvalidLength(hex.len() / 2));
+        }
+        let mut bytes = [0u8; HASH256_LENGTH];
+        for (i, byte) in bytes.iter_mut().rev().enumerate() {
+            // only ASCII hex digits, so slicing and parsing can't fail
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).expect("valid hex digits");
+        }
+        Ok(Hash256(bytes))
+    }
+
+    /// The hash in internal byte order.
+    pub fn to_internal_bytes(&self) -> [u8; HASH256_LENGTH] {
+        self.0
+    }
+
+    /// The hash in display (big-endian) byte order.
+    pub fn to_display_bytes(&self) -> [u8; HASH256_LENGTH] {
+        let mut bytes = self.0;
+        bytes.reverse();
+        bytes
+    }
+}
+
+impl fmt::Display for Hash256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.to_display_bytes() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Hash256 {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Hash256::from_display_hex(s)
+    }
+}
+
+impl From<bitcoin::BlockHash> for Hash256 {
+    fn from(hash: bitcoin::BlockHash) -> Self {
+        Hash256(hash.to_byte_array())
+    }
+}
+
+impl From<bitcoin::Txid> for Hash256 {
+    fn from(txid: bitcoin::Txid) -> Self {
+        Hash256(txid.to_byte_array())
+    }
+}
+
+impl From<bitcoin::Wtxid> for Hash256 {
+    fn from(wtxid: bitcoin::Wtxid) -> Self {
+        Hash256(wtxid.to_byte_array())
+    }
+}
+
+impl From<Hash256> for bitcoin::BlockHash {
+    fn from(hash: Hash256) -> Self {
+        bitcoin::BlockHash::from_byte_array(hash.0)
+    }
+}
+
+impl From<Hash256> for bitcoin::Txid {
+    fn from(hash: Hash256) -> Self {
+        bitcoin::Txid::from_byte_array(hash.0)
+    }
+}
+
+impl From<Hash256> for bitcoin::Wtxid {
+    fn from(hash: Hash256) -> Self {
+        bitcoin::Wtxid::from_byte_array(hash.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HashError {
+    /// The hash doesn't have 32 bytes.
+    InvalidLength(usize),
+    /// The hash isn't a valid hex string.
+    InvalidHex(String),
+}
+
+impl fmt::Display for HashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashError::InvalidLength(len) => {
+                write!(f, "invalid hash length: {} bytes (expected 32)", len)
+            }
+            HashError::InvalidHex(hex) => write!(f, "invalid hash hex string: '{}'", hex),
+        }
+    }
+}
+
+impl error::Error for HashError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::ebpf_extractor::validation::BlockConnected;
+    use crate::protobuf::log_extractor::BlockConnectedLog;
+    use crate::protobuf::p2p_extractor::CompactBlockAnnouncement;
+
+    // Block 100000 on mainnet.
+    const BLOCK_HASH: &str = "000000000003ba27aa200b1cecaad478d2b00432346c3f1f3986da1afd33e506";
+
+    fn block_hash_internal_bytes() -> Vec<u8> {
+        bitcoin::BlockHash::from_str(BLOCK_HASH)
+            .unwrap()
+            .to_byte_array()
+            .to_vec()
+    }
+
+    #[test]
+    fn test_hash256_byte_order() {
+        let hash = Hash256::from_display_hex(BLOCK_HASH).unwrap();
+        assert_eq!(hash.to_string(), BLOCK_HASH);
+        // internal byte order is reversed: the leading zeros are at the end
+        assert_eq!(hash.to_internal_bytes()[0], 0x06);
+        assert_eq!(hash.to_internal_bytes()[31], 0x00);
+        assert_eq!(hash.to_display_bytes()[0], 0x00);
+        assert_eq!(hash.to_display_bytes()[31], 0x06);
+        assert_eq!(
+            Hash256::from_internal_bytes(&block_hash_internal_bytes()).unwrap(),
+            hash
+        );
+        assert_eq!(
+            bitcoin::BlockHash::from(hash),
+            bitcoin::BlockHash::from_str(BLOCK_HASH).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_hash256_uppercase_hex() {
+        let hash = Hash256::from_display_hex(&BLOCK_HASH.to_uppercase()).unwrap();
+        assert_eq!(hash.to_string(), BLOCK_HASH);
+    }
+
+    #[test]
+    fn test_hash256_invalid() {
+        assert_eq!(
+            Hash256::from_internal_bytes(&[0u8; 20]),
+            Err(HashError::InvalidLength(20))
+        );
+        assert_eq!(
+            Hash256::from_display_hex("00ff"),
+            Err(HashError::InvalidLength(2))
+        );
+        let not_hex = "z".repeat(64);
+        assert_eq!(
+            Hash256::from_display_hex(&not_hex),
+            Err(HashError::InvalidHex(not_hex.clone()))
+        );
+        // multi-byte characters and signs must not be accepted
+        assert!(Hash256::from_display_hex(&"ä".repeat(32)).is_err());
+        assert!(Hash256::from_display_hex(&"+f".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_hash256_log_extractor_byte_order() {
+        let log = BlockConnectedLog {
+            block_hash: BLOCK_HASH.to_string(),
+            block_height: 100000,
+        };
+        assert_eq!(log.hash256().unwrap().to_string(), BLOCK_HASH);
+    }
+
+    #[test]
+    fn test_hash256_ebpf_extractor_byte_order() {
+        let connected = BlockConnected {
+            hash: block_hash_internal_bytes(),
+            height: 100000,
+            transactions: 4,
+            inputs: 3,
+            sigops: 0,
+            connection_time: 0,
+        };
+        assert_eq!(connected.hash256().unwrap().to_string(), BLOCK_HASH);
+    }
+
+    #[test]
+    fn test_hash256_p2p_extractor_byte_order() {
+        let announcement = CompactBlockAnnouncement {
+            block_hash: block_hash_internal_bytes(),
+            short_ids: 3,
+            prefilled_transactions: 1,
+        };
+        assert_eq!(announcement.hash256().unwrap().to_string(), BLOCK_HASH);
+    }
+}
@@ -0,0 +1,167 @@
+use async_nats::connection::State;
+use log;
+use std::io;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const LOG_TARGET: &str = "healthserver";
+
+/// Health of an extractor. The main loop records successful ticks with
+/// [Health::tick]. The extractor is healthy if it's connected to NATS and the
+/// last successful tick isn't older than the maximum tick age.
+#[derive(Clone)]
+pub struct Health {
+    inner: Arc<HealthState>,
+}
+
+struct HealthState {
+    started: Instant,
+    max_tick_age: Duration,
+    /// Milliseconds between `started` and the last successful tick.
+    last_tick_millis: AtomicU64,
+    nats_client: OnceLock<async_nats::Client>,
+}
+
+impl Health {
+    /// Creates a new health state. Until the first tick, the time since
+    /// creation counts as tick age.
+    pub fn new(max_tick_age: Duration) -> Self {
+        Health {
+            inner: Arc::new(HealthState {
+                started: Instant::now(),
+                max_tick_age,
+                last_tick_millis: AtomicU64::new(0),
+                nats_client: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Sets the NATS client whose connection state is checked.
+    pub fn set_nats_client(&self, client: async_nats::Client) {
+        let _ = self.inner.nats_client.set(client);
+    }
+
+    /// Records a successful tick of the main loop.
+    pub fn tick(&self) {
+        let millis = self.inner.started.elapsed().as_millis() as u64;
+        self.inner.last_tick_millis.store(millis, Ordering::Relaxed);
+    }
+
+    /// Returns Ok if healthy, or the reason why not.
+    pub fn status(&self) -> Result<(), String> {
+        let last_tick = Duration::from_millis(self.inner.last_tick_millis.load(Ordering::Relaxed));
+        check(
+            self.inner.nats_client.get().map(|c| c.connection_state()),
+            self.inner.started.elapsed().saturating_sub(last_tick),
+            self.inner.max_tick_age,
+        )
+    }
+}
+
+fn check(
+    nats_state: Option<State>,
+    tick_age: Duration,
+    max_tick_age: Duration,
+) -> Result<(), String> {
+    match nats_state {
+        Some(State::Connected) => (),
+        Some(state) => return Err(format!("NATS connection is {:?}", state)),
+        None => return Err("not connected to NATS yet".to_string()),
+    }
+    if tick_age > max_tick_age {
+        return Err(format!(
+            "last successful tick was {}s ago (max {}s)",
+            tick_age.as_secs(),
+            max_tick_age.as_secs()
+        ));
+    }
+    Ok(())
+}
+
+// Like the metricserver, this is a minimal, per request thread spawning, and
+// incorrect HTTP server. It answers on all request methods and paths with 200
+// if the extractor is healthy and with 503 otherwise. Intended for liveness
+// and readiness probes.
+
+pub fn start(health_address: &str, health: Health) -> Result<(), io::Error> {
+    let listener = TcpListener::bind(health_address)?;
+    let local_addr = listener.local_addr()?;
+    log::info!(
+        target: LOG_TARGET,
+        "Started health server listening on {}.",
+        local_addr
+    );
+    thread::spawn(move || {
+        for incoming_request in listener.incoming() {
+            let stream = match incoming_request {
+                Ok(s) => s,
+                Err(e) => {
+                    log::error!(
+                        target: LOG_TARGET,
+                        "Could not process incoming request {}.",
+                        e
+                    );
+                    continue;
+                }
+            };
+            if let Err(e) = handle_request(stream, &health) {
+                log::error!(target: LOG_TARGET, "Could not handle request {}.", e);
+                continue;
+            };
+        }
+    });
+    Ok(())
+}
+
+fn handle_request(mut stream: TcpStream, health: &Health) -> Result<(), io::Error> {
+    let mut buffer = [0; 1024];
+    let _ = stream.read(&mut buffer)?;
+
+    let (status, contents) = match health.status() {
+        Ok(()) => ("200 OK", "OK".to_string()),
+        Err(reason) => {
+            log::debug!(target: LOG_TARGET, "Unhealthy: {}", reason);
+            ("503 Service Unavailable", reason)
+        }
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+        status,
+        contents.len(),
+        contents
+    );
+
+    stream.write_all(response.as_bytes())?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let max = Duration::from_secs(30);
+        assert!(check(Some(State::Connected), Duration::from_secs(10), max).is_ok());
+        assert!(check(Some(State::Connected), Duration::from_secs(31), max).is_err());
+        assert!(check(Some(State::Disconnected), Duration::from_secs(10), max).is_err());
+        assert!(check(Some(State::Pending), Duration::ZERO, max).is_err());
+        assert!(check(None, Duration::ZERO, max).is_err());
+    }
+
+    #[test]
+    fn test_health_without_nats() {
+        let health = Health::new(Duration::from_secs(30));
+        health.tick();
+        assert_eq!(
+            health.status(),
+            Err("not connected to NATS yet".to_string())
+        );
+    }
+}
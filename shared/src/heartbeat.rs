@@ -0,0 +1,86 @@
+use crate::labels::Labels;
+use crate::nats::TappedClient;
+use crate::nats_subjects::Subject;
+use crate::protobuf::event::{event::PeerObserverEvent, Event, Extractor, Heartbeat};
+use prost::Message;
+use std::time::Duration;
+use tokio::time;
+
+/// Creates the periodic [Heartbeat] events of an extractor, numbered from 0.
+pub struct Heartbeats {
+    extractor: Extractor,
+    interval: Duration,
+    sequence: u64,
+}
+
+impl Heartbeats {
+    pub fn new(extractor: Extractor, interval: Duration) -> Self {
+        Heartbeats {
+            extractor,
+            interval,
+            sequence: 0,
+        }
+    }
+
+    /// Returns the next heartbeat.
+    pub fn next(&mut self) -> Heartbeat {
+        let heartbeat = Heartbeat {
+            extractor: self.extractor.into(),
+            sequence: self.sequence,
+            interval: self.interval.as_secs(),
+        };
+        self.sequence += 1;
+        heartbeat
+    }
+
+    /// Publishes the next heartbeat on the `heartbeat` subject (below the
+    /// subject prefix, if any).
+    pub async fn publish(&mut self, nats_client: &TappedClient, labels: &Labels) {
+        let heartbeat = self.next();
+        log::trace!("Publishing {}", heartbeat);
+        match Event::new(PeerObserverEvent::Heartbeat(heartbeat)) {
+            Ok(proto) => {
+                let proto = proto.with_labels(labels);
+                // Like the extractor metrics, the heartbeats are published
+                // with the wrapped client and aren't retained in the debug
+                // buffer.
+                if let Err(e) = (**nats_client)
+                    .publish(
+                        nats_client.prefixed(&Subject::Heartbeat.to_string()),
+                        proto.encode_to_vec().into(),
+                    )
+                    .await
+                {
+                    log::error!("Could not publish heartbeat: {}", e);
+                }
+            }
+            Err(e) => log::error!("Could not create heartbeat event: {}", e),
+        }
+    }
+}
+
+/// An interval ticking right away and then every heartbeat `interval`, so
+/// that consumers see a started extractor without waiting a whole interval.
+/// Without an interval, it must not be polled.
+pub fn interval(interval: Option<Duration>) -> time::Interval {
+    time::interval(interval.unwrap_or(Duration::from_secs(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeats() {
+        let mut heartbeats = Heartbeats::new(Extractor::P2pExtractor, Duration::from_secs(30));
+        let heartbeat = heartbeats.next();
+        assert_eq!(heartbeat.extractor(), Extractor::P2pExtractor);
+        assert_eq!(heartbeat.sequence, 0);
+        assert_eq!(heartbeat.interval, 30);
+        assert_eq!(heartbeats.next().sequence, 1);
+        assert_eq!(
+            heartbeats.next().to_string(),
+            "Heartbeat(extractor=p2p-extractor, sequence=2, interval=30s)"
+        );
+    }
+}
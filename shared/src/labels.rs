@@ -0,0 +1,269 @@
+use crate::protobuf::event::Event;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs;
+
+/// Labels attached to every event published by an extractor.
+pub type Labels = BTreeMap<String, String>;
+
+/// Parses a `key=value` tag as passed with `--tag`. Can be used as a clap
+/// value parser.
+///
+/// Tag keys must start with an ASCII letter or underscore followed by ASCII
+/// letters, digits, or underscores. This keeps them usable as, for example,
+/// Prometheus label names. Values can be arbitrary strings.
+pub fn parse_tag(tag: &str) -> Result<(String, String), LabelError> {
+    let (key, value) = tag
+        .split_once('=')
+        .ok_or_else(|| LabelError::InvalidTag(tag.to_string()))?;
+    if !is_valid_key(key) {
+        return Err(LabelError::InvalidKey(key.to_string()));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Builds the labels from the parsed tags. Tag keys must be unique.
+pub fn labels_from_tags(tags: &[(String, String)]) -> Result<Labels, LabelError> {
+    let mut labels = Labels::new();
+    for (key, value) in tags {
+        if labels.insert(key.clone(), value.clone()).is_some() {
+            return Err(LabelError::DuplicateKey(key.clone()));
+        }
+    }
+    Ok(labels)
+}
+
+/// The label key the network of the node is attached as.
+pub const NETWORK_LABEL: &str = "network";
+
+/// The networks as named by Bitcoin Core's `-chain` option and the `chain`
+/// field of `getblockchaininfo`.
+pub const NETWORKS: &[&str] = &["main", "test", "testnet4", "signet", "regtest"];
+
+/// Adds the network of the node as `network` label. Consumers reading events
+/// from multiple nodes can use it to not mix data from different networks.
+pub fn add_network_label(labels: &mut Labels, network: &str) -> Result<(), LabelError> {
+    if labels.contains_key(NETWORK_LABEL) {
+        return Err(LabelError::DuplicateKey(NETWORK_LABEL.to_string()));
+    }
+    labels.insert(NETWORK_LABEL.to_string(), network.to_string());
+    Ok(())
+}
+
+/// The label key the identifier of the node is attached as.
+pub const NODE_LABEL: &str = "node";
+
+/// Adds the identifier of the node as `node` label. Consumers reading events
+/// from many extractor instances can use it to tell the nodes apart.
+pub fn add_node_label(labels: &mut Labels, node: &str) -> Result<(), LabelError> {
+    if labels.contains_key(NODE_LABEL) {
+        return Err(LabelError::DuplicateKey(NODE_LABEL.to_string()));
+    }
+    labels.insert(NODE_LABEL.to_string(), node.to_string());
+    Ok(())
+}
+
+/// The label key the identifier of the extractor instance is attached as.
+pub const INSTANCE_LABEL: &str = "instance";
+
+/// Generates a random identifier for an extractor instance: 16 lowercase hex
+/// characters.
+pub fn random_instance_id() -> String {
+    format!("{:016x}", rand::rng().random::<u64>())
+}
+
+/// Adds the identifier of the extractor instance as `instance` label. Events
+/// published by two instances observing the same node, e.g. two
+/// rpc-extractors started by accident, are the same apart from this label.
+/// Consumers can use it to detect and drop the duplicates.
+pub fn add_instance_label(labels: &mut Labels, instance_id: &str) -> Result<(), LabelError> {
+    if labels.contains_key(INSTANCE_LABEL) {
+        return Err(LabelError::DuplicateKey(INSTANCE_LABEL.to_string()));
+    }
+    labels.insert(INSTANCE_LABEL.to_string(), instance_id.to_string());
+    Ok(())
+}
+
+/// Resolves the identifier of the node an extractor observes. The first one
+/// known wins:
+/// 1. the explicitly configured identifier (`--node`),
+/// 2. the identity reported by the node itself, e.g. queried over RPC,
+/// 3. the hostname of the machine the extractor runs on.
+pub fn node_id(explicit: Option<&str>, reported: Option<&str>) -> Option<String> {
+    explicit.or(reported).map(str::to_string).or_else(hostname)
+}
+
+/// The hostname of the machine the extractor runs on, if it can be
+/// determined.
+pub fn hostname() -> Option<String> {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .chain(env::var("HOSTNAME").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .find(|hostname| !hostname.is_empty())
+}
+
+impl Event {
+    /// Attaches the labels to the event.
+    pub fn with_labels(mut self, labels: &Labels) -> Self {
+        self.labels = labels.clone();
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelError {
+    /// The tag isn't in the `key=value` format.
+    InvalidTag(String),
+    /// The tag key contains invalid characters.
+    InvalidKey(String),
+    /// The tag key was specified more than once.
+    DuplicateKey(String),
+}
+
+impl fmt::Display for LabelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LabelError::InvalidTag(tag) => {
+                write!(f, "invalid tag '{}': expected key=value", tag)
+            }
+            LabelError::InvalidKey(key) => write!(
+                f,
+                "invalid tag key '{}': must match [a-zA-Z_][a-zA-Z0-9_]*",
+                key
+            ),
+            LabelError::DuplicateKey(key) => write!(f, "duplicate tag key '{}'", key),
+        }
+    }
+}
+
+impl error::Error for LabelError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tag() {
+        assert_eq!(
+            parse_tag("region=eu"),
+            Ok(("region".to_string(), "eu".to_string()))
+        );
+        // only the first '=' separates key and value
+        assert_eq!(
+            parse_tag("note=a=b"),
+            Ok(("note".to_string(), "a=b".to_string()))
+        );
+        assert_eq!(
+            parse_tag("_empty="),
+            Ok(("_empty".to_string(), "".to_string()))
+        );
+        assert_eq!(
+            parse_tag("region"),
+            Err(LabelError::InvalidTag("region".to_string()))
+        );
+        assert_eq!(
+            parse_tag("=eu"),
+            Err(LabelError::InvalidKey("".to_string()))
+        );
+        assert_eq!(
+            parse_tag("1region=eu"),
+            Err(LabelError::InvalidKey("1region".to_string()))
+        );
+        assert_eq!(
+            parse_tag("re-gion=eu"),
+            Err(LabelError::InvalidKey("re-gion".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_labels_from_tags() {
+        let tags = vec![
+            parse_tag("region=eu").unwrap(),
+            parse_tag("role=listening").unwrap(),
+        ];
+        let labels = labels_from_tags(&tags).unwrap();
+        assert_eq!(labels.get("region"), Some(&"eu".to_string()));
+        assert_eq!(labels.get("role"), Some(&"listening".to_string()));
+
+        let duplicate = vec![
+            parse_tag("region=eu").unwrap(),
+            parse_tag("region=us").unwrap(),
+        ];
+        assert_eq!(
+            labels_from_tags(&duplicate),
+            Err(LabelError::DuplicateKey("region".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_add_network_label() {
+        let mut labels = labels_from_tags(&[parse_tag("region=eu").unwrap()]).unwrap();
+        add_network_label(&mut labels, "signet").unwrap();
+        assert_eq!(labels.get(NETWORK_LABEL), Some(&"signet".to_string()));
+        assert_eq!(labels.get("region"), Some(&"eu".to_string()));
+
+        // --network and --tag network=.. can't be combined
+        let mut labels = labels_from_tags(&[parse_tag("network=main").unwrap()]).unwrap();
+        assert_eq!(
+            add_network_label(&mut labels, "main"),
+            Err(LabelError::DuplicateKey(NETWORK_LABEL.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_add_instance_label() {
+        let id = random_instance_id();
+        assert_eq!(id.len(), 16);
+        assert!(id
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        assert_ne!(id, random_instance_id());
+
+        let mut labels = Labels::new();
+        add_instance_label(&mut labels, &id).unwrap();
+        assert_eq!(labels.get(INSTANCE_LABEL), Some(&id));
+
+        // --instance-id and --tag instance=.. can't be combined
+        let mut labels = labels_from_tags(&[parse_tag("instance=a").unwrap()]).unwrap();
+        assert_eq!(
+            add_instance_label(&mut labels, "b"),
+            Err(LabelError::DuplicateKey(INSTANCE_LABEL.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_node_id() {
+        assert_eq!(
+            node_id(Some("node-1"), Some("203.0.113.5:8333")),
+            Some("node-1".to_string())
+        );
+        assert_eq!(
+            node_id(None, Some("203.0.113.5:8333")),
+            Some("203.0.113.5:8333".to_string())
+        );
+        assert_eq!(node_id(None, None), hostname());
+
+        let mut labels = Labels::new();
+        add_node_label(&mut labels, "node-1").unwrap();
+        assert_eq!(labels.get(NODE_LABEL), Some(&"node-1".to_string()));
+        assert_eq!(
+            add_node_label(&mut labels, "node-2"),
+            Err(LabelError::DuplicateKey(NODE_LABEL.to_string()))
+        );
+    }
+}
@@ -11,6 +11,8 @@ pub extern crate log;
 pub extern crate prometheus;
 pub extern crate prost;
 pub extern crate rand;
+pub extern crate serde;
+pub extern crate serde_json;
 pub extern crate simple_logger;
 pub extern crate tokio;
 
@@ -21,9 +23,15 @@ pub mod protobuf;
 /// Subjects used when publishing and subscribing to NATS.
 pub mod nats_subjects;
 
+/// Connecting to a NATS server or cluster (`--nats-address`).
+pub mod nats;
+
 /// A minimal HTTP webserver (but not spec compliant) used to serve prometheus metrics via HTTP.
 pub mod metricserver;
 
+/// A minimal HTTP server answering liveness and readiness probes of the extractors.
+pub mod healthserver;
+
 /// Used in integration testing.
 pub mod testing;
 
@@ -33,6 +41,56 @@ pub mod util;
 /// Log matchers for the different types of log messages.
 pub mod log_matchers;
 
+/// Predicates for filtering events.
+pub mod filter;
+
+/// Buffering events to release them sorted by timestamp.
+pub mod ordering;
+
+/// Command line arguments common to all extractors.
+pub mod args;
+
+/// Setting up the extractors' own log output (`--log-level`, `--log-format`).
+pub mod logging;
+
+/// Loading the extractor arguments from a TOML file (`--config`).
+pub mod config;
+
+/// Labels (`--tag key=value`) attached to the events published by the extractors.
+pub mod labels;
+
+/// Counters and gauges about the extractors' own operation, published
+/// periodically (`--extractor-metrics-interval`).
+pub mod extractor_metrics;
+
+/// Heartbeat events published periodically to detect dead extractors
+/// (`--heartbeat-secs`).
+pub mod heartbeat;
+
+/// Creating events with the common metadata, e.g. the labels, stamped in one place.
+pub mod event_builder;
+
+/// Retaining the recently published messages of an extractor for debugging
+/// (`--debug-buffer-size`).
+pub mod debug_buffer;
+
+/// Additional outputs the published events are written to (`--output`).
+pub mod sink;
+
+/// Helpers for the tools consuming the published events, e.g. caching the
+/// decoded events.
+pub mod subscriber;
+
+/// Correlating the feefilters announced to the node with its mempool minimum fee.
+pub mod feefilter;
+
+/// The commands of the P2P messages known to Bitcoin Core, to bound
+/// peer-supplied commands.
+pub mod p2p_commands;
+
+/// A 256-bit hash type for block hashes, txids, and wtxids from the different extractors.
+pub mod hash;
+
 #[cfg(test)]
 mod tests {
     #[test]
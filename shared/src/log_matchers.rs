@@ -1,9 +1,15 @@
+use crate::hash::Hash256;
 use crate::protobuf::log_extractor::log::LogEvent;
 use crate::protobuf::log_extractor::{
-    BlockCheckedLog, BlockConnectedLog, Log, LogDebugCategory, UnknownLogMessage,
+    BlockCheckState, BlockCheckedLog, BlockConnectedLog, BlockTemplateLog, BlockTemplateTimingLog,
+    CriticalErrorKind, CriticalErrorLog, DiscourageAction, DiscouragedPeerLog, DiskActivityLog,
+    DiskActivityOperation, HeaderSyncLog, HeaderSyncStage, IbdCompleteLog, Log, LogDebugCategory,
+    LogSeverity, NetworkErrorLog, SnapshotLoadLog, SnapshotValidatedLog, UnknownLogMessage,
 };
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::error;
+use std::fmt;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
@@ -23,6 +29,93 @@ static RFC3339_DATE_REGEX: &str = r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d{1
 
 static BLOCK_HASH_PATTERN: &str = r"[0-9a-f]{64}";
 
+/// The `[snapshot]` prefix of the AssumeUTXO log lines. It looks like a
+/// category, so it's usually already stripped with the prefixes of the line.
+static SNAPSHOT_PREFIX_PATTERN: &str = r"(?:\[snapshot\]\s+)?";
+
+static IBD_COMPLETE_MESSAGE: &str = "Leaving InitialBlockDownload (latching to false)";
+
+/// Phrases the log messages Bitcoin Core logs on critical failures start with,
+/// after one of the [CRITICAL_ERROR_MESSAGE_PREFIXES]. The first matching
+/// phrase is used, so the more specific phrases come first. The phrases are
+/// anchored at the start of the message, as e.g. the user agent of a peer in a
+/// `net` log line could contain them, too.
+static CRITICAL_ERROR_PHRASES: &[(&str, CriticalErrorKind, LogSeverity)] = &[
+    (
+        "Fatal LevelDB error: Corruption",
+        CriticalErrorKind::Corruption,
+        LogSeverity::Critical,
+    ),
+    (
+        "Corruption detected",
+        CriticalErrorKind::Corruption,
+        LogSeverity::Critical,
+    ),
+    (
+        "Corrupted block database detected",
+        CriticalErrorKind::Corruption,
+        LogSeverity::Critical,
+    ),
+    (
+        "Fatal LevelDB error",
+        CriticalErrorKind::Database,
+        LogSeverity::Critical,
+    ),
+    (
+        "Error reading from database",
+        CriticalErrorKind::Database,
+        LogSeverity::Critical,
+    ),
+    (
+        "LevelDB read failure",
+        CriticalErrorKind::Database,
+        LogSeverity::Critical,
+    ),
+    (
+        "Disk space is too low",
+        CriticalErrorKind::DiskSpace,
+        LogSeverity::Critical,
+    ),
+    (
+        "A fatal internal error occurred",
+        CriticalErrorKind::FatalInternal,
+        LogSeverity::Critical,
+    ),
+    (
+        "System error while flushing",
+        CriticalErrorKind::FatalInternal,
+        LogSeverity::Critical,
+    ),
+    // A failed block read is fatal when connecting the block, but not when
+    // serving it to a peer.
+    (
+        "Deserialize or I/O error",
+        CriticalErrorKind::BlockRead,
+        LogSeverity::Error,
+    ),
+    (
+        "Failed to read block",
+        CriticalErrorKind::BlockRead,
+        LogSeverity::Error,
+    ),
+];
+
+/// The prefix of the messages logged by Bitcoin Core's `AbortNode()` before
+/// shutting down, e.g. `*** Failed to write block`.
+static ABORT_NODE_PREFIX: &str = "*** ";
+
+/// Prefixes Bitcoin Core logs before the [CRITICAL_ERROR_PHRASES]: `Error: `
+/// for `InitError()`, the [ABORT_NODE_PREFIX], and the names of the
+/// functions reading blocks from disk.
+static CRITICAL_ERROR_MESSAGE_PREFIXES: &[&str] = &[
+    "Error: ",
+    ABORT_NODE_PREFIX,
+    "ReadBlock: ",
+    "ReadBlockFromDisk: ",
+    "ReadRawBlock: ",
+    "ReadRawBlockFromDisk: ",
+];
+
 /// Regular expression for matching the output of `ValidationState::ToString()`.
 ///
 /// Matches strings produced by the `ToString()` method of a validation state object:
@@ -37,17 +130,22 @@ lazy_static! {
     /// Matches a log line with the following components:
     /// - `^({})`: Captures an RFC3339-compliant timestamp (defined by `RFC3339_DATE_REGEX`) at the start of the line.
     /// - `\s+`: Matches one or more whitespace characters after the timestamp.
-    /// - `(?:\[([^\]]+)\]\s+)?`: Optionally captures content within square brackets (debug category):
-    ///   - `(?:...)`: Non-capturing group for the bracketed content and trailing whitespace.
-    ///   - `[^\]]+`: Matches one or more characters that are not `]`.
-    ///   - `\s+`: Matches trailing whitespace after the brackets (if present).
+    /// - `((?:\[[^\]]+\]\s+)*)`: Captures all bracketed prefixes, see `LOG_LINE_PREFIX_REGEX`.
     /// - `(.+)$`: Captures the remaining log message content until the end of the line
     static ref LOG_LINE_REGEX: Regex = Regex::new(&format!(
-        r"^({})\s+(?:\[([^\]]+)\]\s+)?(.+)$",
+        r"^({})\s+((?:\[[^\]]+\]\s+)*)(.+)$",
         RFC3339_DATE_REGEX
     ))
     .unwrap();
 
+    /// Matches one bracketed prefix of a log line. Depending on the Bitcoin Core
+    /// options, there are multiple, in this order:
+    /// - `[msghand]`: The thread name (`-logthreadnames`).
+    /// - `[validationinterface.cpp:236] [BlockConnected]`: The source location (`-logsourcelocations`).
+    /// - `[validation]` or `[validation:debug]`: The debug category, with the log level
+    ///   for `-loglevelalways` and for non-debug levels.
+    static ref LOG_LINE_PREFIX_REGEX: Regex = Regex::new(r"\[([^\]]+)\]").unwrap();
+
     static ref BLOCK_CONNECTED_REGEX: Regex = Regex::new(&format!(
         r"BlockConnected: block hash=({}) block height=(\d+)",
         BLOCK_HASH_PATTERN
@@ -60,12 +158,114 @@ lazy_static! {
         VALIDATION_STATE_PATTERN
     ))
     .unwrap();
+
+    /// Matches the header (pre-)synchronization progress lines. The first capture
+    /// group is only present for the pre-synchronization phase.
+    static ref HEADER_SYNC_REGEX: Regex = Regex::new(
+        r"(Pre-s|S)ynchronizing blockheaders, height: (\d+) \(~(\d+(?:\.\d+)?)%\)"
+    )
+    .unwrap();
+
+    static ref NEW_HEADER_REGEX: Regex = Regex::new(&format!(
+        r"Saw new header hash=({}) height=(\d+)",
+        BLOCK_HASH_PATTERN
+    ))
+    .unwrap();
+
+    /// Matches the timed FlushStateToDisk operations:
+    /// - `(write coins cache to disk|...)`: Captures the operation.
+    /// - `(?: \((\d+) coins, (\d+(?:\.\d+)?)(?:KiB|kB)\))?`: Optionally captures the number of coins and their size.
+    /// - `(started|completed)`: Captures whether the operation started or completed.
+    /// - `(?: \((\d+(?:\.\d+)?)ms\))?`: Optionally captures the duration of a completed operation.
+    static ref FLUSH_STATE_REGEX: Regex = Regex::new(
+        r"FlushStateToDisk: (write coins cache to disk|write block and undo data to disk|find files to prune)(?: \(manual\))?(?: \((\d+) coins, (\d+(?:\.\d+)?)(?:KiB|kB)\))? (started|completed)(?: \((\d+(?:\.\d+)?)ms\))?"
+    )
+    .unwrap();
+
+    static ref PRUNE_UNLINK_REGEX: Regex =
+        Regex::new(r"Prune: UnlinkPrunedFiles deleted blk/rev \((\d+)\)").unwrap();
+
+    /// Matches misbehavior lines. Older Bitcoin Core versions also log the
+    /// misbehavior score change, e.g. `(0 -> 100)`, and `DISCOURAGE THRESHOLD EXCEEDED`,
+    /// which are ignored here.
+    static ref MISBEHAVING_REGEX: Regex = Regex::new(
+        r"Misbehaving: peer=(\d+)(?: \(\d+ -> \d+\))?(?:(?: DISCOURAGE THRESHOLD EXCEEDED)?:\s*(.+))?$"
+    )
+    .unwrap();
+
+    static ref DISCOURAGED_REGEX: Regex =
+        Regex::new(r"Disconnecting and discouraging peer (\d+)!").unwrap();
+
+    static ref NOT_PUNISHED_REGEX: Regex =
+        Regex::new(r"Warning: not (?:punishing|banning) (.+?) peer (\d+)!").unwrap();
+
+    static ref DISCONNECTED_ONLY_REGEX: Regex =
+        Regex::new(r"Warning: disconnecting but not discouraging (.+?) peer (\d+)!").unwrap();
+
+    static ref PRUNE_SUMMARY_REGEX: Regex = Regex::new(
+        r"target=(\d+)MiB actual=(\d+)MiB diff=-?\d+MiB .*removed (\d+) blk/rev pairs"
+    )
+    .unwrap();
+
+    /// Matches socket receive errors. Older Bitcoin Core versions don't log
+    /// the peer id, e.g. `socket recv error Connection reset by peer (104)`.
+    static ref SOCKET_RECV_ERROR_REGEX: Regex =
+        Regex::new(r"^socket recv error(?: for peer=(\d+))?:?\s+(.+)$").unwrap();
+
+    /// Matches refused outbound connections, e.g.
+    /// `connect() to 203.0.113.5:8333 failed after wait: Connection refused (111)`,
+    /// or refused by the proxy, e.g.
+    /// `Socks5() connect to 203.0.113.5:8333 failed: connection refused`.
+    static ref CONNECTION_REFUSED_REGEX: Regex = Regex::new(
+        r"^(?:connect\(\) to \S+ failed(?: after wait)?|Socks5\(\) connect to \S+ failed): (?:Connection refused \(\d+\)|connection refused)$"
+    )
+    .unwrap();
+
+    static ref SNAPSHOT_LOADING_REGEX: Regex = Regex::new(&format!(
+        r"^{}loading (\d+) coins from snapshot ({})",
+        SNAPSHOT_PREFIX_PATTERN, BLOCK_HASH_PATTERN
+    ))
+    .unwrap();
+
+    static ref SNAPSHOT_LOADED_REGEX: Regex = Regex::new(&format!(
+        r"^{}loaded (\d+) \((\d+(?:\.\d+)?) MB\) coins from snapshot ({})",
+        SNAPSHOT_PREFIX_PATTERN, BLOCK_HASH_PATTERN
+    ))
+    .unwrap();
+
+    static ref SNAPSHOT_VALIDATED_REGEX: Regex = Regex::new(&format!(
+        r"^{}snapshot beginning at ({}) has been fully validated",
+        SNAPSHOT_PREFIX_PATTERN, BLOCK_HASH_PATTERN
+    ))
+    .unwrap();
+
+    static ref CREATE_NEW_BLOCK_REGEX: Regex = Regex::new(
+        r"^CreateNewBlock\(\): block weight: (\d+) txs: (\d+) fees: (-?\d+) sigops (\d+)"
+    )
+    .unwrap();
+
+    /// Matches the [bench] timing of the block template creation:
+    /// - `packages: (\d+(?:\.\d+)?)ms`: Captures the package selection duration.
+    /// - `\((\d+) packages, (\d+) updated descendants\)`: Captures the number of packages and updated descendants.
+    /// - `validity: (\d+(?:\.\d+)?)ms \(total (\d+(?:\.\d+)?)ms\)`: Captures the validity check and total durations.
+    static ref CREATE_NEW_BLOCK_TIMING_REGEX: Regex = Regex::new(
+        r"^CreateNewBlock\(\) packages: (\d+(?:\.\d+)?)ms \((\d+) packages, (\d+) updated descendants\), validity: (\d+(?:\.\d+)?)ms \(total (\d+(?:\.\d+)?)ms\)"
+    )
+    .unwrap();
 }
 
 trait LogMatcher {
     fn parse_event(line: &str) -> Option<LogEvent>;
 }
 
+/// Parses a logged block hash and returns it in the canonical (big-endian,
+/// lowercase) hex format.
+fn parse_block_hash(hash: regex::Match) -> Option<String> {
+    Hash256::from_display_hex(hash.as_str())
+        .ok()
+        .map(|hash| hash.to_string())
+}
+
 impl LogMatcher for UnknownLogMessage {
     fn parse_event(line: &str) -> Option<LogEvent> {
         Some(LogEvent::UnknownLogMessage(UnknownLogMessage {
@@ -78,7 +278,7 @@ impl LogMatcher for BlockConnectedLog {
     fn parse_event(line: &str) -> Option<LogEvent> {
         let caps = BLOCK_CONNECTED_REGEX.captures(line)?;
 
-        let block_hash = caps.get(1)?.as_str().to_string();
+        let block_hash = parse_block_hash(caps.get(1)?)?;
         let block_height = caps.get(2)?.as_str().parse::<u32>().ok()?;
         Some(LogEvent::BlockConnectedLog(BlockConnectedLog {
             block_hash,
@@ -91,53 +291,400 @@ impl LogMatcher for BlockCheckedLog {
     fn parse_event(line: &str) -> Option<LogEvent> {
         let caps = BLOCK_CHECKED_REGEX.captures(line)?;
 
-        let block_hash = caps.get(1)?.as_str().to_string();
+        let block_hash = parse_block_hash(caps.get(1)?)?;
         let state = caps.get(2)?.as_str().to_string();
         let debug_message = caps
             .get(3)
             .map_or_else(String::new, |m| m.as_str().to_string());
         Some(LogEvent::BlockCheckedLog(BlockCheckedLog {
             block_hash,
+            check_state: Some(BlockCheckState::from_state(&state).into()),
             state,
             debug_message,
         }))
     }
 }
 
+impl LogMatcher for HeaderSyncLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        if let Some(caps) = HEADER_SYNC_REGEX.captures(line) {
+            let stage = match caps.get(1)?.as_str() {
+                "Pre-s" => HeaderSyncStage::Presync,
+                _ => HeaderSyncStage::Sync,
+            };
+            let height = caps.get(2)?.as_str().parse::<u32>().ok()?;
+            let progress = caps.get(3)?.as_str().parse::<f64>().ok()?;
+            return Some(LogEvent::HeaderSyncLog(HeaderSyncLog {
+                stage: stage.into(),
+                height,
+                progress: Some(progress),
+                block_hash: None,
+            }));
+        }
+
+        let caps = NEW_HEADER_REGEX.captures(line)?;
+        let block_hash = parse_block_hash(caps.get(1)?)?;
+        let height = caps.get(2)?.as_str().parse::<u32>().ok()?;
+        Some(LogEvent::HeaderSyncLog(HeaderSyncLog {
+            stage: HeaderSyncStage::NewHeader.into(),
+            height,
+            progress: None,
+            block_hash: Some(block_hash),
+        }))
+    }
+}
+
+impl LogMatcher for DiskActivityLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        if let Some(caps) = FLUSH_STATE_REGEX.captures(line) {
+            let operation = match caps.get(1)?.as_str() {
+                "write coins cache to disk" => DiskActivityOperation::FlushCoins,
+                "write block and undo data to disk" => DiskActivityOperation::FlushBlocks,
+                _ => DiskActivityOperation::FindFilesToPrune,
+            };
+            return Some(LogEvent::DiskActivityLog(DiskActivityLog {
+                operation: operation.into(),
+                completed: Some(caps.get(4)?.as_str() == "completed"),
+                duration_ms: caps.get(5).and_then(|m| m.as_str().parse::<f64>().ok()),
+                coins: caps.get(2).and_then(|m| m.as_str().parse::<u64>().ok()),
+                size_kib: caps.get(3).and_then(|m| m.as_str().parse::<f64>().ok()),
+                ..Default::default()
+            }));
+        }
+
+        if let Some(caps) = PRUNE_UNLINK_REGEX.captures(line) {
+            return Some(LogEvent::DiskActivityLog(DiskActivityLog {
+                operation: DiskActivityOperation::PruneUnlink.into(),
+                file_number: Some(caps.get(1)?.as_str().parse::<u32>().ok()?),
+                ..Default::default()
+            }));
+        }
+
+        let caps = PRUNE_SUMMARY_REGEX.captures(line)?;
+        Some(LogEvent::DiskActivityLog(DiskActivityLog {
+            operation: DiskActivityOperation::Prune.into(),
+            target_mib: Some(caps.get(1)?.as_str().parse::<u64>().ok()?),
+            actual_mib: Some(caps.get(2)?.as_str().parse::<u64>().ok()?),
+            removed_files: Some(caps.get(3)?.as_str().parse::<u32>().ok()?),
+            ..Default::default()
+        }))
+    }
+}
+
+impl LogMatcher for DiscouragedPeerLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        let (peer_id, action, reason) = if let Some(caps) = MISBEHAVING_REGEX.captures(line) {
+            (
+                caps.get(1)?.as_str(),
+                DiscourageAction::Misbehaving,
+                caps.get(2).map(|m| m.as_str().to_string()),
+            )
+        } else if let Some(caps) = DISCOURAGED_REGEX.captures(line) {
+            (caps.get(1)?.as_str(), DiscourageAction::Discouraged, None)
+        } else if let Some(caps) = NOT_PUNISHED_REGEX.captures(line) {
+            (
+                caps.get(2)?.as_str(),
+                DiscourageAction::NotPunished,
+                Some(caps.get(1)?.as_str().to_string()),
+            )
+        } else {
+            let caps = DISCONNECTED_ONLY_REGEX.captures(line)?;
+            (
+                caps.get(2)?.as_str(),
+                DiscourageAction::DisconnectedOnly,
+                Some(caps.get(1)?.as_str().to_string()),
+            )
+        };
+
+        Some(LogEvent::DiscouragedPeerLog(DiscouragedPeerLog {
+            peer_id: peer_id.parse::<u64>().ok()?,
+            action: action.into(),
+            reason,
+        }))
+    }
+}
+
+impl LogMatcher for NetworkErrorLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        let (peer_id, error) = if let Some(caps) = SOCKET_RECV_ERROR_REGEX.captures(line) {
+            (
+                caps.get(1).and_then(|m| m.as_str().parse::<u64>().ok()),
+                caps.get(2)?.as_str().to_string(),
+            )
+        } else if CONNECTION_REFUSED_REGEX.is_match(line) {
+            (None, line.to_string())
+        } else {
+            return None;
+        };
+
+        Some(LogEvent::NetworkErrorLog(NetworkErrorLog {
+            peer_id,
+            error,
+        }))
+    }
+}
+
+impl LogMatcher for CriticalErrorLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        let message = CRITICAL_ERROR_MESSAGE_PREFIXES
+            .iter()
+            .find_map(|prefix| line.strip_prefix(prefix))
+            .unwrap_or(line);
+        let (kind, severity) = CRITICAL_ERROR_PHRASES
+            .iter()
+            .find(|(phrase, _, _)| message.starts_with(phrase))
+            .map(|(_, kind, severity)| (*kind, *severity))
+            .or_else(|| {
+                line.starts_with(ABORT_NODE_PREFIX)
+                    .then_some((CriticalErrorKind::FatalInternal, LogSeverity::Critical))
+            })?;
+
+        Some(LogEvent::CriticalErrorLog(CriticalErrorLog {
+            kind: kind.into(),
+            severity: severity.into(),
+            message: line.to_string(),
+        }))
+    }
+}
+
+impl LogMatcher for IbdCompleteLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        (line.trim_end() == IBD_COMPLETE_MESSAGE)
+            .then_some(LogEvent::IbdCompleteLog(IbdCompleteLog {}))
+    }
+}
+
+impl LogMatcher for SnapshotLoadLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        if let Some(caps) = SNAPSHOT_LOADING_REGEX.captures(line) {
+            return Some(LogEvent::SnapshotLoadLog(SnapshotLoadLog {
+                block_hash: parse_block_hash(caps.get(2)?)?,
+                coins: caps.get(1)?.as_str().parse::<u64>().ok()?,
+                completed: false,
+                size_mb: None,
+            }));
+        }
+
+        let caps = SNAPSHOT_LOADED_REGEX.captures(line)?;
+        Some(LogEvent::SnapshotLoadLog(SnapshotLoadLog {
+            block_hash: parse_block_hash(caps.get(3)?)?,
+            coins: caps.get(1)?.as_str().parse::<u64>().ok()?,
+            completed: true,
+            size_mb: Some(caps.get(2)?.as_str().parse::<f64>().ok()?),
+        }))
+    }
+}
+
+impl LogMatcher for SnapshotValidatedLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        let caps = SNAPSHOT_VALIDATED_REGEX.captures(line)?;
+
+        Some(LogEvent::SnapshotValidatedLog(SnapshotValidatedLog {
+            block_hash: parse_block_hash(caps.get(1)?)?,
+        }))
+    }
+}
+
+impl LogMatcher for BlockTemplateLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        let caps = CREATE_NEW_BLOCK_REGEX.captures(line)?;
+
+        Some(LogEvent::BlockTemplateLog(BlockTemplateLog {
+            weight: caps.get(1)?.as_str().parse::<u64>().ok()?,
+            tx_count: caps.get(2)?.as_str().parse::<u64>().ok()?,
+            fees: caps.get(3)?.as_str().parse::<i64>().ok()?,
+            sigops: caps.get(4)?.as_str().parse::<u64>().ok()?,
+        }))
+    }
+}
+
+impl LogMatcher for BlockTemplateTimingLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        let caps = CREATE_NEW_BLOCK_TIMING_REGEX.captures(line)?;
+
+        Some(LogEvent::BlockTemplateTimingLog(BlockTemplateTimingLog {
+            packages_ms: caps.get(1)?.as_str().parse::<f64>().ok()?,
+            packages: caps.get(2)?.as_str().parse::<u64>().ok()?,
+            updated_descendants: caps.get(3)?.as_str().parse::<u64>().ok()?,
+            validity_ms: caps.get(4)?.as_str().parse::<f64>().ok()?,
+            total_ms: caps.get(5)?.as_str().parse::<f64>().ok()?,
+        }))
+    }
+}
+
 impl BlockCheckedLog {
     pub fn is_mutated_block(&self) -> bool {
-        matches!(
-            self.state.as_str(),
-            "bad-txnmrklroot"
-                | "bad-txns-duplicate"
-                | "bad-witness-nonce-size"
-                | "bad-witness-merkle-match"
-                | "unexpected-witness"
+        // Older log-extractors don't set the classified state, and newer ones
+        // might set states this version doesn't know yet.
+        self.check_state
+            .and_then(|state| BlockCheckState::try_from(state).ok())
+            .unwrap_or_else(|| BlockCheckState::from_state(&self.state))
+            .is_mutated()
+    }
+}
+
+/// A log matcher function. Returns `None` if the log message doesn't match.
+pub type LogMatcherFn = fn(&str) -> Option<LogEvent>;
+
+/// The built-in log matchers by name, in the order they are tried by default.
+/// The critical errors are tried first, so that they are never taken for
+/// another log event.
+pub const BUILTIN_LOG_MATCHERS: &[(&str, LogMatcherFn)] = &[
+    ("critical_error", CriticalErrorLog::parse_event),
+    ("block_connected", BlockConnectedLog::parse_event),
+    ("block_checked", BlockCheckedLog::parse_event),
+    ("header_sync", HeaderSyncLog::parse_event),
+    ("disk_activity", DiskActivityLog::parse_event),
+    ("discouraged_peer", DiscouragedPeerLog::parse_event),
+    ("network_error", NetworkErrorLog::parse_event),
+    ("ibd_complete", IbdCompleteLog::parse_event),
+    ("snapshot_load", SnapshotLoadLog::parse_event),
+    ("snapshot_validated", SnapshotValidatedLog::parse_event),
+];
+
+/// Opt-in log matchers for block template creation lines, which are only
+/// logged by nodes creating block templates (e.g. for a mining pool). They
+/// aren't part of the default registry, so that they don't slow down parsing
+/// the log lines on other nodes.
+pub const MINING_LOG_MATCHERS: &[(&str, LogMatcherFn)] = &[
+    ("block_template", BlockTemplateLog::parse_event),
+    ("block_template_timing", BlockTemplateTimingLog::parse_event),
+];
+
+/// All known log matchers: the [BUILTIN_LOG_MATCHERS] followed by the
+/// [MINING_LOG_MATCHERS].
+pub fn known_log_matchers() -> impl Iterator<Item = &'static (&'static str, LogMatcherFn)> {
+    BUILTIN_LOG_MATCHERS
+        .iter()
+        .chain(MINING_LOG_MATCHERS.iter())
+}
+
+lazy_static! {
+    static ref DEFAULT_LOG_MATCHER_REGISTRY: LogMatcherRegistry = LogMatcherRegistry::default();
+}
+
+/// Returned when enabling or disabling a log matcher that isn't a known log
+/// matcher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownLogMatcherError(pub String);
+
+impl fmt::Display for UnknownLogMatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown log matcher '{}' (known log matchers: {})",
+            self.0,
+            known_log_matchers()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
         )
     }
 }
 
-pub fn parse_log_event(line: &str) -> Log {
-    let (timestamp_micro, category, message) = parse_common_log_data(line);
-
-    let matchers: Vec<fn(&str) -> Option<LogEvent>> =
-        vec![BlockConnectedLog::parse_event, BlockCheckedLog::parse_event];
-    for matcher in &matchers {
-        if let Some(event) = matcher(&message) {
-            return Log {
-                log_timestamp: timestamp_micro,
-                category: category.into(),
-                log_event: Some(event),
-            };
+impl error::Error for UnknownLogMatcherError {}
+
+/// An ordered list of named log matchers. The first matcher matching a log
+/// message is used. If no matcher matches, the log message is parsed as
+/// [UnknownLogMessage].
+///
+/// The default registry contains all [BUILTIN_LOG_MATCHERS].
+#[derive(Debug, Clone)]
+pub struct LogMatcherRegistry {
+    matchers: Vec<(String, LogMatcherFn)>,
+}
+
+impl Default for LogMatcherRegistry {
+    fn default() -> Self {
+        BUILTIN_LOG_MATCHERS
+            .iter()
+            .fold(Self::empty(), |registry, (name, matcher)| {
+                registry.with_matcher(name, *matcher)
+            })
+    }
+}
+
+impl LogMatcherRegistry {
+    /// Creates a registry without any matchers. All log messages are parsed
+    /// as [UnknownLogMessage].
+    pub fn empty() -> Self {
+        Self {
+            matchers: Vec::new(),
+        }
+    }
+
+    /// Adds a matcher at the end of the registry. If a matcher with the same
+    /// name exists, it's replaced in place.
+    pub fn with_matcher(mut self, name: &str, matcher: LogMatcherFn) -> Self {
+        match self.matchers.iter_mut().find(|(n, _)| n == name) {
+            Some(existing) => existing.1 = matcher,
+            None => self.matchers.push((name.to_string(), matcher)),
+        }
+        self
+    }
+
+    /// Enables a built-in or mining matcher by adding it at the end of the
+    /// registry. Enabling an already enabled matcher doesn't change the
+    /// registry.
+    pub fn enable(self, name: &str) -> Result<Self, UnknownLogMatcherError> {
+        if self.is_enabled(name) {
+            return Ok(self);
+        }
+        match known_log_matchers().find(|(n, _)| *n == name) {
+            Some((name, matcher)) => Ok(self.with_matcher(name, *matcher)),
+            None => Err(UnknownLogMatcherError(name.to_string())),
+        }
+    }
+
+    /// Disables a known or custom matcher by removing it from the registry.
+    pub fn disable(mut self, name: &str) -> Result<Self, UnknownLogMatcherError> {
+        if !self.is_enabled(name) && !known_log_matchers().any(|(n, _)| *n == name) {
+            return Err(UnknownLogMatcherError(name.to_string()));
         }
+        self.matchers.retain(|(n, _)| n != name);
+        Ok(self)
     }
 
-    // if no matcher succeeds, return unknown
-    Log {
-        log_timestamp: timestamp_micro,
-        category: category.into(),
-        log_event: UnknownLogMessage::parse_event(&message),
+    /// Returns true if a matcher with this name is in the registry.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.matchers.iter().any(|(n, _)| n == name)
     }
+
+    /// The names of the matchers in the order they are tried.
+    pub fn names(&self) -> Vec<&str> {
+        self.matchers.iter().map(|(n, _)| n.as_str()).collect()
+    }
+
+    /// Parses a log line with the matchers in this registry.
+    pub fn parse(&self, line: &str) -> Log {
+        let (timestamp_micro, category, message) = parse_common_log_data(line);
+
+        for (_, matcher) in &self.matchers {
+            if let Some(event) = matcher(&message) {
+                return Log {
+                    occurrences: None,
+                    raw_line: None,
+                    log_timestamp: timestamp_micro,
+                    category: category.into(),
+                    log_event: Some(event),
+                };
+            }
+        }
+
+        // if no matcher succeeds, return unknown
+        Log {
+            occurrences: None,
+            raw_line: None,
+            log_timestamp: timestamp_micro,
+            category: category.into(),
+            log_event: UnknownLogMessage::parse_event(&message),
+        }
+    }
+}
+
+/// Parses a log line with the default [LogMatcherRegistry].
+pub fn parse_log_event(line: &str) -> Log {
+    DEFAULT_LOG_MATCHER_REGISTRY.parse(line)
 }
 
 fn parse_common_log_data(line: &str) -> (u64, LogDebugCategory, String) {
@@ -148,7 +695,6 @@ fn parse_common_log_data(line: &str) -> (u64, LogDebugCategory, String) {
 
     let caps = caps.unwrap();
     let timestamp_str = &caps[1];
-    let category = caps.get(2).map(|m| m.as_str());
 
     let timestamp_nano = match OffsetDateTime::parse(timestamp_str, &Rfc3339) {
         Ok(dt) => dt.unix_timestamp_nanos(),
@@ -156,15 +702,25 @@ fn parse_common_log_data(line: &str) -> (u64, LogDebugCategory, String) {
     };
     let timestamp_micro = (timestamp_nano / NANOS_PER_MICRO) as u64;
 
-    let log_type =
-        match category.and_then(|cat| LogDebugCategory::from_str_name(&cat.to_uppercase())) {
-            Some(cat) => cat,
-            None => LogDebugCategory::Unknown,
-        };
+    let log_type = parse_category(&caps[2]);
 
     (timestamp_micro, log_type, caps[3].to_string())
 }
 
+/// Returns the debug category in the bracketed prefixes of a log line. The
+/// category is the last prefix, but thread names and source locations can come
+/// before it. A log level after the category (`[net:debug]`) is ignored.
+fn parse_category(prefixes: &str) -> LogDebugCategory {
+    LOG_LINE_PREFIX_REGEX
+        .captures_iter(prefixes)
+        .filter_map(|caps| {
+            let category = caps[1].split(':').next().unwrap_or_default();
+            LogDebugCategory::from_str_name(&category.to_uppercase())
+        })
+        .last()
+        .unwrap_or(LogDebugCategory::Unknown)
+}
+
 // TODO: mempool_event::Event::Added
 // TODO: mempool_event::Event::Removed
 // TODO: mempool_event::Event::Replaced
@@ -361,9 +917,810 @@ mod tests {
                 "3909cd2a5ff36b9a40368609f92945e5b7111bca3cb4d04b72c39964aeb5d156"
             );
             assert_eq!(event.state, "bad-txnmrklroot");
+            assert_eq!(event.check_state(), BlockCheckState::BadTxnmrklroot);
             assert_eq!(event.debug_message, "hashMerkleRoot mismatch");
             return;
         }
         panic!("Expected BlockCheckedLog event");
     }
+
+    #[test]
+    fn test_log_matcher_block_check_states() {
+        let states = [
+            ("Valid", BlockCheckState::Valid, false),
+            ("bad-txnmrklroot", BlockCheckState::BadTxnmrklroot, true),
+            (
+                "bad-txns-duplicate",
+                BlockCheckState::BadTxnsDuplicate,
+                true,
+            ),
+            (
+                "bad-witness-nonce-size",
+                BlockCheckState::BadWitnessNonceSize,
+                true,
+            ),
+            (
+                "bad-witness-merkle-match",
+                BlockCheckState::BadWitnessMerkleMatch,
+                true,
+            ),
+            (
+                "unexpected-witness",
+                BlockCheckState::UnexpectedWitness,
+                true,
+            ),
+            ("high-hash", BlockCheckState::HighHash, false),
+            ("bad-diffbits", BlockCheckState::BadDiffbits, false),
+            ("time-too-old", BlockCheckState::TimeTooOld, false),
+            ("time-too-new", BlockCheckState::TimeTooNew, false),
+            ("bad-blk-length", BlockCheckState::BadBlkLength, false),
+            ("bad-blk-weight", BlockCheckState::BadBlkWeight, false),
+            ("bad-blk-sigops", BlockCheckState::BadBlkSigops, false),
+            ("bad-cb-missing", BlockCheckState::BadCbMissing, false),
+            ("bad-cb-multiple", BlockCheckState::BadCbMultiple, false),
+            ("bad-cb-height", BlockCheckState::BadCbHeight, false),
+            ("bad-cb-amount", BlockCheckState::BadCbAmount, false),
+            ("bad-txns-nonfinal", BlockCheckState::BadTxnsNonfinal, false),
+            ("bad-some-new-state", BlockCheckState::Other, false),
+        ];
+        for (state, expected, mutated) in states {
+            let log = format!("2025-10-28T02:18:37Z [validation] BlockChecked: block hash=3909cd2a5ff36b9a40368609f92945e5b7111bca3cb4d04b72c39964aeb5d156 state={}, debug message", state);
+            match parse_log_event(&log).log_event {
+                Some(LogEvent::BlockCheckedLog(event)) => {
+                    assert_eq!(event.state, state);
+                    assert_eq!(event.check_state(), expected, "{}", state);
+                    assert_eq!(event.is_mutated_block(), mutated, "{}", state);
+                }
+                other => panic!("{}: expected BlockCheckedLog event, got {:?}", state, other),
+            }
+        }
+
+        // events without the classified state, e.g. from older log-extractors
+        let event = BlockCheckedLog {
+            block_hash: "3909cd2a5ff36b9a40368609f92945e5b7111bca3cb4d04b72c39964aeb5d156"
+                .to_string(),
+            state: "bad-txns-duplicate".to_string(),
+            debug_message: "duplicate transaction".to_string(),
+            check_state: None,
+        };
+        assert!(event.is_mutated_block());
+    }
+
+    // The validation interface messages are logged by `LOG_EVENT` in Bitcoin
+    // Core's validationinterface.cpp, the prefixes depend on the logging
+    // options: the level is logged with `-loglevelalways` (since v28), and
+    // thread names and source locations can be logged before the category.
+    // BlockConnected is logged when it's enqueued and again when the
+    // scheduler thread runs the queued callback (the `operator()` of the
+    // lambda).
+    const BLOCK_CONNECTED_LINES: [(&str, &str); 7] = [
+        ("default", "2025-09-27T01:52:01Z [validation] BlockConnected: block hash=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b block height=437"),
+        ("default, enqueued", "2025-09-27T01:52:01Z [validation] Enqueuing BlockConnected: block hash=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b block height=437"),
+        ("-loglevelalways", "2025-09-27T01:52:01Z [validation:debug] BlockConnected: block hash=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b block height=437"),
+        ("-logthreadnames", "2025-09-27T01:52:01Z [scheduler] [validation] BlockConnected: block hash=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b block height=437"),
+        ("-logsourcelocations", "2025-09-27T01:52:01Z [validationinterface.cpp:236] [operator()] [validation] BlockConnected: block hash=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b block height=437"),
+        ("-logthreadnames -logsourcelocations, enqueued", "2025-09-27T01:52:01Z [msghand] [validationinterface.cpp:236] [BlockConnected] [validation] Enqueuing BlockConnected: block hash=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b block height=437"),
+        ("-logthreadnames -logsourcelocations -loglevelalways", "2025-09-27T01:52:01Z [scheduler] [validationinterface.cpp:236] [operator()] [validation:debug] BlockConnected: block hash=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b block height=437"),
+    ];
+
+    // BlockChecked isn't enqueued, it's logged by the thread validating the
+    // block.
+    const BLOCK_CHECKED_LINES: [(&str, &str); 5] = [
+        ("default", "2025-10-28T02:18:37Z [validation] BlockChecked: block hash=3909cd2a5ff36b9a40368609f92945e5b7111bca3cb4d04b72c39964aeb5d156 state=Valid"),
+        ("-loglevelalways", "2025-10-28T02:18:37Z [validation:debug] BlockChecked: block hash=3909cd2a5ff36b9a40368609f92945e5b7111bca3cb4d04b72c39964aeb5d156 state=Valid"),
+        ("-logthreadnames", "2025-10-28T02:18:37Z [msghand] [validation] BlockChecked: block hash=3909cd2a5ff36b9a40368609f92945e5b7111bca3cb4d04b72c39964aeb5d156 state=Valid"),
+        ("-logsourcelocations", "2025-10-28T02:18:37Z [validationinterface.cpp:258] [BlockChecked] [validation] BlockChecked: block hash=3909cd2a5ff36b9a40368609f92945e5b7111bca3cb4d04b72c39964aeb5d156 state=Valid"),
+        ("-logthreadnames -logsourcelocations -loglevelalways", "2025-10-28T02:18:37Z [msghand] [validationinterface.cpp:258] [BlockChecked] [validation:debug] BlockChecked: block hash=3909cd2a5ff36b9a40368609f92945e5b7111bca3cb4d04b72c39964aeb5d156 state=Valid"),
+    ];
+
+    #[test]
+    fn test_log_matcher_block_connected_logging_options() {
+        for (options, log) in BLOCK_CONNECTED_LINES {
+            let log_event = parse_log_event(log);
+
+            assert_eq!(
+                log_event.category,
+                LogDebugCategory::Validation as i32,
+                "{}",
+                options
+            );
+            match log_event.log_event {
+                Some(LogEvent::BlockConnectedLog(event)) => {
+                    assert_eq!(
+                        event.block_hash,
+                        "41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b",
+                        "{}",
+                        options
+                    );
+                    assert_eq!(event.block_height, 437, "{}", options);
+                }
+                other => panic!(
+                    "{}: expected BlockConnectedLog event, got {:?}",
+                    options, other
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_log_matcher_block_checked_logging_options() {
+        for (options, log) in BLOCK_CHECKED_LINES {
+            let log_event = parse_log_event(log);
+
+            assert_eq!(
+                log_event.category,
+                LogDebugCategory::Validation as i32,
+                "{}",
+                options
+            );
+            match log_event.log_event {
+                Some(LogEvent::BlockCheckedLog(event)) => {
+                    assert_eq!(
+                        event.block_hash,
+                        "3909cd2a5ff36b9a40368609f92945e5b7111bca3cb4d04b72c39964aeb5d156",
+                        "{}",
+                        options
+                    );
+                    assert_eq!(event.state, "Valid", "{}", options);
+                }
+                other => panic!(
+                    "{}: expected BlockCheckedLog event, got {:?}",
+                    options, other
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_log_matcher_header_presync() {
+        let log = "2025-10-30T10:12:43Z Pre-synchronizing blockheaders, height: 2000 (~0.22%)";
+        let log_event = parse_log_event(log);
+
+        assert_eq!(log_event.category, LogDebugCategory::Unknown as i32);
+
+        if let Some(LogEvent::HeaderSyncLog(event)) = log_event.log_event {
+            assert_eq!(event.stage(), HeaderSyncStage::Presync);
+            assert_eq!(event.height, 2000);
+            assert_eq!(event.progress, Some(0.22));
+            assert_eq!(event.block_hash, None);
+            return;
+        }
+        panic!("Expected HeaderSyncLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_header_sync() {
+        let log = "2025-10-30T10:13:05Z Synchronizing blockheaders, height: 420000 (~45.12%)";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::HeaderSyncLog(event)) = log_event.log_event {
+            assert_eq!(event.stage(), HeaderSyncStage::Sync);
+            assert_eq!(event.height, 420000);
+            assert_eq!(event.progress, Some(45.12));
+            return;
+        }
+        panic!("Expected HeaderSyncLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_new_header() {
+        let log = "2025-10-30T10:14:11Z Saw new header hash=00000000000000000001b4a6e9cd8d8a1bc5fe1f0b5fb74f3af9e1e6e1ab8d7a height=921234";
+        let log_event = parse_log_event(log);
+
+        assert_eq!(log_event.log_timestamp, 1761819251000000);
+
+        if let Some(LogEvent::HeaderSyncLog(event)) = log_event.log_event {
+            assert_eq!(event.stage(), HeaderSyncStage::NewHeader);
+            assert_eq!(event.height, 921234);
+            assert_eq!(event.progress, None);
+            assert_eq!(
+                event.block_hash,
+                Some(
+                    "00000000000000000001b4a6e9cd8d8a1bc5fe1f0b5fb74f3af9e1e6e1ab8d7a".to_string()
+                )
+            );
+            return;
+        }
+        panic!("Expected HeaderSyncLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_flush_coins_started() {
+        let log = "2025-10-30T11:02:13Z [bench] FlushStateToDisk: write coins cache to disk (2319 coins, 312.54KiB) started";
+        let log_event = parse_log_event(log);
+
+        assert_eq!(log_event.category, LogDebugCategory::Bench as i32);
+
+        if let Some(LogEvent::DiskActivityLog(event)) = log_event.log_event {
+            assert_eq!(event.operation(), DiskActivityOperation::FlushCoins);
+            assert_eq!(event.completed, Some(false));
+            assert_eq!(event.duration_ms, None);
+            assert_eq!(event.coins, Some(2319));
+            assert_eq!(event.size_kib, Some(312.54));
+            return;
+        }
+        panic!("Expected DiskActivityLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_flush_coins_completed() {
+        let log = "2025-10-30T11:02:13Z [bench] FlushStateToDisk: write coins cache to disk (2319 coins, 312.54KiB) completed (4.12ms)";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::DiskActivityLog(event)) = log_event.log_event {
+            assert_eq!(event.operation(), DiskActivityOperation::FlushCoins);
+            assert_eq!(event.completed, Some(true));
+            assert_eq!(event.duration_ms, Some(4.12));
+            assert_eq!(event.coins, Some(2319));
+            return;
+        }
+        panic!("Expected DiskActivityLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_flush_blocks() {
+        let log = "2025-10-30T11:02:13Z [bench] FlushStateToDisk: write block and undo data to disk started";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::DiskActivityLog(event)) = log_event.log_event {
+            assert_eq!(event.operation(), DiskActivityOperation::FlushBlocks);
+            assert_eq!(event.completed, Some(false));
+            assert_eq!(event.coins, None);
+            return;
+        }
+        panic!("Expected DiskActivityLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_prune_unlink() {
+        let log = "2025-10-30T11:02:13Z [prune] Prune: UnlinkPrunedFiles deleted blk/rev (00123)";
+        let log_event = parse_log_event(log);
+
+        assert_eq!(log_event.category, LogDebugCategory::Prune as i32);
+
+        if let Some(LogEvent::DiskActivityLog(event)) = log_event.log_event {
+            assert_eq!(event.operation(), DiskActivityOperation::PruneUnlink);
+            assert_eq!(event.file_number, Some(123));
+            return;
+        }
+        panic!("Expected DiskActivityLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_prune_summary() {
+        let log = "2025-10-30T11:02:13Z [prune] target=550MiB actual=549MiB diff=1MiB min_height=2016 max_prune_height=921000 removed 2 blk/rev pairs";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::DiskActivityLog(event)) = log_event.log_event {
+            assert_eq!(event.operation(), DiskActivityOperation::Prune);
+            assert_eq!(event.target_mib, Some(550));
+            assert_eq!(event.actual_mib, Some(549));
+            assert_eq!(event.removed_files, Some(2));
+            return;
+        }
+        panic!("Expected DiskActivityLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_misbehaving() {
+        let log = "2025-10-30T12:21:40Z [net] Misbehaving: peer=12: invalid header received";
+        let log_event = parse_log_event(log);
+
+        assert_eq!(log_event.category, LogDebugCategory::Net as i32);
+
+        if let Some(LogEvent::DiscouragedPeerLog(event)) = log_event.log_event {
+            assert_eq!(event.peer_id, 12);
+            assert_eq!(event.action(), DiscourageAction::Misbehaving);
+            assert_eq!(event.reason, Some("invalid header received".to_string()));
+            return;
+        }
+        panic!("Expected DiscouragedPeerLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_misbehaving_with_score() {
+        let log = "2024-03-01T12:21:40Z [net] Misbehaving: peer=3 (0 -> 100) DISCOURAGE THRESHOLD EXCEEDED: invalid header received";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::DiscouragedPeerLog(event)) = log_event.log_event {
+            assert_eq!(event.peer_id, 3);
+            assert_eq!(event.action(), DiscourageAction::Misbehaving);
+            assert_eq!(event.reason, Some("invalid header received".to_string()));
+            return;
+        }
+        panic!("Expected DiscouragedPeerLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_discouraged() {
+        let log = "2025-10-30T12:21:40Z [net] Disconnecting and discouraging peer 12!";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::DiscouragedPeerLog(event)) = log_event.log_event {
+            assert_eq!(event.peer_id, 12);
+            assert_eq!(event.action(), DiscourageAction::Discouraged);
+            assert_eq!(event.reason, None);
+            return;
+        }
+        panic!("Expected DiscouragedPeerLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_not_punished() {
+        let log = "2025-10-30T12:21:40Z Warning: not punishing manually connected peer 7!";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::DiscouragedPeerLog(event)) = log_event.log_event {
+            assert_eq!(event.peer_id, 7);
+            assert_eq!(event.action(), DiscourageAction::NotPunished);
+            assert_eq!(event.reason, Some("manually connected".to_string()));
+            return;
+        }
+        panic!("Expected DiscouragedPeerLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_disconnected_only() {
+        let log = "2025-10-30T12:21:40Z [net] Warning: disconnecting but not discouraging inbound onion peer 42!";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::DiscouragedPeerLog(event)) = log_event.log_event {
+            assert_eq!(event.peer_id, 42);
+            assert_eq!(event.action(), DiscourageAction::DisconnectedOnly);
+            assert_eq!(event.reason, Some("inbound onion".to_string()));
+            return;
+        }
+        panic!("Expected DiscouragedPeerLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_socket_recv_error() {
+        let log = "2025-11-04T09:12:51Z [net] socket recv error for peer=7: Connection reset by peer (104)";
+        let log_event = parse_log_event(log);
+
+        assert_eq!(log_event.category, LogDebugCategory::Net as i32);
+
+        if let Some(LogEvent::NetworkErrorLog(event)) = log_event.log_event {
+            assert_eq!(event.peer_id, Some(7));
+            assert_eq!(event.error, "Connection reset by peer (104)");
+            return;
+        }
+        panic!("Expected NetworkErrorLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_socket_recv_error_without_peer() {
+        let log = "2021-06-01T09:12:51Z [net] socket recv error Connection timed out (110)";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::NetworkErrorLog(event)) = log_event.log_event {
+            assert_eq!(event.peer_id, None);
+            assert_eq!(event.error, "Connection timed out (110)");
+            return;
+        }
+        panic!("Expected NetworkErrorLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_connection_refused() {
+        for message in [
+            "connect() to 203.0.113.5:8333 failed after wait: Connection refused (111)",
+            "connect() to [2001:db8::1]:8333 failed: Connection refused (111)",
+            "Socks5() connect to 203.0.113.5:8333 failed: connection refused",
+        ] {
+            let log = format!("2025-11-04T09:12:51Z [net] {}", message);
+            let log_event = parse_log_event(&log);
+
+            if let Some(LogEvent::NetworkErrorLog(event)) = log_event.log_event {
+                assert_eq!(event.peer_id, None);
+                assert_eq!(event.error, message);
+                continue;
+            }
+            panic!("Expected NetworkErrorLog event for '{}'", message);
+        }
+
+        // other messages mentioning a refused connection don't match
+        for message in [
+            "Received: version (102 bytes) peer=3 useragent=/connection refused/",
+            "Error: connection refused while loading the wallet",
+        ] {
+            let log = format!("2025-11-04T09:12:51Z [net] {}", message);
+            assert!(
+                !matches!(
+                    parse_log_event(&log).log_event,
+                    Some(LogEvent::NetworkErrorLog(_))
+                ),
+                "{}",
+                message
+            );
+        }
+    }
+
+    const BLOCK_CONNECTED_LINE: &str = "2025-09-27T01:52:01Z [validation] BlockConnected: block hash=6022a9138d879a9d525dba16a0e7d85eda9874736c1aed5c8da0c23ee878db4f block height=5";
+
+    #[test]
+    fn test_log_matcher_ibd_complete() {
+        let log = "2025-11-05T14:02:33Z Leaving InitialBlockDownload (latching to false)";
+        let log_event = parse_log_event(log);
+
+        assert_eq!(log_event.category, LogDebugCategory::Unknown as i32);
+        assert_eq!(log_event.log_timestamp, 1762351353000000);
+        assert!(matches!(
+            log_event.log_event,
+            Some(LogEvent::IbdCompleteLog(_))
+        ));
+
+        // only the exact message matches
+        let log = "2025-11-05T14:02:33Z Leaving InitialBlockDownload soon";
+        assert!(matches!(
+            parse_log_event(log).log_event,
+            Some(LogEvent::UnknownLogMessage(_))
+        ));
+    }
+
+    const SNAPSHOT_HASH: &str = "0000000000000000000320283a032748cef8227873ff4872689bf23f1cda83a5";
+
+    #[test]
+    fn test_log_matcher_snapshot_loading() {
+        let log = format!(
+            "2025-11-20T10:15:02Z [snapshot] loading 176948713 coins from snapshot {}",
+            SNAPSHOT_HASH
+        );
+        let log_event = parse_log_event(&log);
+
+        assert_eq!(log_event.category, LogDebugCategory::Unknown as i32);
+        assert_eq!(log_event.log_timestamp, 1763633702000000);
+        match log_event.log_event {
+            Some(LogEvent::SnapshotLoadLog(load)) => {
+                assert_eq!(load.block_hash, SNAPSHOT_HASH);
+                assert_eq!(load.coins, 176948713);
+                assert!(!load.completed);
+                assert_eq!(load.size_mb, None);
+            }
+            _ => panic!("expected SnapshotLoadLog"),
+        }
+    }
+
+    #[test]
+    fn test_log_matcher_snapshot_loaded() {
+        // with a thread name and the source location (v29)
+        let log = format!(
+            "2025-11-20T10:31:47Z [loadtxoutset] [validation.cpp:5901] [PopulateAndValidateSnapshot] [snapshot] loaded 176948713 (9127.43 MB) coins from snapshot {}",
+            SNAPSHOT_HASH
+        );
+        match parse_log_event(&log).log_event {
+            Some(LogEvent::SnapshotLoadLog(load)) => {
+                assert_eq!(load.block_hash, SNAPSHOT_HASH);
+                assert_eq!(load.coins, 176948713);
+                assert!(load.completed);
+                assert_eq!(load.size_mb, Some(9127.43));
+            }
+            _ => panic!("expected SnapshotLoadLog"),
+        }
+
+        // the progress lines while loading aren't matched
+        let log = "2025-11-20T10:16:02Z [snapshot] 5000000 coins loaded (2.83%, 258.11 MB)";
+        assert!(matches!(
+            parse_log_event(log).log_event,
+            Some(LogEvent::UnknownLogMessage(_))
+        ));
+    }
+
+    #[test]
+    fn test_log_matcher_snapshot_validated() {
+        let log = format!(
+            "2025-11-22T03:44:10Z [snapshot] snapshot beginning at {} has been fully validated",
+            SNAPSHOT_HASH
+        );
+        let log_event = parse_log_event(&log);
+
+        assert_eq!(log_event.log_timestamp, 1763783050000000);
+        match log_event.log_event {
+            Some(LogEvent::SnapshotValidatedLog(validated)) => {
+                assert_eq!(validated.block_hash, SNAPSHOT_HASH);
+            }
+            _ => panic!("expected SnapshotValidatedLog"),
+        }
+    }
+
+    #[test]
+    fn test_log_matcher_critical_error() {
+        let lines = [
+            (
+                "2025-11-04T09:12:51Z Fatal LevelDB error: Corruption: block checksum mismatch: /home/bitcoin/.bitcoin/chainstate/012345.ldb",
+                CriticalErrorKind::Corruption,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z [error] Corruption detected in the chainstate database",
+                CriticalErrorKind::Corruption,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z Error: Corrupted block database detected.",
+                CriticalErrorKind::Corruption,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z Fatal LevelDB error: IO error: /home/bitcoin/.bitcoin/chainstate/012345.ldb: Input/output error",
+                CriticalErrorKind::Database,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z Error reading from database: Database I/O error",
+                CriticalErrorKind::Database,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z Error reading from database, shutting down.",
+                CriticalErrorKind::Database,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z LevelDB read failure: Corruption: block checksum mismatch",
+                CriticalErrorKind::Database,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z [error] *** Disk space is too low!",
+                CriticalErrorKind::DiskSpace,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z Error: A fatal internal error occurred, see debug.log for details",
+                CriticalErrorKind::FatalInternal,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z [error] *** System error while flushing: Database I/O error",
+                CriticalErrorKind::FatalInternal,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z *** Failed to write block",
+                CriticalErrorKind::FatalInternal,
+                LogSeverity::Critical,
+            ),
+            (
+                "2025-11-04T09:12:51Z [error] ReadBlock: Deserialize or I/O error - ReadCompactSize(): size too large at FlatFilePos(nFile=123, nPos=456)",
+                CriticalErrorKind::BlockRead,
+                LogSeverity::Error,
+            ),
+            (
+                "2025-11-04T09:12:51Z [error] Failed to read block 0000000000000000000320283a032748cef8227873ff4872689bf23f1cda83a5 from disk",
+                CriticalErrorKind::BlockRead,
+                LogSeverity::Error,
+            ),
+        ];
+        for (line, kind, severity) in lines {
+            let log_event = parse_log_event(line);
+            assert_eq!(log_event.log_timestamp, 1762247571000000, "{}", line);
+            match log_event.log_event {
+                Some(LogEvent::CriticalErrorLog(error)) => {
+                    assert_eq!(error.kind(), kind, "{}", line);
+                    assert_eq!(error.severity(), severity, "{}", line);
+                    assert!(line.ends_with(&error.message), "{}", line);
+                }
+                other => panic!("{}: expected CriticalErrorLog event, got {:?}", line, other),
+            }
+        }
+
+        assert!(
+            CriticalErrorLog::parse_event("Flushed fee estimates to fee_estimates.dat.").is_none()
+        );
+    }
+
+    #[test]
+    fn test_log_matcher_critical_error_peer_controlled() {
+        // a peer can choose its user agent, which is logged in net lines
+        let log = "2025-11-04T09:12:51Z [net] receive version message: /Satoshi:29.0.0(Corrupted block database detected)/: version 70016, blocks=921000, us=203.0.113.1:8333, txrelay=1, peer=7";
+        let log_event = parse_log_event(log);
+        assert_eq!(log_event.category, LogDebugCategory::Net as i32);
+        assert!(matches!(
+            log_event.log_event,
+            Some(LogEvent::UnknownLogMessage(_))
+        ));
+
+        // the network error isn't lost to a phrase in the error message
+        let log =
+            "2025-11-04T09:12:51Z [net] socket recv error for peer=7: Error reading from database";
+        assert!(matches!(
+            parse_log_event(log).log_event,
+            Some(LogEvent::NetworkErrorLog(_))
+        ));
+    }
+
+    #[test]
+    fn test_log_matcher_registry_default() {
+        let registry = LogMatcherRegistry::default();
+        assert_eq!(
+            registry.names(),
+            BUILTIN_LOG_MATCHERS
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            registry.parse(BLOCK_CONNECTED_LINE).log_event,
+            Some(LogEvent::BlockConnectedLog(_))
+        ));
+    }
+
+    #[test]
+    fn test_log_matcher_registry_disable_enable() {
+        let registry = LogMatcherRegistry::default()
+            .disable("block_connected")
+            .unwrap();
+        assert!(!registry.is_enabled("block_connected"));
+        assert!(matches!(
+            registry.parse(BLOCK_CONNECTED_LINE).log_event,
+            Some(LogEvent::UnknownLogMessage(_))
+        ));
+
+        let registry = registry.enable("block_connected").unwrap();
+        assert_eq!(registry.names().last(), Some(&"block_connected"));
+        assert!(matches!(
+            registry.parse(BLOCK_CONNECTED_LINE).log_event,
+            Some(LogEvent::BlockConnectedLog(_))
+        ));
+
+        assert_eq!(
+            LogMatcherRegistry::default().disable("foo").unwrap_err(),
+            UnknownLogMatcherError("foo".to_string())
+        );
+        assert!(LogMatcherRegistry::empty().enable("foo").is_err());
+    }
+
+    const BLOCK_TEMPLATE_LINE: &str = "2025-11-12T08:30:02Z CreateNewBlock(): block weight: 3996960 txs: 3140 fees: 2301450 sigops 9516";
+
+    #[test]
+    fn test_log_matcher_block_template() {
+        // not matched by the default registry
+        assert!(matches!(
+            parse_log_event(BLOCK_TEMPLATE_LINE).log_event,
+            Some(LogEvent::UnknownLogMessage(_))
+        ));
+
+        let registry = LogMatcherRegistry::default()
+            .enable("block_template")
+            .unwrap();
+        if let Some(LogEvent::BlockTemplateLog(event)) =
+            registry.parse(BLOCK_TEMPLATE_LINE).log_event
+        {
+            assert_eq!(event.weight, 3996960);
+            assert_eq!(event.tx_count, 3140);
+            assert_eq!(event.fees, 2301450);
+            assert_eq!(event.sigops, 9516);
+            return;
+        }
+        panic!("Expected BlockTemplateLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_block_template_timing() {
+        let log = "2025-11-12T08:30:02Z [bench] CreateNewBlock() packages: 21.07ms (3084 packages, 1822 updated descendants), validity: 18.63ms (total 39.70ms)";
+        let registry = LogMatcherRegistry::default()
+            .enable("block_template_timing")
+            .unwrap();
+        let log_event = registry.parse(log);
+
+        assert_eq!(log_event.category, LogDebugCategory::Bench as i32);
+        if let Some(LogEvent::BlockTemplateTimingLog(event)) = log_event.log_event {
+            assert_eq!(event.packages_ms, 21.07);
+            assert_eq!(event.packages, 3084);
+            assert_eq!(event.updated_descendants, 1822);
+            assert_eq!(event.validity_ms, 18.63);
+            assert_eq!(event.total_ms, 39.70);
+            return;
+        }
+        panic!("Expected BlockTemplateTimingLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_registry_mining_matchers() {
+        let registry = MINING_LOG_MATCHERS
+            .iter()
+            .try_fold(LogMatcherRegistry::default(), |registry, (name, _)| {
+                registry.enable(name)
+            })
+            .unwrap();
+        assert_eq!(registry.names().len(), known_log_matchers().count());
+        assert!(registry.is_enabled("block_template"));
+
+        // mining matchers can be disabled even if they aren't enabled
+        let registry = LogMatcherRegistry::default()
+            .disable("block_template")
+            .unwrap();
+        assert!(!registry.is_enabled("block_template"));
+    }
+
+    #[test]
+    fn test_log_matcher_registry_custom_matcher() {
+        fn match_all(line: &str) -> Option<LogEvent> {
+            Some(LogEvent::UnknownLogMessage(UnknownLogMessage {
+                raw_message: format!("custom: {}", line),
+            }))
+        }
+
+        let registry = LogMatcherRegistry::empty().with_matcher("custom", match_all);
+        assert_eq!(registry.names(), vec!["custom"]);
+
+        if let Some(LogEvent::UnknownLogMessage(unknown_log)) =
+            registry.parse(BLOCK_CONNECTED_LINE).log_event
+        {
+            assert!(unknown_log
+                .raw_message
+                .starts_with("custom: BlockConnected"));
+            return;
+        }
+
+        panic!("Expected UnknownLogMessage event");
+    }
+
+    const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/log_matchers");
+
+    /// The log lines of a corpus file, without comments and empty lines.
+    fn corpus_lines(path: &std::path::Path) -> Vec<String> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Parses the log lines of the corpus files and compares the events to
+    /// the golden files. With `UPDATE_GOLDEN` set, the golden files are
+    /// rewritten instead (see testdata/log_matchers/README.md).
+    #[test]
+    fn test_log_matcher_corpus() {
+        let registry = MINING_LOG_MATCHERS
+            .iter()
+            .try_fold(LogMatcherRegistry::default(), |registry, (name, _)| {
+                registry.enable(name)
+            })
+            .unwrap();
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+        let mut corpus_files = 0;
+        for entry in std::fs::read_dir(CORPUS_DIR).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("log") {
+                continue;
+            }
+            corpus_files += 1;
+            let golden_path = path.with_extension("golden.jsonl");
+            let logs: Vec<(String, Log)> = corpus_lines(&path)
+                .into_iter()
+                .map(|line| {
+                    let log = registry.parse(&line);
+                    (line, log)
+                })
+                .collect();
+
+            if update {
+                let golden: String = logs
+                    .iter()
+                    .map(|(_, log)| format!("{}\n", serde_json::to_string(log).unwrap()))
+                    .collect();
+                std::fs::write(&golden_path, golden).unwrap();
+                continue;
+            }
+
+            let golden = std::fs::read_to_string(&golden_path)
+                .unwrap_or_else(|e| panic!("{}: {}", golden_path.display(), e));
+            let golden: Vec<&str> = golden.lines().collect();
+            assert_eq!(
+                logs.len(),
+                golden.len(),
+                "{}: the number of log lines and golden events differ",
+                path.display()
+            );
+            for ((line, log), expected) in logs.iter().zip(golden) {
+                let expected: Log = serde_json::from_str(expected).unwrap();
+                assert_eq!(log, &expected, "{}: {}", path.display(), line);
+            }
+        }
+        assert!(corpus_files > 0, "no corpus files in {}", CORPUS_DIR);
+    }
 }
@@ -0,0 +1,133 @@
+use crate::args::CommonArgs;
+use crate::sink::Output;
+use std::io::Write;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// The format of the extractors' own log output (`--log-format`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, e.g. for log aggregators like Loki or ELK.
+    Json,
+}
+
+/// Initializes the logger with the log level and format of the arguments.
+/// Logs to stdout, or to stderr if the published events are written to
+/// stdout (`--output stdout`), so that the two don't interleave.
+pub fn init(args: &CommonArgs) -> Result<(), log::SetLoggerError> {
+    let stderr = args.outputs.contains(&Output::Stdout);
+    if args.log_format == LogFormat::Text && !stderr {
+        return simple_logger::init_with_level(args.log_level);
+    }
+    log::set_boxed_logger(Box::new(LineLogger {
+        level: args.log_level,
+        format: args.log_format,
+        stderr,
+    }))?;
+    log::set_max_level(args.log_level.to_level_filter());
+    Ok(())
+}
+
+/// Writes each log record on a line to stdout or stderr.
+struct LineLogger {
+    level: log::Level,
+    format: LogFormat,
+    stderr: bool,
+}
+
+impl log::Log for LineLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let now = OffsetDateTime::now_utc();
+        let line = match self.format {
+            LogFormat::Text => text_line(record, now),
+            LogFormat::Json => json_line(record, now),
+        };
+        // there's nowhere to report a failed write of a log line to
+        let _ = if self.stderr {
+            writeln!(std::io::stderr().lock(), "{}", line)
+        } else {
+            writeln!(std::io::stdout().lock(), "{}", line)
+        };
+    }
+
+    fn flush(&self) {
+        let _ = if self.stderr {
+            std::io::stderr().flush()
+        } else {
+            std::io::stdout().flush()
+        };
+    }
+}
+
+/// Formats the record like simple_logger: the timestamp, level, target, and
+/// message.
+fn text_line(record: &log::Record, now: OffsetDateTime) -> String {
+    format!(
+        "{} {:<5} [{}] {}",
+        now.format(&Rfc3339).unwrap_or_default(),
+        record.level().as_str(),
+        record.target(),
+        record.args()
+    )
+}
+
+/// Formats the record as JSON object with the timestamp, level, target, and
+/// message.
+fn json_line(record: &log::Record, now: OffsetDateTime) -> String {
+    serde_json::json!({
+        "timestamp": now.format(&Rfc3339).unwrap_or_default(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_line() {
+        let line = text_line(
+            &log::Record::builder()
+                .args(format_args!("connected to {}", "127.0.0.1:4222"))
+                .level(log::Level::Info)
+                .target("rpc_extractor")
+                .build(),
+            OffsetDateTime::from_unix_timestamp(1700000000).unwrap(),
+        );
+        assert_eq!(
+            line,
+            "2023-11-14T22:13:20Z INFO  [rpc_extractor] connected to 127.0.0.1:4222"
+        );
+    }
+
+    #[test]
+    fn test_json_line() {
+        let line = json_line(
+            &log::Record::builder()
+                .args(format_args!("connected to \"{}\"", "127.0.0.1:4222"))
+                .level(log::Level::Warn)
+                .target("rpc_extractor")
+                .build(),
+            OffsetDateTime::from_unix_timestamp(1700000000).unwrap(),
+        );
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["timestamp"], "2023-11-14T22:13:20Z");
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["target"], "rpc_extractor");
+        assert_eq!(value["message"], "connected to \"127.0.0.1:4222\"");
+        assert!(!line.contains('\n'));
+    }
+}
@@ -0,0 +1,487 @@
+use crate::debug_buffer::DebugBuffer;
+use crate::nats_subjects::{
+    event_subscription_patterns, is_batch_subject, is_event_subject, parse_event_subject,
+    prefixed_subject, ParsedSubject,
+};
+use crate::protobuf::event::{event::PeerObserverEvent, Event, EventBatch};
+use crate::sink::MultiSink;
+use async_nats::client::SubscribeError;
+use async_nats::subject::ToSubject;
+use async_nats::{Client, ConnectError, ConnectOptions, Message, ServerAddr, Subscriber};
+use futures::stream::{self, SelectAll};
+use prost::bytes::Bytes;
+use prost::Message as _;
+use std::error;
+use std::fmt;
+use std::ops::Deref;
+
+/// Splits a comma-separated list of NATS server addresses, e.g. the seed
+/// servers of a NATS cluster: `host1:4222,host2:4222`.
+pub fn nats_addresses(address: &str) -> Vec<&str> {
+    address.split(',').map(str::trim).collect()
+}
+
+/// Validates a `--nats-address` argument. Can be used as a clap value parser.
+///
+/// Each of the comma-separated addresses must be a valid NATS server address
+/// like `127.0.0.1:4222` or `nats://host:4222`.
+pub fn parse_nats_address(address: &str) -> Result<String, String> {
+    for addr in nats_addresses(address) {
+        if addr.is_empty() {
+            return Err(format!("empty NATS server address in '{}'", address));
+        }
+        addr.parse::<ServerAddr>()
+            .map_err(|e| format!("invalid NATS server address '{}': {}", addr, e))?;
+    }
+    Ok(address.to_string())
+}
+
+/// Connects to the NATS server(s) in the comma-separated `address`. With
+/// multiple servers, the client connects to any of the reachable ones and
+/// reconnects to another if the connection is lost.
+pub async fn connect(address: &str) -> Result<Client, ConnectError> {
+    ConnectOptions::new()
+        .connect(nats_addresses(address).as_slice())
+        .await
+}
+
+/// Subscribes to the subjects events are published on (see
+/// [event_subscription_patterns]), below the subject prefix
+/// (`--nats-subject-prefix`) if any. The messages of all subscriptions are
+/// merged into one stream. Requests to the extractors and their replies still
+/// need to be skipped with [is_event_subject].
+pub async fn subscribe_events(
+    client: &Client,
+    prefix: Option<&str>,
+) -> Result<SelectAll<Subscriber>, SubscribeError> {
+    let mut subscribers = vec![];
+    for pattern in event_subscription_patterns() {
+        subscribers.push(client.subscribe(prefixed_subject(prefix, &pattern)).await?);
+    }
+    Ok(stream::select_all(subscribers))
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The message wasn't published on an event subject.
+    Subject(String),
+    Protobuf(prost::DecodeError),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Subject(e) => write!(f, "invalid subject: {}", e),
+            DecodeError::Protobuf(e) => write!(f, "could not decode event: {}", e),
+        }
+    }
+}
+
+impl error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DecodeError::Subject(_) => None,
+            DecodeError::Protobuf(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<prost::DecodeError> for DecodeError {
+    fn from(e: prost::DecodeError) -> Self {
+        DecodeError::Protobuf(e)
+    }
+}
+
+/// Decodes the Event in a NATS message published below the subject prefix
+/// (`--nats-subject-prefix`), if any. Returns an error if the message wasn't
+/// published on an event subject below the prefix, e.g. requests to the
+/// extractors, or contains a batch of events (see [decode_events]).
+pub fn decode_message(msg: &Message, prefix: Option<&str>) -> Result<Event, DecodeError> {
+    decode_message_with_subject(msg, prefix).map(|(_, event)| event)
+}
+
+/// Like [decode_message], but also returns the parsed subject, e.g. to tell
+/// which node an event is from.
+pub fn decode_message_with_subject(
+    msg: &Message,
+    prefix: Option<&str>,
+) -> Result<(ParsedSubject, Event), DecodeError> {
+    let parsed = parse_single_event_subject(prefix, &msg.subject)?;
+    let event = Event::decode(msg.payload.as_ref())?;
+    Ok((parsed, event))
+}
+
+/// Parses the subject of a message carrying a single event.
+pub(crate) fn parse_single_event_subject(
+    prefix: Option<&str>,
+    subject: &str,
+) -> Result<ParsedSubject, DecodeError> {
+    let parsed = parse_event_subject(prefix, subject).map_err(DecodeError::Subject)?;
+    if parsed.batch {
+        return Err(DecodeError::Subject(format!(
+            "'{}' carries batches of events",
+            subject
+        )));
+    }
+    Ok(parsed)
+}
+
+/// Decodes the events in a NATS message: all events of an EventBatch for batch
+/// subjects, and the single Event otherwise. Unlike [decode_message], this
+/// doesn't validate the subject.
+pub fn decode_events(msg: &Message) -> Result<Vec<Event>, prost::DecodeError> {
+    decode_payload(&msg.subject, &msg.payload)
+}
+
+/// Like [decode_events], but for a payload published on the subject.
+pub fn decode_payload(subject: &str, payload: &[u8]) -> Result<Vec<Event>, prost::DecodeError> {
+    if is_batch_subject(subject) {
+        Ok(EventBatch::decode(payload)?.events)
+    } else {
+        Ok(vec![Event::decode(payload)?])
+    }
+}
+
+#[derive(Debug)]
+pub enum PublishError {
+    /// The payload is larger than the maximum NATS payload and wasn't
+    /// published.
+    PayloadTooLarge {
+        subject: String,
+        /// The type of the (largest) event in the payload, see [event_type].
+        event_type: String,
+        size: usize,
+        max_payload: usize,
+    },
+    Nats(async_nats::client::PublishError),
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PublishError::PayloadTooLarge {
+                subject,
+                event_type,
+                size,
+                max_payload,
+            } => write!(
+                f,
+                "the {} event on '{}' is {} bytes, larger than the maximum NATS payload of {} bytes",
+                event_type, subject, size, max_payload
+            ),
+            PublishError::Nats(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for PublishError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            PublishError::PayloadTooLarge { .. } => None,
+            PublishError::Nats(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<async_nats::client::PublishError> for PublishError {
+    fn from(e: async_nats::client::PublishError) -> Self {
+        PublishError::Nats(e)
+    }
+}
+
+/// A short name of the event's type for diagnostics, e.g.
+/// `rpc-extractor PeerInfos`.
+pub fn event_type(event: &Event) -> String {
+    let description = match &event.peer_observer_event {
+        Some(PeerObserverEvent::EbpfExtractor(e)) => e.ebpf_event.as_ref().map(|e| e.to_string()),
+        Some(PeerObserverEvent::RpcExtractor(r)) => r.rpc_event.as_ref().map(|e| e.to_string()),
+        Some(PeerObserverEvent::P2pExtractor(p)) => p.p2p_event.as_ref().map(|e| e.to_string()),
+        Some(PeerObserverEvent::LogExtractor(l)) => l.log_event.as_ref().map(|e| e.to_string()),
+        Some(PeerObserverEvent::ErrorEvent(_)) => Some("ErrorEvent".to_string()),
+        Some(PeerObserverEvent::ExtractorMetrics(_)) => Some("ExtractorMetrics".to_string()),
+        Some(PeerObserverEvent::Heartbeat(_)) => Some("Heartbeat".to_string()),
+        None => None,
+    };
+    // The descriptions start with the name of the type, e.g. `PeerInfos(...)`.
+    let name = description
+        .as_deref()
+        .and_then(|d| d.split('(').next())
+        .unwrap_or("empty");
+    match event.extractor_kind() {
+        Some(kind) => format!("{} {}", kind, name),
+        None => name.to_string(),
+    }
+}
+
+/// Checks that the payload published on the subject isn't larger than
+/// `max_payload`. The error names the type of the overflowing event, or of
+/// the largest event in a batch.
+pub fn check_payload_size(
+    subject: &str,
+    payload: &[u8],
+    max_payload: usize,
+) -> Result<(), PublishError> {
+    if payload.len() <= max_payload {
+        return Ok(());
+    }
+    let event_type = match decode_payload(subject, payload) {
+        Ok(events) => events
+            .iter()
+            .max_by_key(|event| event.encoded_len())
+            .map(event_type)
+            .unwrap_or_else(|| "empty batch".to_string()),
+        Err(_) => "unknown".to_string(),
+    };
+    Err(PublishError::PayloadTooLarge {
+        subject: subject.to_string(),
+        event_type,
+        size: payload.len(),
+        max_payload,
+    })
+}
+
+/// A NATS client that also hands the messages it publishes to the
+/// [DebugBuffer] and the additional outputs (`--output`) of the extractor.
+/// With a subject prefix (`--nats-subject-prefix`), events are published and
+/// subscriptions are made below the prefix. Derefs to the wrapped client for
+/// everything else.
+#[derive(Clone)]
+pub struct TappedClient {
+    client: Client,
+    buffer: DebugBuffer,
+    sinks: MultiSink,
+    max_payload: Option<usize>,
+    subject_prefix: Option<String>,
+}
+
+impl TappedClient {
+    pub fn new(client: Client, buffer: DebugBuffer, sinks: MultiSink) -> Self {
+        TappedClient {
+            client,
+            buffer,
+            sinks,
+            max_payload: None,
+            subject_prefix: None,
+        }
+    }
+
+    /// Publishes the events and subscribes below the prefix
+    /// (`--nats-subject-prefix`), e.g. on `team-a.rpc` instead of `rpc`.
+    pub fn with_subject_prefix(mut self, subject_prefix: Option<String>) -> Self {
+        self.subject_prefix = subject_prefix;
+        self
+    }
+
+    /// Returns the subject below the subject prefix, if any.
+    pub fn prefixed(&self, subject: &str) -> String {
+        prefixed_subject(self.subject_prefix.as_deref(), subject)
+    }
+
+    /// Subscribes to the subject below the subject prefix, if any.
+    pub async fn subscribe<S: ToSubject>(&self, subject: S) -> Result<Subscriber, SubscribeError> {
+        let subject = subject.to_subject();
+        self.client.subscribe(self.prefixed(subject.as_str())).await
+    }
+
+    /// Rejects event payloads larger than `max_payload` bytes
+    /// (`--max-nats-payload`) before publishing them. If not set, the
+    /// maximum payload announced by the NATS server is used.
+    pub fn with_max_payload(mut self, max_payload: Option<usize>) -> Self {
+        self.max_payload = max_payload;
+        self
+    }
+
+    pub fn debug_buffer(&self) -> &DebugBuffer {
+        &self.buffer
+    }
+
+    /// The maximum size of the published event payloads in bytes, if known.
+    pub fn max_payload(&self) -> Option<usize> {
+        self.max_payload.or_else(|| {
+            let max_payload = self.client.server_info().max_payload;
+            (max_payload > 0).then_some(max_payload)
+        })
+    }
+
+    /// Publishes the payload on the subject, retains it in the debug buffer,
+    /// and writes it to the outputs. Failing outputs are only logged. Event
+    /// payloads larger than the maximum payload are rejected with
+    /// [PublishError::PayloadTooLarge]. Events are published below the
+    /// subject prefix, replies to requests on the given subject.
+    pub async fn publish<S: ToSubject>(
+        &self,
+        subject: S,
+        payload: Bytes,
+    ) -> Result<(), PublishError> {
+        let subject = subject.to_subject();
+        let is_event = is_event_subject(subject.as_str());
+        if let (true, Some(max_payload)) = (is_event, self.max_payload()) {
+            check_payload_size(subject.as_str(), &payload, max_payload)?;
+        }
+        self.buffer.record(subject.as_str(), payload.clone());
+        if !is_event {
+            return Ok(self.client.publish(subject, payload).await?);
+        }
+        self.sinks.write(subject.as_str(), payload.clone());
+        let subject = self.prefixed(subject.as_str());
+        Ok(self.client.publish(subject, payload).await?)
+    }
+}
+
+impl Deref for TappedClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nats_address() {
+        assert_eq!(
+            parse_nats_address("127.0.0.1:4222"),
+            Ok("127.0.0.1:4222".to_string())
+        );
+        assert!(parse_nats_address("host1:4222,host2:4222").is_ok());
+        assert!(parse_nats_address("nats://host1:4222, nats://host2:4222").is_ok());
+        assert!(parse_nats_address("host1:4222,").is_err());
+        assert!(parse_nats_address("").is_err());
+        assert!(parse_nats_address("host1:notaport").is_err());
+    }
+
+    #[test]
+    fn test_nats_addresses() {
+        assert_eq!(nats_addresses("127.0.0.1:4222"), vec!["127.0.0.1:4222"]);
+        assert_eq!(
+            nats_addresses("host1:4222, host2:4222"),
+            vec!["host1:4222", "host2:4222"]
+        );
+    }
+
+    fn message(subject: &str, payload: Vec<u8>) -> Message {
+        Message {
+            subject: subject.into(),
+            reply: None,
+            payload: payload.into(),
+            headers: None,
+            status: None,
+            description: None,
+            length: 0,
+        }
+    }
+
+    fn uptime_event() -> Event {
+        use crate::protobuf::event::event::PeerObserverEvent;
+        use crate::protobuf::rpc_extractor::{rpc::RpcEvent, Rpc};
+        Event {
+            timestamp: 1000,
+            peer_observer_event: Some(PeerObserverEvent::RpcExtractor(Rpc {
+                rpc_event: Some(RpcEvent::Uptime(42)),
+                raw_json: None,
+                correlation_id: None,
+            })),
+            labels: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_decode_message() {
+        let event = uptime_event();
+        let msg = message("rpc.node-1", event.encode_to_vec());
+        assert_eq!(decode_message(&msg, None).unwrap(), event);
+
+        let (subject, decoded) = decode_message_with_subject(&msg, None).unwrap();
+        assert_eq!(subject.subject, crate::nats_subjects::Subject::Rpc);
+        assert_eq!(subject.node_value(), Some("node-1"));
+        assert_eq!(decoded, event);
+
+        // published below a subject prefix
+        let msg = message("team-a.rpc.node-1", event.encode_to_vec());
+        let (subject, decoded) = decode_message_with_subject(&msg, Some("team-a")).unwrap();
+        assert_eq!(subject.prefix.as_deref(), Some("team-a"));
+        assert_eq!(subject.node_value(), Some("node-1"));
+        assert_eq!(decoded, event);
+        assert!(matches!(
+            decode_message(&msg, None),
+            Err(DecodeError::Subject(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_message_errors() {
+        let payload = uptime_event().encode_to_vec();
+        for subject in ["rpc.request", "_INBOX.abc", "foo", "rpc.a.b.c.d"] {
+            assert!(matches!(
+                decode_message(&message(subject, payload.clone()), None),
+                Err(DecodeError::Subject(_))
+            ));
+        }
+        assert!(matches!(
+            decode_message(&message("rpc", vec![0xff, 0xff, 0xff]), None),
+            Err(DecodeError::Protobuf(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_events() {
+        let event = uptime_event();
+        let msg = message("rpc", event.encode_to_vec());
+        assert_eq!(decode_events(&msg).unwrap(), vec![event.clone()]);
+
+        let mut later = event.clone();
+        later.timestamp += 1;
+        let batch = EventBatch {
+            events: vec![event.clone(), later.clone()],
+        };
+        let msg = message("netmsg.batch", batch.encode_to_vec());
+        assert_eq!(decode_events(&msg).unwrap(), vec![event, later]);
+        // a batch isn't a single event
+        assert!(matches!(
+            decode_message(&msg, None),
+            Err(DecodeError::Subject(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_payload_size() {
+        let payload = uptime_event().encode_to_vec();
+        assert!(check_payload_size("rpc", &payload, payload.len()).is_ok());
+
+        let error = check_payload_size("rpc", &payload, payload.len() - 1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "the rpc-extractor Uptime event on 'rpc' is {} bytes, larger than the maximum NATS payload of {} bytes",
+                payload.len(),
+                payload.len() - 1
+            )
+        );
+
+        // the largest event of a batch is named
+        let mut metrics = uptime_event();
+        metrics.peer_observer_event = Some(PeerObserverEvent::ExtractorMetrics(
+            crate::protobuf::event::ExtractorMetrics {
+                extractor: crate::protobuf::event::Extractor::RpcExtractor.into(),
+                window: 60,
+                counters: [("lines_read".to_string(), 1)].into(),
+                gauges: Default::default(),
+            },
+        ));
+        let batch = EventBatch {
+            events: vec![uptime_event(), metrics],
+        }
+        .encode_to_vec();
+        assert!(matches!(
+            check_payload_size("rpc.batch", &batch, 10),
+            Err(PublishError::PayloadTooLarge { event_type, .. }) if event_type == "rpc-extractor ExtractorMetrics"
+        ));
+        assert!(matches!(
+            check_payload_size("rpc", &[0xff, 0xff, 0xff], 2),
+            Err(PublishError::PayloadTooLarge { event_type, .. }) if event_type == "unknown"
+        ));
+    }
+}
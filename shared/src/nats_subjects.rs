@@ -1,4 +1,5 @@
 use std::fmt;
+use std::str::FromStr;
 
 const NATS_SUBJECT_ADDRMAN: &str = "addrman";
 const NATS_SUBJECT_MEMPOOL: &str = "mempool";
@@ -8,7 +9,28 @@ const NATS_SUBJECT_VALIDATION: &str = "validation";
 const NATS_SUBJECT_RPC: &str = "rpc";
 const NATS_SUBJECT_P2P_EXTRACTOR: &str = "p2p-extractor";
 const NATS_SUBJECT_LOG_EXTRACTOR: &str = "log-extractor";
+const NATS_SUBJECT_EBPF_EXTRACTOR: &str = "ebpf-extractor";
+const NATS_SUBJECT_ERRORS: &str = "errors";
+const NATS_SUBJECT_EXTRACTOR_METRICS: &str = "extractor-metrics";
+const NATS_SUBJECT_HEARTBEAT: &str = "heartbeat";
+const NATS_SUBJECT_REQUEST: &str = "request";
+const NATS_SUBJECT_SNAPSHOT: &str = "snapshot";
+const NATS_SUBJECT_DEBUG: &str = "debug";
+const NATS_SUBJECT_PEERINFO: &str = "peerinfo";
+const NATS_SUBJECT_BATCH: &str = "batch";
+// The tokens appended to subjects. A node identity can't be one of these,
+// see [Subject::for_node].
+const RESERVED_TOKENS: [&str; 5] = [
+    NATS_SUBJECT_REQUEST,
+    NATS_SUBJECT_SNAPSHOT,
+    NATS_SUBJECT_DEBUG,
+    NATS_SUBJECT_PEERINFO,
+    NATS_SUBJECT_BATCH,
+];
+// NATS publishes request-reply replies on subjects with this prefix.
+const NATS_INBOX_PREFIX: &str = "_INBOX.";
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Subject {
     Addrman,
     Mempool,
@@ -18,6 +40,10 @@ pub enum Subject {
     Rpc,
     P2PExtractor,
     LogExtractor,
+    EbpfExtractor,
+    Errors,
+    ExtractorMetrics,
+    Heartbeat,
 }
 
 impl fmt::Display for Subject {
@@ -31,6 +57,574 @@ impl fmt::Display for Subject {
             Subject::Rpc => write!(f, "{}", NATS_SUBJECT_RPC),
             Subject::P2PExtractor => write!(f, "{}", NATS_SUBJECT_P2P_EXTRACTOR),
             Subject::LogExtractor => write!(f, "{}", NATS_SUBJECT_LOG_EXTRACTOR),
+            Subject::EbpfExtractor => write!(f, "{}", NATS_SUBJECT_EBPF_EXTRACTOR),
+            Subject::Errors => write!(f, "{}", NATS_SUBJECT_ERRORS),
+            Subject::ExtractorMetrics => write!(f, "{}", NATS_SUBJECT_EXTRACTOR_METRICS),
+            Subject::Heartbeat => write!(f, "{}", NATS_SUBJECT_HEARTBEAT),
         }
     }
 }
+
+impl FromStr for Subject {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            NATS_SUBJECT_ADDRMAN => Ok(Subject::Addrman),
+            NATS_SUBJECT_MEMPOOL => Ok(Subject::Mempool),
+            NATS_SUBJECT_NETCONN => Ok(Subject::NetConn),
+            NATS_SUBJECT_NETMSG => Ok(Subject::NetMsg),
+            NATS_SUBJECT_VALIDATION => Ok(Subject::Validation),
+            NATS_SUBJECT_RPC => Ok(Subject::Rpc),
+            NATS_SUBJECT_P2P_EXTRACTOR => Ok(Subject::P2PExtractor),
+            NATS_SUBJECT_LOG_EXTRACTOR => Ok(Subject::LogExtractor),
+            NATS_SUBJECT_EBPF_EXTRACTOR => Ok(Subject::EbpfExtractor),
+            NATS_SUBJECT_ERRORS => Ok(Subject::Errors),
+            NATS_SUBJECT_EXTRACTOR_METRICS => Ok(Subject::ExtractorMetrics),
+            NATS_SUBJECT_HEARTBEAT => Ok(Subject::Heartbeat),
+            _ => Err(format!("unknown subject '{}'", s)),
+        }
+    }
+}
+
+impl Subject {
+    /// All subjects events are published on.
+    pub const ALL: [Subject; 12] = [
+        Subject::Addrman,
+        Subject::Mempool,
+        Subject::NetMsg,
+        Subject::NetConn,
+        Subject::Validation,
+        Subject::Rpc,
+        Subject::P2PExtractor,
+        Subject::LogExtractor,
+        Subject::EbpfExtractor,
+        Subject::Errors,
+        Subject::ExtractorMetrics,
+        Subject::Heartbeat,
+    ];
+
+    /// Decomposes a subject, e.g. `rpc.127_0_0_1_8332.peerinfo.42`, into its
+    /// parts. This is the inverse of [Subject::for_node], [peer_info_subject],
+    /// and [batch_subject]. The node and peer id can be the `*` wildcard and
+    /// the subject can end with the `>` wildcard, as in subscription patterns.
+    /// Returns None if the subject doesn't follow this scheme.
+    pub fn parse(subject: &str) -> Option<ParsedSubject> {
+        Subject::parse_prefixed(None, subject)
+    }
+
+    /// Like [Subject::parse], but for a subject below the prefix
+    /// (`--nats-subject-prefix`), e.g. `team-a.rpc.127_0_0_1_8332`. This is
+    /// also the inverse of [prefixed_subject]. Returns None if the subject
+    /// isn't below the prefix.
+    pub fn parse_prefixed(prefix: Option<&str>, subject: &str) -> Option<ParsedSubject> {
+        let unprefixed = match prefix {
+            Some(prefix) => subject.strip_prefix(prefix)?.strip_prefix('.')?,
+            None => subject,
+        };
+        let tokens: Vec<&str> = unprefixed.split('.').collect();
+        let (tokens, tail) = match tokens.split_last() {
+            Some((&">", rest)) if !rest.is_empty() => (rest, true),
+            _ => (tokens.as_slice(), false),
+        };
+        let (tokens, batch) = match tokens.split_last() {
+            Some((&NATS_SUBJECT_BATCH, rest)) if !rest.is_empty() => (rest, true),
+            _ => (tokens, false),
+        };
+        let (node, peer_id) = match tokens[1..] {
+            [] => (None, None),
+            [NATS_SUBJECT_PEERINFO, peer_id] => (None, Some(peer_id)),
+            [node, NATS_SUBJECT_PEERINFO, peer_id] => (Some(node), Some(peer_id)),
+            [node] => (Some(node), None),
+            _ => return None,
+        };
+        let node = match node {
+            Some(node) => Some(Token::parse(node, |n| Some(n.to_string()))?),
+            None => None,
+        };
+        let peer_id = match peer_id {
+            Some(id) => Some(Token::parse(id, |id| id.parse().ok())?),
+            None => None,
+        };
+        Some(ParsedSubject {
+            prefix: prefix.map(str::to_string),
+            subject: tokens[0].parse().ok()?,
+            node,
+            peer_id,
+            batch,
+            tail,
+        })
+    }
+
+    /// Returns the subject tagged with the identity of a node (e.g. `rpc.127_0_0_1_8332`).
+    /// Characters with a special meaning in NATS subjects (`.`, `*`, `>`) and
+    /// other non-alphanumeric characters in the node identity are replaced with `_`.
+    /// A node identity equal to a token appended to subjects (e.g. `batch`)
+    /// gets a trailing `_` (`rpc.batch_`), so that e.g. [is_batch_subject]
+    /// can't mistake it for that token.
+    pub fn for_node(&self, node: &str) -> String {
+        let mut node: String = node
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if RESERVED_TOKENS.contains(&node.as_str()) {
+            node.push('_');
+        }
+        format!("{}.{}", self, node)
+    }
+}
+
+/// Returns the subject requests to an extractor are sent to, e.g. `rpc.request`
+/// for the `rpc` subject.
+pub fn request_subject(subject: &str) -> String {
+    format!("{}.{}", subject, NATS_SUBJECT_REQUEST)
+}
+
+/// Returns the subject the last known events of an extractor can be requested
+/// on, e.g. `rpc.snapshot` for the `rpc` subject.
+pub fn snapshot_subject(subject: &str) -> String {
+    format!("{}.{}", subject, NATS_SUBJECT_SNAPSHOT)
+}
+
+/// Returns the subject the recently published events of an extractor can be
+/// requested on, e.g. `rpc.debug` for the `rpc` subject.
+pub fn debug_subject(subject: &str) -> String {
+    format!("{}.{}", subject, NATS_SUBJECT_DEBUG)
+}
+
+/// Returns the subject a single peer of a getpeerinfo response is published
+/// on, e.g. `rpc.peerinfo.42` for the `rpc` subject and peer id 42.
+pub fn peer_info_subject(subject: &str, peer_id: u32) -> String {
+    format!("{}.{}.{}", subject, NATS_SUBJECT_PEERINFO, peer_id)
+}
+
+/// Returns the subject batches of events are published on, e.g.
+/// `netmsg.batch` for the `netmsg` subject. The messages on these subjects
+/// contain an EventBatch instead of a single Event.
+pub fn batch_subject(subject: &str) -> String {
+    format!("{}.{}", subject, NATS_SUBJECT_BATCH)
+}
+
+/// Returns true for subjects batches of events are published on, see
+/// [batch_subject].
+pub fn is_batch_subject(subject: &str) -> bool {
+    subject.rsplit('.').next() == Some(NATS_SUBJECT_BATCH)
+}
+
+/// Returns false for subjects no events are published on: requests to the
+/// extractors (e.g. `rpc.request`, `rpc.snapshot`, and `rpc.debug`) and the
+/// replies to them.
+/// Tools subscribing to all subjects with `>` need to skip these.
+pub fn is_event_subject(subject: &str) -> bool {
+    let last = subject.rsplit('.').next();
+    !(subject.starts_with(NATS_INBOX_PREFIX)
+        || last == Some(NATS_SUBJECT_REQUEST)
+        || last == Some(NATS_SUBJECT_SNAPSHOT)
+        || last == Some(NATS_SUBJECT_DEBUG))
+}
+
+/// Returns the subscription patterns matching the subjects events are
+/// published on: each subject (e.g. `rpc`) and the subjects below it (e.g.
+/// `rpc.127_0_0_1_8332` or `netmsg.batch`). Unlike `>`, these don't match
+/// unrelated messages on a shared NATS server. The requests to the extractors
+/// are matched too and need to be skipped with [is_event_subject].
+pub fn event_subscription_patterns() -> Vec<String> {
+    Subject::ALL
+        .iter()
+        .flat_map(|subject| [subject.to_string(), format!("{}.>", subject)])
+        .collect()
+}
+
+/// Validates a `--nats-subject-prefix` argument. Can be used as a clap value
+/// parser.
+///
+/// The prefix consists of one or more `.`-separated tokens, e.g. `team-a` or
+/// `observer.mainnet`. Following the NATS subject naming rules, tokens must
+/// not be empty, and must only contain printable ASCII characters other than
+/// the `*` and `>` wildcards. Prefixes starting with `$` (system subjects) or
+/// with the inbox prefix of replies are reserved.
+pub fn parse_subject_prefix(prefix: &str) -> Result<String, String> {
+    let invalid = |reason: &str| Err(format!("invalid subject prefix '{}': {}", prefix, reason));
+    if prefix.starts_with('$') || format!("{}.", prefix).starts_with(NATS_INBOX_PREFIX) {
+        return invalid("reserved by NATS");
+    }
+    for token in prefix.split('.') {
+        if token.is_empty() {
+            return invalid("empty token");
+        }
+        if !token.chars().all(|c| c.is_ascii_graphic()) {
+            return invalid("only printable ASCII characters without whitespace are allowed");
+        }
+        if token.contains(['*', '>']) {
+            return invalid("wildcards are not allowed");
+        }
+    }
+    Ok(prefix.to_string())
+}
+
+/// Returns the subject below the prefix (`--nats-subject-prefix`), e.g.
+/// `team-a.rpc` for the prefix `team-a` and the `rpc` subject. Without a
+/// prefix, the subject is returned unchanged.
+pub fn prefixed_subject(prefix: Option<&str>, subject: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{}.{}", prefix, subject),
+        None => subject.to_string(),
+    }
+}
+
+/// Parses a subject events are published on, below the prefix if any (see
+/// [Subject::parse_prefixed]). Returns an error for subjects that don't
+/// follow the scheme used by the extractors, contain wildcards, or are
+/// request and reply subjects (see [is_event_subject]).
+pub fn parse_event_subject(prefix: Option<&str>, subject: &str) -> Result<ParsedSubject, String> {
+    Subject::parse_prefixed(prefix, subject)
+        .filter(|parsed| !parsed.has_wildcard() && is_event_subject(subject))
+        .ok_or_else(|| format!("'{}' is not a valid event subject", subject))
+}
+
+/// A token of a parsed subject: a value or the `*` wildcard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<T> {
+    Value(T),
+    Any,
+}
+
+impl<T> Token<T> {
+    /// Returns the value, or None for the wildcard.
+    pub fn value(self) -> Option<T> {
+        match self {
+            Token::Value(value) => Some(value),
+            Token::Any => None,
+        }
+    }
+
+    fn parse<F: FnOnce(&str) -> Option<T>>(token: &str, parse_value: F) -> Option<Self> {
+        match token {
+            "*" => Some(Token::Any),
+            t if t.is_empty() || t.contains(['*', '>']) => None,
+            t => parse_value(t).map(Token::Value),
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Token<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Value(value) => write!(f, "{}", value),
+            Token::Any => write!(f, "*"),
+        }
+    }
+}
+
+/// A subject or subscription pattern decomposed into its parts, e.g.
+/// `rpc.127_0_0_1_8332.peerinfo.42` or `rpc.*.>`. Segments missing in the
+/// subject are None. See [Subject::parse].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedSubject {
+    /// The subject prefix (`--nats-subject-prefix`) the subject was parsed
+    /// below, see [Subject::parse_prefixed].
+    pub prefix: Option<String>,
+    pub subject: Subject,
+    /// The node identity of per-node subjects, see [Subject::for_node].
+    pub node: Option<Token<String>>,
+    /// The peer id of per-peer getpeerinfo subjects, see [peer_info_subject].
+    pub peer_id: Option<Token<u32>>,
+    /// The subject carries batches of events, see [batch_subject].
+    pub batch: bool,
+    /// The subject ends with the `>` wildcard, matching all further tokens.
+    pub tail: bool,
+}
+
+impl ParsedSubject {
+    /// The node identity, if the subject is a per-node subject without a
+    /// wildcard in its place.
+    pub fn node_value(&self) -> Option<&str> {
+        match &self.node {
+            Some(Token::Value(node)) => Some(node),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the subject contains a `*` or `>` wildcard and is a
+    /// subscription pattern rather than a subject events are published on.
+    pub fn has_wildcard(&self) -> bool {
+        self.tail || self.node == Some(Token::Any) || self.peer_id == Some(Token::Any)
+    }
+}
+
+impl fmt::Display for ParsedSubject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(prefix) = &self.prefix {
+            write!(f, "{}.", prefix)?;
+        }
+        write!(f, "{}", self.subject)?;
+        if let Some(node) = &self.node {
+            write!(f, ".{}", node)?;
+        }
+        if let Some(peer_id) = &self.peer_id {
+            write!(f, ".{}.{}", NATS_SUBJECT_PEERINFO, peer_id)?;
+        }
+        if self.batch {
+            write!(f, ".{}", NATS_SUBJECT_BATCH)?;
+        }
+        if self.tail {
+            write!(f, ".>")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subject_for_node() {
+        assert_eq!(
+            Subject::Rpc.for_node("127.0.0.1:8332"),
+            "rpc.127_0_0_1_8332"
+        );
+        assert_eq!(Subject::Rpc.for_node("node-1"), "rpc.node-1");
+        assert_eq!(Subject::Rpc.for_node("a b*>"), "rpc.a_b__");
+        assert_eq!(Subject::Rpc.for_node("batch"), "rpc.batch_");
+        assert_eq!(Subject::Rpc.for_node("snapshot"), "rpc.snapshot_");
+        assert_eq!(Subject::Rpc.for_node("batches"), "rpc.batches");
+    }
+
+    #[test]
+    fn test_is_event_subject() {
+        assert!(is_event_subject("rpc"));
+        assert!(is_event_subject("rpc.127_0_0_1_8332"));
+        assert!(is_event_subject("netmsg"));
+        assert!(!is_event_subject(&request_subject("rpc")));
+        assert!(!is_event_subject(&request_subject(
+            &Subject::Rpc.for_node("127.0.0.1:8332")
+        )));
+        assert!(!is_event_subject(&snapshot_subject("rpc")));
+        assert!(!is_event_subject(&snapshot_subject(
+            &Subject::Rpc.for_node("127.0.0.1:8332")
+        )));
+        assert!(!is_event_subject(&debug_subject("rpc")));
+        assert!(!is_event_subject(&debug_subject("p2p-extractor")));
+        assert!(!is_event_subject("_INBOX.abc.def"));
+        assert!(is_event_subject(&peer_info_subject("rpc", 42)));
+    }
+
+    #[test]
+    fn test_event_subscription_patterns() {
+        let patterns = event_subscription_patterns();
+        assert_eq!(patterns.len(), Subject::ALL.len() * 2);
+        assert!(patterns.contains(&"rpc".to_string()));
+        assert!(patterns.contains(&"rpc.>".to_string()));
+        assert!(patterns.contains(&"heartbeat".to_string()));
+        for pattern in patterns {
+            assert!(Subject::parse(&pattern).is_some(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_peer_info_subject() {
+        assert_eq!(peer_info_subject("rpc", 42), "rpc.peerinfo.42");
+        assert_eq!(
+            peer_info_subject(&Subject::Rpc.for_node("node-1"), 0),
+            "rpc.node-1.peerinfo.0"
+        );
+    }
+
+    #[test]
+    fn test_subject_from_str() {
+        for subject in Subject::ALL {
+            assert_eq!(subject.to_string().parse::<Subject>(), Ok(subject));
+        }
+        assert!("foo".parse::<Subject>().is_err());
+    }
+
+    fn event_subject(subject: Subject, node: Option<&str>, peer_id: Option<u32>) -> ParsedSubject {
+        ParsedSubject {
+            prefix: None,
+            subject,
+            node: node.map(|node| Token::Value(node.to_string())),
+            peer_id: peer_id.map(Token::Value),
+            batch: false,
+            tail: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_event_subject() {
+        assert_eq!(
+            parse_event_subject(None, "netmsg"),
+            Ok(event_subject(Subject::NetMsg, None, None))
+        );
+        let parsed = parse_event_subject(None, &Subject::Rpc.for_node("127.0.0.1:8332"));
+        assert_eq!(
+            parsed,
+            Ok(event_subject(Subject::Rpc, Some("127_0_0_1_8332"), None))
+        );
+        assert_eq!(parsed.unwrap().node_value(), Some("127_0_0_1_8332"));
+        assert_eq!(
+            parse_event_subject(None, &peer_info_subject("rpc", 42)),
+            Ok(event_subject(Subject::Rpc, None, Some(42)))
+        );
+        assert_eq!(
+            parse_event_subject(
+                None,
+                &peer_info_subject(&Subject::Rpc.for_node("node-1"), 7)
+            ),
+            Ok(event_subject(Subject::Rpc, Some("node-1"), Some(7)))
+        );
+    }
+
+    #[test]
+    fn test_parse_prefixed_event_subject() {
+        let subject = prefixed_subject(Some("observer.mainnet"), &Subject::Rpc.for_node("node-1"));
+        let parsed = parse_event_subject(Some("observer.mainnet"), &subject).unwrap();
+        assert_eq!(parsed.prefix.as_deref(), Some("observer.mainnet"));
+        assert_eq!(parsed.subject, Subject::Rpc);
+        assert_eq!(parsed.node_value(), Some("node-1"));
+        assert_eq!(parsed.to_string(), subject);
+
+        // not below the prefix
+        assert!(parse_event_subject(Some("team-a"), "rpc").is_err());
+        assert!(parse_event_subject(Some("team-a"), "team-ab.rpc").is_err());
+        assert!(parse_event_subject(None, "team-a.rpc").is_err());
+        assert!(parse_event_subject(Some("team-a"), &request_subject("team-a.rpc")).is_err());
+        assert_eq!(
+            Subject::parse_prefixed(Some("team-a"), "team-a.netmsg.batch.>")
+                .unwrap()
+                .to_string(),
+            "team-a.netmsg.batch.>"
+        );
+    }
+
+    #[test]
+    fn test_parse_event_subject_malformed() {
+        assert!(parse_event_subject(None, "").is_err());
+        assert!(parse_event_subject(None, "foo").is_err());
+        assert!(parse_event_subject(None, "foo.node-1").is_err());
+        assert!(parse_event_subject(None, "rpc.").is_err());
+        assert!(parse_event_subject(None, "rpc..node-1").is_err());
+        assert!(parse_event_subject(None, "rpc.node-1.extra").is_err());
+        assert!(parse_event_subject(None, "rpc.peerinfo.abc").is_err());
+        assert!(parse_event_subject(None, "rpc.node-1.peerinfo.-1").is_err());
+        assert!(parse_event_subject(None, &request_subject("rpc")).is_err());
+        assert!(parse_event_subject(None, "_INBOX.abc.def").is_err());
+        assert!(parse_event_subject(None, "rpc.*").is_err());
+    }
+
+    #[test]
+    fn test_subject_parse_roundtrip() {
+        let node = Subject::Rpc.for_node("127.0.0.1:8332");
+        for subject in [
+            Subject::NetMsg.to_string(),
+            Subject::Errors.for_node("node-1"),
+            node.clone(),
+            peer_info_subject(&Subject::Rpc.to_string(), 0),
+            peer_info_subject(&node, 42),
+            "rpc.*".to_string(),
+            "rpc.>".to_string(),
+            "rpc.*.peerinfo.*".to_string(),
+            format!("{}.>", node),
+            batch_subject(&Subject::NetMsg.to_string()),
+            "netmsg.batch.>".to_string(),
+        ] {
+            let parsed = Subject::parse(&subject).unwrap();
+            assert_eq!(parsed.to_string(), subject);
+        }
+    }
+
+    #[test]
+    fn test_subject_parse() {
+        assert_eq!(
+            Subject::parse("rpc.node-1.peerinfo.42"),
+            Some(ParsedSubject {
+                prefix: None,
+                subject: Subject::Rpc,
+                node: Some(Token::Value("node-1".to_string())),
+                peer_id: Some(Token::Value(42)),
+                batch: false,
+                tail: false,
+            })
+        );
+        let pattern = Subject::parse("rpc.*.>").unwrap();
+        assert_eq!(pattern.node, Some(Token::Any));
+        assert_eq!(pattern.peer_id, None);
+        assert!(pattern.tail);
+        assert!(pattern.has_wildcard());
+        assert!(!Subject::parse("rpc.node-1").unwrap().has_wildcard());
+
+        for invalid in [
+            "",
+            ">",
+            "*",
+            "*.node-1",
+            "foo",
+            "rpc.",
+            "rpc.>.node-1",
+            "rpc.no*de",
+            "rpc.node-1.peerinfo.abc",
+            "rpc.a.b.c.d",
+        ] {
+            assert_eq!(Subject::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_subject_prefix() {
+        assert_eq!(parse_subject_prefix("team-a"), Ok("team-a".to_string()));
+        assert_eq!(
+            parse_subject_prefix("observer.mainnet"),
+            Ok("observer.mainnet".to_string())
+        );
+        assert!(parse_subject_prefix("").is_err());
+        assert!(parse_subject_prefix("team-a.").is_err());
+        assert!(parse_subject_prefix(".team-a").is_err());
+        assert!(parse_subject_prefix("team a").is_err());
+        assert!(parse_subject_prefix("team-*").is_err());
+        assert!(parse_subject_prefix("team.>").is_err());
+        assert!(parse_subject_prefix("$SYS").is_err());
+        assert!(parse_subject_prefix("_INBOX").is_err());
+        assert!(parse_subject_prefix("_INBOX.team-a").is_err());
+        assert!(parse_subject_prefix("_INBOXES").is_ok());
+    }
+
+    #[test]
+    fn test_prefixed_subject() {
+        assert_eq!(prefixed_subject(Some("team-a"), "rpc"), "team-a.rpc");
+        assert_eq!(
+            prefixed_subject(Some("observer.mainnet"), &batch_subject("netmsg")),
+            "observer.mainnet.netmsg.batch"
+        );
+        assert_eq!(prefixed_subject(None, "rpc.request"), "rpc.request");
+    }
+
+    #[test]
+    fn test_batch_subject() {
+        let subject = batch_subject(&Subject::NetMsg.to_string());
+        assert_eq!(subject, "netmsg.batch");
+        assert!(is_batch_subject(&subject));
+        assert!(is_event_subject(&subject));
+        assert!(!is_batch_subject("netmsg"));
+        // a node named "batch" isn't a batch subject
+        let node = Subject::Rpc.for_node("batch");
+        assert!(!is_batch_subject(&node));
+        assert!(is_event_subject(&node));
+        assert_eq!(
+            parse_event_subject(None, &node).map(|parsed| parsed.batch),
+            Ok(false)
+        );
+        assert_eq!(
+            parse_event_subject(None, &subject),
+            Ok(ParsedSubject {
+                batch: true,
+                ..event_subject(Subject::NetMsg, None, None)
+            })
+        );
+    }
+}
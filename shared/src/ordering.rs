@@ -0,0 +1,201 @@
+use crate::protobuf::event::Event;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Buffers events for a small delay and releases them sorted by their
+/// `timestamp`.
+///
+/// NATS doesn't guarantee ordering across subjects and the extractors publish
+/// concurrently, so consumers receive events slightly out of timestamp order.
+/// Holding each event back until `delay` has passed since its timestamp gives
+/// events with an earlier timestamp the chance to arrive first.
+///
+/// This trades latency for ordering: every event is delayed by up to `delay`
+/// (plus the consumer's polling interval). Events are only released in order
+/// if they arrive within `delay` of their timestamp. An event arriving later
+/// than that (e.g. due to a NATS backlog, or clock skew between the extractor
+/// and consumer hosts) can't be ordered anymore. It's released with the next
+/// call to [OrderingBuffer::release] and counted in [OrderingBuffer::late].
+/// Events with the same timestamp are released in arrival order.
+pub struct OrderingBuffer {
+    delay_millis: u64,
+    events: BinaryHeap<Reverse<Buffered>>,
+    /// Incremented for each buffered event to keep the arrival order of events
+    /// with the same timestamp.
+    sequence: u64,
+    /// The timestamp of the last released event.
+    released_until: u64,
+    late: u64,
+}
+
+struct Buffered {
+    timestamp: u64,
+    sequence: u64,
+    event: Event,
+}
+
+impl PartialEq for Buffered {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Buffered {}
+
+impl PartialOrd for Buffered {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Buffered {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.timestamp, self.sequence).cmp(&(other.timestamp, other.sequence))
+    }
+}
+
+impl OrderingBuffer {
+    pub fn new(delay: Duration) -> Self {
+        OrderingBuffer {
+            delay_millis: delay.as_millis() as u64,
+            events: BinaryHeap::new(),
+            sequence: 0,
+            released_until: 0,
+            late: 0,
+        }
+    }
+
+    /// Buffers an event until it's released.
+    pub fn push(&mut self, event: Event) {
+        if event.timestamp < self.released_until {
+            self.late += 1;
+        }
+        self.sequence += 1;
+        self.events.push(Reverse(Buffered {
+            timestamp: event.timestamp,
+            sequence: self.sequence,
+            event,
+        }));
+    }
+
+    /// Releases the events whose timestamp is at least `delay` older than
+    /// `now_millis` (milliseconds since UNIX epoch), sorted by timestamp.
+    pub fn release(&mut self, now_millis: u64) -> Vec<Event> {
+        let until = now_millis.saturating_sub(self.delay_millis);
+        let mut released = Vec::new();
+        while let Some(Reverse(next)) = self.events.peek() {
+            if next.timestamp > until {
+                break;
+            }
+            if let Some(Reverse(buffered)) = self.events.pop() {
+                self.released_until = self.released_until.max(buffered.timestamp);
+                released.push(buffered.event);
+            }
+        }
+        released
+    }
+
+    /// Like [OrderingBuffer::release] with the current time.
+    pub fn release_now(&mut self) -> Vec<Event> {
+        self.release(now_millis())
+    }
+
+    /// Releases all buffered events sorted by timestamp, e.g. on shutdown.
+    pub fn release_all(&mut self) -> Vec<Event> {
+        self.release(u64::MAX)
+    }
+
+    /// When (in milliseconds since UNIX epoch) the next buffered event will be
+    /// released. Can be used to schedule the next call to
+    /// [OrderingBuffer::release].
+    pub fn next_release(&self) -> Option<u64> {
+        self.events
+            .peek()
+            .map(|Reverse(next)| next.timestamp.saturating_add(self.delay_millis))
+    }
+
+    /// Number of buffered events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Number of events that arrived too late to be released in order.
+    pub fn late(&self) -> u64 {
+        self.late
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::event::event::PeerObserverEvent;
+    use crate::protobuf::rpc_extractor::{rpc::RpcEvent, Rpc};
+    use crate::testing::events;
+
+    fn event(timestamp: u64, uptime: u32) -> Event {
+        Event {
+            peer_observer_event: Some(PeerObserverEvent::RpcExtractor(Rpc {
+                rpc_event: Some(RpcEvent::Uptime(uptime)),
+                raw_json: None,
+                correlation_id: None,
+            })),
+            ..events::event(timestamp)
+        }
+    }
+
+    fn uptimes(events: Vec<Event>) -> Vec<u32> {
+        events
+            .into_iter()
+            .map(|e| match e.peer_observer_event {
+                Some(PeerObserverEvent::RpcExtractor(Rpc {
+                    rpc_event: Some(RpcEvent::Uptime(uptime)),
+                    ..
+                })) => uptime,
+                _ => panic!("unexpected event"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ordering_buffer_sorts() {
+        let mut buffer = OrderingBuffer::new(Duration::from_millis(100));
+        buffer.push(event(1030, 3));
+        buffer.push(event(1010, 1));
+        buffer.push(event(1020, 2));
+        // same timestamp: arrival order
+        buffer.push(event(1020, 4));
+        assert_eq!(buffer.next_release(), Some(1110));
+
+        // nothing is older than the delay yet
+        assert!(buffer.release(1100).is_empty());
+        assert_eq!(uptimes(buffer.release(1120)), vec![1, 2, 4]);
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(uptimes(buffer.release_all()), vec![3]);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.late(), 0);
+    }
+
+    #[test]
+    fn test_ordering_buffer_late_events() {
+        let mut buffer = OrderingBuffer::new(Duration::from_millis(100));
+        buffer.push(event(1020, 2));
+        assert_eq!(uptimes(buffer.release(1200)), vec![2]);
+
+        // arrives after an event with a later timestamp was released
+        buffer.push(event(1010, 1));
+        assert_eq!(buffer.late(), 1);
+        assert_eq!(uptimes(buffer.release(1200)), vec![1]);
+    }
+}
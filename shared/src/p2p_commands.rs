@@ -0,0 +1,67 @@
+/// The commands of the P2P messages Bitcoin Core knows, see
+/// `ALL_NET_MESSAGE_TYPES` in Bitcoin Core's `src/protocol.cpp`.
+pub const KNOWN_COMMANDS: [&str; 35] = [
+    "version",
+    "verack",
+    "addr",
+    "addrv2",
+    "sendaddrv2",
+    "inv",
+    "getdata",
+    "merkleblock",
+    "getblocks",
+    "getheaders",
+    "tx",
+    "headers",
+    "block",
+    "getaddr",
+    "mempool",
+    "ping",
+    "pong",
+    "notfound",
+    "filterload",
+    "filteradd",
+    "filterclear",
+    "sendheaders",
+    "feefilter",
+    "sendcmpct",
+    "cmpctblock",
+    "getblocktxn",
+    "blocktxn",
+    "getcfilters",
+    "cfilter",
+    "getcfheaders",
+    "cfheaders",
+    "getcfcheckpt",
+    "cfcheckpt",
+    "wtxidrelay",
+    "sendtxrcncl",
+];
+
+/// Stands in for all commands not in [KNOWN_COMMANDS].
+pub const OTHER_COMMAND: &str = "other";
+
+/// Returns the command if it's one of the [KNOWN_COMMANDS], and
+/// [OTHER_COMMAND] otherwise. Peers can send messages with any command, so
+/// commands used as metric labels or map keys need to be bounded.
+pub fn known_or_other(command: &str) -> &'static str {
+    KNOWN_COMMANDS
+        .iter()
+        .find(|known| **known == command)
+        .copied()
+        .unwrap_or(OTHER_COMMAND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_or_other() {
+        assert_eq!(known_or_other("inv"), "inv");
+        assert_eq!(known_or_other("sendtxrcncl"), "sendtxrcncl");
+        assert_eq!(known_or_other("foo"), OTHER_COMMAND);
+        assert_eq!(known_or_other("INV"), OTHER_COMMAND);
+        assert_eq!(known_or_other(""), OTHER_COMMAND);
+    }
+}
@@ -211,22 +211,105 @@ impl From<p2p::message_blockdata::Inventory> for InventoryItem {
     }
 }
 
+/// The inventory type of MSG_FILTERED_BLOCK, which has no item of its own
+/// and is published as unknown item.
+const MSG_FILTERED_BLOCK: u32 = 3;
+
+/// The type of an inventory item. Its name is used for inventory items from
+/// all extractors, e.g. in their Display and as metric label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InvType {
+    Tx,
+    Block,
+    WTx,
+    WitnessTx,
+    WitnessBlock,
+    CompactBlock,
+    FilteredBlock,
+    Unknown,
+    Error,
+    None,
+}
+
+impl InvType {
+    pub const ALL: [InvType; 10] = [
+        InvType::Tx,
+        InvType::Block,
+        InvType::WTx,
+        InvType::WitnessTx,
+        InvType::WitnessBlock,
+        InvType::CompactBlock,
+        InvType::FilteredBlock,
+        InvType::Unknown,
+        InvType::Error,
+        InvType::None,
+    ];
+
+    /// The canonical name of the inventory type.
+    pub fn name(&self) -> &'static str {
+        match self {
+            InvType::Tx => "Tx",
+            InvType::Block => "Block",
+            InvType::WTx => "WTx",
+            InvType::WitnessTx => "WitnessTx",
+            InvType::WitnessBlock => "WitnessBlock",
+            InvType::CompactBlock => "CompactBlock",
+            InvType::FilteredBlock => "FilteredBlock",
+            InvType::Unknown => "Unknown",
+            InvType::Error => "Error",
+            InvType::None => "None",
+        }
+    }
+}
+
+impl fmt::Display for InvType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 impl InventoryItem {
-    pub fn inv_type(&self) -> &str {
+    pub fn kind(&self) -> InvType {
         use inventory_item::Item;
-        if let Some(item) = &self.item {
-            match item {
-                Item::Transaction(_) => "Tx",
-                Item::Block(_) => "Block",
-                Item::Wtx(_) => "WTx",
-                Item::WitnessTransaction(_) => "WitnessTx",
-                Item::WitnessBlock(_) => "WitnessBlock",
-                Item::CompactBlock(_) => "CompactBlock",
-                Item::Unknown(_) => "Unknown",
-                Item::Error(_) => "Error",
+        match &self.item {
+            Some(Item::Transaction(_)) => InvType::Tx,
+            Some(Item::Block(_)) => InvType::Block,
+            Some(Item::Wtx(_)) => InvType::WTx,
+            Some(Item::WitnessTransaction(_)) => InvType::WitnessTx,
+            Some(Item::WitnessBlock(_)) => InvType::WitnessBlock,
+            Some(Item::CompactBlock(_)) => InvType::CompactBlock,
+            Some(Item::Unknown(uitem)) if uitem.inv_type == MSG_FILTERED_BLOCK => {
+                InvType::FilteredBlock
             }
-        } else {
-            "None"
+            Some(Item::Unknown(_)) => InvType::Unknown,
+            Some(Item::Error(_)) => InvType::Error,
+            None => InvType::None,
+        }
+    }
+
+    /// The name of the item's inventory type, see [InvType::name].
+    pub fn inv_type(&self) -> &'static str {
+        self.kind().name()
+    }
+
+    /// The txid of a transaction inventory item identified by txid (`Tx` and
+    /// `WitnessTx`). None for all other items, including `WTx`, as the txid
+    /// can't be derived from the wtxid.
+    pub fn txid(&self) -> Option<&[u8]> {
+        use inventory_item::Item;
+        match &self.item {
+            Some(Item::Transaction(txid)) | Some(Item::WitnessTransaction(txid)) => Some(txid),
+            _ => None,
+        }
+    }
+
+    /// The wtxid of a transaction inventory item identified by wtxid (`WTx`).
+    /// None for all other items, as the wtxid can't be derived from the txid.
+    pub fn wtxid(&self) -> Option<&[u8]> {
+        use inventory_item::Item;
+        match &self.item {
+            Some(Item::Wtx(wtxid)) => Some(wtxid),
+            _ => None,
         }
     }
 }
@@ -234,50 +317,37 @@ impl InventoryItem {
 impl fmt::Display for InventoryItem {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use inventory_item::Item;
+        let name = self.inv_type();
         if let Some(item) = &self.item {
             match item {
-                Item::Transaction(txid) => {
-                    write!(f, "Tx({})", bitcoin::Txid::from_slice(txid).unwrap())
+                Item::Transaction(txid) | Item::WitnessTransaction(txid) => {
+                    write!(f, "{}({})", name, bitcoin::Txid::from_slice(txid).unwrap())
                 }
-                Item::Block(hash) => write!(
-                    f,
-                    "Block({})",
-                    bitcoin::BlockHash::from_slice(hash).unwrap()
-                ),
                 Item::Wtx(wtxid) => {
-                    write!(f, "WTx({})", bitcoin::Wtxid::from_slice(wtxid).unwrap())
-                }
-                Item::WitnessTransaction(wtxid) => {
                     write!(
                         f,
-                        "WitnessTx({})",
+                        "{}({})",
+                        name,
                         bitcoin::Wtxid::from_slice(wtxid).unwrap()
                     )
                 }
-                Item::WitnessBlock(hash) => {
-                    write!(
-                        f,
-                        "WitnessBlock({})",
-                        bitcoin::BlockHash::from_slice(hash).unwrap()
-                    )
-                }
-                Item::CompactBlock(hash) => {
-                    write!(
-                        f,
-                        "CompactBlock({})",
-                        bitcoin::BlockHash::from_slice(hash).unwrap()
-                    )
-                }
+                Item::Block(hash) | Item::WitnessBlock(hash) | Item::CompactBlock(hash) => write!(
+                    f,
+                    "{}({})",
+                    name,
+                    bitcoin::BlockHash::from_slice(hash).unwrap()
+                ),
                 Item::Unknown(uitem) => write!(
                     f,
-                    "Unknown(type={}, hash={})",
+                    "{}(type={}, hash={})",
+                    name,
                     uitem.inv_type,
                     bitcoin::hashes::sha256::Hash::from_slice(&uitem.hash).unwrap()
                 ),
-                Item::Error(_) => write!(f, "Error"),
+                Item::Error(_) => write!(f, "{}", name),
             }
         } else {
-            write!(f, "None")
+            write!(f, "{}", name)
         }
     }
 }
@@ -369,4 +439,76 @@ mod tests {
             bitcoin_primitives::address::Address::Torv2(String::from("5wyqrzbvrdsumnok.onion"))
         );
     }
+
+    #[test]
+    fn test_inventory_item_txid_wtxid() {
+        use crate::protobuf::bitcoin_primitives::InventoryItem;
+        use bitcoin::hashes::Hash;
+        use bitcoin::p2p::message_blockdata::Inventory;
+
+        let txid = bitcoin::Txid::from_byte_array([1; 32]);
+        let wtxid = bitcoin::Wtxid::from_byte_array([2; 32]);
+        let block = bitcoin::BlockHash::from_byte_array([3; 32]);
+
+        let tx = InventoryItem::from(Inventory::Transaction(txid));
+        assert_eq!(tx.txid(), Some(&[1u8; 32][..]));
+        assert_eq!(tx.wtxid(), None);
+
+        let witness_tx = InventoryItem::from(Inventory::WitnessTransaction(txid));
+        assert_eq!(witness_tx.txid(), Some(&[1u8; 32][..]));
+        assert_eq!(witness_tx.wtxid(), None);
+
+        let wtx = InventoryItem::from(Inventory::WTx(wtxid));
+        assert_eq!(wtx.txid(), None);
+        assert_eq!(wtx.wtxid(), Some(&[2u8; 32][..]));
+
+        let block = InventoryItem::from(Inventory::Block(block));
+        assert_eq!(block.txid(), None);
+        assert_eq!(block.wtxid(), None);
+    }
+
+    #[test]
+    fn test_inv_type_names() {
+        use crate::protobuf::bitcoin_primitives::{InvType, InventoryItem};
+        use bitcoin::hashes::Hash;
+        use bitcoin::p2p::message_blockdata::Inventory;
+
+        let txid = bitcoin::Txid::from_byte_array([1; 32]);
+        let wtxid = bitcoin::Wtxid::from_byte_array([2; 32]);
+        let block = bitcoin::BlockHash::from_byte_array([3; 32]);
+        for (inventory, name) in [
+            (Inventory::Transaction(txid), "Tx"),
+            (Inventory::Block(block), "Block"),
+            (Inventory::WTx(wtxid), "WTx"),
+            (Inventory::WitnessTransaction(txid), "WitnessTx"),
+            (Inventory::WitnessBlock(block), "WitnessBlock"),
+            (Inventory::CompactBlock(block), "CompactBlock"),
+            (
+                Inventory::Unknown {
+                    inv_type: 3,
+                    hash: [4; 32],
+                },
+                "FilteredBlock",
+            ),
+            (
+                Inventory::Unknown {
+                    inv_type: 99,
+                    hash: [4; 32],
+                },
+                "Unknown",
+            ),
+            (Inventory::Error, "Error"),
+        ] {
+            let item = InventoryItem::from(inventory);
+            assert_eq!(item.inv_type(), name);
+            // the Display starts with the name too
+            assert!(item.to_string().starts_with(name));
+        }
+        assert_eq!(InventoryItem { item: None }.inv_type(), "None");
+
+        let mut names: Vec<&str> = InvType::ALL.iter().map(InvType::name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), InvType::ALL.len());
+    }
 }
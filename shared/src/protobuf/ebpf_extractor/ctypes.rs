@@ -14,6 +14,10 @@ const MAX_PEER_CONN_TYPE_LENGTH: usize = 20;
 const MAX_MSG_TYPE_LENGTH: usize = 12;
 const MAX_MISBEHAVING_MESSAGE_LENGTH: usize = 128;
 
+/// Size of the (v1 transport) P2P message header: network magic (4 bytes),
+/// command (12 bytes), payload length (4 bytes), and checksum (4 bytes).
+const P2P_MESSAGE_HEADER_SIZE: u64 = 24;
+
 /// Based on Bitcoin Core's MAX_PROTOCOL_MESSAGE_LENGTH. Longer messages are rejected.
 const MAX_P2P_MESSAGE_SIZE: usize = 4194304; // 4 MB
 
@@ -26,6 +30,8 @@ const HASH_LENGTH: usize = 32;
 #[repr(C)]
 #[derive(Clone, Debug)]
 pub struct P2PMessageMetadata {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     pub peer_id: u64,
     pub peer_addr: [u8; MAX_PEER_ADDR_LENGTH],
     pub peer_conn_type: [u8; MAX_PEER_CONN_TYPE_LENGTH],
@@ -51,6 +57,17 @@ impl P2PMessageMetadata {
         String::from_utf8_lossy(self.msg_type.split(|c| *c == 0x00u8).next().unwrap()).into_owned()
     }
 
+    /// The size of the message payload. The tracepoints pass the payload size
+    /// as message size.
+    pub fn payload_bytes(&self) -> u64 {
+        self.msg_size
+    }
+
+    /// The serialized size of the message including the P2P message header.
+    pub fn total_bytes(&self) -> u64 {
+        self.msg_size + P2P_MESSAGE_HEADER_SIZE
+    }
+
     pub fn from_bytes(x: &[u8]) -> Self {
         unsafe { ptr::read_unaligned(x.as_ptr() as *const Self) }
     }
@@ -65,6 +82,9 @@ impl P2PMessageMetadata {
             command: self.msg_type(),
             inbound: self.msg_inbound,
             size: self.msg_size,
+            sample_rate: None,
+            payload_bytes: Some(self.payload_bytes()),
+            total_bytes: Some(self.total_bytes()),
         }
     }
 }
@@ -108,6 +128,8 @@ impl P2PMessage {
 
 #[repr(C)]
 pub struct Connection {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     pub id: u64,
     pub addr: [u8; MAX_PEER_ADDR_LENGTH],
     pub conn_type: [u8; MAX_PEER_CONN_TYPE_LENGTH],
@@ -213,6 +235,8 @@ impl fmt::Display for OutboundConnection {
 
 #[repr(C)]
 pub struct MisbehavingConnection {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     pub id: u64,
     pub message: [u8; MAX_MISBEHAVING_MESSAGE_LENGTH],
 }
@@ -241,6 +265,8 @@ impl fmt::Display for MisbehavingConnection {
 
 #[repr(C)]
 pub struct MempoolAdded {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     /// Txid of the added transaction
     pub txid: [u8; TXID_LENGTH],
     /// Vsize of the added transaction
@@ -269,6 +295,8 @@ impl MempoolAdded {
 
 #[repr(C)]
 pub struct MempoolRemoved {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     /// Txid of the removed transaction
     pub txid: [u8; TXID_LENGTH],
     /// Removal reason of the transaction
@@ -308,6 +336,8 @@ impl MempoolRemoved {
 
 #[repr(C)]
 pub struct MempoolReplaced {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     /// Txid of the replaced transaction
     pub replaced_txid: [u8; TXID_LENGTH],
     /// Virtual size of the replaced transaction
@@ -361,6 +391,8 @@ impl fmt::Display for MempoolReplaced {
 
 #[repr(C)]
 pub struct MempoolRejected {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     /// Txid of the added transaction
     pub txid: [u8; TXID_LENGTH],
     /// Reason why the transaction was rejected
@@ -391,6 +423,8 @@ impl MempoolRejected {
 
 #[repr(C)]
 pub struct ValidationBlockConnected {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     /// Hash of the connected block
     pub hash: [u8; HASH_LENGTH],
     /// Height of the connected block
@@ -519,6 +553,8 @@ impl fmt::Display for P2PMessageDecodeError {
 
 #[repr(C)]
 pub struct AddrmanInsertNew {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     pub inserted: bool,
     pub bucket: i32,
     pub bucket_pos: i32,
@@ -562,6 +598,8 @@ impl fmt::Display for AddrmanInsertNew {
 
 #[repr(C)]
 pub struct AddrmanInsertTried {
+    /// Kernel time (CLOCK_MONOTONIC, in ns) the tracepoint fired at.
+    pub ktime: u64,
     pub bucket: i32,
     pub bucket_pos: i32,
     pub addr: [u8; MAX_PEER_ADDR_LENGTH],
@@ -610,10 +648,11 @@ mod tests {
     fn p2p_message_from_bytes_1() {
         // The actual message ends after the "92e4200d3021c21b" payload. It's a few bytes larger
         // on purpose to test that it's still parsed correctly.
-        let data_hex = "c79e9300000000003230392e3232322e3235322e34303a36343830390000000069746e6573732076657273696f6e20726573657276656420666f7220736f66742d666f726b20757067726164696e626f756e64005583899738227ad1576a13fc70696e6700000000f5d60e67005930cb080000000000000092e4200d3021c21b649b92000000000033";
+        let data_hex = "7b10a5d4e8000000c79e9300000000003230392e3232322e3235322e34303a36343830390000000069746e6573732076657273696f6e20726573657276656420666f7220736f66742d666f726b20757067726164696e626f756e64005583899738227ad1576a13fc70696e6700000000f5d60e67005930cb080000000000000092e4200d3021c21b649b92000000000033";
         let data = hex::decode(data_hex).unwrap();
         let message = P2PMessage::from_bytes(&data);
 
+        assert_eq!(message.meta.ktime, 1_000_000_000_123u64);
         assert_eq!(message.meta.peer_id, 9674439u64);
         assert_eq!(message.meta.peer_addr(), "209.222.252.40:64809");
         assert_eq!(message.meta.peer_conn_type(), "inbound");
@@ -625,9 +664,34 @@ mod tests {
         message.decode_to_protobuf_network_message().unwrap();
     }
 
+    #[test]
+    fn p2p_message_sizes() {
+        let data_hex = "7b10a5d4e8000000c79e9300000000003230392e3232322e3235322e34303a36343830390000000069746e6573732076657273696f6e20726573657276656420666f7220736f66742d666f726b20757067726164696e626f756e64005583899738227ad1576a13fc70696e6700000000f5d60e67005930cb080000000000000092e4200d3021c21b";
+        let message = P2PMessage::from_bytes(&hex::decode(data_hex).unwrap());
+
+        // A ping message has an 8 byte nonce as payload and is 32 bytes on the wire.
+        let ping = RawNetworkMessage::new(
+            bitcoin::p2p::Magic::BITCOIN,
+            NetworkMessage::Ping(u64::from_le_bytes(
+                message.payload.clone().try_into().unwrap(),
+            )),
+        );
+        assert_eq!(message.meta.payload_bytes(), 8);
+        assert_eq!(message.meta.total_bytes(), 32);
+        assert_eq!(
+            message.meta.total_bytes(),
+            consensus::serialize(&ping).len() as u64
+        );
+
+        let meta = message.meta.create_protobuf_metadata();
+        assert_eq!(meta.size, 8);
+        assert_eq!(meta.payload_bytes, Some(8));
+        assert_eq!(meta.total_bytes, Some(32));
+    }
+
     #[test]
     fn p2p_message_from_bytes_huge() {
-        let metadata_hex = "c79e9300000000003230392e3232322e3235322e34303a36343830390000000069746e6573732076657273696f6e20726573657276656420666f7220736f66742d666f726b20757067726164696e626f756e64005583899738227ad1576a13fc70696e6700000000f5d60e67005930cb080000000000000092e4200d3021c21b";
+        let metadata_hex = "7b10a5d4e8000000c79e9300000000003230392e3232322e3235322e34303a36343830390000000069746e6573732076657273696f6e20726573657276656420666f7220736f66742d666f726b20757067726164696e626f756e64005583899738227ad1576a13fc70696e6700000000f5d60e67005930cb080000000000000092e4200d3021c21b";
         let metadata = hex::decode(metadata_hex).unwrap();
         let max_len_payload = vec![0; MAX_P2P_MESSAGE_SIZE];
 
@@ -636,4 +700,69 @@ mod tests {
         // an all zero payload.
         let _message = P2PMessage::from_bytes(&[metadata, max_len_payload.to_vec()].concat());
     }
+
+    /// An address as NUL-terminated C string in a MAX_PEER_ADDR_LENGTH buffer.
+    fn addr_bytes(addr: &str) -> Vec<u8> {
+        let mut bytes = addr.as_bytes().to_vec();
+        bytes.resize(MAX_PEER_ADDR_LENGTH, 0);
+        bytes
+    }
+
+    #[test]
+    fn addrman_insert_new_from_bytes() {
+        // struct AddrmanNew in tracing.bpf.c: the bool is padded to the
+        // alignment of the following s32 bucket, and the struct to the
+        // alignment of the u64 ktime.
+        let data = [
+            1_000_000_000_123u64.to_le_bytes().to_vec(),
+            vec![1, 0, 0, 0],
+            42i32.to_le_bytes().to_vec(),
+            7i32.to_le_bytes().to_vec(),
+            addr_bytes("1.2.3.4:8333"),
+            1234u32.to_le_bytes().to_vec(),
+            addr_bytes("5.6.7.8"),
+            5678u32.to_le_bytes().to_vec(),
+            vec![0, 0, 0, 0],
+        ]
+        .concat();
+        assert_eq!(data.len(), std::mem::size_of::<AddrmanInsertNew>());
+
+        let new = AddrmanInsertNew::from_bytes(&data);
+        assert_eq!(new.ktime, 1_000_000_000_123);
+        assert!(new.inserted);
+        assert_eq!(new.bucket, 42);
+        assert_eq!(new.bucket_pos, 7);
+        assert_eq!(new.addr(), "1.2.3.4:8333");
+        assert_eq!(new.addr_as, 1234);
+        assert_eq!(new.source(), "5.6.7.8");
+        assert_eq!(new.source_as, 5678);
+        assert_eq!(
+            new.to_string(),
+            "AddrmanInsertNew(inserted=true, bucket=42, bucket_pos=7, addr=1.2.3.4:8333, addr_AS=1234, source=5.6.7.8, source_AS=5678)"
+        );
+    }
+
+    #[test]
+    fn addrman_insert_tried_from_bytes() {
+        let data = [
+            1_000_000_000_456u64.to_le_bytes().to_vec(),
+            255i32.to_le_bytes().to_vec(),
+            63i32.to_le_bytes().to_vec(),
+            addr_bytes("[2001:db8::1]:8333"),
+            1234u32.to_le_bytes().to_vec(),
+            addr_bytes("5.6.7.8"),
+            5678u32.to_le_bytes().to_vec(),
+        ]
+        .concat();
+        assert_eq!(data.len(), std::mem::size_of::<AddrmanInsertTried>());
+
+        let tried = AddrmanInsertTried::from_bytes(&data);
+        assert_eq!(tried.ktime, 1_000_000_000_456);
+        assert_eq!(tried.bucket, 255);
+        assert_eq!(tried.bucket_pos, 63);
+        assert_eq!(tried.addr(), "[2001:db8::1]:8333");
+        assert_eq!(tried.addr_as, 1234);
+        assert_eq!(tried.source(), "5.6.7.8");
+        assert_eq!(tried.source_as, 5678);
+    }
 }
@@ -31,6 +31,7 @@ impl From<ctypes::MempoolRemoved> for Removed {
     fn from(removed: ctypes::MempoolRemoved) -> Self {
         Removed {
             txid: removed.txid.to_vec(),
+            removal_reason: Some(removed::RemovalReason::from_reason(&removed.reason()) as i32),
             reason: removed.reason(),
             vsize: removed.vsize,
             fee: removed.fee,
@@ -39,13 +40,49 @@ impl From<ctypes::MempoolRemoved> for Removed {
     }
 }
 
+impl removed::RemovalReason {
+    /// Parses the removal reason string of the mempool:removed tracepoint.
+    pub fn from_reason(reason: &str) -> Self {
+        match reason {
+            "expiry" => removed::RemovalReason::Expiry,
+            "sizelimit" => removed::RemovalReason::Sizelimit,
+            "reorg" => removed::RemovalReason::Reorg,
+            "block" => removed::RemovalReason::Block,
+            "conflict" => removed::RemovalReason::Conflict,
+            "replaced" => removed::RemovalReason::Replaced,
+            _ => removed::RemovalReason::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for removed::RemovalReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str_name().to_lowercase())
+    }
+}
+
+impl Removed {
+    /// The removal reason. Parsed from the reason string for events published
+    /// before the removal_reason field was added.
+    pub fn parsed_reason(&self) -> removed::RemovalReason {
+        match self.removal_reason {
+            Some(_) => self.removal_reason(),
+            None => removed::RemovalReason::from_reason(&self.reason),
+        }
+    }
+}
+
 impl fmt::Display for Removed {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.parsed_reason() {
+            removed::RemovalReason::Unknown => format!("unknown({})", self.reason),
+            reason => reason.to_string(),
+        };
         write!(
             f,
             "Removed({}, reason={}, vsize={}, fee={}, entry_time={})",
             bitcoin::Txid::from_slice(&self.txid).unwrap(),
-            self.reason,
+            reason,
             self.vsize,
             self.fee,
             self.entry_time,
@@ -134,3 +171,58 @@ impl fmt::Display for MempoolEvent {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn removed(reason: &str, removal_reason: Option<removed::RemovalReason>) -> Removed {
+        Removed {
+            txid: vec![0u8; 32],
+            reason: reason.to_string(),
+            vsize: 141,
+            fee: 1000,
+            entry_time: 1700000000,
+            removal_reason: removal_reason.map(|r| r as i32),
+        }
+    }
+
+    #[test]
+    fn test_removal_reason() {
+        for (reason, expected) in [
+            ("expiry", removed::RemovalReason::Expiry),
+            ("sizelimit", removed::RemovalReason::Sizelimit),
+            ("reorg", removed::RemovalReason::Reorg),
+            ("block", removed::RemovalReason::Block),
+            ("conflict", removed::RemovalReason::Conflict),
+            ("replaced", removed::RemovalReason::Replaced),
+            ("evicted", removed::RemovalReason::Unknown),
+        ] {
+            assert_eq!(removed::RemovalReason::from_reason(reason), expected);
+        }
+    }
+
+    #[test]
+    fn test_removed_display() {
+        let txid = "0".repeat(64);
+        assert_eq!(
+            removed("sizelimit", Some(removed::RemovalReason::Sizelimit)).to_string(),
+            format!(
+                "Removed({}, reason=sizelimit, vsize=141, fee=1000, entry_time=1700000000)",
+                txid
+            )
+        );
+        // events without the removal_reason field
+        assert_eq!(
+            removed("expiry", None).parsed_reason(),
+            removed::RemovalReason::Expiry
+        );
+        assert_eq!(
+            removed("evicted", Some(removed::RemovalReason::Unknown)).to_string(),
+            format!(
+                "Removed({}, reason=unknown(evicted), vsize=141, fee=1000, entry_time=1700000000)",
+                txid
+            )
+        );
+    }
+}
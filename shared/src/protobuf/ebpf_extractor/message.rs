@@ -405,9 +405,16 @@ impl fmt::Display for Metadata {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Metadata(peer_id={}, addr={}, conn_type={}, command={}, inbound={}, size={})",
+            "Metadata(peer_id={}, addr={}, conn_type={}, command={}, inbound={}, size={}",
             self.peer_id, self.addr, self.conn_type, self.command, self.inbound, self.size
-        )
+        )?;
+        if let Some(total_bytes) = self.total_bytes {
+            write!(f, ", total_bytes={}", total_bytes)?;
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            write!(f, ", sample_rate={}", sample_rate)?;
+        }
+        write!(f, ")")
     }
 }
 
@@ -570,9 +577,9 @@ impl fmt::Display for Unknown {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "FilterLoad(command={}, payload={})",
+            "Unknown(command={}, payload_size={})",
             self.command,
-            self.payload.to_lower_hex_string(),
+            self.payload.len(),
         )
     }
 }
@@ -648,20 +655,26 @@ impl fmt::Display for message_event::Msg {
 
 impl fmt::Display for MessageEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} id={} (conn_type={}): ",
+            if self.meta.inbound {
+                "inbound from"
+            } else {
+                "outbound to"
+            },
+            self.meta.peer_id,
+            self.meta.conn_type,
+        )?;
         match &self.msg {
-            Some(msg) => write!(
+            Some(msg) => write!(f, "{}", msg),
+            // The message wasn't decoded: show the raw command and size from
+            // the metadata.
+            None => write!(
                 f,
-                "{} id={} (conn_type={}): {}",
-                if self.meta.inbound {
-                    "inbound from"
-                } else {
-                    "outbound to"
-                },
-                self.meta.peer_id,
-                self.meta.conn_type,
-                msg
+                "Undecoded(command={}, size={})",
+                self.meta.command, self.meta.size
             ),
-            None => write!(f, "Message(meta={}, msg=None)", self.meta),
         }
     }
 }
@@ -764,4 +777,41 @@ impl From<&p2p::message::NetworkMessage> for message_event::Msg {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    fn meta(command: &str, size: u64) -> Metadata {
+        Metadata {
+            peer_id: 3,
+            command: command.to_string(),
+            inbound: true,
+            size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_display_unknown() {
+        let event = MessageEvent {
+            meta: meta("sendtxrcncl", 36),
+            msg: Some(message_event::Msg::Unknown(Unknown {
+                command: "sendtxrcncl".to_string(),
+                payload: vec![0; 12],
+            })),
+        };
+        assert!(event
+            .to_string()
+            .ends_with(": Unknown(command=sendtxrcncl, payload_size=12)"));
+    }
+
+    #[test]
+    fn test_display_undecoded() {
+        let event = MessageEvent {
+            meta: meta("sendtxrcncl", 36),
+            msg: None,
+        };
+        assert!(event
+            .to_string()
+            .ends_with(": Undecoded(command=sendtxrcncl, size=36)"));
+    }
+}
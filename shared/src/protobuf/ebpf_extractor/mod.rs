@@ -17,6 +17,30 @@ use std::fmt;
 // Generated types for ebpf_extractor.proto (EBPFEvent).
 include!(concat!(env!("OUT_DIR"), "/ebpf_extractor.rs"));
 
+impl fmt::Display for DroppedEvents {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DroppedEvents(dropped={}, buffer_size={}",
+            self.dropped, self.buffer_size
+        )?;
+        if let Some(stale) = self.stale {
+            write!(f, ", stale={}", stale)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for PublishLatency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PublishLatency(count={}, p50={}µs, p90={}µs, p99={}µs, max={}µs)",
+            self.count, self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
 impl fmt::Display for ebpf::EbpfEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -25,6 +49,8 @@ impl fmt::Display for ebpf::EbpfEvent {
             ebpf::EbpfEvent::Addrman(addrman) => write!(f, "{}", addrman),
             ebpf::EbpfEvent::Mempool(mempool) => write!(f, "{}", mempool),
             ebpf::EbpfEvent::Validation(validation) => write!(f, "{}", validation),
+            ebpf::EbpfEvent::DroppedEvents(dropped) => write!(f, "{}", dropped),
+            ebpf::EbpfEvent::PublishLatency(latency) => write!(f, "{}", latency),
         }
     }
 }
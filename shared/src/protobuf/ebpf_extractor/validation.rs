@@ -1,4 +1,5 @@
 use crate::bitcoin::hashes::Hash;
+use crate::hash::{Hash256, HashError};
 use crate::protobuf::ebpf_extractor::ctypes;
 use std::fmt;
 
@@ -18,6 +19,13 @@ impl From<ctypes::ValidationBlockConnected> for BlockConnected {
     }
 }
 
+impl BlockConnected {
+    /// The hash of the connected block.
+    pub fn hash256(&self) -> Result<Hash256, HashError> {
+        Hash256::from_internal_bytes(&self.hash)
+    }
+}
+
 impl fmt::Display for BlockConnected {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
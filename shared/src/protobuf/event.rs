@@ -5,21 +5,227 @@
 #![allow(clippy::module_inception)]
 
 use log::trace;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
 use std::time::SystemTime;
 use std::time::SystemTimeError;
 
 // structs are generated via the wrapper.proto file
 include!(concat!(env!("OUT_DIR"), "/event.rs"));
 
+/// The source of the current time of new [Event]s. [SystemClock] in
+/// production, tests can use a [FixedClock] to assert exact timestamps.
+pub trait Clock {
+    /// The current time as duration since the UNIX epoch.
+    fn now(&self) -> Result<Duration, SystemTimeError>;
+}
+
+/// The real clock of the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Result<Duration, SystemTimeError> {
+        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+    }
+}
+
+/// A clock that always returns the same time since the UNIX epoch. For tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub Duration);
+
+impl Clock for FixedClock {
+    fn now(&self) -> Result<Duration, SystemTimeError> {
+        Ok(self.0)
+    }
+}
+
 impl Event {
     pub fn new(event: event::PeerObserverEvent) -> Result<Event, SystemTimeError> {
-        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        Event::new_with_clock(event, &SystemClock)
+    }
+
+    /// Like [Event::new], but takes the timestamp from the clock.
+    pub fn new_with_clock(
+        event: event::PeerObserverEvent,
+        clock: &impl Clock,
+    ) -> Result<Event, SystemTimeError> {
+        let now = clock.now()?;
         trace!("creating new Event: {:?}", event);
         Ok(Event {
             // We can store a UNIX epoch timestamp in millisecond precision
             // for more than the next 500.000 years..
             timestamp: now.as_millis() as u64,
             peer_observer_event: Some(event),
+            labels: BTreeMap::new(),
         })
     }
 }
+
+impl fmt::Display for Extractor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Extractor::EbpfExtractor => write!(f, "ebpf-extractor"),
+            Extractor::RpcExtractor => write!(f, "rpc-extractor"),
+            Extractor::P2pExtractor => write!(f, "p2p-extractor"),
+            Extractor::LogExtractor => write!(f, "log-extractor"),
+        }
+    }
+}
+
+impl event::PeerObserverEvent {
+    /// The extractor this event originates from.
+    pub fn extractor_kind(&self) -> Extractor {
+        match self {
+            event::PeerObserverEvent::EbpfExtractor(_) => Extractor::EbpfExtractor,
+            event::PeerObserverEvent::RpcExtractor(_) => Extractor::RpcExtractor,
+            event::PeerObserverEvent::P2pExtractor(_) => Extractor::P2pExtractor,
+            event::PeerObserverEvent::LogExtractor(_) => Extractor::LogExtractor,
+            event::PeerObserverEvent::ErrorEvent(e) => e.extractor(),
+            event::PeerObserverEvent::ExtractorMetrics(m) => m.extractor(),
+            event::PeerObserverEvent::Heartbeat(h) => h.extractor(),
+        }
+    }
+}
+
+impl ErrorEvent {
+    pub fn new(
+        extractor: Extractor,
+        operation: &str,
+        error: String,
+        severity: ErrorSeverity,
+    ) -> Self {
+        ErrorEvent {
+            extractor: extractor.into(),
+            operation: operation.to_string(),
+            error,
+            severity: severity.into(),
+        }
+    }
+}
+
+impl fmt::Display for ErrorEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ErrorEvent(extractor={}, operation={}, severity={}, error={})",
+            self.extractor(),
+            self.operation,
+            self.severity().as_str_name().to_lowercase(),
+            self.error
+        )
+    }
+}
+
+impl fmt::Display for ExtractorMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counters: Vec<String> = self
+            .counters
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        let gauges: Vec<String> = self
+            .gauges
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        write!(
+            f,
+            "ExtractorMetrics(extractor={}, window={}s, counters=[{}], gauges=[{}])",
+            self.extractor(),
+            self.window,
+            counters.join(", "),
+            gauges.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for Heartbeat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Heartbeat(extractor={}, sequence={}, interval={}s)",
+            self.extractor(),
+            self.sequence,
+            self.interval
+        )
+    }
+}
+
+impl Event {
+    /// The encoded length of the event in an [EventBatch]: the field tag, the
+    /// length delimiter, and the encoded event.
+    pub fn batched_len(&self) -> usize {
+        let len = prost::Message::encoded_len(self);
+        1 + prost::length_delimiter_len(len) + len
+    }
+
+    /// The extractor this event originates from. None if the event is empty.
+    pub fn extractor_kind(&self) -> Option<Extractor> {
+        self.peer_observer_event
+            .as_ref()
+            .map(|event| event.extractor_kind())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::log_extractor::{self, log::LogEvent, UnknownLogMessage};
+    use crate::protobuf::p2p_extractor::{self, p2p::P2pEvent, PingDuration};
+
+    #[test]
+    fn test_new_with_clock() {
+        let clock = FixedClock(Duration::from_micros(1_700_000_000_123_456));
+        let event = Event::new_with_clock(
+            event::PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+                p2p_event: Some(P2pEvent::PingDuration(PingDuration { duration: 1 })),
+            }),
+            &clock,
+        )
+        .unwrap();
+        // millisecond precision
+        assert_eq!(event.timestamp, 1_700_000_000_123);
+        assert!(event.labels.is_empty());
+    }
+
+    #[test]
+    fn test_extractor_kind() {
+        let p2p = Event::new(event::PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+            p2p_event: Some(P2pEvent::PingDuration(PingDuration { duration: 1 })),
+        }))
+        .unwrap();
+        assert_eq!(p2p.extractor_kind(), Some(Extractor::P2pExtractor));
+
+        let log = Event::new(event::PeerObserverEvent::LogExtractor(log_extractor::Log {
+            occurrences: None,
+            raw_line: None,
+            log_timestamp: 0,
+            category: 0,
+            log_event: Some(LogEvent::UnknownLogMessage(UnknownLogMessage {
+                raw_message: String::new(),
+            })),
+        }))
+        .unwrap();
+        assert_eq!(log.extractor_kind(), Some(Extractor::LogExtractor));
+
+        // error events originate from the extractor that ran into the error
+        let error = Event::new(event::PeerObserverEvent::ErrorEvent(ErrorEvent::new(
+            Extractor::RpcExtractor,
+            "getpeerinfo",
+            "connection refused".to_string(),
+            ErrorSeverity::Error,
+        )))
+        .unwrap();
+        assert_eq!(error.extractor_kind(), Some(Extractor::RpcExtractor));
+
+        let empty = Event {
+            timestamp: 0,
+            peer_observer_event: None,
+            labels: BTreeMap::new(),
+        };
+        assert_eq!(empty.extractor_kind(), None);
+    }
+}
@@ -1,14 +1,102 @@
+use crate::hash::{Hash256, HashError};
 use std::fmt;
 
 // structs are generated via the log_extractor.proto file
 include!(concat!(env!("OUT_DIR"), "/log_extractor.rs"));
 
+impl BlockConnectedLog {
+    /// The logged block hash.
+    pub fn hash256(&self) -> Result<Hash256, HashError> {
+        Hash256::from_display_hex(&self.block_hash)
+    }
+}
+
+impl BlockCheckedLog {
+    /// The logged block hash.
+    pub fn hash256(&self) -> Result<Hash256, HashError> {
+        Hash256::from_display_hex(&self.block_hash)
+    }
+}
+
+impl BlockCheckState {
+    /// Classifies a logged block validation state, e.g. `bad-txnmrklroot`.
+    /// Unknown states are [BlockCheckState::Other].
+    pub fn from_state(state: &str) -> Self {
+        match state {
+            "Valid" => BlockCheckState::Valid,
+            "bad-txnmrklroot" => BlockCheckState::BadTxnmrklroot,
+            "bad-txns-duplicate" => BlockCheckState::BadTxnsDuplicate,
+            "bad-witness-nonce-size" => BlockCheckState::BadWitnessNonceSize,
+            "bad-witness-merkle-match" => BlockCheckState::BadWitnessMerkleMatch,
+            "unexpected-witness" => BlockCheckState::UnexpectedWitness,
+            "high-hash" => BlockCheckState::HighHash,
+            "bad-diffbits" => BlockCheckState::BadDiffbits,
+            "time-too-old" => BlockCheckState::TimeTooOld,
+            "time-too-new" => BlockCheckState::TimeTooNew,
+            "bad-blk-length" => BlockCheckState::BadBlkLength,
+            "bad-blk-weight" => BlockCheckState::BadBlkWeight,
+            "bad-blk-sigops" => BlockCheckState::BadBlkSigops,
+            "bad-cb-missing" => BlockCheckState::BadCbMissing,
+            "bad-cb-multiple" => BlockCheckState::BadCbMultiple,
+            "bad-cb-height" => BlockCheckState::BadCbHeight,
+            "bad-cb-amount" => BlockCheckState::BadCbAmount,
+            "bad-txns-nonfinal" => BlockCheckState::BadTxnsNonfinal,
+            _ => BlockCheckState::Other,
+        }
+    }
+
+    /// True if the block was mutated, i.e. its transactions or witness data
+    /// don't match the header. A mutated block doesn't make the block hash
+    /// invalid.
+    pub fn is_mutated(&self) -> bool {
+        matches!(
+            self,
+            BlockCheckState::BadTxnmrklroot
+                | BlockCheckState::BadTxnsDuplicate
+                | BlockCheckState::BadWitnessNonceSize
+                | BlockCheckState::BadWitnessMerkleMatch
+                | BlockCheckState::UnexpectedWitness
+        )
+    }
+}
+
+impl HeaderSyncLog {
+    /// The logged header hash. None if no hash was logged.
+    pub fn hash256(&self) -> Option<Result<Hash256, HashError>> {
+        self.block_hash.as_deref().map(Hash256::from_display_hex)
+    }
+}
+
 impl fmt::Display for UnknownLogMessage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "UnknownLogMessage({})", self.raw_message)
     }
 }
 
+impl UnknownLogSummary {
+    /// Ratio of unknown log lines to all log lines read during the window.
+    /// Returns 0 if no log lines were read.
+    pub fn unknown_ratio(&self) -> f64 {
+        if self.total_lines == 0 {
+            return 0.0;
+        }
+        self.unknown_lines as f64 / self.total_lines as f64
+    }
+}
+
+impl fmt::Display for UnknownLogSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "UnknownLogSummary(unknown={}/{} ({:.2}%), window={}s)",
+            self.unknown_lines,
+            self.total_lines,
+            self.unknown_ratio() * 100.0,
+            self.window_seconds
+        )
+    }
+}
+
 impl fmt::Display for BlockConnectedLog {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -29,6 +117,142 @@ impl fmt::Display for BlockCheckedLog {
     }
 }
 
+impl fmt::Display for HeaderSyncLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "HeaderSync(stage={:?}, height={}",
+            self.stage(),
+            self.height
+        )?;
+        if let Some(progress) = self.progress {
+            write!(f, ", progress={:.2}%", progress)?;
+        }
+        if let Some(block_hash) = &self.block_hash {
+            write!(f, ", hash={}", block_hash)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for DiskActivityLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DiskActivity(operation={:?}", self.operation())?;
+        if let Some(completed) = self.completed {
+            write!(f, ", completed={}", completed)?;
+        }
+        if let Some(duration_ms) = self.duration_ms {
+            write!(f, ", duration={:.2}ms", duration_ms)?;
+        }
+        if let Some(coins) = self.coins {
+            write!(f, ", coins={}", coins)?;
+        }
+        if let Some(size_kib) = self.size_kib {
+            write!(f, ", size={:.2}KiB", size_kib)?;
+        }
+        if let Some(file_number) = self.file_number {
+            write!(f, ", file={:05}", file_number)?;
+        }
+        if let Some(removed_files) = self.removed_files {
+            write!(f, ", removed_files={}", removed_files)?;
+        }
+        if let Some(target_mib) = self.target_mib {
+            write!(f, ", target={}MiB", target_mib)?;
+        }
+        if let Some(actual_mib) = self.actual_mib {
+            write!(f, ", actual={}MiB", actual_mib)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for DiscouragedPeerLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DiscouragedPeer(peer_id={}, action={:?}",
+            self.peer_id,
+            self.action()
+        )?;
+        if let Some(reason) = &self.reason {
+            write!(f, ", reason={}", reason)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for NetworkErrorLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NetworkError(")?;
+        if let Some(peer_id) = self.peer_id {
+            write!(f, "peer_id={}, ", peer_id)?;
+        }
+        write!(f, "error={})", self.error)
+    }
+}
+
+impl fmt::Display for IbdCompleteLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IbdComplete")
+    }
+}
+
+impl fmt::Display for CriticalErrorLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CriticalError(kind={:?}, severity={:?}, message={})",
+            self.kind(),
+            self.severity(),
+            self.message
+        )
+    }
+}
+
+impl fmt::Display for SnapshotLoadLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SnapshotLoad(block_hash={}, coins={}, completed={}",
+            self.block_hash, self.coins, self.completed
+        )?;
+        if let Some(size_mb) = self.size_mb {
+            write!(f, ", size={:.2}MB", size_mb)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for SnapshotValidatedLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SnapshotValidated(block_hash={})", self.block_hash)
+    }
+}
+
+impl fmt::Display for BlockTemplateLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BlockTemplate(weight={}, txs={}, fees={}sat, sigops={})",
+            self.weight, self.tx_count, self.fees, self.sigops
+        )
+    }
+}
+
+impl fmt::Display for BlockTemplateTimingLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BlockTemplateTiming(packages={:.2}ms ({} packages, {} updated descendants), validity={:.2}ms, total={:.2}ms)",
+            self.packages_ms,
+            self.packages,
+            self.updated_descendants,
+            self.validity_ms,
+            self.total_ms
+        )
+    }
+}
+
 impl fmt::Display for log::LogEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -37,6 +261,17 @@ impl fmt::Display for log::LogEvent {
             log::LogEvent::BlockCheckedLog(block) => {
                 write!(f, "{}", block)
             }
+            log::LogEvent::HeaderSyncLog(sync) => write!(f, "{}", sync),
+            log::LogEvent::DiskActivityLog(activity) => write!(f, "{}", activity),
+            log::LogEvent::DiscouragedPeerLog(discouraged) => write!(f, "{}", discouraged),
+            log::LogEvent::NetworkErrorLog(error) => write!(f, "{}", error),
+            log::LogEvent::IbdCompleteLog(ibd) => write!(f, "{}", ibd),
+            log::LogEvent::CriticalErrorLog(error) => write!(f, "{}", error),
+            log::LogEvent::SnapshotLoadLog(load) => write!(f, "{}", load),
+            log::LogEvent::SnapshotValidatedLog(validated) => write!(f, "{}", validated),
+            log::LogEvent::BlockTemplateLog(template) => write!(f, "{}", template),
+            log::LogEvent::BlockTemplateTimingLog(timing) => write!(f, "{}", timing),
+            log::LogEvent::UnknownLogSummary(summary) => write!(f, "{}", summary),
         }
     }
 }
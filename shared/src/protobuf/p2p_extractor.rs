@@ -1,3 +1,6 @@
+use crate::hash::{Hash256, HashError};
+use bitcoin::hashes::Hash;
+use std::collections::BTreeMap;
 use std::fmt;
 
 // structs are generated via the p2p-extractor.proto file
@@ -25,8 +28,34 @@ impl fmt::Display for AddressAnnouncement {
     }
 }
 
+/// Inventory announcements with more items are displayed as a summary of the
+/// item counts by type. Use the alternate flag (`{:#}`) to display all items.
+const INVENTORY_ANNOUNCEMENT_MAX_DISPLAYED_ITEMS: usize = 10;
+
+impl InventoryAnnouncement {
+    /// Returns the number of inventory items by type (e.g. Tx, WTx, Block).
+    pub fn counts_by_type(&self) -> BTreeMap<&str, u64> {
+        let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+        for item in self.inventory.iter() {
+            *counts.entry(item.inv_type()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
 impl fmt::Display for InventoryAnnouncement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.inventory.len() > INVENTORY_ANNOUNCEMENT_MAX_DISPLAYED_ITEMS && !f.alternate() {
+            // most frequent inventory types first
+            let mut counts: Vec<(&str, u64)> = self.counts_by_type().into_iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1));
+            let counts: Vec<String> = counts
+                .iter()
+                .map(|(inv_type, count)| format!("{} {}", count, inv_type))
+                .collect();
+            return write!(f, "InventoryAnnouncement: {}", counts.join(", "));
+        }
+
         write!(f, "InventoryAnnouncement: [")?;
         let mut first = true;
         for v in &self.inventory {
@@ -41,6 +70,216 @@ impl fmt::Display for InventoryAnnouncement {
     }
 }
 
+impl fmt::Display for SendCompact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SendCompact(high_bandwidth={}, version={})",
+            self.high_bandwidth, self.version
+        )
+    }
+}
+
+impl CompactBlockAnnouncement {
+    /// The hash of the announced block.
+    pub fn hash256(&self) -> Result<Hash256, HashError> {
+        Hash256::from_internal_bytes(&self.block_hash)
+    }
+}
+
+impl fmt::Display for CompactBlockAnnouncement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CompactBlockAnnouncement(hash={}, short_ids={}, prefilled_transactions={})",
+            bitcoin::BlockHash::from_slice(&self.block_hash).unwrap(),
+            self.short_ids,
+            self.prefilled_transactions
+        )
+    }
+}
+
+impl fmt::Display for TransactionRelay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TransactionRelay(txid={}, wtxid={}, size={}",
+            bitcoin::Txid::from_slice(&self.txid).unwrap(),
+            bitcoin::Wtxid::from_slice(&self.wtxid).unwrap(),
+            self.size
+        )?;
+        if let Some(raw_tx) = &self.raw_tx {
+            write!(f, ", raw_tx={} bytes", raw_tx.len())?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for BlockRelay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BlockRelay(hash={}, transactions={}, size={}",
+            bitcoin::BlockHash::from_slice(&self.header.hash).unwrap(),
+            self.transactions,
+            self.size
+        )?;
+        if let Some(raw_block) = &self.raw_block {
+            write!(f, ", raw_block={} bytes", raw_block.len())?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl FeefilterAnnouncement {
+    /// Creates an announcement from the raw feefilter value in sat/kvB.
+    pub fn new(fee_rate: i64) -> Self {
+        FeefilterAnnouncement {
+            fee_rate,
+            sat_per_vbyte: fee_rate as f64 / 1000.0,
+        }
+    }
+}
+
+impl fmt::Display for FeefilterAnnouncement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FeefilterAnnouncement({:.3} sat/vB, {} sat/kvB)",
+            self.sat_per_vbyte, self.fee_rate
+        )
+    }
+}
+
+impl fmt::Display for FeatureNegotiation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FeatureNegotiation(sendaddrv2={}, wtxidrelay={}, sendheaders={})",
+            self.send_addr_v2, self.wtxid_relay, self.send_headers
+        )
+    }
+}
+
+/// Header requests with more locator hashes only display the first ones and
+/// the number of omitted hashes. Use the alternate flag (`{:#}`) to display
+/// all locator hashes.
+const HEADER_REQUEST_MAX_DISPLAYED_LOCATOR_HASHES: usize = 3;
+
+impl HeaderRequest {
+    pub fn new(
+        command: HeaderRequestCommand,
+        version: u32,
+        locator_hashes: &[bitcoin::BlockHash],
+        stop_hash: bitcoin::BlockHash,
+    ) -> Self {
+        HeaderRequest {
+            command: command.into(),
+            version,
+            locator_hashes: locator_hashes
+                .iter()
+                .map(|hash| hash.to_byte_array().to_vec())
+                .collect(),
+            stop_hash: stop_hash.to_byte_array().to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for HeaderRequestCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeaderRequestCommand::Getheaders => write!(f, "getheaders"),
+            HeaderRequestCommand::Getblocks => write!(f, "getblocks"),
+        }
+    }
+}
+
+fn write_block_hash(f: &mut fmt::Formatter, hash: &[u8]) -> fmt::Result {
+    match Hash256::from_internal_bytes(hash) {
+        Ok(hash) => write!(f, "{}", hash),
+        Err(_) => write!(f, "invalid({} bytes)", hash.len()),
+    }
+}
+
+impl fmt::Display for HeaderRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "HeaderRequest({}, version={}, locator=[",
+            self.command(),
+            self.version
+        )?;
+        let displayed = if f.alternate() {
+            self.locator_hashes.len()
+        } else {
+            HEADER_REQUEST_MAX_DISPLAYED_LOCATOR_HASHES
+        };
+        for (i, hash) in self.locator_hashes.iter().take(displayed).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write_block_hash(f, hash)?;
+        }
+        if self.locator_hashes.len() > displayed {
+            write!(f, ", ... {} more", self.locator_hashes.len() - displayed)?;
+        }
+        write!(f, "], stop=")?;
+        if self.stop_hash.iter().all(|b| *b == 0) {
+            write!(f, "none)")
+        } else {
+            write_block_hash(f, &self.stop_hash)?;
+            write!(f, ")")
+        }
+    }
+}
+
+impl fmt::Display for AddrRateAlert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AddrRateAlert(peer={}, addresses={}/min, threshold={}/min)",
+            self.peer, self.addresses, self.threshold
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ParseError(peer={}, command={}, length={}, reason={}, suppressed={})",
+            self.peer, self.command, self.length, self.reason, self.suppressed
+        )
+    }
+}
+
+impl fmt::Display for ServiceMismatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServiceMismatchKind::MessageAboveVersion => write!(f, "message_above_version"),
+            ServiceMismatchKind::WitnessStripped => write!(f, "witness_stripped"),
+        }
+    }
+}
+
+impl fmt::Display for ServiceMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ServiceMismatch(peer={}, kind={}, command={}, version={}, services={:#x}",
+            self.peer,
+            self.kind(),
+            self.command,
+            self.version,
+            self.services
+        )?;
+        if let Some(required_version) = self.required_version {
+            write!(f, ", required_version={}", required_version)?;
+        }
+        write!(f, ")")
+    }
+}
+
 impl fmt::Display for p2p::P2pEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -51,9 +290,148 @@ impl fmt::Display for p2p::P2pEvent {
             p2p::P2pEvent::InventoryAnnouncement(inventory) => {
                 write!(f, "{}", inventory)
             }
-            p2p::P2pEvent::FeefilterAnnouncement(feefilter) => {
-                write!(f, "FeefilterAnnouncement({})", feefilter)
+            p2p::P2pEvent::FeefilterAnnouncement(feefilter) => write!(f, "{}", feefilter),
+            p2p::P2pEvent::SendCompact(sendcmpct) => write!(f, "{}", sendcmpct),
+            p2p::P2pEvent::CompactBlockAnnouncement(cmpctblock) => {
+                write!(f, "{}", cmpctblock)
             }
+            p2p::P2pEvent::TransactionRelay(tx) => write!(f, "{}", tx),
+            p2p::P2pEvent::BlockRelay(block) => write!(f, "{}", block),
+            p2p::P2pEvent::AddrRateAlert(alert) => write!(f, "{}", alert),
+            p2p::P2pEvent::FeatureNegotiation(features) => write!(f, "{}", features),
+            p2p::P2pEvent::HeaderRequest(request) => write!(f, "{}", request),
+            p2p::P2pEvent::ParseError(parse_error) => write!(f, "{}", parse_error),
+            p2p::P2pEvent::ServiceMismatch(mismatch) => write!(f, "{}", mismatch),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::bitcoin_primitives::{inventory_item::Item, InventoryItem};
+
+    fn inventory_announcement(wtxs: usize, blocks: usize) -> InventoryAnnouncement {
+        let wtx = InventoryItem {
+            item: Some(Item::Wtx(vec![0u8; 32])),
+        };
+        let block = InventoryItem {
+            item: Some(Item::Block(vec![0u8; 32])),
+        };
+        let mut inventory = vec![wtx; wtxs];
+        inventory.extend(vec![block; blocks]);
+        InventoryAnnouncement { inventory }
+    }
+
+    #[test]
+    fn test_inventory_announcement_counts_by_type() {
+        let counts = inventory_announcement(3, 1).counts_by_type();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["WTx"], 3);
+        assert_eq!(counts["Block"], 1);
+    }
+
+    #[test]
+    fn test_inventory_announcement_display() {
+        let short = inventory_announcement(1, 1);
+        assert_eq!(
+            short.to_string(),
+            "InventoryAnnouncement: [WTx(0000000000000000000000000000000000000000000000000000000000000000), Block(0000000000000000000000000000000000000000000000000000000000000000)]"
+        );
+
+        let long = inventory_announcement(12, 1);
+        assert_eq!(long.to_string(), "InventoryAnnouncement: 12 WTx, 1 Block");
+        // the alternate flag displays all items
+        assert!(format!("{:#}", long).starts_with("InventoryAnnouncement: [WTx("));
+        assert_eq!(format!("{:#}", long).matches("WTx(").count(), 12);
+    }
+
+    #[test]
+    fn test_feefilter_announcement() {
+        let feefilter = FeefilterAnnouncement::new(1234);
+        assert_eq!(feefilter.fee_rate, 1234);
+        assert_eq!(feefilter.sat_per_vbyte, 1.234);
+        assert_eq!(
+            feefilter.to_string(),
+            "FeefilterAnnouncement(1.234 sat/vB, 1234 sat/kvB)"
+        );
+        assert_eq!(
+            FeefilterAnnouncement::new(100).to_string(),
+            "FeefilterAnnouncement(0.100 sat/vB, 100 sat/kvB)"
+        );
+    }
+
+    #[test]
+    fn test_feature_negotiation_display() {
+        let features = FeatureNegotiation {
+            send_addr_v2: true,
+            wtxid_relay: true,
+            send_headers: false,
+        };
+        assert_eq!(
+            features.to_string(),
+            "FeatureNegotiation(sendaddrv2=true, wtxidrelay=true, sendheaders=false)"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_display() {
+        let parse_error = ParseError {
+            peer: "127.0.0.1:8333".to_string(),
+            command: "tx".to_string(),
+            length: 3,
+            reason: "unexpected end of file".to_string(),
+            suppressed: 0,
+        };
+        assert_eq!(
+            parse_error.to_string(),
+            "ParseError(peer=127.0.0.1:8333, command=tx, length=3, reason=unexpected end of file, suppressed=0)"
+        );
+    }
+
+    #[test]
+    fn test_header_request_display() {
+        let hash = |i: u8| bitcoin::BlockHash::from_byte_array([i; 32]);
+        let locator: Vec<bitcoin::BlockHash> = (1..=5).map(hash).collect();
+
+        let request = HeaderRequest::new(
+            HeaderRequestCommand::Getheaders,
+            70016,
+            &locator[..2],
+            bitcoin::BlockHash::all_zeros(),
+        );
+        assert_eq!(
+            request.to_string(),
+            format!(
+                "HeaderRequest(getheaders, version=70016, locator=[{}, {}], stop=none)",
+                hash(1),
+                hash(2)
+            )
+        );
+
+        let request = HeaderRequest::new(HeaderRequestCommand::Getblocks, 70016, &locator, hash(9));
+        assert_eq!(
+            request.to_string(),
+            format!(
+                "HeaderRequest(getblocks, version=70016, locator=[{}, {}, {}, ... 2 more], stop={})",
+                hash(1),
+                hash(2),
+                hash(3),
+                hash(9)
+            )
+        );
+        // the alternate flag displays all locator hashes
+        assert_eq!(
+            format!("{:#}", request),
+            format!(
+                "HeaderRequest(getblocks, version=70016, locator=[{}, {}, {}, {}, {}], stop={})",
+                hash(1),
+                hash(2),
+                hash(3),
+                hash(4),
+                hash(5),
+                hash(9)
+            )
+        );
+    }
+}
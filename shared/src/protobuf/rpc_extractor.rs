@@ -1,16 +1,51 @@
+use crate::feefilter::btc_per_kvb_to_sat;
+use crate::util;
 use corepc_client::types::v17::{
-    GetMemoryInfoStats as RPCGetMemoryInfoStats, GetNetTotals as RPCGetNetTotals,
+    EstimateSmartFee as RPCEstimateSmartFee, GetMemoryInfoStats as RPCGetMemoryInfoStats,
+    GetMempoolAncestors as RPCGetMempoolAncestors,
+    GetMempoolDescendants as RPCGetMempoolDescendants, GetNetTotals as RPCGetNetTotals,
     UploadTarget as RPCUploadTarget,
 };
 use corepc_client::types::v26::{
     AddrManInfoNetwork as RPCAddrManInfoNetwork, GetAddrManInfo as RPCGetAddrManInfo,
     GetMempoolInfo, GetPeerInfo as RPCGetPeerInfo, PeerInfo as RPCPeerInfo,
 };
+use corepc_client::types::v29::GetBlockchainInfo as RPCGetBlockchainInfo;
+use prost::Message;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 // structs are generated via the rpc_extractor.proto file
 include!(concat!(env!("OUT_DIR"), "/rpc_extractor.rs"));
 
+/// Splits the items of a repeated field into chunks with an encoded size of
+/// at most `max_bytes` each, including the chunk_index and chunk_total fields.
+/// `len` is the encoded length of an item. An item larger than `max_bytes`
+/// gets a chunk of its own.
+fn split_into_chunks<T>(items: Vec<T>, max_bytes: usize, len: impl Fn(&T) -> usize) -> Vec<Vec<T>> {
+    // the chunk_index and chunk_total fields take up to 6 bytes each
+    const CHUNK_FIELDS_LEN: usize = 12;
+    let mut chunks: Vec<Vec<T>> = vec![];
+    let mut chunk_len = CHUNK_FIELDS_LEN;
+    for item in items {
+        // field tag, length delimiter, and the encoded item
+        let len = len(&item);
+        let item_len = 1 + prost::length_delimiter_len(len) + len;
+        match chunks.last_mut() {
+            Some(chunk) if chunk_len + item_len <= max_bytes => {
+                chunk.push(item);
+                chunk_len += item_len;
+            }
+            _ => {
+                chunks.push(vec![item]);
+                chunk_len = CHUNK_FIELDS_LEN + item_len;
+            }
+        }
+    }
+    chunks
+}
+
 impl fmt::Display for PeerInfos {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let info_strs: Vec<String> = self.infos.iter().map(|i| i.to_string()).collect();
@@ -20,15 +55,391 @@ impl fmt::Display for PeerInfos {
 
 impl From<RPCGetPeerInfo> for PeerInfos {
     fn from(infos: RPCGetPeerInfo) -> Self {
+        PeerInfos::new(infos.0.iter().map(|i| i.clone().into()).collect())
+    }
+}
+
+impl PeerInfos {
+    /// Creates the PeerInfos sorted by peer id. The order of the getpeerinfo
+    /// response isn't guaranteed, and sorting keeps successive samples
+    /// comparable.
+    pub fn new(mut infos: Vec<PeerInfo>) -> Self {
+        infos.sort_by_key(|info| info.id);
         PeerInfos {
-            infos: infos.0.iter().map(|i| i.clone().into()).collect(),
+            infos,
+            chunk_index: None,
+            chunk_total: None,
+        }
+    }
+
+    /// Splits the peers into chunks with an encoded size of at most
+    /// `max_bytes` each, so that large getpeerinfo responses stay below the
+    /// maximum NATS message size. Returns the PeerInfos unchanged if it's
+    /// small enough. A single peer larger than `max_bytes` gets a chunk of
+    /// its own.
+    pub fn into_chunks(self, max_bytes: usize) -> Vec<PeerInfos> {
+        if self.encoded_len() <= max_bytes {
+            return vec![self];
+        }
+
+        let chunks = split_into_chunks(self.infos, max_bytes, PeerInfo::encoded_len);
+        let total = util::clamp_u32("chunk_total", chunks.len());
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, infos)| PeerInfos {
+                infos,
+                chunk_index: Some(index as u32),
+                chunk_total: Some(total),
+            })
+            .collect()
+    }
+
+    /// Buckets the peers by direction, network, and connection type and
+    /// returns the peer count per bucket.
+    pub fn summary(&self) -> PeerInfoSummary {
+        let mut counts: BTreeMap<(bool, &str, &str), u32> = BTreeMap::new();
+        for info in self.infos.iter() {
+            counts
+                .entry((info.inbound, &info.network, &info.connection_type))
+                .and_modify(|c| *c += 1)
+                .or_insert(1);
+        }
+
+        PeerInfoSummary {
+            total: util::clamp_u32("total", self.infos.len()),
+            buckets: counts
+                .into_iter()
+                .map(
+                    |((inbound, network, connection_type), count)| PeerInfoSummaryBucket {
+                        inbound,
+                        network: network.to_string(),
+                        connection_type: connection_type.to_string(),
+                        count,
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    /// Groups the peers by network and returns the peer counts and the bytes
+    /// sent and received per network. Peers with an empty network are grouped
+    /// as "unknown".
+    pub fn network_stats(&self) -> NetworkPeerStats {
+        let mut networks: BTreeMap<&str, NetworkPeerStatsEntry> = BTreeMap::new();
+        for info in self.infos.iter() {
+            let network = if info.network.is_empty() {
+                "unknown"
+            } else {
+                info.network.as_str()
+            };
+            let entry = networks
+                .entry(network)
+                .or_insert_with(|| NetworkPeerStatsEntry {
+                    network: network.to_string(),
+                    ..Default::default()
+                });
+            entry.peers += 1;
+            if info.inbound {
+                entry.inbound += 1;
+            } else {
+                entry.outbound += 1;
+            }
+            entry.bytes_sent = entry.bytes_sent.saturating_add(info.bytes_sent);
+            entry.bytes_received = entry.bytes_received.saturating_add(info.bytes_received);
+        }
+
+        NetworkPeerStats {
+            networks: networks.into_values().collect(),
+        }
+    }
+
+    /// The peers of this sample keyed by id. Kept until the next sample to
+    /// derive the ConnectionChurn between them.
+    pub fn churn_peers(&self) -> BTreeMap<u32, ChurnPeer> {
+        self.infos
+            .iter()
+            .map(|info| {
+                (
+                    info.id,
+                    ChurnPeer {
+                        id: info.id,
+                        address: info.address.clone(),
+                        connection_type: info.connection_type.clone(),
+                        connection_time: info.connection_time,
+                        session_duration: 0,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Leaves the bytes_sent_per_message and bytes_received_per_message maps
+    /// of all peers empty and sets per_message_omitted.
+    pub fn omit_per_message(&mut self) {
+        for info in self.infos.iter_mut() {
+            info.omit_per_message();
+        }
+    }
+
+    /// Leaves the per-message maps of the peers empty that are unchanged
+    /// since they were last published, and sets per_message_omitted for
+    /// them. `published` holds the last published maps by peer id and is
+    /// updated with the maps of this sample. A peer id with a different
+    /// connection time, e.g. after a node restart, counts as a different
+    /// peer.
+    pub fn omit_unchanged_per_message(&mut self, published: &mut BTreeMap<u32, PerMessageBytes>) {
+        let mut current = BTreeMap::new();
+        for info in self.infos.iter_mut() {
+            let bytes = PerMessageBytes::of(info);
+            if published.get(&info.id) == Some(&bytes) {
+                info.omit_per_message();
+            }
+            current.insert(info.id, bytes);
+        }
+        // Drops the peers that disconnected.
+        *published = current;
+    }
+}
+
+/// The per-message byte counters of a peer as last published, see
+/// [PeerInfos::omit_unchanged_per_message].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerMessageBytes {
+    connection_time: i64,
+    sent: HashMap<String, u64>,
+    received: HashMap<String, u64>,
+}
+
+impl PerMessageBytes {
+    fn of(info: &PeerInfo) -> Self {
+        PerMessageBytes {
+            connection_time: info.connection_time,
+            sent: info.bytes_sent_per_message.clone(),
+            received: info.bytes_received_per_message.clone(),
+        }
+    }
+}
+
+impl PeerInfo {
+    fn omit_per_message(&mut self) {
+        self.bytes_sent_per_message.clear();
+        self.bytes_received_per_message.clear();
+        self.per_message_omitted = Some(true);
+    }
+}
+
+/// Reassembles getpeerinfo responses that were split into chunks (see
+/// [PeerInfos::into_chunks]). The chunks are expected in order, and per key,
+/// e.g. the NATS subject, so that chunks of multiple nodes don't mix.
+#[derive(Default)]
+pub struct PeerInfosAssembler {
+    pending: HashMap<String, PeerInfos>,
+}
+
+impl PeerInfosAssembler {
+    /// Adds a chunk. Returns the reassembled PeerInfos once the last chunk
+    /// was added. PeerInfos that weren't split are returned right away. An
+    /// incomplete response is dropped when a chunk is missing.
+    pub fn push(&mut self, key: &str, chunk: PeerInfos) -> Option<PeerInfos> {
+        let (Some(index), Some(total)) = (chunk.chunk_index, chunk.chunk_total) else {
+            return Some(chunk);
+        };
+
+        if index == 0 {
+            self.pending.insert(key.to_string(), PeerInfos::default());
+        }
+        match self.pending.get_mut(key) {
+            Some(pending) if pending.chunk_index.map_or(0, |i| i + 1) == index => {
+                pending.infos.extend(chunk.infos);
+                pending.chunk_index = Some(index);
+            }
+            _ => {
+                log::warn!(
+                    "Dropping PeerInfos chunk {}/{} on {}: the previous chunk is missing",
+                    index + 1,
+                    total,
+                    key
+                );
+                self.pending.remove(key);
+                return None;
+            }
+        }
+
+        if index + 1 < total {
+            return None;
+        }
+        self.pending.remove(key).map(|infos| PeerInfos {
+            chunk_index: None,
+            ..infos
+        })
+    }
+}
+
+impl ConnectionChurn {
+    /// The peers that connected and disconnected between the `previous` and
+    /// `current` sample (see [PeerInfos::churn_peers]). `now` is the UNIX
+    /// epoch time in seconds of the current sample. A peer id with a different
+    /// connection time, e.g. after a node restart, counts as a different peer.
+    pub fn between(
+        previous: &BTreeMap<u32, ChurnPeer>,
+        current: &BTreeMap<u32, ChurnPeer>,
+        now: u64,
+    ) -> Self {
+        let missing_in = |peers: &BTreeMap<u32, ChurnPeer>, other: &BTreeMap<u32, ChurnPeer>| {
+            peers
+                .values()
+                .filter(|peer| {
+                    other
+                        .get(&peer.id)
+                        .is_none_or(|o| o.connection_time != peer.connection_time)
+                })
+                .map(|peer| ChurnPeer {
+                    session_duration: now.saturating_sub(peer.connection_time.max(0) as u64),
+                    ..peer.clone()
+                })
+                .collect()
+        };
+        ConnectionChurn {
+            connected: missing_in(current, previous),
+            disconnected: missing_in(previous, current),
+        }
+    }
+}
+
+impl fmt::Display for ConnectionChurn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let peers = |peers: &[ChurnPeer]| {
+            peers
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            f,
+            "ConnectionChurn(connected=[{}], disconnected=[{}])",
+            peers(&self.connected),
+            peers(&self.disconnected)
+        )
+    }
+}
+
+impl fmt::Display for ChurnPeer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ChurnPeer(id={}, address={}, type={}, session={}s)",
+            self.id,
+            util::normalize_address(&self.address),
+            self.connection_type,
+            self.session_duration
+        )
+    }
+}
+
+impl From<PeerInfos> for CompactPeerInfos {
+    fn from(infos: PeerInfos) -> Self {
+        CompactPeerInfos {
+            infos: infos.infos.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl fmt::Display for CompactPeerInfos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let info_strs: Vec<String> = self.infos.iter().map(|i| i.to_string()).collect();
+        write!(f, "CompactPeerInfos([{}])", info_strs.join(", "))
+    }
+}
+
+impl From<PeerInfo> for CompactPeerInfo {
+    fn from(info: PeerInfo) -> Self {
+        CompactPeerInfo {
+            id: info.id,
+            address: info.address,
+            inbound: info.inbound,
+            services: info.services,
+            bytes_sent: info.bytes_sent,
+            bytes_received: info.bytes_received,
+            connection_time: info.connection_time,
+            subversion: info.subversion,
         }
     }
 }
 
+impl fmt::Display for CompactPeerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CompactPeerInfo(id={}, address={}, inbound={}, subversion={})",
+            self.id, self.address, self.inbound, self.subversion
+        )
+    }
+}
+
+impl fmt::Display for PeerInfoSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bucket_strs: Vec<String> = self.buckets.iter().map(|b| b.to_string()).collect();
+        write!(
+            f,
+            "PeerInfoSummary(total={}, buckets=[{}])",
+            self.total,
+            bucket_strs.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for PeerInfoSummaryBucket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PeerInfoSummaryBucket(inbound={}, network={}, connection_type={}, count={})",
+            self.inbound, self.network, self.connection_type, self.count
+        )
+    }
+}
+
+impl fmt::Display for NetworkPeerStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let network_strs: Vec<String> = self.networks.iter().map(|n| n.to_string()).collect();
+        write!(
+            f,
+            "NetworkPeerStats(networks=[{}])",
+            network_strs.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for NetworkPeerStatsEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NetworkPeerStatsEntry(network={}, peers={}, inbound={}, outbound={}, bytes_sent={}, bytes_received={})",
+            self.network,
+            self.peers,
+            self.inbound,
+            self.outbound,
+            self.bytes_sent,
+            self.bytes_received
+        )
+    }
+}
+
 impl fmt::Display for PeerInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "PeerInfo(id={})", self.id,)
+        let network = if self.network.is_empty() {
+            "unknown"
+        } else {
+            self.network.as_str()
+        };
+        write!(
+            f,
+            "PeerInfo(id={}, address={}, network={})",
+            self.id,
+            util::normalize_address(&self.address),
+            network
+        )
     }
 }
 
@@ -41,19 +452,38 @@ impl fmt::Display for rpc::RpcEvent {
             rpc::RpcEvent::NetTotals(totals) => write!(f, "{}", totals),
             rpc::RpcEvent::MemoryInfo(info) => write!(f, "{}", info),
             rpc::RpcEvent::AddrmanInfo(info) => write!(f, "{}", info),
+            rpc::RpcEvent::PeerInfoSummary(summary) => write!(f, "{}", summary),
+            rpc::RpcEvent::BlockchainInfo(info) => write!(f, "{}", info),
+            rpc::RpcEvent::FeeEstimates(estimates) => write!(f, "{}", estimates),
+            rpc::RpcEvent::PeerInfo(info) => write!(f, "{}", info),
+            rpc::RpcEvent::CompactPeerInfos(infos) => write!(f, "{}", infos),
+            rpc::RpcEvent::OrphanTxs(orphans) => write!(f, "{}", orphans),
+            rpc::RpcEvent::ScanProgress(progress) => write!(f, "{}", progress),
+            rpc::RpcEvent::RawAddrman(addrman) => write!(f, "{}", addrman),
+            rpc::RpcEvent::ConnectionChurn(churn) => write!(f, "{}", churn),
+            rpc::RpcEvent::NodeAddresses(addresses) => write!(f, "{}", addresses),
+            rpc::RpcEvent::RawMempool(mempool) => write!(f, "{}", mempool),
+            rpc::RpcEvent::NetworkPeerStats(stats) => write!(f, "{}", stats),
         }
     }
 }
 
 impl From<RPCPeerInfo> for PeerInfo {
     fn from(info: RPCPeerInfo) -> Self {
+        let connection_type = info.connection_type.unwrap_or_default();
         PeerInfo {
             address: info.address,
             address_bind: info.address_bind.unwrap_or_default(),
             address_local: info.address_local.unwrap_or_default(),
-            addr_rate_limited: info.addresses_rate_limited.unwrap_or_default() as u64,
+            addr_rate_limited: util::clamp_u64(
+                "addr_rate_limited",
+                info.addresses_rate_limited.unwrap_or_default(),
+            ),
             addr_relay_enabled: info.addresses_relay_enabled.unwrap_or_default(),
-            addr_processed: info.addresses_processed.unwrap_or_default() as u64,
+            addr_processed: util::clamp_u64(
+                "addr_processed",
+                info.addresses_processed.unwrap_or_default(),
+            ),
             bip152_hb_from: info.bip152_hb_from,
             bip152_hb_to: info.bip152_hb_to,
             bytes_received: info.bytes_received,
@@ -61,7 +491,8 @@ impl From<RPCPeerInfo> for PeerInfo {
             bytes_sent: info.bytes_sent,
             bytes_sent_per_message: info.bytes_sent_per_message.into_iter().collect(),
             connection_time: info.connection_time,
-            connection_type: info.connection_type.unwrap_or_default(),
+            connection_type_kind: PeerConnectionType::from(connection_type.as_str()).into(),
+            connection_type,
             id: info.id,
             inbound: info.inbound,
             inflight: info.inflight.unwrap_or_default(),
@@ -72,12 +503,14 @@ impl From<RPCPeerInfo> for PeerInfo {
             mapped_as: info.mapped_as.unwrap_or_default(),
             minfeefilter: info.minimum_fee_filter,
             minimum_ping: info.minimum_ping.unwrap_or_default(),
+            network_kind: PeerNetwork::from(info.network.as_str()).into(),
             network: info.network,
             ping_time: info.ping_time.unwrap_or_default(),
             ping_wait: info.ping_wait.unwrap_or_default(),
             permissions: info.permissions,
             relay_transactions: info.relay_transactions,
             services: info.services,
+            services_names: info.services_names,
             starting_height: info.starting_height.unwrap_or_default(),
             subversion: info.subversion,
             synced_blocks: info.synced_blocks.unwrap_or_default(),
@@ -87,8 +520,39 @@ impl From<RPCPeerInfo> for PeerInfo {
             version: info.version,
 
             // temporary
-            inv_to_send: info.inv_to_send.unwrap_or_default() as u64,
+            inv_to_send: util::clamp_u64("inv_to_send", info.inv_to_send.unwrap_or_default()),
             cpu_load: info.cpu_load.unwrap_or_default() as f64,
+
+            per_message_omitted: None,
+        }
+    }
+}
+
+impl From<&str> for PeerNetwork {
+    fn from(network: &str) -> Self {
+        match network {
+            "ipv4" => PeerNetwork::Ipv4,
+            "ipv6" => PeerNetwork::Ipv6,
+            "onion" => PeerNetwork::Onion,
+            "i2p" => PeerNetwork::I2p,
+            "cjdns" => PeerNetwork::Cjdns,
+            "not_publicly_routable" => PeerNetwork::NotPubliclyRoutable,
+            "internal" => PeerNetwork::Internal,
+            _ => PeerNetwork::Unknown,
+        }
+    }
+}
+
+impl From<&str> for PeerConnectionType {
+    fn from(connection_type: &str) -> Self {
+        match connection_type {
+            "inbound" => PeerConnectionType::Inbound,
+            "outbound-full-relay" => PeerConnectionType::OutboundFullRelay,
+            "manual" => PeerConnectionType::Manual,
+            "feeler" => PeerConnectionType::Feeler,
+            "block-relay-only" => PeerConnectionType::BlockRelayOnly,
+            "addr-fetch" => PeerConnectionType::AddrFetch,
+            _ => PeerConnectionType::Unknown,
         }
     }
 }
@@ -109,7 +573,32 @@ impl From<GetMempoolInfo> for MempoolInfo {
             unbroadcastcount: info.unbroadcast_count,
             // maxdatacarriersize: info.max_datacarrier_size,
             // permitbaremultisig: info.permit_bare_multisig,
+            mempoolminfee_delta: None,
+            mempoolminfee_trend: None,
+        }
+    }
+}
+
+impl MempoolInfo {
+    /// Sets the change of the mempool minimum fee since the previous sample.
+    /// Left unset if the mempool of either sample isn't loaded, for example,
+    /// because the node restarted in between.
+    pub fn set_trend_since(&mut self, previous: &MempoolInfo) {
+        self.mempoolminfee_delta = None;
+        self.mempoolminfee_trend = None;
+        if !self.loaded || !previous.loaded {
+            return;
         }
+        // Compared in satoshis to not report floating point noise as change.
+        let delta_sat =
+            btc_per_kvb_to_sat(self.mempoolminfee) - btc_per_kvb_to_sat(previous.mempoolminfee);
+        self.mempoolminfee_delta = Some(delta_sat as f64 / 100_000_000.0);
+        let trend = match delta_sat.cmp(&0) {
+            std::cmp::Ordering::Greater => FeeTrend::Rising,
+            std::cmp::Ordering::Less => FeeTrend::Falling,
+            std::cmp::Ordering::Equal => FeeTrend::Unchanged,
+        };
+        self.set_mempoolminfee_trend(trend);
     }
 }
 
@@ -123,6 +612,38 @@ impl fmt::Display for MempoolInfo {
     }
 }
 
+impl From<RPCGetBlockchainInfo> for BlockchainInfo {
+    fn from(info: RPCGetBlockchainInfo) -> Self {
+        BlockchainInfo {
+            chain: info.chain,
+            blocks: info.blocks,
+            headers: info.headers,
+            best_block_hash: info.best_block_hash,
+            difficulty: info.difficulty,
+            median_time: info.median_time,
+            verification_progress: info.verification_progress,
+            initial_block_download: info.initial_block_download,
+            size_on_disk: info.size_on_disk,
+            pruned: info.pruned,
+        }
+    }
+}
+
+impl fmt::Display for BlockchainInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "BlockchainInfo(chain={}, blocks={}, headers={}, best_block_hash={}, ibd={}, progress={:.4})",
+            self.chain,
+            self.blocks,
+            self.headers,
+            self.best_block_hash,
+            self.initial_block_download,
+            self.verification_progress
+        )
+    }
+}
+
 impl From<RPCGetNetTotals> for NetTotals {
     fn from(totals: RPCGetNetTotals) -> Self {
         NetTotals {
@@ -130,10 +651,33 @@ impl From<RPCGetNetTotals> for NetTotals {
             total_bytes_sent: totals.total_bytes_sent,
             time_millis: totals.time_millis,
             upload_target: totals.upload_target.into(),
+            recv_bytes_per_sec: None,
+            sent_bytes_per_sec: None,
         }
     }
 }
 
+impl NetTotals {
+    /// Sets the received and sent bytes per second since the previous sample.
+    /// The rates are left unset if the byte counters or the time didn't
+    /// increase, for example, because the node restarted in between.
+    pub fn set_rates_since(&mut self, previous: &NetTotals) {
+        self.recv_bytes_per_sec = None;
+        self.sent_bytes_per_sec = None;
+        if self.time_millis <= previous.time_millis
+            || self.total_bytes_received < previous.total_bytes_received
+            || self.total_bytes_sent < previous.total_bytes_sent
+        {
+            return;
+        }
+        let seconds = (self.time_millis - previous.time_millis) as f64 / 1000.0;
+        self.recv_bytes_per_sec =
+            Some((self.total_bytes_received - previous.total_bytes_received) as f64 / seconds);
+        self.sent_bytes_per_sec =
+            Some((self.total_bytes_sent - previous.total_bytes_sent) as f64 / seconds);
+    }
+}
+
 impl From<RPCUploadTarget> for UploadTarget {
     fn from(target: RPCUploadTarget) -> Self {
         UploadTarget {
@@ -232,3 +776,1033 @@ impl From<RPCAddrManInfoNetwork> for AddrManInfoNetwork {
         }
     }
 }
+
+impl FeeEstimates {
+    /// Builds the fee estimates from the estimatesmartfee responses for each
+    /// confirmation target.
+    pub fn new(
+        estimate_mode: String,
+        responses: impl IntoIterator<Item = (u32, RPCEstimateSmartFee)>,
+    ) -> Self {
+        FeeEstimates {
+            estimate_mode,
+            estimates: responses
+                .into_iter()
+                .map(|(target, response)| (target, response.into()))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for FeeEstimates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let estimate_strs: Vec<String> = self
+            .estimates
+            .iter()
+            .map(|(target, estimate)| format!("{}={}", target, estimate))
+            .collect();
+        write!(
+            f,
+            "FeeEstimates(mode={}, [{}])",
+            self.estimate_mode,
+            estimate_strs.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for FeeEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.feerate {
+            Some(feerate) => write!(f, "{} BTC/kvB", feerate),
+            None => write!(f, "none"),
+        }
+    }
+}
+
+impl From<RPCEstimateSmartFee> for FeeEstimate {
+    fn from(estimate: RPCEstimateSmartFee) -> Self {
+        FeeEstimate {
+            feerate: estimate.fee_rate,
+            blocks: estimate.blocks,
+            errors: estimate.errors.unwrap_or_default(),
+        }
+    }
+}
+
+impl rpc_request::Request {
+    /// The name of the RPC method answering the request.
+    pub fn method(&self) -> &'static str {
+        match self {
+            rpc_request::Request::MempoolAncestors(_) => "getmempoolancestors",
+            rpc_request::Request::MempoolDescendants(_) => "getmempooldescendants",
+            rpc_request::Request::ScanTxOutSet(_) => "scantxoutset",
+        }
+    }
+}
+
+impl RpcResponse {
+    pub fn error(error: String) -> Self {
+        RpcResponse {
+            response: Some(rpc_response::Response::Error(error)),
+        }
+    }
+}
+
+/// An entry of a getorphantxs RPC response with verbosity 1. getorphantxs is a
+/// hidden RPC not covered by corepc. Not all Bitcoin Core versions report the
+/// entry and expiration times.
+#[derive(Debug, Deserialize)]
+pub struct RPCOrphanTx {
+    pub txid: String,
+    pub wtxid: String,
+    pub vsize: u32,
+    pub entry: Option<i64>,
+    pub expiration: Option<i64>,
+    pub from: Vec<i64>,
+}
+
+impl From<Vec<RPCOrphanTx>> for OrphanTxs {
+    fn from(orphans: Vec<RPCOrphanTx>) -> Self {
+        OrphanTxs {
+            orphans: orphans.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<RPCOrphanTx> for OrphanTx {
+    fn from(orphan: RPCOrphanTx) -> Self {
+        OrphanTx {
+            txid: orphan.txid,
+            wtxid: orphan.wtxid,
+            vsize: orphan.vsize,
+            entry: orphan.entry,
+            expiration: orphan.expiration,
+            from: orphan.from,
+        }
+    }
+}
+
+impl RawMempool {
+    pub fn new(txids: Vec<String>) -> Self {
+        RawMempool {
+            txids,
+            chunk_index: None,
+            chunk_total: None,
+        }
+    }
+
+    /// Splits the txids into chunks with an encoded size of at most
+    /// `max_bytes` each, like [PeerInfos::into_chunks]. Returns the
+    /// RawMempool unchanged if it's small enough.
+    pub fn into_chunks(self, max_bytes: usize) -> Vec<RawMempool> {
+        if self.encoded_len() <= max_bytes {
+            return vec![self];
+        }
+
+        let chunks = split_into_chunks(self.txids, max_bytes, String::len);
+        let total = util::clamp_u32("chunk_total", chunks.len());
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, txids)| RawMempool {
+                txids,
+                chunk_index: Some(index as u32),
+                chunk_total: Some(total),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for RawMempool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // the txids are left out, the mempool can contain many transactions
+        write!(f, "RawMempool(txs={}", self.txids.len())?;
+        if let (Some(index), Some(total)) = (self.chunk_index, self.chunk_total) {
+            write!(f, ", chunk={}/{}", index + 1, total)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for OrphanTxs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let orphan_strs: Vec<String> = self.orphans.iter().map(|o| o.to_string()).collect();
+        write!(f, "OrphanTxs([{}])", orphan_strs.join(", "))
+    }
+}
+
+impl fmt::Display for OrphanTx {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let from_strs: Vec<String> = self.from.iter().map(|id| id.to_string()).collect();
+        write!(
+            f,
+            "OrphanTx(txid={}, vsize={}, from=[{}])",
+            self.txid,
+            self.vsize,
+            from_strs.join(", ")
+        )
+    }
+}
+
+/// A getrawaddrman RPC response. getrawaddrman is a hidden RPC not covered by
+/// corepc. The entries of the tables are keyed by "bucket/position".
+#[derive(Debug, Deserialize)]
+pub struct RPCRawAddrMan {
+    pub new: BTreeMap<String, RPCRawAddrManEntry>,
+    pub tried: BTreeMap<String, RPCRawAddrManEntry>,
+}
+
+/// An entry of a getrawaddrman RPC response. Older Bitcoin Core versions
+/// don't report the networks of the address and its source, they are empty
+/// then.
+#[derive(Debug, Deserialize)]
+pub struct RPCRawAddrManEntry {
+    pub address: String,
+    pub mapped_as: Option<u32>,
+    pub port: u32,
+    #[serde(default)]
+    pub network: String,
+    pub services: u64,
+    pub time: i64,
+    pub source: String,
+    #[serde(default)]
+    pub source_network: String,
+    pub source_mapped_as: Option<u32>,
+}
+
+impl From<RPCRawAddrMan> for RawAddrMan {
+    fn from(addrman: RPCRawAddrMan) -> Self {
+        let entries = |table: BTreeMap<String, RPCRawAddrManEntry>| {
+            table
+                .into_iter()
+                .filter_map(|(key, entry)| RawAddrManEntry::new(&key, entry))
+                .collect()
+        };
+        RawAddrMan {
+            new_entries: entries(addrman.new),
+            tried_entries: entries(addrman.tried),
+        }
+    }
+}
+
+impl RawAddrManEntry {
+    /// Creates an entry from its "bucket/position" key and the RPC entry.
+    /// Entries with an invalid key are skipped with a warning, as bucket and
+    /// position 0 are valid.
+    fn new(key: &str, entry: RPCRawAddrManEntry) -> Option<Self> {
+        let Some((bucket, position)) = key
+            .split_once('/')
+            .and_then(|(bucket, position)| Some((bucket.parse().ok()?, position.parse().ok()?)))
+        else {
+            log::warn!(
+                "Skipping the getrawaddrman entry of {} with the invalid key '{}'",
+                entry.address,
+                key
+            );
+            return None;
+        };
+        Some(RawAddrManEntry {
+            bucket,
+            position,
+            address: entry.address,
+            port: entry.port,
+            network: entry.network,
+            services: entry.services,
+            time: entry.time,
+            mapped_as: entry.mapped_as,
+            source: entry.source,
+            source_network: entry.source_network,
+            source_mapped_as: entry.source_mapped_as,
+        })
+    }
+}
+
+impl fmt::Display for RawAddrMan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RawAddrMan(new={}, tried={})",
+            self.new_entries.len(),
+            self.tried_entries.len()
+        )
+    }
+}
+
+impl fmt::Display for RawAddrManEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RawAddrManEntry({}/{}: {}:{}, network={}, services={}, time={}, source={})",
+            self.bucket,
+            self.position,
+            self.address,
+            self.port,
+            self.network,
+            self.services,
+            self.time,
+            self.source
+        )
+    }
+}
+
+/// An entry of a getnodeaddresses RPC response. Older Bitcoin Core versions
+/// don't report the network of the address.
+#[derive(Debug, Deserialize)]
+pub struct RPCNodeAddress {
+    pub time: i64,
+    pub services: u64,
+    pub address: String,
+    pub port: u32,
+    pub network: Option<String>,
+}
+
+impl From<Vec<RPCNodeAddress>> for NodeAddresses {
+    fn from(addresses: Vec<RPCNodeAddress>) -> Self {
+        NodeAddresses {
+            addresses: addresses.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<RPCNodeAddress> for NodeAddress {
+    fn from(address: RPCNodeAddress) -> Self {
+        NodeAddress {
+            address: address.address,
+            port: address.port,
+            services: address.services,
+            time: address.time,
+            network: address.network,
+        }
+    }
+}
+
+impl fmt::Display for NodeAddresses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NodeAddresses({})", self.addresses.len())
+    }
+}
+
+impl fmt::Display for NodeAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NodeAddress({}:{}, network={}, services={}, time={})",
+            self.address,
+            self.port,
+            self.network.as_deref().unwrap_or("unknown"),
+            self.services,
+            self.time
+        )
+    }
+}
+
+/// The result of a finished `scantxoutset start` RPC call. The unspents
+/// themselves aren't published, only their number.
+#[derive(Debug, Deserialize)]
+pub struct RPCScanTxOutSet {
+    pub success: bool,
+    pub txouts: u64,
+    pub height: u64,
+    pub bestblock: String,
+    pub unspents: Vec<serde_json::Value>,
+    pub total_amount: f64,
+}
+
+/// The result of a `scantxoutset status` RPC call while a scan is running.
+/// The RPC returns null if no scan is running.
+#[derive(Debug, Deserialize)]
+pub struct RPCScanTxOutSetStatus {
+    pub progress: f64,
+}
+
+impl ScanProgress {
+    pub fn started() -> Self {
+        ScanProgress {
+            state: ScanState::Started.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn running(status: RPCScanTxOutSetStatus) -> Self {
+        ScanProgress {
+            state: ScanState::Running.into(),
+            progress: Some(status.progress),
+            ..Default::default()
+        }
+    }
+
+    pub fn failed(error: String) -> Self {
+        ScanProgress {
+            state: ScanState::Failed.into(),
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<RPCScanTxOutSet> for ScanProgress {
+    fn from(result: RPCScanTxOutSet) -> Self {
+        ScanProgress {
+            state: ScanState::Finished.into(),
+            progress: Some(100.0),
+            success: Some(result.success),
+            height: Some(result.height),
+            bestblock: Some(result.bestblock),
+            txouts: Some(result.txouts),
+            unspents: Some(util::clamp_u64("unspents", result.unspents.len())),
+            total_amount: bitcoin::Amount::from_btc(result.total_amount)
+                .ok()
+                .map(|amount| amount.to_sat()),
+            error: None,
+        }
+    }
+}
+
+impl fmt::Display for ScanProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ScanProgress(state={:?}", self.state())?;
+        if let Some(progress) = self.progress {
+            write!(f, ", progress={:.2}%", progress)?;
+        }
+        if let Some(success) = self.success {
+            write!(f, ", success={}", success)?;
+        }
+        if let Some(height) = self.height {
+            write!(f, ", height={}", height)?;
+        }
+        if let Some(txouts) = self.txouts {
+            write!(f, ", txouts={}", txouts)?;
+        }
+        if let Some(unspents) = self.unspents {
+            write!(f, ", unspents={}", unspents)?;
+        }
+        if let Some(total_amount) = self.total_amount {
+            write!(f, ", total_amount={}sat", total_amount)?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, ", error={}", error)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl From<RPCGetMempoolAncestors> for Txids {
+    fn from(ancestors: RPCGetMempoolAncestors) -> Self {
+        Txids { txids: ancestors.0 }
+    }
+}
+
+impl From<RPCGetMempoolDescendants> for Txids {
+    fn from(descendants: RPCGetMempoolDescendants) -> Self {
+        Txids {
+            txids: descendants.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(inbound: bool, network: &str, connection_type: &str) -> PeerInfo {
+        PeerInfo {
+            inbound,
+            network: network.to_string(),
+            connection_type: connection_type.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_peer_info_display() {
+        let display = |id: u32, address: &str, network: &str| {
+            PeerInfo {
+                id,
+                address: address.to_string(),
+                network: network.to_string(),
+                ..Default::default()
+            }
+            .to_string()
+        };
+        assert_eq!(
+            display(1, "1.2.3.4:8333", "ipv4"),
+            "PeerInfo(id=1, address=1.2.3.4:8333, network=ipv4)"
+        );
+        assert_eq!(
+            display(2, "[2001:db8:0::1]:8333", "ipv6"),
+            "PeerInfo(id=2, address=[2001:db8::1]:8333, network=ipv6)"
+        );
+        assert_eq!(
+            display(3, "abcdefghijklmnop.onion:8333", "onion"),
+            "PeerInfo(id=3, address=abcdefghijklmnop.onion:8333, network=onion)"
+        );
+        assert_eq!(
+            display(4, "abcdefgh.b32.i2p:0", "i2p"),
+            "PeerInfo(id=4, address=abcdefgh.b32.i2p:0, network=i2p)"
+        );
+        assert_eq!(
+            display(5, "127.0.0.1:51234", ""),
+            "PeerInfo(id=5, address=127.0.0.1:51234, network=unknown)"
+        );
+    }
+
+    #[test]
+    fn test_connection_churn() {
+        let peer = |id: u32, connection_time: i64| PeerInfo {
+            id,
+            address: format!("10.0.0.{}:8333", id),
+            connection_type: "outbound-full-relay".to_string(),
+            connection_time,
+            ..Default::default()
+        };
+        let previous = PeerInfos {
+            infos: vec![peer(1, 1000), peer(2, 1100), peer(3, 1200)],
+            ..Default::default()
+        }
+        .churn_peers();
+        let current = PeerInfos {
+            // peer 2 disconnected, peer 3 reconnected with the same id after
+            // a node restart, and peer 4 connected
+            infos: vec![peer(1, 1000), peer(3, 1500), peer(4, 1550)],
+            ..Default::default()
+        }
+        .churn_peers();
+
+        let churn = ConnectionChurn::between(&previous, &current, 1600);
+        let ids = |peers: &[ChurnPeer]| peers.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(&churn.connected), vec![3, 4]);
+        assert_eq!(ids(&churn.disconnected), vec![2, 3]);
+        assert_eq!(churn.connected[1].session_duration, 50);
+        assert_eq!(churn.disconnected[0].session_duration, 500);
+        assert_eq!(
+            churn.connected[1].to_string(),
+            "ChurnPeer(id=4, address=10.0.0.4:8333, type=outbound-full-relay, session=50s)"
+        );
+
+        // no churn between equal samples
+        let churn = ConnectionChurn::between(&current, &current, 1600);
+        assert!(churn.connected.is_empty());
+        assert!(churn.disconnected.is_empty());
+        assert_eq!(
+            churn.to_string(),
+            "ConnectionChurn(connected=[], disconnected=[])"
+        );
+    }
+
+    #[test]
+    fn test_omit_unchanged_per_message() {
+        let peer = |id: u32, connection_time: i64, tx_bytes: u64| PeerInfo {
+            id,
+            connection_time,
+            bytes_sent_per_message: [("tx".to_string(), tx_bytes)].into(),
+            bytes_received_per_message: [("ping".to_string(), 32)].into(),
+            ..Default::default()
+        };
+        let omitted = |infos: &PeerInfos| {
+            infos
+                .infos
+                .iter()
+                .filter(|i| i.per_message_omitted == Some(true))
+                .map(|i| i.id)
+                .collect::<Vec<_>>()
+        };
+        let mut published = BTreeMap::new();
+
+        // the first sample is published in full
+        let mut infos = PeerInfos::new(vec![peer(1, 1000, 100), peer(2, 1100, 200)]);
+        infos.omit_unchanged_per_message(&mut published);
+        assert!(omitted(&infos).is_empty());
+        assert_eq!(published.len(), 2);
+
+        // peer 1 is unchanged, peer 2 sent a tx
+        let mut infos = PeerInfos::new(vec![peer(1, 1000, 100), peer(2, 1100, 300)]);
+        infos.omit_unchanged_per_message(&mut published);
+        assert_eq!(omitted(&infos), vec![1]);
+        assert!(infos.infos[0].bytes_sent_per_message.is_empty());
+        assert!(infos.infos[0].bytes_received_per_message.is_empty());
+        assert_eq!(infos.infos[1].bytes_sent_per_message["tx"], 300);
+        assert_eq!(infos.infos[1].per_message_omitted, None);
+
+        // peer 1 is still unchanged since it was last published, peer 2
+        // reconnected with the same id and the same counters, and peer 3 is
+        // new
+        let mut infos = PeerInfos::new(vec![
+            peer(1, 1000, 100),
+            peer(2, 1500, 300),
+            peer(3, 1600, 0),
+        ]);
+        infos.omit_unchanged_per_message(&mut published);
+        assert_eq!(omitted(&infos), vec![1]);
+
+        // disconnected peers are forgotten
+        let mut infos = PeerInfos::new(vec![peer(3, 1600, 0)]);
+        infos.omit_unchanged_per_message(&mut published);
+        assert_eq!(omitted(&infos), vec![3]);
+        assert_eq!(published.keys().copied().collect::<Vec<_>>(), vec![3]);
+
+        let mut infos = PeerInfos::new(vec![peer(1, 1000, 100), peer(3, 1600, 0)]);
+        infos.omit_per_message();
+        assert_eq!(omitted(&infos), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_peer_infos_sorted_by_id() {
+        let peer = |id: u32| PeerInfo {
+            id,
+            ..Default::default()
+        };
+        let infos = PeerInfos::new(vec![peer(7), peer(2), peer(11), peer(0)]);
+        let ids: Vec<u32> = infos.infos.iter().map(|i| i.id).collect();
+        assert_eq!(ids, vec![0, 2, 7, 11]);
+        assert_eq!(infos.chunk_index, None);
+    }
+
+    #[test]
+    fn test_peer_infos_chunks() {
+        let peer = |id: u32| PeerInfo {
+            id,
+            address: format!("10.0.0.{}:8333", id),
+            subversion: "/Satoshi:29.0.0/".repeat(10),
+            ..Default::default()
+        };
+        let infos = PeerInfos {
+            infos: (0..100).map(peer).collect(),
+            ..Default::default()
+        };
+        let ids = |infos: &PeerInfos| infos.infos.iter().map(|i| i.id).collect::<Vec<_>>();
+
+        // small enough, not split
+        let chunks = infos.clone().into_chunks(infos.encoded_len());
+        assert_eq!(chunks, vec![infos.clone()]);
+
+        let max_bytes = infos.encoded_len() / 3;
+        let chunks = infos.clone().into_chunks(max_bytes);
+        assert!(chunks.len() >= 3);
+        for (index, chunk) in chunks.iter().enumerate() {
+            assert!(chunk.encoded_len() <= max_bytes);
+            assert_eq!(chunk.chunk_index, Some(index as u32));
+            assert_eq!(chunk.chunk_total, Some(chunks.len() as u32));
+        }
+
+        // a single peer larger than max_bytes gets its own chunk
+        let chunks = infos.clone().into_chunks(10);
+        assert_eq!(chunks.len(), 100);
+        assert_eq!(ids(&chunks[42]), vec![42]);
+
+        let mut assembler = PeerInfosAssembler::default();
+        let chunks = infos.clone().into_chunks(max_bytes);
+        let last = chunks.len() - 1;
+        for chunk in chunks[..last].iter() {
+            assert_eq!(assembler.push("rpc", chunk.clone()), None);
+        }
+        // chunks of another node don't interfere
+        assert_eq!(assembler.push("rpc.node2", chunks[0].clone()), None);
+        assert_eq!(
+            assembler.push("rpc", chunks[last].clone()),
+            Some(infos.clone())
+        );
+
+        // unsplit PeerInfos are passed through
+        assert_eq!(assembler.push("rpc", infos.clone()), Some(infos.clone()));
+
+        // an incomplete response is dropped
+        assert_eq!(assembler.push("rpc", chunks[0].clone()), None);
+        assert_eq!(assembler.push("rpc", chunks[2].clone()), None);
+        assert_eq!(assembler.push("rpc", chunks[last].clone()), None);
+        // the next complete response is reassembled again
+        for chunk in chunks[..last].iter() {
+            assert_eq!(assembler.push("rpc", chunk.clone()), None);
+        }
+        assert_eq!(assembler.push("rpc", chunks[last].clone()), Some(infos));
+    }
+
+    #[test]
+    fn test_raw_mempool_chunks() {
+        let txids: Vec<String> = (0..1000).map(|i| format!("{:064x}", i)).collect();
+        let mempool = RawMempool::new(txids.clone());
+
+        // small enough, not split
+        assert_eq!(
+            mempool.clone().into_chunks(mempool.encoded_len()),
+            vec![mempool.clone()]
+        );
+
+        let max_bytes = 10_000;
+        let chunks = mempool.clone().into_chunks(max_bytes);
+        // 151 txids of 66 encoded bytes each per chunk
+        assert_eq!(chunks.len(), 7);
+        for (index, chunk) in chunks.iter().enumerate() {
+            assert!(chunk.encoded_len() <= max_bytes);
+            assert_eq!(chunk.chunk_index, Some(index as u32));
+            assert_eq!(chunk.chunk_total, Some(chunks.len() as u32));
+        }
+        assert_eq!(
+            chunks
+                .iter()
+                .flat_map(|c| c.txids.clone())
+                .collect::<Vec<_>>(),
+            txids
+        );
+        assert_eq!(
+            chunks[1].to_string(),
+            format!("RawMempool(txs=151, chunk=2/{})", chunks.len())
+        );
+    }
+
+    #[test]
+    fn test_peer_info_summary() {
+        let infos = PeerInfos {
+            infos: vec![
+                peer(true, "ipv4", "inbound"),
+                peer(true, "ipv4", "inbound"),
+                peer(true, "onion", "inbound"),
+                peer(false, "ipv4", "outbound-full-relay"),
+                peer(false, "ipv4", "block-relay-only"),
+                peer(false, "ipv4", "block-relay-only"),
+            ],
+            ..Default::default()
+        };
+
+        let summary = infos.summary();
+        assert_eq!(summary.total, 6);
+        assert_eq!(
+            summary.buckets,
+            vec![
+                PeerInfoSummaryBucket {
+                    inbound: false,
+                    network: "ipv4".to_string(),
+                    connection_type: "block-relay-only".to_string(),
+                    count: 2,
+                },
+                PeerInfoSummaryBucket {
+                    inbound: false,
+                    network: "ipv4".to_string(),
+                    connection_type: "outbound-full-relay".to_string(),
+                    count: 1,
+                },
+                PeerInfoSummaryBucket {
+                    inbound: true,
+                    network: "ipv4".to_string(),
+                    connection_type: "inbound".to_string(),
+                    count: 2,
+                },
+                PeerInfoSummaryBucket {
+                    inbound: true,
+                    network: "onion".to_string(),
+                    connection_type: "inbound".to_string(),
+                    count: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_network_peer_stats() {
+        let traffic = |inbound: bool, network: &str, sent: u64, received: u64| PeerInfo {
+            bytes_sent: sent,
+            bytes_received: received,
+            ..peer(inbound, network, "")
+        };
+        let infos = PeerInfos {
+            infos: vec![
+                traffic(true, "ipv4", 100, 1000),
+                traffic(false, "onion", 200, 2000),
+                traffic(false, "ipv4", 300, 3000),
+                traffic(true, "i2p", 400, 4000),
+                traffic(true, "", 500, 5000),
+                traffic(false, "onion", 600, 6000),
+            ],
+            ..Default::default()
+        };
+
+        let stats = infos.network_stats();
+        let entry =
+            |network: &str, inbound, outbound, bytes_sent, bytes_received| NetworkPeerStatsEntry {
+                network: network.to_string(),
+                peers: inbound + outbound,
+                inbound,
+                outbound,
+                bytes_sent,
+                bytes_received,
+            };
+        assert_eq!(
+            stats.networks,
+            vec![
+                entry("i2p", 1, 0, 400, 4000),
+                entry("ipv4", 1, 1, 400, 4000),
+                entry("onion", 0, 2, 800, 8000),
+                entry("unknown", 1, 0, 500, 5000),
+            ]
+        );
+        assert_eq!(
+            stats.networks[0].to_string(),
+            "NetworkPeerStatsEntry(network=i2p, peers=1, inbound=1, outbound=0, bytes_sent=400, bytes_received=4000)"
+        );
+        assert!(PeerInfos::default().network_stats().networks.is_empty());
+    }
+
+    fn net_totals(received: u64, sent: u64, time_millis: u64) -> NetTotals {
+        NetTotals {
+            total_bytes_received: received,
+            total_bytes_sent: sent,
+            time_millis,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_net_totals_rates() {
+        let previous = net_totals(1000, 500, 10_000);
+        let mut current = net_totals(3000, 1500, 12_000);
+        current.set_rates_since(&previous);
+        assert_eq!(current.recv_bytes_per_sec, Some(1000.0));
+        assert_eq!(current.sent_bytes_per_sec, Some(500.0));
+    }
+
+    #[test]
+    fn test_net_totals_rates_counter_reset() {
+        // the node restarted and the counters were reset
+        let previous = net_totals(1000, 500, 10_000);
+        let mut current = net_totals(100, 50, 12_000);
+        current.set_rates_since(&previous);
+        assert_eq!(current.recv_bytes_per_sec, None);
+        assert_eq!(current.sent_bytes_per_sec, None);
+
+        // the time didn't advance
+        let mut current = net_totals(3000, 1500, 10_000);
+        current.set_rates_since(&previous);
+        assert_eq!(current.recv_bytes_per_sec, None);
+        assert_eq!(current.sent_bytes_per_sec, None);
+    }
+
+    fn mempool_info(mempoolminfee: f64, loaded: bool) -> MempoolInfo {
+        MempoolInfo {
+            loaded,
+            mempoolminfee,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mempool_info_trend() {
+        let previous = mempool_info(0.00001, true);
+        let mut current = mempool_info(0.00001234, true);
+        current.set_trend_since(&previous);
+        assert_eq!(current.mempoolminfee_delta, Some(0.00000234));
+        assert_eq!(current.mempoolminfee_trend(), FeeTrend::Rising);
+
+        let mut current = mempool_info(0.000001, true);
+        current.set_trend_since(&previous);
+        assert_eq!(current.mempoolminfee_delta, Some(-0.000009));
+        assert_eq!(current.mempoolminfee_trend(), FeeTrend::Falling);
+
+        // floating point noise isn't a change
+        let mut current = mempool_info(0.00001 + 1e-15, true);
+        current.set_trend_since(&previous);
+        assert_eq!(current.mempoolminfee_delta, Some(0.0));
+        assert_eq!(current.mempoolminfee_trend(), FeeTrend::Unchanged);
+    }
+
+    #[test]
+    fn test_mempool_info_trend_not_loaded() {
+        // the node restarted and is loading the mempool
+        let previous = mempool_info(0.00002, true);
+        let mut current = mempool_info(0.000001, false);
+        current.set_trend_since(&previous);
+        assert_eq!(current.mempoolminfee_delta, None);
+        assert_eq!(current.mempoolminfee_trend, None);
+
+        let mut next = mempool_info(0.000001, true);
+        next.set_trend_since(&current);
+        assert_eq!(next.mempoolminfee_delta, None);
+    }
+
+    #[test]
+    fn test_peer_network_from_str() {
+        assert_eq!(PeerNetwork::from("ipv4"), PeerNetwork::Ipv4);
+        assert_eq!(PeerNetwork::from("onion"), PeerNetwork::Onion);
+        assert_eq!(
+            PeerNetwork::from("not_publicly_routable"),
+            PeerNetwork::NotPubliclyRoutable
+        );
+        assert_eq!(PeerNetwork::from(""), PeerNetwork::Unknown);
+        assert_eq!(PeerNetwork::from("ipv5"), PeerNetwork::Unknown);
+    }
+
+    #[test]
+    fn test_peer_connection_type_from_str() {
+        assert_eq!(
+            PeerConnectionType::from("inbound"),
+            PeerConnectionType::Inbound
+        );
+        assert_eq!(
+            PeerConnectionType::from("outbound-full-relay"),
+            PeerConnectionType::OutboundFullRelay
+        );
+        assert_eq!(
+            PeerConnectionType::from("feeler"),
+            PeerConnectionType::Feeler
+        );
+        assert_eq!(
+            PeerConnectionType::from("private-broadcast"),
+            PeerConnectionType::Unknown
+        );
+    }
+
+    #[test]
+    fn test_compact_peer_info() {
+        let info = PeerInfo {
+            id: 7,
+            address: "127.0.0.1:8333".to_string(),
+            inbound: true,
+            services: "0000000000000409".to_string(),
+            bytes_sent: 100,
+            bytes_received: 200,
+            connection_time: 1700000000,
+            subversion: "/Satoshi:29.0.0/".to_string(),
+            ping_time: 12.5,
+            ..Default::default()
+        };
+        assert_eq!(
+            CompactPeerInfo::from(info),
+            CompactPeerInfo {
+                id: 7,
+                address: "127.0.0.1:8333".to_string(),
+                inbound: true,
+                services: "0000000000000409".to_string(),
+                bytes_sent: 100,
+                bytes_received: 200,
+                connection_time: 1700000000,
+                subversion: "/Satoshi:29.0.0/".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_orphan_txs_from_rpc() {
+        let json = r#"[
+            {"txid": "aa", "wtxid": "bb", "bytes": 200, "vsize": 150, "weight": 600, "entry": 1700000000, "expiration": 1700001200, "from": [3, 7]},
+            {"txid": "cc", "wtxid": "dd", "bytes": 100, "vsize": 100, "weight": 400, "from": [5]}
+        ]"#;
+        let orphans: Vec<RPCOrphanTx> = serde_json::from_str(json).unwrap();
+        let orphans: OrphanTxs = orphans.into();
+        assert_eq!(orphans.orphans.len(), 2);
+        assert_eq!(orphans.orphans[0].entry, Some(1700000000));
+        assert_eq!(orphans.orphans[0].from, vec![3, 7]);
+        assert_eq!(orphans.orphans[1].entry, None);
+        assert_eq!(orphans.orphans[1].vsize, 100);
+        assert_eq!(
+            orphans.to_string(),
+            "OrphanTxs([OrphanTx(txid=aa, vsize=150, from=[3, 7]), OrphanTx(txid=cc, vsize=100, from=[5])])"
+        );
+    }
+
+    #[test]
+    fn test_raw_addrman_from_rpc() {
+        let json = r#"{
+            "new": {
+                "12/34": {"address": "1.2.3.4", "port": 8333, "network": "ipv4", "services": 1033, "time": 1700000000, "source": "5.6.7.8", "source_network": "ipv4"},
+                "invalid": {"address": "::1", "port": 18444, "network": "ipv6", "services": 0, "time": 1, "source": "::1", "source_network": "ipv6"}
+            },
+            "tried": {
+                "200/3": {"address": "1.2.3.5", "mapped_as": 64496, "port": 8333, "network": "ipv4", "services": 9, "time": 1700000001, "source": "5.6.7.8", "source_network": "ipv4", "source_mapped_as": 64497}
+            }
+        }"#;
+        let addrman: RPCRawAddrMan = serde_json::from_str(json).unwrap();
+        let addrman: RawAddrMan = addrman.into();
+        // the entry with the invalid key is skipped
+        assert_eq!(addrman.new_entries.len(), 1);
+        assert_eq!(addrman.tried_entries.len(), 1);
+        assert_eq!(
+            addrman.new_entries[0].to_string(),
+            "RawAddrManEntry(12/34: 1.2.3.4:8333, network=ipv4, services=1033, time=1700000000, source=5.6.7.8)"
+        );
+        assert_eq!(addrman.new_entries[0].mapped_as, None);
+        let tried = &addrman.tried_entries[0];
+        assert_eq!((tried.bucket, tried.position), (200, 3));
+        assert_eq!(tried.mapped_as, Some(64496));
+        assert_eq!(tried.source_mapped_as, Some(64497));
+        assert_eq!(addrman.to_string(), "RawAddrMan(new=1, tried=1)");
+    }
+
+    #[test]
+    fn test_raw_addrman_from_rpc_without_networks() {
+        // older Bitcoin Core versions don't report the networks
+        let json = r#"{
+            "new": {
+                "12/34": {"address": "1.2.3.4", "port": 8333, "services": 1033, "time": 1700000000, "source": "5.6.7.8"}
+            },
+            "tried": {}
+        }"#;
+        let addrman: RPCRawAddrMan = serde_json::from_str(json).unwrap();
+        let addrman: RawAddrMan = addrman.into();
+        assert_eq!(addrman.new_entries.len(), 1);
+        let entry = &addrman.new_entries[0];
+        assert_eq!(entry.network, "");
+        assert_eq!(entry.source_network, "");
+        assert_eq!((entry.bucket, entry.position), (12, 34));
+        assert_eq!(entry.source, "5.6.7.8");
+    }
+
+    #[test]
+    fn test_node_addresses_from_rpc() {
+        let json = r#"[
+            {"time": 1700000000, "services": 1033, "address": "1.2.3.4", "port": 8333, "network": "ipv4"},
+            {"time": 1700000001, "services": 9, "address": "2001:db8::1", "port": 8333}
+        ]"#;
+        let addresses: Vec<RPCNodeAddress> = serde_json::from_str(json).unwrap();
+        let addresses: NodeAddresses = addresses.into();
+        assert_eq!(addresses.addresses.len(), 2);
+        assert_eq!(
+            addresses.addresses[0].to_string(),
+            "NodeAddress(1.2.3.4:8333, network=ipv4, services=1033, time=1700000000)"
+        );
+        assert_eq!(addresses.addresses[1].network, None);
+        assert_eq!(
+            addresses.addresses[1].to_string(),
+            "NodeAddress(2001:db8::1:8333, network=unknown, services=9, time=1700000001)"
+        );
+        assert_eq!(addresses.to_string(), "NodeAddresses(2)");
+    }
+
+    #[test]
+    fn test_scan_progress_from_rpc() {
+        let json = r#"{
+            "success": true,
+            "txouts": 185000000,
+            "height": 921000,
+            "bestblock": "00000000000000000001b4a6e9cd8d8a1bc5fe1f0b5fb74f3af9e1e6e1ab8d7a",
+            "unspents": [
+                {"txid": "aa", "vout": 0, "scriptPubKey": "0014", "desc": "addr(bc1q)#abc", "amount": 0.5, "coinbase": false, "height": 920000},
+                {"txid": "bb", "vout": 1, "scriptPubKey": "0014", "desc": "addr(bc1q)#abc", "amount": 0.25, "coinbase": false, "height": 920500}
+            ],
+            "total_amount": 0.75
+        }"#;
+        let result: RPCScanTxOutSet = serde_json::from_str(json).unwrap();
+        let progress: ScanProgress = result.into();
+        assert_eq!(progress.state(), ScanState::Finished);
+        assert_eq!(progress.unspents, Some(2));
+        assert_eq!(progress.total_amount, Some(75_000_000));
+        assert_eq!(
+            progress.to_string(),
+            "ScanProgress(state=Finished, progress=100.00%, success=true, height=921000, txouts=185000000, unspents=2, total_amount=75000000sat)"
+        );
+
+        let status: Option<RPCScanTxOutSetStatus> =
+            serde_json::from_str(r#"{"progress": 42.5}"#).unwrap();
+        let progress = ScanProgress::running(status.unwrap());
+        assert_eq!(
+            progress.to_string(),
+            "ScanProgress(state=Running, progress=42.50%)"
+        );
+        let status: Option<RPCScanTxOutSetStatus> = serde_json::from_str("null").unwrap();
+        assert!(status.is_none());
+    }
+
+    #[test]
+    fn test_peer_info_summary_empty() {
+        let summary = PeerInfos::default().summary();
+        assert_eq!(summary.total, 0);
+        assert!(summary.buckets.is_empty());
+    }
+}
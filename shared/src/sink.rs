@@ -0,0 +1,313 @@
+use crate::nats::decode_payload;
+use crate::protobuf::event::Event;
+use prost::bytes::Bytes;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Number of published messages buffered until their events are written to
+/// the outputs. If the outputs can't keep up, further messages are dropped.
+const OUTPUT_BUFFER_MESSAGES: usize = 4096;
+
+/// An additional output the published events are written to (`--output`),
+/// besides NATS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    /// One JSON object per event and line on stdout.
+    Stdout,
+    /// One JSON object per event and line, appended to the file.
+    File(PathBuf),
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Output::Stdout => write!(f, "stdout"),
+            Output::File(path) => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+/// Parses an `--output` argument: `stdout` or `file:<PATH>`. Can be used as a
+/// clap value parser.
+pub fn parse_output(output: &str) -> Result<Output, String> {
+    match output.split_once(':') {
+        None if output == "stdout" => Ok(Output::Stdout),
+        Some(("file", "")) => Err("missing path in 'file:<PATH>' output".to_string()),
+        Some(("file", path)) => Ok(Output::File(PathBuf::from(path))),
+        _ => Err(format!(
+            "invalid output '{}', expected 'stdout' or 'file:<PATH>'",
+            output
+        )),
+    }
+}
+
+enum Sink {
+    Stdout,
+    File(PathBuf, LineWriter<File>),
+}
+
+impl Sink {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        match self {
+            Sink::Stdout => writeln!(io::stdout().lock(), "{}", line),
+            Sink::File(_, writer) => writeln!(writer, "{}", line),
+        }
+    }
+}
+
+impl fmt::Display for Sink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sink::Stdout => write!(f, "stdout"),
+            Sink::File(path, _) => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+/// Fans the published events out to the configured outputs. The events are
+/// encoded and written on a dedicated thread, so that the blocking writes
+/// don't hold up publishing. A failing output is reported, but doesn't stop
+/// the others or the publishing into NATS. Clones write to the same outputs.
+/// The buffered events are written before the last clone is dropped.
+#[derive(Clone, Default)]
+pub struct MultiSink {
+    writer: Option<Arc<Writer>>,
+}
+
+/// The thread writing to the outputs. It stops once the channel is closed
+/// and all buffered messages are written.
+struct Writer {
+    tx: Option<SyncSender<(String, Bytes)>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        self.tx.take();
+        if let Some(thread) = self.thread.take() {
+            // a panic of the thread was already reported by the panic hook
+            let _ = thread.join();
+        }
+    }
+}
+
+impl MultiSink {
+    /// Opens the outputs. Files are created if they don't exist and appended
+    /// to otherwise.
+    pub fn open(outputs: &[Output]) -> io::Result<Self> {
+        let mut sinks = Vec::with_capacity(outputs.len());
+        for output in outputs {
+            sinks.push(match output {
+                Output::Stdout => Sink::Stdout,
+                Output::File(path) => {
+                    let file = OpenOptions::new().create(true).append(true).open(path)?;
+                    Sink::File(path.clone(), LineWriter::new(file))
+                }
+            });
+            log::info!("Writing the published events to {}", output);
+        }
+        Self::spawn(sinks)
+    }
+
+    /// Starts the thread writing to the sinks.
+    fn spawn(mut sinks: Vec<Sink>) -> io::Result<Self> {
+        if sinks.is_empty() {
+            return Ok(MultiSink::default());
+        }
+        let (tx, rx) = mpsc::sync_channel::<(String, Bytes)>(OUTPUT_BUFFER_MESSAGES);
+        let thread = thread::Builder::new()
+            .name("output-writer".to_string())
+            .spawn(move || {
+                for (subject, payload) in rx {
+                    write_events(&mut sinks, &subject, &payload);
+                }
+            })?;
+        Ok(MultiSink {
+            writer: Some(Arc::new(Writer {
+                tx: Some(tx),
+                thread: Some(thread),
+            })),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writer.is_none()
+    }
+
+    /// Hands a message published on the subject to the thread writing its
+    /// events to all outputs, one JSON object with the subject and the event
+    /// per line. Doesn't block: if the outputs can't keep up, the message is
+    /// dropped.
+    pub fn write(&self, subject: &str, payload: Bytes) {
+        let Some(tx) = self.writer.as_ref().and_then(|writer| writer.tx.as_ref()) else {
+            return;
+        };
+        match tx.try_send((subject.to_string(), payload)) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => log::warn!(
+                "Dropping the events published on '{}' for the outputs: writing them can't keep up",
+                subject
+            ),
+            Err(TrySendError::Disconnected(_)) => log::error!(
+                "Dropping the events published on '{}' for the outputs: the output writer stopped",
+                subject
+            ),
+        }
+    }
+}
+
+/// Writes the events in a message published on the subject to the sinks.
+fn write_events(sinks: &mut [Sink], subject: &str, payload: &[u8]) {
+    let events = match decode_payload(subject, payload) {
+        Ok(events) => events,
+        Err(e) => {
+            log::warn!(
+                "Could not decode the events published on '{}': {}",
+                subject,
+                e
+            );
+            return;
+        }
+    };
+    let lines: Vec<String> = events
+        .iter()
+        .map(|event| json_line(subject, event))
+        .collect();
+    for sink in sinks.iter_mut() {
+        for line in lines.iter() {
+            if let Err(e) = sink.write_line(line) {
+                log::error!("Could not write event to output {}: {}", sink, e);
+                // skip the remaining events of the message for this output
+                break;
+            }
+        }
+    }
+}
+
+fn json_line(subject: &str, event: &Event) -> String {
+    serde_json::json!({
+        "subject": subject,
+        "event": event,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::event::EventBatch;
+    use crate::testing::events::event;
+    use prost::Message;
+    use std::fs;
+    use std::path::Path;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "peer-observer-sink-test-{}-{}.jsonl",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn timestamps(path: &Path) -> Vec<(String, u64)> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                (
+                    value["subject"].as_str().unwrap().to_string(),
+                    value["event"]["timestamp"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_output() {
+        assert_eq!(parse_output("stdout"), Ok(Output::Stdout));
+        assert_eq!(
+            parse_output("file:/tmp/events.jsonl"),
+            Ok(Output::File(PathBuf::from("/tmp/events.jsonl")))
+        );
+        assert_eq!(
+            parse_output("file:/tmp/events.jsonl").unwrap().to_string(),
+            "file:/tmp/events.jsonl"
+        );
+        assert!(parse_output("file:").is_err());
+        assert!(parse_output("nats").is_err());
+        assert!(parse_output("stdout:x").is_err());
+    }
+
+    #[test]
+    fn test_multi_sink() {
+        let first = temp_path("first");
+        let second = temp_path("second");
+        let sink =
+            MultiSink::open(&[Output::File(first.clone()), Output::File(second.clone())]).unwrap();
+
+        sink.write("rpc", event(1).encode_to_vec().into());
+        let batch = EventBatch {
+            events: vec![event(2), event(3)],
+        };
+        sink.clone()
+            .write("netmsg.batch", batch.encode_to_vec().into());
+        // the buffered events are written before the last clone is dropped
+        drop(sink);
+
+        let expected = vec![
+            ("rpc".to_string(), 1),
+            ("netmsg.batch".to_string(), 2),
+            ("netmsg.batch".to_string(), 3),
+        ];
+        assert_eq!(timestamps(&first), expected);
+        assert_eq!(timestamps(&second), expected);
+
+        // the file is appended to when opened again
+        MultiSink::open(&[Output::File(first.clone())])
+            .unwrap()
+            .write("rpc", event(4).encode_to_vec().into());
+        assert_eq!(timestamps(&first).len(), 4);
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn test_multi_sink_failing_output() {
+        let failing = temp_path("failing");
+        fs::write(&failing, "").unwrap();
+        let working = temp_path("working");
+        let working_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&working)
+            .unwrap();
+        let sink = MultiSink::spawn(vec![
+            // opened read-only, so all writes fail
+            Sink::File(
+                failing.clone(),
+                LineWriter::new(File::open(&failing).unwrap()),
+            ),
+            Sink::File(working.clone(), LineWriter::new(working_file)),
+        ])
+        .unwrap();
+
+        sink.write("rpc", event(1).encode_to_vec().into());
+        sink.write("rpc", event(2).encode_to_vec().into());
+        drop(sink);
+        assert_eq!(fs::read_to_string(&failing).unwrap(), "");
+        assert_eq!(
+            timestamps(&working),
+            vec![("rpc".to_string(), 1), ("rpc".to_string(), 2)]
+        );
+        fs::remove_file(failing).unwrap();
+        fs::remove_file(working).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+use crate::nats::{parse_single_event_subject, DecodeError};
+use crate::nats_subjects::ParsedSubject;
+use crate::protobuf::event::Event;
+use async_nats::Message;
+use prost::bytes::Bytes;
+use prost::Message as _;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+/// Caches the recently decoded events by their payload, so that consumers in
+/// the same process that receive the same message, e.g. multiple
+/// subscriptions on overlapping subjects, decode it only once. Clones share
+/// the same cache.
+#[derive(Clone)]
+pub struct DecodeCache {
+    capacity: usize,
+    inner: Arc<Mutex<Cached>>,
+}
+
+#[derive(Default)]
+struct Cached {
+    // payload hash -> payload and event; the payload is compared on lookup
+    // as different payloads can have the same hash
+    events: HashMap<u64, (Bytes, Arc<Event>)>,
+    // payload hashes, oldest first
+    order: VecDeque<u64>,
+}
+
+impl DecodeCache {
+    /// Creates a cache retaining up to `capacity` decoded events. A cache
+    /// with a capacity of zero is disabled and decodes every message.
+    pub fn new(capacity: usize) -> Self {
+        DecodeCache {
+            capacity,
+            inner: Arc::new(Mutex::new(Cached::default())),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Like [crate::nats::decode_message], but returns the cached event if a
+    /// message with the same payload was decoded recently. The subject is
+    /// validated for every message.
+    pub fn decode_message(
+        &self,
+        msg: &Message,
+        prefix: Option<&str>,
+    ) -> Result<Arc<Event>, DecodeError> {
+        self.decode_message_with_subject(msg, prefix)
+            .map(|(_, event)| event)
+    }
+
+    /// Like [crate::nats::decode_message_with_subject], but with the cache.
+    pub fn decode_message_with_subject(
+        &self,
+        msg: &Message,
+        prefix: Option<&str>,
+    ) -> Result<(ParsedSubject, Arc<Event>), DecodeError> {
+        let subject = parse_single_event_subject(prefix, &msg.subject)?;
+        if !self.is_enabled() {
+            return Ok((subject, Arc::new(Event::decode(msg.payload.as_ref())?)));
+        }
+        let key = payload_hash(&msg.payload);
+        if let Some((payload, event)) = self.inner.lock().unwrap().events.get(&key) {
+            if *payload == msg.payload {
+                return Ok((subject, event.clone()));
+            }
+        }
+        let event = Arc::new(Event::decode(msg.payload.as_ref())?);
+        self.insert(key, msg.payload.clone(), event.clone());
+        Ok((subject, event))
+    }
+
+    fn insert(&self, key: u64, payload: Bytes, event: Arc<Event>) {
+        let mut cached = self.inner.lock().unwrap();
+        if cached.events.insert(key, (payload, event)).is_some() {
+            // replaced a colliding payload, which keeps its place in the order
+            return;
+        }
+        cached.order.push_back(key);
+        if cached.order.len() > self.capacity {
+            if let Some(oldest) = cached.order.pop_front() {
+                cached.events.remove(&oldest);
+            }
+        }
+    }
+}
+
+fn payload_hash(payload: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    payload.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(subject: &str, timestamp: u64) -> Message {
+        let event = Event {
+            timestamp,
+            peer_observer_event: None,
+            labels: Default::default(),
+        };
+        Message {
+            subject: subject.into(),
+            reply: None,
+            payload: event.encode_to_vec().into(),
+            headers: None,
+            status: None,
+            description: None,
+            length: 0,
+        }
+    }
+
+    #[test]
+    fn test_decode_cache() {
+        let cache = DecodeCache::new(2);
+        let first = cache.decode_message(&message("rpc", 1), None).unwrap();
+        let again = cache
+            .clone()
+            .decode_message(&message("rpc", 1), None)
+            .unwrap();
+        assert_eq!(first.timestamp, 1);
+        assert!(Arc::ptr_eq(&first, &again));
+
+        // the subject is still validated for cached payloads
+        assert!(matches!(
+            cache.decode_message(&message("_INBOX.abc", 1), None),
+            Err(DecodeError::Subject(_))
+        ));
+        let (subject, event) = cache
+            .decode_message_with_subject(&message("rpc.node-1", 1), None)
+            .unwrap();
+        assert_eq!(subject.node_value(), Some("node-1"));
+        assert!(Arc::ptr_eq(&first, &event));
+
+        // the oldest event is dropped
+        cache.decode_message(&message("rpc", 2), None).unwrap();
+        cache.decode_message(&message("rpc", 3), None).unwrap();
+        let decoded = cache.decode_message(&message("rpc", 1), None).unwrap();
+        assert_eq!(decoded.timestamp, 1);
+        assert!(!Arc::ptr_eq(&first, &decoded));
+        assert!(cache.inner.lock().unwrap().events.len() <= 2);
+    }
+
+    #[test]
+    fn test_decode_cache_disabled() {
+        let cache = DecodeCache::new(0);
+        assert!(!cache.is_enabled());
+        let first = cache.decode_message(&message("rpc", 1), None).unwrap();
+        let again = cache.decode_message(&message("rpc", 1), None).unwrap();
+        assert_eq!(first, again);
+        assert!(!Arc::ptr_eq(&first, &again));
+        assert!(cache.inner.lock().unwrap().events.is_empty());
+    }
+}
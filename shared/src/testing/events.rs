@@ -0,0 +1,11 @@
+use crate::protobuf::event::Event;
+
+/// An event without content with the timestamp, e.g. to test the ordering,
+/// batching, or buffering of events.
+pub fn event(timestamp: u64) -> Event {
+    Event {
+        timestamp,
+        peer_observer_event: None,
+        labels: Default::default(),
+    }
+}
@@ -1,3 +1,5 @@
+/// Events to be used in tests.
+pub mod events;
 /// A NATS publisher to be used in integration tests.
 pub mod nats_publisher;
 /// A NATS server runnner to be used in integration tests.
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -23,6 +24,43 @@ pub fn ip_from_ipport(addr: String) -> String {
     }
 }
 
+/// Splits an address as reported by Bitcoin Core (e.g. `1.2.3.4:8333`,
+/// `[2001:db8::1]:8333`, or `abc...xyz.onion:8333`) into host and port. The
+/// brackets around IPv6 hosts are removed. An IPv6 address without brackets
+/// is returned as host without port.
+pub fn split_host_port(addr: &str) -> (&str, Option<u16>) {
+    if let Some(rest) = addr.strip_prefix('[') {
+        if let Some((host, port)) = rest.split_once(']') {
+            return (host, port.strip_prefix(':').and_then(|p| p.parse().ok()));
+        }
+    }
+    match addr.rsplit_once(':') {
+        // more than one colon without brackets: an IPv6 address without port
+        Some((host, _)) if host.contains(':') => (addr, None),
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, Some(port)),
+            Err(_) => (addr, None),
+        },
+        None => (addr, None),
+    }
+}
+
+/// Normalizes an address as reported by Bitcoin Core for display: IP
+/// addresses are printed in their canonical form, IPv6 addresses in brackets
+/// if there is a port, and Tor, I2P, and other hostnames in lowercase.
+pub fn normalize_address(addr: &str) -> String {
+    let (host, port) = split_host_port(addr);
+    let host = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) if port.is_some() => format!("[{}]", ip),
+        Ok(ip) => ip.to_string(),
+        Err(_) => host.to_ascii_lowercase(),
+    };
+    match port {
+        Some(port) => format!("{}:{}", host, port),
+        None => host,
+    }
+}
+
 /// Returns the /24 subnet for IPv4 or the /64 subnet for IPv6 address.
 /// If [ip] is not a valid IPv4 or IPv6 address, the original ip is returned.
 /// This is the case for Tor and I2P addresses.
@@ -52,6 +90,39 @@ pub fn current_timestamp() -> u64 {
         .as_secs()
 }
 
+/// Converts an integer, e.g. from an RPC response, into an u64. Unlike an `as`
+/// cast, values that don't fit don't wrap: negative values are clamped to 0
+/// and too large values to `u64::MAX`. Clamping is logged with the `field`.
+pub fn clamp_u64<T>(field: &str, value: T) -> u64
+where
+    T: TryInto<u64> + PartialOrd + Default + fmt::Display + Copy,
+{
+    clamp(field, value, 0, u64::MAX)
+}
+
+/// Converts an integer into an u32, clamping it like [clamp_u64].
+pub fn clamp_u32<T>(field: &str, value: T) -> u32
+where
+    T: TryInto<u32> + PartialOrd + Default + fmt::Display + Copy,
+{
+    clamp(field, value, 0, u32::MAX)
+}
+
+fn clamp<T, U>(field: &str, value: T, min: U, max: U) -> U
+where
+    T: TryInto<U> + PartialOrd + Default + fmt::Display + Copy,
+    U: fmt::Display,
+{
+    match value.try_into() {
+        Ok(converted) => converted,
+        Err(_) => {
+            let clamped = if value < T::default() { min } else { max };
+            log::warn!("{field}={value} is out of range, clamping it to {clamped}");
+            clamped
+        }
+    }
+}
+
 /// Checks if the IP belongs to the LinkingLion entity.
 pub fn is_on_linkinglion_banlist(ip: &str) -> bool {
     // Since this list is rather small and doesn't update frequently,
@@ -84,6 +155,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(split_host_port("1.2.3.4:8333"), ("1.2.3.4", Some(8333)));
+        assert_eq!(
+            split_host_port("[2001:db8::1]:8333"),
+            ("2001:db8::1", Some(8333))
+        );
+        assert_eq!(split_host_port("[2001:db8::1]"), ("2001:db8::1", None));
+        assert_eq!(split_host_port("2001:db8::1"), ("2001:db8::1", None));
+        assert_eq!(
+            split_host_port("abcdefgh.onion:8333"),
+            ("abcdefgh.onion", Some(8333))
+        );
+        assert_eq!(split_host_port("1.2.3.4"), ("1.2.3.4", None));
+        assert_eq!(split_host_port("host:port"), ("host:port", None));
+    }
+
+    #[test]
+    fn test_normalize_address() {
+        assert_eq!(normalize_address("1.2.3.4:8333"), "1.2.3.4:8333");
+        assert_eq!(
+            normalize_address("[2001:0db8:0000::0001]:8333"),
+            "[2001:db8::1]:8333"
+        );
+        assert_eq!(normalize_address("2001:0db8::0001"), "2001:db8::1");
+        assert_eq!(
+            normalize_address("ABCDEFGH.onion:8333"),
+            "abcdefgh.onion:8333"
+        );
+        assert_eq!(
+            normalize_address("abcdefgh.b32.i2p:0"),
+            "abcdefgh.b32.i2p:0"
+        );
+        assert_eq!(normalize_address(""), "");
+    }
+
     #[test]
     fn test_subnet_24_or_64_or_ip() {
         assert_eq!(subnet(String::from("127.0.0.1")).as_str(), "127.0.0.0");
@@ -176,6 +283,19 @@ mod tests {
         assert!(is_on_linkinglion_banlist("2604:d500:4:1::3:fe"));
     }
 
+    #[test]
+    fn test_clamp() {
+        assert_eq!(clamp_u64("f", 0usize), 0);
+        assert_eq!(clamp_u64("f", usize::MAX), usize::MAX as u64);
+        assert_eq!(clamp_u64("f", -1i64), 0);
+        assert_eq!(clamp_u64("f", i64::MIN), 0);
+        assert_eq!(clamp_u64("f", i64::MAX), i64::MAX as u64);
+        assert_eq!(clamp_u64("f", u128::MAX), u64::MAX);
+        assert_eq!(clamp_u32("f", u32::MAX as usize), u32::MAX);
+        assert_eq!(clamp_u32("f", u32::MAX as u64 + 1), u32::MAX);
+        assert_eq!(clamp_u32("f", -1i32), 0);
+    }
+
     #[test]
     fn test_bitprojects() {
         assert!(!belongs_to_bitprojects(
@@ -11,6 +11,7 @@ use shared::clap::Parser;
 use shared::futures::stream::StreamExt;
 use shared::log;
 use shared::metricserver;
+use shared::nats::{self, parse_nats_address};
 use shared::prost::Message as ProstMessage;
 use shared::protobuf::bitcoin_primitives::address::Address as AddressType;
 use shared::protobuf::bitcoin_primitives::Address;
@@ -21,7 +22,7 @@ use shared::protobuf::event;
 use shared::protobuf::event::event::PeerObserverEvent;
 use shared::simple_logger;
 use shared::util;
-use shared::{async_nats, clap, tokio};
+use shared::{clap, tokio};
 use std::collections::HashMap;
 use std::fmt;
 use std::fs::OpenOptions;
@@ -47,7 +48,8 @@ const RECENT_CONNECTION_DURATION: Duration = Duration::from_secs(60 * 60);
 #[command(version, about, long_about = None)]
 struct Args {
     /// The NATS server address the tool should connect and subscribe to.
-    #[arg(short, long, default_value = "127.0.0.1:4222")]
+    /// Multiple comma-separated addresses, e.g. of the servers in a NATS cluster, can be given.
+    #[arg(short, long, default_value = "127.0.0.1:4222", value_parser = parse_nats_address)]
     nats_address: String,
     /// The metrics server address the tool should listen on.
     #[arg(short, long, default_value = "127.0.0.1:18282")]
@@ -327,7 +329,7 @@ async fn main() {
     metricserver::start(&args.metrics_address, None).unwrap();
     log::info!("metrics-server started on {}", &args.metrics_address);
 
-    let nc = async_nats::connect(args.nats_address)
+    let nc = nats::connect(&args.nats_address)
         .await
         .expect("should be able to connect to NATS server");
     let mut sub = nc
@@ -1,15 +1,16 @@
 #![cfg_attr(feature = "strict", deny(warnings))]
 
+use shared::clap;
 use shared::clap::Parser;
 use shared::futures::stream::StreamExt;
 use shared::log;
-use shared::prost::Message;
+use shared::nats::{self, decode_events, parse_nats_address};
+use shared::nats_subjects::{is_event_subject, parse_subject_prefix};
 use shared::protobuf::ebpf_extractor::ebpf;
 use shared::protobuf::event::event::PeerObserverEvent;
-use shared::protobuf::event::{self, Event};
+use shared::protobuf::event::Event;
 use shared::protobuf::log_extractor::LogDebugCategory;
 use shared::tokio::sync::watch;
-use shared::{async_nats, clap};
 
 use crate::error::RuntimeError;
 
@@ -25,7 +26,8 @@ pub mod error;
 #[command(version, about, long_about = None)]
 pub struct Args {
     /// The NATS server address the tool should connect and subscribe to.
-    #[arg(short, long, default_value = "127.0.0.1:4222")]
+    /// Multiple comma-separated addresses, e.g. of the servers in a NATS cluster, can be given.
+    #[arg(short, long, default_value = "127.0.0.1:4222", value_parser = parse_nats_address)]
     pub nats_address: String,
     /// The log level the tool should run on. Events are logged with
     /// the INFO log level. Valid log levels are "trace", "debug",
@@ -33,6 +35,11 @@ pub struct Args {
     #[arg(short, long, default_value_t = log::Level::Debug)]
     pub log_level: log::Level,
 
+    /// Subscribe to the events published below this subject prefix, e.g. on
+    /// `team-a.rpc` for extractors started with `--nats-subject-prefix team-a`.
+    #[arg(long, value_name = "PREFIX", value_parser = parse_subject_prefix)]
+    pub nats_subject_prefix: Option<String>,
+
     /// If passed, show P2P message events
     #[arg(long)]
     pub messages: bool,
@@ -94,6 +101,7 @@ impl Args {
         Self {
             nats_address,
             log_level,
+            nats_subject_prefix: None,
             messages,
             connections,
             addrman,
@@ -122,16 +130,31 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
     }
 
     log::debug!("Connecting to NATS-server at {}", args.nats_address);
-    let nc = async_nats::connect(args.nats_address.clone()).await?;
-    let mut sub = nc.subscribe("*").await?;
+    let nc = nats::connect(&args.nats_address).await?;
+    let mut sub = nats::subscribe_events(&nc, args.nats_subject_prefix.as_deref()).await?;
     log::info!("Connected to NATS-server at {}", args.nats_address);
 
     loop {
         shared::tokio::select! {
             maybe_msg = sub.next() => {
                 if let Some(msg) = maybe_msg {
-                    let event = event::Event::decode(msg.payload)?;
-                    log_event(event, args.clone());
+                    if !is_event_subject(&msg.subject) {
+                        continue;
+                    }
+                    let events = match decode_events(&msg) {
+                        Ok(events) => events,
+                        Err(e) => {
+                            log::error!(
+                                "Could not deserialize protobuf message on '{}': {}",
+                                msg.subject,
+                                e
+                            );
+                            continue;
+                        }
+                    };
+                    for event in events {
+                        log_event(event, args.clone());
+                    }
                 } else {
                     break; // subscription ended
                 }
@@ -185,6 +208,15 @@ fn log_event(event: Event, args: Args) {
                     log::info!("validation: {}", validation);
                 }
             }
+            ebpf::EbpfEvent::DroppedEvents(dropped) => {
+                // always log these as they indicate missing events
+                log::warn!("ebpf-extractor: {}", dropped);
+            }
+            ebpf::EbpfEvent::PublishLatency(latency) => {
+                if log_all {
+                    log::info!("ebpf-extractor: {}", latency);
+                }
+            }
         },
         PeerObserverEvent::RpcExtractor(r) => {
             if log_all || args.rpc {
@@ -196,16 +228,35 @@ fn log_event(event: Event, args: Args) {
                 log::info!("p2p event: {}", p.p2p_event.unwrap());
             }
         }
+        PeerObserverEvent::ErrorEvent(e) => {
+            // always log these as they indicate problems with the extractors
+            log::warn!("error: {}", e);
+        }
+        PeerObserverEvent::ExtractorMetrics(m) => {
+            if log_all {
+                log::info!("extractor metrics: {}", m);
+            }
+        }
+        PeerObserverEvent::Heartbeat(h) => {
+            if log_all {
+                log::info!("heartbeat: {}", h);
+            }
+        }
         PeerObserverEvent::LogExtractor(l) => {
             if log_all || args.log_extractor {
+                let occurrences = l
+                    .occurrences
+                    .map(|n| format!(" (repeated {} times)", n))
+                    .unwrap_or_default();
                 log::info!(
-                    "log event: {} [{}] {}",
+                    "log event: {} [{}] {}{}",
                     l.log_timestamp,
                     LogDebugCategory::try_from(l.category)
                         .unwrap_or(LogDebugCategory::Unknown)
                         .as_str_name()
                         .to_lowercase(),
-                    l.log_event.unwrap()
+                    l.log_event.unwrap(),
+                    occurrences
                 );
             }
         }
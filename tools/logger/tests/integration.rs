@@ -4,7 +4,7 @@ use logger::{error::RuntimeError, Args};
 
 use shared::{
     log::{self, Level, Record, SetLoggerError},
-    nats_subjects::Subject,
+    nats_subjects::{prefixed_subject, Subject},
     prost::Message,
     protobuf::{
         ebpf_extractor::{
@@ -19,7 +19,7 @@ use shared::{
         event::{event::PeerObserverEvent, Event},
         log_extractor::{self, LogDebugCategory},
         p2p_extractor,
-        rpc_extractor::{self, PeerInfo, PeerInfos},
+        rpc_extractor::{self, PeerConnectionType, PeerInfo, PeerInfos, PeerNetwork},
     },
     testing::{nats_publisher::NatsPublisherForTesting, nats_server::NatsServerForTesting},
     tokio::{self, sync::watch, time::sleep},
@@ -108,6 +108,17 @@ fn check_logs(expected: &[&str]) -> Result<bool, std::io::Error> {
 }
 
 async fn publish_and_check(events: &[Event], subject: Subject, expected: &str) {
+    publish_below_prefix_and_check(None, events, subject, expected).await;
+}
+
+/// Like [publish_and_check], but the events are published below the subject
+/// prefix and the logger tool subscribes below it.
+async fn publish_below_prefix_and_check(
+    prefix: Option<&str>,
+    events: &[Event],
+    subject: Subject,
+    expected: &str,
+) {
     init_logger().unwrap();
 
     let nats_server = NatsServerForTesting::new().await;
@@ -115,8 +126,9 @@ async fn publish_and_check(events: &[Event], subject: Subject, expected: &str) {
 
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
+    let nats_subject_prefix = prefix.map(str::to_string);
     let logger_handle = tokio::spawn(async move {
-        let args = make_test_args(
+        let mut args = make_test_args(
             nats_server.port,
             true,
             true,
@@ -127,6 +139,7 @@ async fn publish_and_check(events: &[Event], subject: Subject, expected: &str) {
             true,
             true,
         );
+        args.nats_subject_prefix = nats_subject_prefix;
         logger::run(args, shutdown_rx.clone()).await.unwrap();
     });
     // allow the logger tool to start
@@ -135,7 +148,10 @@ async fn publish_and_check(events: &[Event], subject: Subject, expected: &str) {
     for event in events {
         log::debug!("publishing: {:?}", event);
         nats_publisher
-            .publish(subject.to_string(), event.encode_to_vec())
+            .publish(
+                prefixed_subject(prefix, &subject.to_string()),
+                event.encode_to_vec(),
+            )
             .await;
     }
 
@@ -198,6 +214,9 @@ async fn test_integration_logger_p2p_messages() {
                         command: "ping".to_string(),
                         inbound: true,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Ping(Ping { value: 1336 })),
                 })),
@@ -212,6 +231,9 @@ async fn test_integration_logger_p2p_messages() {
                         command: "pong".to_string(),
                         inbound: false,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Pong(Pong { value: 1337 })),
                 })),
@@ -227,6 +249,37 @@ async fn test_integration_logger_p2p_messages() {
     .await;
 }
 
+#[tokio::test]
+async fn test_integration_logger_subject_prefix() {
+    println!("test that events published below a subject prefix are logged");
+
+    publish_below_prefix_and_check(
+        Some("team-a"),
+        &[Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
+            ebpf_event: Some(ebpf::EbpfEvent::Message(message::MessageEvent {
+                meta: Metadata {
+                    peer_id: 3,
+                    addr: "127.0.0.1:8333".to_string(),
+                    conn_type: 1,
+                    command: "ping".to_string(),
+                    inbound: true,
+                    size: 8,
+                    sample_rate: None,
+                    payload_bytes: None,
+                    total_bytes: None,
+                },
+                msg: Some(Msg::Ping(Ping { value: 4242 })),
+            })),
+        }))
+        .unwrap()],
+        Subject::NetMsg,
+        r#"
+        message: inbound from id=3 (conn_type=1): Ping(4242)
+        "#,
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn test_integration_logger_connections() {
     println!("test that connections are logged");
@@ -316,6 +369,8 @@ async fn test_integration_logger_rpc_peerinfo() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
                 rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(PeerInfos {
                     infos: vec![
                         PeerInfo {
@@ -335,6 +390,7 @@ async fn test_integration_logger_rpc_peerinfo() {
                             bytes_sent: 0,
                             connection_time: 1,
                             connection_type: "type0".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type0").into(),
                             id: 1,
                             inbound: true,
                             inflight: vec![1337, 45324],
@@ -346,11 +402,13 @@ async fn test_integration_logger_rpc_peerinfo() {
                             minfeefilter: 1234.0,
                             minimum_ping: 1234.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 1234.0,
                             ping_wait: 1234.0,
                             relay_transactions: true,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 1337,
                             subversion: "subversion".to_string(),
                             synced_blocks: 4,
@@ -360,6 +418,7 @@ async fn test_integration_logger_rpc_peerinfo() {
                             version: 2841,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                         PeerInfo {
                             addr_processed: 342,
@@ -376,6 +435,7 @@ async fn test_integration_logger_rpc_peerinfo() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: false,
                             inflight: vec![],
@@ -387,11 +447,13 @@ async fn test_integration_logger_rpc_peerinfo() {
                             minfeefilter: 2.0,
                             minimum_ping: 13.0,
                             network: "ipv6".to_string(),
+                            network_kind: PeerNetwork::from("ipv6").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -401,6 +463,7 @@ async fn test_integration_logger_rpc_peerinfo() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                         PeerInfo {
                             addr_processed: 342,
@@ -417,6 +480,7 @@ async fn test_integration_logger_rpc_peerinfo() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: false,
                             inflight: vec![],
@@ -428,11 +492,13 @@ async fn test_integration_logger_rpc_peerinfo() {
                             minfeefilter: 2.0,
                             minimum_ping: 13.0,
                             network: "ipv6".to_string(),
+                            network_kind: PeerNetwork::from("ipv6").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -442,15 +508,18 @@ async fn test_integration_logger_rpc_peerinfo() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                     ],
+                    chunk_index: None,
+                    chunk_total: None,
                 })),
             }))
             .unwrap(),
         ],
         Subject::Rpc,
         r#"
-        rpc: PeerInfos([PeerInfo(id=1), PeerInfo(id=2), PeerInfo(id=2)])
+        rpc: PeerInfos([PeerInfo(id=1, address=179.43.182.232:1234, network=ipv4), PeerInfo(id=2, address=162.218.65.123:8332, network=ipv6), PeerInfo(id=2, address=162.218.65.123:8332, network=ipv6)])
         "#,
     )
     .await;
@@ -524,6 +593,8 @@ async fn test_integration_logger_logextractor_unknown_log() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
                 category: LogDebugCategory::Unknown.into(),
                 log_timestamp: 1234,
                 log_event: Some(log_extractor::log::LogEvent::UnknownLogMessage(
@@ -549,6 +620,8 @@ async fn test_integration_logger_logextractor_blockconnected_log() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
                 category: LogDebugCategory::Validation.into(),
                 log_timestamp: 345,
                 log_event: Some(log_extractor::log::LogEvent::BlockConnectedLog(
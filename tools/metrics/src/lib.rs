@@ -2,11 +2,14 @@
 // Allow for more metric macros in metrics.rs
 #![recursion_limit = "256"]
 
+use shared::clap;
 use shared::clap::Parser;
 use shared::futures::StreamExt;
-use shared::log::{debug, info, warn, Level};
+use shared::log::{debug, error, info, warn, Level};
 use shared::metricserver;
-use shared::prost::Message;
+use shared::nats::{self, decode_events, parse_nats_address};
+use shared::nats_subjects::{is_event_subject, parse_subject_prefix};
+use shared::p2p_commands;
 use shared::protobuf::bitcoin_primitives;
 use shared::protobuf::{
     ebpf_extractor::{
@@ -18,14 +21,13 @@ use shared::protobuf::{
         message::{message_event::Msg, reject::RejectReason},
         validation::validation_event,
     },
-    event::{event::PeerObserverEvent, Event},
+    event::{event::PeerObserverEvent, Event, Extractor},
     log_extractor::{log, Log, LogDebugCategory},
     p2p_extractor::p2p,
-    rpc_extractor::rpc,
+    rpc_extractor::{rpc, PeerInfosAssembler},
 };
 use shared::tokio::sync::watch;
 use shared::util::{self, is_on_linkinglion_banlist};
-use shared::{async_nats, clap};
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
@@ -41,7 +43,8 @@ const LOG_TARGET: &str = "main";
 #[command(version, about, long_about = None)]
 pub struct Args {
     /// The NATS server address the tool should connect and subscribe to.
-    #[arg(short, long, default_value = "127.0.0.1:4222")]
+    /// Multiple comma-separated addresses, e.g. of the servers in a NATS cluster, can be given.
+    #[arg(short, long, default_value = "127.0.0.1:4222", value_parser = parse_nats_address)]
     nats_address: String,
     /// The metrics server address the tool should listen on.
     #[arg(short, long, default_value = "127.0.0.1:8282")]
@@ -50,6 +53,11 @@ pub struct Args {
     /// are "trace", "debug", "info", "warn", "error". See https://docs.rs/log/latest/log/enum.Level.html
     #[arg(short, long, default_value_t = Level::Debug)]
     pub log_level: Level,
+
+    /// Subscribe to the events published below this subject prefix, e.g. on
+    /// `team-a.rpc` for extractors started with `--nats-subject-prefix team-a`.
+    #[arg(long, value_name = "PREFIX", value_parser = parse_subject_prefix)]
+    pub nats_subject_prefix: Option<String>,
 }
 
 impl Args {
@@ -58,6 +66,7 @@ impl Args {
             nats_address,
             metrics_address,
             log_level,
+            nats_subject_prefix: None,
         }
     }
 }
@@ -75,9 +84,10 @@ pub async fn run(
     metricserver::start(&args.metrics_address, Some(metrics.registry.clone()))?;
 
     debug!("Connecting to NATS-server at {}", args.nats_address.clone());
-    let nc = async_nats::connect(args.nats_address.clone()).await?;
+    let nc = nats::connect(&args.nats_address).await?;
     info!("Connected to NATS-server at {}", args.nats_address);
-    let mut sub = nc.subscribe("*").await?;
+    let mut sub = nats::subscribe_events(&nc, args.nats_subject_prefix.as_deref()).await?;
+    let mut peer_infos = PeerInfosAssembler::default();
 
     metrics
         .runtime_start_timestamp
@@ -87,7 +97,26 @@ pub async fn run(
         shared::tokio::select! {
             maybe_msg = sub.next() => {
                 if let Some(msg) = maybe_msg {
-                    handle_event(msg, metrics.clone())?;
+                    if !is_event_subject(&msg.subject) {
+                        continue;
+                    }
+                    let events = match decode_events(&msg) {
+                        Ok(events) => events,
+                        Err(e) => {
+                            error!(
+                                "Could not deserialize protobuf message on '{}': {}",
+                                msg.subject,
+                                e
+                            );
+                            continue;
+                        }
+                    };
+                    for event in events {
+                        let event = reassemble_peer_infos(&mut peer_infos, &msg.subject, event);
+                        if let Some(event) = event {
+                            handle_event(event, metrics.clone())?;
+                        }
+                    }
                 } else {
                     break; // subscription ended
                 }
@@ -113,11 +142,23 @@ pub async fn run(
     Ok(())
 }
 
-fn handle_event(
-    msg: async_nats::Message,
-    metrics: metrics::Metrics,
-) -> Result<(), error::RuntimeError> {
-    let unwrapped = Event::decode(msg.payload)?;
+/// Passes the event on, unless it's a chunk of a getpeerinfo response that
+/// was split by the rpc-extractor. The peer metrics are aggregates over all
+/// peers, so the response is only passed on once it's reassembled.
+fn reassemble_peer_infos(
+    assembler: &mut PeerInfosAssembler,
+    subject: &str,
+    mut event: Event,
+) -> Option<Event> {
+    if let Some(PeerObserverEvent::RpcExtractor(r)) = event.peer_observer_event.as_mut() {
+        if let Some(rpc::RpcEvent::PeerInfos(infos)) = r.rpc_event.as_mut() {
+            *infos = assembler.push(subject, std::mem::take(infos))?;
+        }
+    }
+    Some(event)
+}
+
+fn handle_event(unwrapped: Event, metrics: metrics::Metrics) -> Result<(), error::RuntimeError> {
     if let Some(event) = unwrapped.peer_observer_event {
         match event {
             PeerObserverEvent::EbpfExtractor(ebpf) => match ebpf.ebpf_event.unwrap() {
@@ -136,6 +177,16 @@ fn handle_event(
                 ebpf::EbpfEvent::Validation(validation) => {
                     handle_validation_event(&validation.event.unwrap(), metrics);
                 }
+                ebpf::EbpfEvent::DroppedEvents(dropped) => {
+                    metrics.ebpf_dropped_events.inc_by(dropped.dropped);
+                    metrics.ebpf_dropped_stale_events.inc_by(dropped.stale());
+                }
+                ebpf::EbpfEvent::PublishLatency(latency) => {
+                    metrics.ebpf_publish_latency_p50.set(latency.p50 as i64);
+                    metrics.ebpf_publish_latency_p90.set(latency.p90 as i64);
+                    metrics.ebpf_publish_latency_p99.set(latency.p99 as i64);
+                    metrics.ebpf_publish_latency_max.set(latency.max as i64);
+                }
             },
             PeerObserverEvent::RpcExtractor(r) => {
                 if let Some(e) = r.rpc_event {
@@ -150,6 +201,42 @@ fn handle_event(
             PeerObserverEvent::LogExtractor(l) => {
                 handle_log_event(&l, metrics);
             }
+            PeerObserverEvent::ErrorEvent(e) => {
+                metrics
+                    .errors
+                    .with_label_values(&[
+                        e.extractor().to_string(),
+                        e.operation.clone(),
+                        e.severity().as_str_name().to_lowercase(),
+                    ])
+                    .inc();
+            }
+            PeerObserverEvent::ExtractorMetrics(m) => {
+                let extractor = m.extractor().to_string();
+                for (name, count) in m.counters.iter() {
+                    metrics
+                        .extractor_counter
+                        .with_label_values(&[extractor.as_str(), name.as_str()])
+                        .inc_by(*count);
+                }
+                for (name, value) in m.gauges.iter() {
+                    metrics
+                        .extractor_gauge
+                        .with_label_values(&[extractor.as_str(), name.as_str()])
+                        .set(*value);
+                }
+            }
+            PeerObserverEvent::Heartbeat(h) => {
+                let extractor = h.extractor().to_string();
+                metrics
+                    .extractor_heartbeats
+                    .with_label_values(&[extractor.as_str()])
+                    .inc();
+                metrics
+                    .extractor_last_heartbeat_timestamp
+                    .with_label_values(&[extractor.as_str()])
+                    .set((unwrapped.timestamp / 1000) as i64);
+            }
         }
     }
 
@@ -168,6 +255,16 @@ fn handle_rpc_event(e: &rpc::RpcEvent, metrics: metrics::Metrics) {
             metrics
                 .rpc_nettotals_total_bytes_sent
                 .set(net_totals.total_bytes_sent as i64);
+            if let Some(recv_bytes_per_sec) = net_totals.recv_bytes_per_sec {
+                metrics
+                    .rpc_nettotals_recv_bytes_per_second
+                    .set(recv_bytes_per_sec);
+            }
+            if let Some(sent_bytes_per_sec) = net_totals.sent_bytes_per_sec {
+                metrics
+                    .rpc_nettotals_sent_bytes_per_second
+                    .set(sent_bytes_per_sec);
+            }
         }
         rpc::RpcEvent::MemoryInfo(info) => {
             metrics.rpc_memoryinfo_locked_used.set(info.used as i64);
@@ -216,6 +313,86 @@ fn handle_rpc_event(e: &rpc::RpcEvent, metrics: metrics::Metrics) {
                 .rpc_mempoolinfo_incremental_relay_feerate
                 .set(info.incrementalrelayfee);
         }
+        rpc::RpcEvent::BlockchainInfo(info) => {
+            metrics.rpc_blockchaininfo_blocks.set(info.blocks);
+            metrics.rpc_blockchaininfo_headers.set(info.headers);
+            metrics
+                .rpc_blockchaininfo_verification_progress
+                .set(info.verification_progress);
+            metrics
+                .rpc_blockchaininfo_initial_block_download
+                .set(if info.initial_block_download { 1 } else { 0 });
+            metrics
+                .rpc_blockchaininfo_size_on_disk
+                .set(info.size_on_disk as i64);
+        }
+        rpc::RpcEvent::FeeEstimates(estimates) => {
+            for (target, estimate) in estimates.estimates.iter() {
+                let target = target.to_string();
+                match estimate.feerate {
+                    Some(feerate) => metrics
+                        .rpc_feeestimate_feerate
+                        .with_label_values(&[target.as_str()])
+                        .set(feerate),
+                    None => {
+                        // Don't keep exposing a previous estimate if there's
+                        // no estimate for this target anymore.
+                        let _ = metrics
+                            .rpc_feeestimate_feerate
+                            .remove_label_values(&[target.as_str()]);
+                    }
+                }
+            }
+        }
+        rpc::RpcEvent::PeerInfoSummary(_) => {
+            // The peer counts by network and connection type are already
+            // derived from the full PeerInfos.
+        }
+        rpc::RpcEvent::NetworkPeerStats(_) => {
+            // The peer counts and bytes by network are already derived from
+            // the full PeerInfos.
+        }
+        rpc::RpcEvent::PeerInfo(_) => {
+            // The peer metrics are aggregates over all peers and are
+            // derived from the full PeerInfos.
+        }
+        rpc::RpcEvent::OrphanTxs(orphans) => {
+            metrics.rpc_orphantxs.set(orphans.orphans.len() as i64);
+            metrics
+                .rpc_orphantxs_vsize
+                .set(orphans.orphans.iter().map(|o| o.vsize as i64).sum());
+        }
+        rpc::RpcEvent::CompactPeerInfos(_) => {
+            // The compact form lacks most fields the peer metrics are
+            // derived from.
+        }
+        rpc::RpcEvent::ScanProgress(_) => {
+            // One-off scans requested by a consumer, not node metrics.
+        }
+        rpc::RpcEvent::NodeAddresses(addresses) => {
+            metrics.rpc_nodeaddresses.reset();
+            for address in addresses.addresses.iter() {
+                metrics
+                    .rpc_nodeaddresses
+                    .with_label_values(&[address.network.as_deref().unwrap_or("unknown")])
+                    .inc();
+            }
+        }
+        rpc::RpcEvent::RawMempool(_) => {
+            // The mempool size is covered by the getmempoolinfo metrics.
+        }
+        rpc::RpcEvent::RawAddrman(_) => {
+            // Address manager entries for research. The counts are covered by
+            // the getaddrmaninfo metrics.
+        }
+        rpc::RpcEvent::ConnectionChurn(churn) => {
+            metrics
+                .rpc_churn_connected_peers
+                .inc_by(churn.connected.len() as u64);
+            metrics
+                .rpc_churn_disconnected_peers
+                .inc_by(churn.disconnected.len() as u64);
+        }
         rpc::RpcEvent::PeerInfos(info) => {
             let mut on_gmax_banlist = 0;
             let mut on_monero_banlist = 0;
@@ -258,6 +435,10 @@ fn handle_rpc_event(e: &rpc::RpcEvent, metrics: metrics::Metrics) {
 
             // We keep track of the number of peers that relay sub 1 sat/vbyte transactions.
             let mut sub1satvb_relay_peers = 0;
+            // Set if the rpc-extractor left the per-message data of a peer
+            // empty (--peerinfo-per-message), which the sub-1 sat/vbyte peers
+            // are detected with.
+            let mut per_message_omitted = false;
 
             // Keep track of the total number of inbound IPv4 peers and
             // the number distinct /16 subnets of these peers.
@@ -361,6 +542,9 @@ fn handle_rpc_event(e: &rpc::RpcEvent, metrics: metrics::Metrics) {
                         .or_insert(1);
                 }
 
+                if peer.per_message_omitted == Some(true) {
+                    per_message_omitted = true;
+                }
                 if peer.relay_transactions
                     // check that the minfeefilter of this pee is below 1 sat/vbyte
                     && peer.minfeefilter < 0.00001000 // in BTC/kvB (0.00001000 BTC/kvB = 1 sat/vbyte)
@@ -510,9 +694,12 @@ fn handle_rpc_event(e: &rpc::RpcEvent, metrics: metrics::Metrics) {
                     .set(*v);
             }
 
-            metrics
-                .rpc_peer_info_sub1satvb_relay
-                .set(sub1satvb_relay_peers);
+            // Keep the last value instead of undercounting the peers.
+            if !per_message_omitted {
+                metrics
+                    .rpc_peer_info_sub1satvb_relay
+                    .set(sub1satvb_relay_peers);
+            }
 
             if ipv4_inbound_peers > 0 {
                 // avoid division by zero
@@ -731,14 +918,7 @@ fn handle_p2p_extractor_event(p2p_event: &p2p::P2pEvent, metrics: metrics::Metri
             metrics
                 .p2pextractor_invs_size
                 .set(annoucement.inventory.len() as i64);
-            let mut invs_by_type: BTreeMap<&str, u64> = BTreeMap::new();
-            for item in annoucement.inventory.iter() {
-                invs_by_type
-                    .entry(item.inv_type())
-                    .and_modify(|e| *e += 1)
-                    .or_insert(1);
-            }
-            for (type_, v) in invs_by_type.iter() {
+            for (type_, v) in annoucement.counts_by_type().iter() {
                 metrics
                     .p2pextractor_invs_items
                     .with_label_values(&[&type_.to_string()])
@@ -747,7 +927,65 @@ fn handle_p2p_extractor_event(p2p_event: &p2p::P2pEvent, metrics: metrics::Metri
         }
         p2p::P2pEvent::FeefilterAnnouncement(feefilter) => {
             metrics.p2pextractor_feefilter_messages.inc();
-            metrics.p2pextractor_feefilter_last.set(*feefilter);
+            metrics.p2pextractor_feefilter_last.set(feefilter.fee_rate);
+        }
+        p2p::P2pEvent::SendCompact(sendcmpct) => {
+            metrics
+                .p2pextractor_sendcmpct_messages
+                .with_label_values(&[&sendcmpct.high_bandwidth.to_string()])
+                .inc();
+        }
+        p2p::P2pEvent::FeatureNegotiation(features) => {
+            for (feature, negotiated) in [
+                ("sendaddrv2", features.send_addr_v2),
+                ("wtxidrelay", features.wtxid_relay),
+                ("sendheaders", features.send_headers),
+            ] {
+                metrics
+                    .p2pextractor_feature_negotiated
+                    .with_label_values(&[feature])
+                    .set(negotiated as i64);
+            }
+        }
+        p2p::P2pEvent::CompactBlockAnnouncement(_) => {
+            metrics.p2pextractor_cmpctblock_messages.inc();
+        }
+        p2p::P2pEvent::TransactionRelay(tx) => {
+            metrics.p2pextractor_tx_messages.inc();
+            metrics.p2pextractor_tx_bytes.inc_by(tx.size as u64);
+        }
+        p2p::P2pEvent::BlockRelay(block) => {
+            metrics.p2pextractor_block_messages.inc();
+            metrics
+                .p2pextractor_block_last_transactions
+                .set(block.transactions as i64);
+            metrics.p2pextractor_block_last_size.set(block.size as i64);
+        }
+        p2p::P2pEvent::AddrRateAlert(_) => {
+            metrics.p2pextractor_addr_rate_alerts.inc();
+        }
+        p2p::P2pEvent::HeaderRequest(request) => {
+            metrics
+                .p2pextractor_header_requests
+                .with_label_values(&[&request.command().to_string()])
+                .inc();
+        }
+        p2p::P2pEvent::ParseError(parse_error) => {
+            // the suppressed parse errors were from the same peer, but not
+            // necessarily with the same command
+            metrics
+                .p2pextractor_parse_errors
+                .with_label_values(&[p2p_commands::known_or_other(&parse_error.command)])
+                .inc();
+            metrics
+                .p2pextractor_parse_errors_suppressed
+                .inc_by(parse_error.suppressed);
+        }
+        p2p::P2pEvent::ServiceMismatch(mismatch) => {
+            metrics
+                .p2pextractor_service_mismatches
+                .with_label_values(&[&mismatch.kind().to_string(), &mismatch.command])
+                .inc();
         }
     }
 }
@@ -950,8 +1188,12 @@ fn handle_p2p_message(msg: &message::MessageEvent, timestamp_ms: u64, metrics: m
                 }
                 // Large inv-to-send, see bitcoincore.org/en/2024/10/08/disclose-large-inv-to-send/
                 if direction == "outbound" {
-                    let tx_count = count_by_invtype.get("Tx").unwrap_or(&0);
-                    let wtx_count = count_by_invtype.get("WTx").unwrap_or(&0);
+                    let tx_count = count_by_invtype
+                        .get(bitcoin_primitives::InvType::Tx.name())
+                        .unwrap_or(&0);
+                    let wtx_count = count_by_invtype
+                        .get(bitcoin_primitives::InvType::WTx.name())
+                        .unwrap_or(&0);
                     if (*tx_count > 35) || (*wtx_count > 35) {
                         metrics.p2p_invs_outbound_large.inc();
                     }
@@ -1020,16 +1262,29 @@ fn handle_p2p_message(msg: &message::MessageEvent, timestamp_ms: u64, metrics: m
 }
 
 fn handle_log_event(log: &Log, metrics: metrics::Metrics) {
+    // Summaries are published by the log-extractor itself and aren't log lines.
+    if let Some(log::LogEvent::UnknownLogSummary(summary)) = &log.log_event {
+        metrics.log_unknown_ratio.set(summary.unknown_ratio());
+        return;
+    }
+
     let category = LogDebugCategory::try_from(log.category)
         .unwrap_or(LogDebugCategory::Unknown)
         .as_str_name()
         .to_lowercase();
 
-    metrics.log_events.with_label_values(&[&category]).inc();
+    // A deduplicated event stands for multiple identical log lines.
+    metrics
+        .log_events
+        .with_label_values(&[&category])
+        .inc_by(log.occurrences.unwrap_or(1) as u64);
 
     let Some(e) = &log.log_event else { return };
     match e {
-        log::LogEvent::UnknownLogMessage(_) => {}
+        log::LogEvent::UnknownLogMessage(_) => {
+            metrics.log_unknown_messages.inc();
+        }
+        log::LogEvent::UnknownLogSummary(_) => {} // handled above
         log::LogEvent::BlockConnectedLog(_) => {
             metrics.log_block_connected_events.inc();
         }
@@ -1043,5 +1298,84 @@ fn handle_log_event(log: &Log, metrics: metrics::Metrics) {
                     .inc();
             }
         }
+        log::LogEvent::HeaderSyncLog(sync) => {
+            metrics
+                .log_header_sync_height
+                .with_label_values(&[&sync
+                    .stage()
+                    .as_str_name()
+                    .trim_start_matches("HEADER_SYNC_STAGE_")
+                    .to_lowercase()])
+                .set(sync.height as i64);
+        }
+        log::LogEvent::DiskActivityLog(activity) => {
+            // Only count the timed FlushStateToDisk operations once.
+            if activity.completed != Some(false) {
+                metrics
+                    .log_disk_activity_events
+                    .with_label_values(&[&activity
+                        .operation()
+                        .as_str_name()
+                        .trim_start_matches("DISK_ACTIVITY_OPERATION_")
+                        .to_lowercase()])
+                    .inc();
+            }
+        }
+        log::LogEvent::DiscouragedPeerLog(discouraged) => {
+            metrics
+                .log_discouraged_peer_events
+                .with_label_values(&[&discouraged
+                    .action()
+                    .as_str_name()
+                    .trim_start_matches("DISCOURAGE_ACTION_")
+                    .to_lowercase()])
+                .inc();
+        }
+        log::LogEvent::NetworkErrorLog(_) => {
+            metrics.log_network_error_events.inc();
+        }
+        log::LogEvent::CriticalErrorLog(error) => {
+            metrics
+                .log_critical_error_events
+                .with_label_values(&[
+                    error
+                        .kind()
+                        .as_str_name()
+                        .trim_start_matches("CRITICAL_ERROR_KIND_")
+                        .to_lowercase(),
+                    error
+                        .severity()
+                        .as_str_name()
+                        .trim_start_matches("LOG_SEVERITY_")
+                        .to_lowercase(),
+                ])
+                .inc();
+        }
+        log::LogEvent::IbdCompleteLog(_) => {
+            metrics
+                .log_ibd_complete_timestamp
+                .set((log.log_timestamp / 1_000_000) as i64);
+        }
+        log::LogEvent::SnapshotLoadLog(load) => {
+            if load.completed {
+                metrics.log_snapshot_coins_loaded.set(load.coins as i64);
+            }
+        }
+        log::LogEvent::SnapshotValidatedLog(_) => {
+            metrics
+                .log_snapshot_validated_timestamp
+                .set((log.log_timestamp / 1_000_000) as i64);
+        }
+        log::LogEvent::BlockTemplateLog(template) => {
+            metrics.log_block_template_events.inc();
+            metrics
+                .log_block_template_weight
+                .set(template.weight as i64);
+            metrics.log_block_template_txs.set(template.tx_count as i64);
+            metrics.log_block_template_fees.set(template.fees);
+        }
+        log::LogEvent::BlockTemplateTimingLog(timing) => {
+            metrics.log_block_template_duration_ms.set(timing.total_ms);
+        }
     }
 }
@@ -1,10 +1,12 @@
 use shared::prometheus::{
-    register_gauge_with_registry, register_histogram_vec_with_registry,
-    register_int_counter_vec_with_registry, register_int_counter_with_registry,
-    register_int_gauge_vec_with_registry, register_int_gauge_with_registry, HistogramOpts, Opts,
-    Registry,
+    register_gauge_vec_with_registry, register_gauge_with_registry,
+    register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
+    register_int_counter_with_registry, register_int_gauge_vec_with_registry,
+    register_int_gauge_with_registry, HistogramOpts, Opts, Registry,
+};
+use shared::prometheus::{
+    Gauge, GaugeVec, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
 };
-use shared::prometheus::{Gauge, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
 
 const NAMESPACE: &str = "peerobserver";
 
@@ -21,6 +23,9 @@ pub const LABEL_P2P_FEEFILTER_FEERATE: &str = "feerate";
 pub const LABEL_P2P_REJECT_REASON: &str = "rejectreason";
 pub const LABEL_P2P_REJECT_COMMAND: &str = "rejectcommand";
 pub const LABEL_P2P_PING_VALUE: &str = "value";
+pub const LABEL_P2P_HIGH_BANDWIDTH: &str = "high_bandwidth";
+pub const LABEL_P2P_FEATURE: &str = "feature";
+pub const LABEL_P2P_SERVICE_MISMATCH: &str = "mismatch";
 
 pub const LABEL_CONN_NETWORK: &str = "network";
 pub const LABEL_CONN_ADDR: &str = "addr";
@@ -34,9 +39,20 @@ pub const LABEL_RPC_NETWORK_TYPE: &str = "network";
 pub const LABEL_RPC_CONNECTION_TYPE: &str = "connection_type";
 pub const LABEL_RPC_PROTOCOL_VERSION: &str = "protocol_version";
 pub const LABEL_RPC_ASN: &str = "ASN";
+pub const LABEL_RPC_FEEESTIMATE_TARGET: &str = "target";
 
 pub const LABEL_LOG_CATEGORY: &str = "category";
 pub const LABEL_LOG_MUTATED_BLOCK_STATUS: &str = "status";
+pub const LABEL_LOG_HEADER_SYNC_STAGE: &str = "stage";
+pub const LABEL_LOG_DISK_ACTIVITY_OPERATION: &str = "operation";
+pub const LABEL_LOG_DISCOURAGE_ACTION: &str = "action";
+pub const LABEL_LOG_CRITICAL_ERROR_KIND: &str = "kind";
+pub const LABEL_LOG_CRITICAL_ERROR_SEVERITY: &str = "severity";
+pub const LABEL_ERROR_EXTRACTOR: &str = "extractor";
+pub const LABEL_ERROR_OPERATION: &str = "operation";
+pub const LABEL_ERROR_SEVERITY: &str = "severity";
+pub const LABEL_EXTRACTOR_METRICS_EXTRACTOR: &str = "extractor";
+pub const LABEL_EXTRACTOR_METRICS_NAME: &str = "name";
 
 pub const BUCKETS_ADDR_ADDRESS_COUNT: [f64; 30] = [
     0f64, 1f64, 2f64, 3f64, 4f64, 5f64, 6f64, 7f64, 8f64, 9f64, 10f64, 15f64, 20f64, 25f64, 30f64,
@@ -110,6 +126,17 @@ macro_rules! g {
     };
 }
 
+macro_rules! gv {
+    ($name:ident, $desc:expr, $labels:expr, $registry:expr) => {
+        let $name: GaugeVec = register_gauge_vec_with_registry!(
+            Opts::new(stringify!($name), $desc),
+            &$labels,
+            $registry
+        )
+        .expect(concat!("Could not create metric '", stringify!($name), "'"));
+    };
+}
+
 macro_rules! ig {
     ($name:ident, $desc:expr, $registry:expr) => {
         let $name: IntGauge =
@@ -219,6 +246,14 @@ pub struct Metrics {
     pub validation_block_connected_latest_transactions: IntGauge,
     pub validation_block_connected_connection_time: IntCounter,
 
+    // ebpf-extractor
+    pub ebpf_dropped_events: IntCounter,
+    pub ebpf_dropped_stale_events: IntCounter,
+    pub ebpf_publish_latency_p50: IntGauge,
+    pub ebpf_publish_latency_p90: IntGauge,
+    pub ebpf_publish_latency_p99: IntGauge,
+    pub ebpf_publish_latency_max: IntGauge,
+
     // RPC-extractor
     // getpeeinfo
     pub rpc_peer_info_list_peers_gmax_ban: IntGauge,
@@ -275,9 +310,28 @@ pub struct Metrics {
     // uptime
     pub rpc_uptime: IntGauge,
 
+    // getblockchaininfo
+    pub rpc_blockchaininfo_blocks: IntGauge,
+    pub rpc_blockchaininfo_headers: IntGauge,
+    pub rpc_blockchaininfo_verification_progress: Gauge,
+    pub rpc_blockchaininfo_initial_block_download: IntGauge,
+    pub rpc_blockchaininfo_size_on_disk: IntGauge,
+
+    // estimatesmartfee
+    pub rpc_feeestimate_feerate: GaugeVec,
+
+    // getorphantxs
+    pub rpc_orphantxs: IntGauge,
+    pub rpc_orphantxs_vsize: IntGauge,
+    pub rpc_nodeaddresses: IntGaugeVec,
+    pub rpc_churn_connected_peers: IntCounter,
+    pub rpc_churn_disconnected_peers: IntCounter,
+
     // getnettotals
     pub rpc_nettotals_total_bytes_received: IntGauge,
     pub rpc_nettotals_total_bytes_sent: IntGauge,
+    pub rpc_nettotals_recv_bytes_per_second: Gauge,
+    pub rpc_nettotals_sent_bytes_per_second: Gauge,
 
     // getmemoryinfo
     pub rpc_memoryinfo_locked_used: IntGauge,
@@ -301,12 +355,51 @@ pub struct Metrics {
     pub p2pextractor_invs_messages: IntCounter,
     pub p2pextractor_feefilter_messages: IntCounter,
     pub p2pextractor_feefilter_last: IntGauge,
+    pub p2pextractor_sendcmpct_messages: IntCounterVec,
+    pub p2pextractor_feature_negotiated: IntGaugeVec,
+    pub p2pextractor_cmpctblock_messages: IntCounter,
+    pub p2pextractor_tx_messages: IntCounter,
+    pub p2pextractor_tx_bytes: IntCounter,
+    pub p2pextractor_block_messages: IntCounter,
+    pub p2pextractor_block_last_transactions: IntGauge,
+    pub p2pextractor_block_last_size: IntGauge,
+    pub p2pextractor_addr_rate_alerts: IntCounter,
+    pub p2pextractor_header_requests: IntCounterVec,
+    pub p2pextractor_parse_errors: IntCounterVec,
+    pub p2pextractor_parse_errors_suppressed: IntCounter,
+    pub p2pextractor_service_mismatches: IntCounterVec,
 
     // log-extractor
     pub log_events: IntCounterVec,
     pub log_block_connected_events: IntCounter,
     pub log_block_checked_events: IntCounter,
     pub log_mutated_blocks: IntCounterVec,
+    pub log_header_sync_height: IntGaugeVec,
+    pub log_disk_activity_events: IntCounterVec,
+    pub log_discouraged_peer_events: IntCounterVec,
+    pub log_critical_error_events: IntCounterVec,
+    pub log_network_error_events: IntCounter,
+    pub log_ibd_complete_timestamp: IntGauge,
+    pub log_snapshot_coins_loaded: IntGauge,
+    pub log_snapshot_validated_timestamp: IntGauge,
+    pub log_block_template_events: IntCounter,
+    pub log_block_template_weight: IntGauge,
+    pub log_block_template_txs: IntGauge,
+    pub log_block_template_fees: IntGauge,
+    pub log_block_template_duration_ms: Gauge,
+    pub log_unknown_messages: IntCounter,
+    pub log_unknown_ratio: Gauge,
+
+    // errors reported by the extractors
+    pub errors: IntCounterVec,
+
+    // ExtractorMetrics reported by the extractors
+    pub extractor_counter: IntCounterVec,
+    pub extractor_gauge: GaugeVec,
+
+    // Heartbeats published by the extractors
+    pub extractor_heartbeats: IntCounterVec,
+    pub extractor_last_heartbeat_timestamp: IntGaugeVec,
 }
 
 impl Metrics {
@@ -371,6 +464,14 @@ impl Metrics {
         ig!(validation_block_connected_latest_transactions, "Last connected block transactions.", registry);
         ic!(validation_block_connected_connection_time, "Last connected block connection time in µs", registry);
 
+        // ebpf-extractor
+        ic!(ebpf_dropped_events, "Number of events the ebpf-extractor dropped because publishing them into NATS couldn't keep up.", registry);
+        ic!(ebpf_dropped_stale_events, "Number of events the ebpf-extractor dropped because they were older than --max-event-age-ms when publishing them.", registry);
+        ig!(ebpf_publish_latency_p50, "Median latency (in µs) between the tracepoints firing and the ebpf-extractor publishing the events into NATS.", registry);
+        ig!(ebpf_publish_latency_p90, "90th percentile latency (in µs) between the tracepoints firing and the ebpf-extractor publishing the events into NATS.", registry);
+        ig!(ebpf_publish_latency_p99, "99th percentile latency (in µs) between the tracepoints firing and the ebpf-extractor publishing the events into NATS.", registry);
+        ig!(ebpf_publish_latency_max, "Maximum latency (in µs) between the tracepoints firing and the ebpf-extractor publishing the events into NATS.", registry);
+
         // RPC-extractor
         // getpeerinfo
         ig!(rpc_peer_info_list_peers_gmax_ban, "Number of peers connected to us that are on the 2018 ban list by gmax.", registry);
@@ -427,9 +528,28 @@ impl Metrics {
         // uptime
         ig!(rpc_uptime, "Node uptime in seconds", registry);
 
+        // getblockchaininfo
+        ig!(rpc_blockchaininfo_blocks, "The height of the most-work fully-validated chain.", registry);
+        ig!(rpc_blockchaininfo_headers, "The current number of headers the node has validated.", registry);
+        g!(rpc_blockchaininfo_verification_progress, "Estimate of the verification progress [0..1].", registry);
+        ig!(rpc_blockchaininfo_initial_block_download, "1 if the node is in Initial Block Download mode.", registry);
+        ig!(rpc_blockchaininfo_size_on_disk, "The estimated size of the block and undo files on disk in bytes.", registry);
+
+        // estimatesmartfee
+        gv!(rpc_feeestimate_feerate, "Estimated fee rate in BTC/kvB by confirmation target (in blocks).", [LABEL_RPC_FEEESTIMATE_TARGET], registry);
+
+        // getorphantxs
+        ig!(rpc_orphantxs, "Number of transactions in the orphanage.", registry);
+        ig!(rpc_orphantxs_vsize, "Sum of the virtual sizes of the transactions in the orphanage.", registry);
+        igv!(rpc_nodeaddresses, "Number of addresses in the last getnodeaddresses response by network.", ["network"], registry);
+        ic!(rpc_churn_connected_peers, "Number of peers that connected between two getpeerinfo samples.", registry);
+        ic!(rpc_churn_disconnected_peers, "Number of peers that disconnected between two getpeerinfo samples.", registry);
+
         // getnettotals
         ig!(rpc_nettotals_total_bytes_received, "Total bytes received by the node", registry);
         ig!(rpc_nettotals_total_bytes_sent, "Total bytes sent by the node", registry);
+        g!(rpc_nettotals_recv_bytes_per_second, "Bytes received by the node per second since the previous getnettotals sample", registry);
+        g!(rpc_nettotals_sent_bytes_per_second, "Bytes sent by the node per second since the previous getnettotals sample", registry);
 
         // getmemoryinfo
         ig!(rpc_memoryinfo_locked_used, "Number of bytes used in locked memory", registry);
@@ -452,13 +572,52 @@ impl Metrics {
         ic!(p2pextractor_invs_messages, "The total number of inv messages that the node sent to the p2p-extractor.", registry);
         ig!(p2pextractor_invs_size, "The size of the last inv message received by the p2p-extractor from the node.", registry);
         ic!(p2pextractor_feefilter_messages, "The total number of feefilter messages that the node sent to the p2p-extractor.", registry);
-        ig!(p2pextractor_feefilter_last, "The value of the last feefilter received by the p2p-extractor from the node.", registry);
+        ig!(p2pextractor_feefilter_last, "The value of the last feefilter received by the p2p-extractor from the node in sat/kvB.", registry);
+        icv!(p2pextractor_sendcmpct_messages, "The total number of sendcmpct messages that the node sent to the p2p-extractor, by high-bandwidth mode.", [LABEL_P2P_HIGH_BANDWIDTH], registry);
+        igv!(p2pextractor_feature_negotiated, "If the node sent the p2p-extractor the feature negotiation message (sendaddrv2, wtxidrelay, sendheaders) on the current connection (1) or not (0).", [LABEL_P2P_FEATURE], registry);
+        ic!(p2pextractor_cmpctblock_messages, "The total number of cmpctblock messages that the node sent to the p2p-extractor.", registry);
+        ic!(p2pextractor_tx_messages, "The total number of tx messages that the node sent to the p2p-extractor.", registry);
+        ic!(p2pextractor_tx_bytes, "The total size of the transactions in bytes that the node sent to the p2p-extractor in tx messages.", registry);
+        ic!(p2pextractor_block_messages, "The total number of block messages that the node sent to the p2p-extractor.", registry);
+        ig!(p2pextractor_block_last_transactions, "The number of transactions in the last block the node sent to the p2p-extractor.", registry);
+        ig!(p2pextractor_block_last_size, "The size in bytes of the last block the node sent to the p2p-extractor.", registry);
+        icv!(p2pextractor_header_requests, "The total number of getheaders and getblocks messages that the node sent to the p2p-extractor, by message.", [LABEL_P2P_MSG_TYPE], registry);
+        icv!(p2pextractor_parse_errors, "The total number of published messages from the node that the p2p-extractor couldn't parse, by message command (unknown commands as \"other\").", [LABEL_P2P_MSG_TYPE], registry);
+        ic!(p2pextractor_parse_errors_suppressed, "The total number of messages from the node that the p2p-extractor couldn't parse, but didn't publish due to the rate limit.", registry);
+        icv!(p2pextractor_service_mismatches, "The total number of messages from the node that contradicted the protocol version or services it advertised, by mismatch and message command.", [LABEL_P2P_SERVICE_MISMATCH, LABEL_P2P_MSG_TYPE], registry);
+        ic!(p2pextractor_addr_rate_alerts, "The total number of alerts raised by the p2p-extractor because the node announced more addresses per minute than the threshold.", registry);
 
         // log-extractor
         icv!(log_events, "Number of log events received by category.", [LABEL_LOG_CATEGORY], registry);
         ic!(log_block_connected_events, "Number of block connected log events received.", registry);
         ic!(log_block_checked_events, "Number of block checked log events received.", registry);
         icv!(log_mutated_blocks, "Number of mutated blocks detected by status.", [LABEL_LOG_MUTATED_BLOCK_STATUS], registry);
+        igv!(log_header_sync_height, "Height of the last header logged during header synchronization by stage.", [LABEL_LOG_HEADER_SYNC_STAGE], registry);
+        icv!(log_disk_activity_events, "Number of disk activity (flush and prune) log events received by operation.", [LABEL_LOG_DISK_ACTIVITY_OPERATION], registry);
+        icv!(log_discouraged_peer_events, "Number of misbehaving and discouraged peer log events received by action.", [LABEL_LOG_DISCOURAGE_ACTION], registry);
+        icv!(log_critical_error_events, "Number of critical error log events, e.g. a corrupted database, received by kind and severity. Alert on any increase.", [LABEL_LOG_CRITICAL_ERROR_KIND, LABEL_LOG_CRITICAL_ERROR_SEVERITY], registry);
+        ic!(log_network_error_events, "Number of network error (socket recv error, connection refused) log events received.", registry);
+        ig!(log_ibd_complete_timestamp, "UNIX timestamp (in seconds) of the log line of the node leaving the initial block download.", registry);
+        ig!(log_snapshot_coins_loaded, "Number of coins loaded from the last AssumeUTXO snapshot.", registry);
+        ig!(log_snapshot_validated_timestamp, "UNIX timestamp (in seconds) of the log line of the node completing the background validation of an AssumeUTXO snapshot.", registry);
+        ic!(log_block_template_events, "Number of block template creation (CreateNewBlock) log events received.", registry);
+        ig!(log_block_template_weight, "Weight of the last block template created by the node.", registry);
+        ig!(log_block_template_txs, "Number of transactions (excluding the coinbase) in the last block template created by the node.", registry);
+        ig!(log_block_template_fees, "Fees (in sat) of the transactions in the last block template created by the node.", registry);
+        g!(log_block_template_duration_ms, "Time (in milliseconds) it took the node to create the last block template.", registry);
+        ic!(log_unknown_messages, "Number of log messages that did not match any known log format.", registry);
+        g!(log_unknown_ratio, "Ratio of log lines that did not match any known log format during the last log-extractor window. A high ratio indicates that the log format changed.", registry);
+
+        // errors reported by the extractors
+        icv!(errors, "Number of errors reported by the extractors by extractor, operation, and severity.", [LABEL_ERROR_EXTRACTOR, LABEL_ERROR_OPERATION, LABEL_ERROR_SEVERITY], registry);
+
+        // ExtractorMetrics reported by the extractors
+        icv!(extractor_counter, "Counters reported by the extractors about their own operation, by extractor and counter name.", [LABEL_EXTRACTOR_METRICS_EXTRACTOR, LABEL_EXTRACTOR_METRICS_NAME], registry);
+        gv!(extractor_gauge, "Gauges reported by the extractors about their own operation, by extractor and gauge name.", [LABEL_EXTRACTOR_METRICS_EXTRACTOR, LABEL_EXTRACTOR_METRICS_NAME], registry);
+
+        // Heartbeats published by the extractors
+        icv!(extractor_heartbeats, "Number of heartbeats received from the extractors, by extractor.", [LABEL_EXTRACTOR_METRICS_EXTRACTOR], registry);
+        igv!(extractor_last_heartbeat_timestamp, "UNIX timestamp in seconds of the last heartbeat received from the extractors, by extractor. Alert if it's older than a few heartbeat intervals.", [LABEL_EXTRACTOR_METRICS_EXTRACTOR], registry);
 
         Self {
             registry,
@@ -518,6 +677,12 @@ impl Metrics {
             validation_block_connected_latest_inputs,
             validation_block_connected_latest_transactions,
             validation_block_connected_connection_time,
+            ebpf_dropped_events,
+            ebpf_dropped_stale_events,
+            ebpf_publish_latency_p50,
+            ebpf_publish_latency_p90,
+            ebpf_publish_latency_p99,
+            ebpf_publish_latency_max,
 
             // RPC-extractor
             // getpeerinfo
@@ -575,9 +740,26 @@ impl Metrics {
             // uptime
             rpc_uptime,
 
+            // getblockchaininfo
+            rpc_blockchaininfo_blocks,
+            rpc_blockchaininfo_headers,
+            rpc_blockchaininfo_verification_progress,
+            rpc_blockchaininfo_initial_block_download,
+            rpc_blockchaininfo_size_on_disk,
+
+            // estimatesmartfee
+            rpc_feeestimate_feerate,
+            rpc_orphantxs,
+            rpc_orphantxs_vsize,
+            rpc_nodeaddresses,
+            rpc_churn_connected_peers,
+            rpc_churn_disconnected_peers,
+
             // getnettotals
             rpc_nettotals_total_bytes_received,
             rpc_nettotals_total_bytes_sent,
+            rpc_nettotals_recv_bytes_per_second,
+            rpc_nettotals_sent_bytes_per_second,
 
             // getmemoryinfo
             rpc_memoryinfo_locked_used,
@@ -601,11 +783,50 @@ impl Metrics {
             p2pextractor_invs_size,
             p2pextractor_feefilter_messages,
             p2pextractor_feefilter_last,
+            p2pextractor_sendcmpct_messages,
+            p2pextractor_feature_negotiated,
+            p2pextractor_cmpctblock_messages,
+            p2pextractor_tx_messages,
+            p2pextractor_tx_bytes,
+            p2pextractor_block_messages,
+            p2pextractor_block_last_transactions,
+            p2pextractor_block_last_size,
+            p2pextractor_addr_rate_alerts,
+            p2pextractor_header_requests,
+            p2pextractor_parse_errors,
+            p2pextractor_parse_errors_suppressed,
+            p2pextractor_service_mismatches,
             // log-extractor
             log_events,
             log_block_connected_events,
             log_block_checked_events,
             log_mutated_blocks,
+            log_header_sync_height,
+            log_disk_activity_events,
+            log_discouraged_peer_events,
+            log_critical_error_events,
+            log_network_error_events,
+            log_ibd_complete_timestamp,
+            log_snapshot_coins_loaded,
+            log_snapshot_validated_timestamp,
+            log_block_template_events,
+            log_block_template_weight,
+            log_block_template_txs,
+            log_block_template_fees,
+            log_block_template_duration_ms,
+            log_unknown_messages,
+            log_unknown_ratio,
+
+            // errors reported by the extractors
+            errors,
+
+            // ExtractorMetrics reported by the extractors
+            extractor_counter,
+            extractor_gauge,
+
+            // Heartbeats published by the extractors
+            extractor_heartbeats,
+            extractor_last_heartbeat_timestamp,
         }
     }
 }
@@ -22,14 +22,19 @@ use shared::{
                 Reject, Version,
             },
             validation::{self, BlockConnected},
-            Ebpf,
+            DroppedEvents, Ebpf, PublishLatency,
+        },
+        event::{
+            event::PeerObserverEvent, ErrorEvent, ErrorSeverity, Event, Extractor,
+            ExtractorMetrics, Heartbeat,
         },
-        event::{event::PeerObserverEvent, Event},
         log_extractor::{self, LogDebugCategory},
         p2p_extractor,
         rpc_extractor::{
-            self, AddrManInfo, AddrManInfoNetwork, MemoryInfo, MempoolInfo, NetTotals, PeerInfo,
-            PeerInfos, UploadTarget,
+            self, AddrManInfo, AddrManInfoNetwork, BlockchainInfo, ChurnPeer, ConnectionChurn,
+            FeeEstimate, FeeEstimates, MemoryInfo, MempoolInfo, NetTotals, NodeAddress,
+            NodeAddresses, OrphanTx, OrphanTxs, PeerConnectionType, PeerInfo, PeerInfos,
+            PeerNetwork, UploadTarget,
         },
     },
     rand::{self, Rng},
@@ -44,7 +49,7 @@ use shared::{
 };
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     io::ErrorKind,
     io::{Read, Write},
     net::TcpStream,
@@ -143,6 +148,17 @@ fn check_metrics(port: u16, expected: &[&str]) -> Result<bool, std::io::Error> {
 }
 
 async fn publish_and_check(events: &[Event], subject: Subject, expected: &str) {
+    publish_raw_and_check(&[], events, subject, expected).await;
+}
+
+/// Like [publish_and_check], but first publishes the raw payloads on their
+/// subjects, e.g. messages that aren't events.
+async fn publish_raw_and_check(
+    raw: &[(&str, &[u8])],
+    events: &[Event],
+    subject: Subject,
+    expected: &str,
+) {
     let initial_metrics_port = setup();
     let metrics_port: Arc<Mutex<u16>> = Arc::new(Mutex::new(initial_metrics_port));
 
@@ -185,6 +201,11 @@ async fn publish_and_check(events: &[Event], subject: Subject, expected: &str) {
     // allow the metrics tool to start
     sleep(Duration::from_secs(1)).await;
 
+    for (subject, payload) in raw {
+        nats_publisher
+            .publish(subject.to_string(), payload.to_vec())
+            .await;
+    }
     for event in events {
         debug!("publishing: {:?}", event);
         nats_publisher
@@ -249,6 +270,9 @@ async fn test_integration_metrics_p2p_message_count() {
                         command: "ping".to_string(),
                         inbound: true,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Ping(Ping { value: 1 })),
                 })),
@@ -263,6 +287,9 @@ async fn test_integration_metrics_p2p_message_count() {
                         command: "pong".to_string(),
                         inbound: false,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Pong(Pong { value: 1 })),
                 })),
@@ -295,6 +322,9 @@ async fn test_integration_metrics_p2p_traffic_linkinglion() {
                         command: "ping".to_string(),
                         inbound: true,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Ping(Ping { value: 1 })),
                 })),
@@ -309,6 +339,9 @@ async fn test_integration_metrics_p2p_traffic_linkinglion() {
                         command: "pong".to_string(),
                         inbound: true,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Pong(Pong { value: 1 })),
                 })),
@@ -346,6 +379,9 @@ async fn test_integration_metrics_p2p_addr() {
                     command: "addr".to_string(),
                     inbound: true,
                     size: 1234,
+                    sample_rate: None,
+                    payload_bytes: None,
+                    total_bytes: None,
                 },
                 msg: Some(Msg::Addr(Addr {
                     addresses: [
@@ -560,6 +596,9 @@ async fn test_integration_metrics_p2p_addrv2() {
                     command: "addrv2".to_string(),
                     inbound: true,
                     size: 5432,
+                    sample_rate: None,
+                    payload_bytes: None,
+                    total_bytes: None,
                 },
                 msg: Some(Msg::Addrv2(AddrV2 {
                     addresses: [
@@ -767,6 +806,9 @@ async fn test_integration_metrics_p2p_address_self_announcement() {
                         command: "addrv2".to_string(),
                         inbound: true,
                         size: 5432,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Addrv2(AddrV2 {
                         addresses: [
@@ -801,6 +843,9 @@ async fn test_integration_metrics_p2p_address_self_announcement() {
                         command: "addr".to_string(),
                         inbound: true,
                         size: 5432,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Addrv2(AddrV2 {
                         addresses: [
@@ -853,6 +898,9 @@ async fn test_integration_metrics_p2p_address_subnet_announcement() {
                         command: "addrv2".to_string(),
                         inbound: true,
                         size: 5432,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Addrv2(AddrV2 {
                         addresses: [
@@ -887,6 +935,9 @@ async fn test_integration_metrics_p2p_address_subnet_announcement() {
                         command: "addr".to_string(),
                         inbound: true,
                         size: 5432,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Addrv2(AddrV2 {
                         addresses: [
@@ -939,6 +990,9 @@ async fn test_integration_metrics_p2p_version() {
                         command: "version".to_string(),
                         inbound: true,
                         size: 2,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Version(Version {
                         nonce: 2,
@@ -977,6 +1031,9 @@ async fn test_integration_metrics_p2p_version() {
                         command: "version".to_string(),
                         inbound: true,
                         size: 1,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Version(Version {
                         nonce: 2,
@@ -1036,6 +1093,9 @@ async fn test_integration_metrics_p2p_feefilter() {
                     command: "feefilter".to_string(),
                     inbound: true,
                     size: 6,
+                    sample_rate: None,
+                    payload_bytes: None,
+                    total_bytes: None,
                 },
                 msg: Some(Msg::Feefilter(FeeFilter {
                     fee: 12345
@@ -1067,6 +1127,9 @@ async fn test_integration_metrics_p2p_rejected() {
                     command: "rejected".to_string(),
                     inbound: true,
                     size: 6,
+                    sample_rate: None,
+                    payload_bytes: None,
+                    total_bytes: None,
                 },
                 msg: Some(Msg::Reject(Reject {
                     reason: 1,
@@ -1086,6 +1149,9 @@ async fn test_integration_metrics_p2p_rejected() {
                     command: "rejected".to_string(),
                     inbound: true,
                     size: 6,
+                    sample_rate: None,
+                    payload_bytes: None,
+                    total_bytes: None,
                 },
                 msg: Some(Msg::Reject(Reject {
                     reason: 10000,
@@ -1122,6 +1188,9 @@ async fn test_integration_metrics_p2p_inv() {
                         command: "inv".to_string(),
                         inbound: true,
                         size: 80,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Inv(Inv {
                         // homogenus
@@ -1147,6 +1216,9 @@ async fn test_integration_metrics_p2p_inv() {
                         command: "inv".to_string(),
                         inbound: true,
                         size: 80,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Inv(Inv {
                         // heterogenous
@@ -1253,6 +1325,9 @@ async fn test_integration_metrics_p2p_inv_large_outbound() {
                         command: "inv".to_string(),
                         inbound: false,
                         size: 1000,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Inv(Inv {
                         items: large_inv_items_tx,
@@ -1269,6 +1344,9 @@ async fn test_integration_metrics_p2p_inv_large_outbound() {
                         command: "inv".to_string(),
                         inbound: false,
                         size: 1000,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Inv(Inv {
                         items: large_inv_items_wtx,
@@ -1285,6 +1363,9 @@ async fn test_integration_metrics_p2p_inv_large_outbound() {
                         command: "inv".to_string(),
                         inbound: false,
                         size: 80,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Inv(Inv {
                         items: [
@@ -1323,6 +1404,9 @@ async fn test_integration_metrics_p2p_oldping() {
                     command: "ping".to_string(),
                     inbound: true,
                     size: 0,
+                    sample_rate: None,
+                    payload_bytes: None,
+                    total_bytes: None,
                 },
                 msg: Some(Msg::Oldping(false)),
             })),
@@ -1370,6 +1454,9 @@ async fn test_integration_metrics_p2p_ping_value() {
                         command: "ping".to_string(),
                         inbound: true,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Ping(Ping { value: *v })),
                 })),
@@ -1406,6 +1493,9 @@ async fn test_integration_metrics_p2p_empty_addrv2() {
                     command: "addrv2".to_string(),
                     inbound: true,
                     size: 0,
+                    sample_rate: None,
+                    payload_bytes: None,
+                    total_bytes: None,
                 },
                 msg: Some(Msg::Emptyaddrv2(false)),
             })),
@@ -1661,6 +1751,64 @@ async fn test_integration_metrics_validation() {
     .await;
 }
 
+#[tokio::test]
+async fn test_integration_metrics_ebpf_dropped_events() {
+    println!("test that ebpf-extractor dropped events metrics work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
+                ebpf_event: Some(ebpf::EbpfEvent::DroppedEvents(DroppedEvents {
+                    dropped: 12,
+                    buffer_size: 100,
+                    stale: None,
+                })),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
+                ebpf_event: Some(ebpf::EbpfEvent::DroppedEvents(DroppedEvents {
+                    dropped: 3,
+                    buffer_size: 100,
+                    stale: Some(7),
+                })),
+            }))
+            .unwrap(),
+        ],
+        Subject::EbpfExtractor,
+        r#"
+        peerobserver_ebpf_dropped_events 15
+        peerobserver_ebpf_dropped_stale_events 7
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_ebpf_publish_latency() {
+    println!("test that ebpf-extractor publish latency metrics work");
+
+    publish_and_check(
+        &[Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
+            ebpf_event: Some(ebpf::EbpfEvent::PublishLatency(PublishLatency {
+                count: 1000,
+                p50: 120,
+                p90: 450,
+                p99: 2100,
+                max: 15000,
+            })),
+        }))
+        .unwrap()],
+        Subject::EbpfExtractor,
+        r#"
+        peerobserver_ebpf_publish_latency_p50 120
+        peerobserver_ebpf_publish_latency_p90 450
+        peerobserver_ebpf_publish_latency_p99 2100
+        peerobserver_ebpf_publish_latency_max 15000
+        "#,
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn test_integration_metrics_mempool_added() {
     println!("test that the mempool added metrics work");
@@ -1791,6 +1939,7 @@ async fn test_integration_metrics_mempool_removed() {
                         vsize: 0,      // not covered by test
                         txid: vec![],  // not covered by test
                         reason: "expired".to_string(),
+                        removal_reason: None,
                     })),
                 })),
             }))
@@ -1803,6 +1952,7 @@ async fn test_integration_metrics_mempool_removed() {
                         vsize: 0,      // not covered by test
                         txid: vec![],  // not covered by test
                         reason: "evicted".to_string(),
+                        removal_reason: None,
                     })),
                 })),
             }))
@@ -1824,6 +1974,8 @@ async fn test_integration_metrics_rpc_peerinfo() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
                 rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(PeerInfos {
                     infos: vec![
                         PeerInfo {
@@ -1843,6 +1995,7 @@ async fn test_integration_metrics_rpc_peerinfo() {
                             bytes_sent: 0,
                             connection_time: 1,
                             connection_type: "type0".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type0").into(),
                             id: 1,
                             inbound: true,
                             inflight: vec![1337, 45324],
@@ -1854,11 +2007,13 @@ async fn test_integration_metrics_rpc_peerinfo() {
                             minfeefilter: 1234.0,
                             minimum_ping: 1234.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 1234.0,
                             ping_wait: 1234.0,
                             relay_transactions: true,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 1337,
                             subversion: "subversion".to_string(),
                             synced_blocks: 4,
@@ -1868,6 +2023,7 @@ async fn test_integration_metrics_rpc_peerinfo() {
                             version: 2841,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                         PeerInfo {
                             addr_processed: 342,
@@ -1884,6 +2040,7 @@ async fn test_integration_metrics_rpc_peerinfo() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: false,
                             inflight: vec![],
@@ -1895,11 +2052,13 @@ async fn test_integration_metrics_rpc_peerinfo() {
                             minfeefilter: 2.0,
                             minimum_ping: 13.0,
                             network: "ipv6".to_string(),
+                            network_kind: PeerNetwork::from("ipv6").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -1909,6 +2068,7 @@ async fn test_integration_metrics_rpc_peerinfo() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                         PeerInfo {
                             addr_processed: 342,
@@ -1925,6 +2085,7 @@ async fn test_integration_metrics_rpc_peerinfo() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: false,
                             inflight: vec![],
@@ -1936,11 +2097,13 @@ async fn test_integration_metrics_rpc_peerinfo() {
                             minfeefilter: 2.0,
                             minimum_ping: 13.0,
                             network: "ipv6".to_string(),
+                            network_kind: PeerNetwork::from("ipv6").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -1950,8 +2113,11 @@ async fn test_integration_metrics_rpc_peerinfo() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                     ],
+                    chunk_index: None,
+                    chunk_total: None,
                 })),
             }))
             .unwrap(),
@@ -2035,152 +2201,196 @@ async fn test_integration_metrics_addrman() {
     .await;
 }
 
+/// Three peers of which one is a sub-1 sat/vbyte peer.
+fn sub1satvb_peer_infos() -> PeerInfos {
+    let mut bytes_received_per_message = HashMap::new();
+    bytes_received_per_message.insert("tx".to_string(), 1234);
+
+    PeerInfos {
+        infos: vec![
+            // This peer is a sub-1 sat/vbyte peer as the minfeefilter is 0.1 sat/vbyte
+            // and it has received txns (bytes_received_per_message).
+            PeerInfo {
+                addr_processed: 1234,
+                addr_rate_limited: 1234,
+                addr_relay_enabled: false,
+                // a random IP belonging to a tor exit node.
+                // This might not be a tor exit node IP in the future and the IP would need to updated.
+                address: "179.43.182.232:1234".to_string(),
+                address_bind: "1.2.3.4:8332".to_string(),
+                address_local: "1.2.3.4:8332".to_string(),
+                bip152_hb_from: true,
+                bip152_hb_to: false,
+                bytes_received: 1,
+                bytes_received_per_message: bytes_received_per_message.clone(),
+                bytes_sent_per_message: HashMap::new(),
+                bytes_sent: 0,
+                connection_time: 1,
+                connection_type: "type0".to_string(),
+                connection_type_kind: PeerConnectionType::from("type0").into(),
+                id: 1,
+                inbound: true,
+                inflight: vec![1337, 45324],
+                last_block: 1337,
+                last_received: 1234,
+                last_send: 1234,
+                last_transaction: 1234,
+                mapped_as: 1234,
+                minfeefilter: 0.000001, // 0.1 sat/vbyte
+                minimum_ping: 1234.0,
+                network: "ipv4".to_string(),
+                network_kind: PeerNetwork::from("ipv4").into(),
+                permissions: vec!["permission".to_string()],
+                ping_time: 1234.0,
+                ping_wait: 1234.0,
+                relay_transactions: true,
+                services: "service".to_string(),
+                services_names: vec![],
+                starting_height: 1337,
+                subversion: "subversion".to_string(),
+                synced_blocks: 4,
+                synced_headers: 5,
+                time_offset: 1234,
+                transport_protocol_type: "v1".to_string(),
+                version: 2841,
+                cpu_load: 0.0,
+                inv_to_send: 0,
+                per_message_omitted: None,
+            },
+            // This peer is not a sub-1 sat/vbyte peer as the minfeefilter is 1 sat/vbyte.
+            PeerInfo {
+                addr_processed: 342,
+                addr_rate_limited: 0,
+                addr_relay_enabled: true,
+                address: "162.218.65.123:8332".to_string(), // LinkingLion IP
+                address_bind: "1.2.3.4:8332".to_string(),
+                address_local: "1.2.3.4:8332".to_string(),
+                bip152_hb_from: false,
+                bip152_hb_to: true,
+                bytes_received: 2344,
+                bytes_received_per_message: bytes_received_per_message.clone(),
+                bytes_sent_per_message: HashMap::new(),
+                bytes_sent: 3483,
+                connection_time: 8432,
+                connection_type: "type1".to_string(),
+                connection_type_kind: PeerConnectionType::from("type1").into(),
+                id: 2,
+                inbound: false,
+                inflight: vec![],
+                last_block: 1337,
+                last_received: 1234,
+                last_send: 1234,
+                last_transaction: 1234,
+                mapped_as: 0,
+                minfeefilter: 0.00001, // 1 sat/vbyte,
+                minimum_ping: 13.0,
+                network: "ipv6".to_string(),
+                network_kind: PeerNetwork::from("ipv6").into(),
+                permissions: vec!["permission".to_string()],
+                ping_time: 23.0,
+                ping_wait: 53.0,
+                relay_transactions: false,
+                services: "service".to_string(),
+                services_names: vec![],
+                starting_height: 231,
+                subversion: "subversion2".to_string(),
+                synced_blocks: 4,
+                synced_headers: 5,
+                time_offset: -1239,
+                transport_protocol_type: "v2".to_string(),
+                version: 2342,
+                cpu_load: 0.0,
+                inv_to_send: 0,
+                per_message_omitted: None,
+            },
+            // This peer is not counted as a sub-1 sat/vbyte peer even if the the minfeefilter is 0.5 sat/vbyte.
+            // It didn't receive or send any tx yet.
+            PeerInfo {
+                addr_processed: 342,
+                addr_rate_limited: 434,
+                addr_relay_enabled: true,
+                address: "162.218.65.123:8332".to_string(), // LinkingLion IP
+                address_bind: "1.2.3.4:8332".to_string(),
+                address_local: "1.2.3.4:8332".to_string(),
+                bip152_hb_from: false,
+                bip152_hb_to: true,
+                bytes_received: 2344,
+                bytes_received_per_message: HashMap::new(),
+                bytes_sent_per_message: HashMap::new(),
+                bytes_sent: 3483,
+                connection_time: 8432,
+                connection_type: "type1".to_string(),
+                connection_type_kind: PeerConnectionType::from("type1").into(),
+                id: 2,
+                inbound: false,
+                inflight: vec![],
+                last_block: 1337,
+                last_received: 1234,
+                last_send: 1234,
+                last_transaction: 1234,
+                mapped_as: 1234,
+                minfeefilter: 0.000005, // 0.5 sat/vbyte,
+                minimum_ping: 13.0,
+                network: "ipv6".to_string(),
+                network_kind: PeerNetwork::from("ipv6").into(),
+                permissions: vec!["permission".to_string()],
+                ping_time: 23.0,
+                ping_wait: 53.0,
+                relay_transactions: false,
+                services: "service".to_string(),
+                services_names: vec![],
+                starting_height: 231,
+                subversion: "subversion2".to_string(),
+                synced_blocks: 4,
+                synced_headers: 5,
+                time_offset: -1239,
+                transport_protocol_type: "v2".to_string(),
+                version: 2342,
+                cpu_load: 0.0,
+                inv_to_send: 0,
+                per_message_omitted: None,
+            },
+        ],
+        chunk_index: None,
+        chunk_total: None,
+    }
+}
+
+fn peer_infos_event(peer_infos: PeerInfos) -> Event {
+    Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+        raw_json: None,
+        correlation_id: None,
+        rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(peer_infos)),
+    }))
+    .unwrap()
+}
+
 #[tokio::test]
 async fn test_integration_metrics_rpc_peerinfo_sub1satvbyte() {
     println!("test that the sub-1 sat/vbyte peers metric works");
 
-    let mut bytes_received_per_message = HashMap::new();
-    bytes_received_per_message.insert("tx".to_string(), 1234);
+    publish_and_check(
+        &[peer_infos_event(sub1satvb_peer_infos())],
+        Subject::Rpc,
+        r#"
+        peerobserver_rpc_peer_info_sub1satvb_relay 1
+        "#,
+    )
+    .await;
+}
 
+#[tokio::test]
+async fn test_integration_metrics_rpc_peerinfo_sub1satvbyte_per_message_omitted() {
+    println!(
+        "test that the sub-1 sat/vbyte peers metric is kept when the per-message data is omitted"
+    );
+
+    let mut omitted = sub1satvb_peer_infos();
+    omitted.omit_per_message();
     publish_and_check(
         &[
-            Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
-                rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(PeerInfos {
-                    infos: vec![
-                        // This peer is a sub-1 sat/vbyte peer as the minfeefilter is 0.1 sat/vbyte
-                        // and it has received txns (bytes_received_per_message).
-                        PeerInfo {
-                            addr_processed: 1234,
-                            addr_rate_limited: 1234,
-                            addr_relay_enabled: false,
-                            // a random IP belonging to a tor exit node.
-                            // This might not be a tor exit node IP in the future and the IP would need to updated.
-                            address: "179.43.182.232:1234".to_string(),
-                            address_bind: "1.2.3.4:8332".to_string(),
-                            address_local: "1.2.3.4:8332".to_string(),
-                            bip152_hb_from: true,
-                            bip152_hb_to: false,
-                            bytes_received: 1,
-                            bytes_received_per_message: bytes_received_per_message.clone(),
-                            bytes_sent_per_message: HashMap::new(),
-                            bytes_sent: 0,
-                            connection_time: 1,
-                            connection_type: "type0".to_string(),
-                            id: 1,
-                            inbound: true,
-                            inflight: vec![1337, 45324],
-                            last_block: 1337,
-                            last_received: 1234,
-                            last_send: 1234,
-                            last_transaction: 1234,
-                            mapped_as: 1234,
-                            minfeefilter: 0.000001, // 0.1 sat/vbyte
-                            minimum_ping: 1234.0,
-                            network: "ipv4".to_string(),
-                            permissions: vec!["permission".to_string()],
-                            ping_time: 1234.0,
-                            ping_wait: 1234.0,
-                            relay_transactions: true,
-                            services: "service".to_string(),
-                            starting_height: 1337,
-                            subversion: "subversion".to_string(),
-                            synced_blocks: 4,
-                            synced_headers: 5,
-                            time_offset: 1234,
-                            transport_protocol_type: "v1".to_string(),
-                            version: 2841,
-                            cpu_load: 0.0,
-                            inv_to_send: 0,
-                        },
-                        // This peer is not a sub-1 sat/vbyte peer as the minfeefilter is 1 sat/vbyte.
-                        PeerInfo {
-                            addr_processed: 342,
-                            addr_rate_limited: 0,
-                            addr_relay_enabled: true,
-                            address: "162.218.65.123:8332".to_string(), // LinkingLion IP
-                            address_bind: "1.2.3.4:8332".to_string(),
-                            address_local: "1.2.3.4:8332".to_string(),
-                            bip152_hb_from: false,
-                            bip152_hb_to: true,
-                            bytes_received: 2344,
-                            bytes_received_per_message: bytes_received_per_message.clone(),
-                            bytes_sent_per_message: HashMap::new(),
-                            bytes_sent: 3483,
-                            connection_time: 8432,
-                            connection_type: "type1".to_string(),
-                            id: 2,
-                            inbound: false,
-                            inflight: vec![],
-                            last_block: 1337,
-                            last_received: 1234,
-                            last_send: 1234,
-                            last_transaction: 1234,
-                            mapped_as: 0,
-                            minfeefilter: 0.00001, // 1 sat/vbyte,
-                            minimum_ping: 13.0,
-                            network: "ipv6".to_string(),
-                            permissions: vec!["permission".to_string()],
-                            ping_time: 23.0,
-                            ping_wait: 53.0,
-                            relay_transactions: false,
-                            services: "service".to_string(),
-                            starting_height: 231,
-                            subversion: "subversion2".to_string(),
-                            synced_blocks: 4,
-                            synced_headers: 5,
-                            time_offset: -1239,
-                            transport_protocol_type: "v2".to_string(),
-                            version: 2342,
-                            cpu_load: 0.0,
-                            inv_to_send: 0,
-                        },
-                        // This peer is not counted as a sub-1 sat/vbyte peer even if the the minfeefilter is 0.5 sat/vbyte.
-                        // It didn't receive or send any tx yet.
-                        PeerInfo {
-                            addr_processed: 342,
-                            addr_rate_limited: 434,
-                            addr_relay_enabled: true,
-                            address: "162.218.65.123:8332".to_string(), // LinkingLion IP
-                            address_bind: "1.2.3.4:8332".to_string(),
-                            address_local: "1.2.3.4:8332".to_string(),
-                            bip152_hb_from: false,
-                            bip152_hb_to: true,
-                            bytes_received: 2344,
-                            bytes_received_per_message: HashMap::new(),
-                            bytes_sent_per_message: HashMap::new(),
-                            bytes_sent: 3483,
-                            connection_time: 8432,
-                            connection_type: "type1".to_string(),
-                            id: 2,
-                            inbound: false,
-                            inflight: vec![],
-                            last_block: 1337,
-                            last_received: 1234,
-                            last_send: 1234,
-                            last_transaction: 1234,
-                            mapped_as: 1234,
-                            minfeefilter: 0.000005, // 0.5 sat/vbyte,
-                            minimum_ping: 13.0,
-                            network: "ipv6".to_string(),
-                            permissions: vec!["permission".to_string()],
-                            ping_time: 23.0,
-                            ping_wait: 53.0,
-                            relay_transactions: false,
-                            services: "service".to_string(),
-                            starting_height: 231,
-                            subversion: "subversion2".to_string(),
-                            synced_blocks: 4,
-                            synced_headers: 5,
-                            time_offset: -1239,
-                            transport_protocol_type: "v2".to_string(),
-                            version: 2342,
-                            cpu_load: 0.0,
-                            inv_to_send: 0,
-                        },
-                    ],
-                })),
-            }))
-            .unwrap(),
+            peer_infos_event(sub1satvb_peer_infos()),
+            peer_infos_event(omitted),
         ],
         Subject::Rpc,
         r#"
@@ -2197,6 +2407,8 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
                 rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(PeerInfos {
                     infos: vec![
                         // This peer has an inv-to-send queue of 77.
@@ -2217,6 +2429,7 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
                             bytes_sent: 0,
                             connection_time: 1,
                             connection_type: "type0".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type0").into(),
                             id: 1,
                             inbound: true,
                             inflight: vec![1337, 45324],
@@ -2228,11 +2441,13 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
                             minfeefilter: 0.000001, // 0.1 sat/vbyte
                             minimum_ping: 1234.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 1234.0,
                             ping_wait: 1234.0,
                             relay_transactions: true,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 1337,
                             subversion: "subversion".to_string(),
                             synced_blocks: 4,
@@ -2242,6 +2457,7 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
                             version: 2841,
                             cpu_load: 0.0,
                             inv_to_send: 77,
+                            per_message_omitted: None,
                         },
                         // This peer has an inv-to-send queue of 1, but is not relaying transactions -> it's not counted.
                         PeerInfo {
@@ -2259,6 +2475,7 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: false,
                             inflight: vec![],
@@ -2270,11 +2487,13 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
                             minfeefilter: 0.00001, // 1 sat/vbyte,
                             minimum_ping: 13.0,
                             network: "ipv6".to_string(),
+                            network_kind: PeerNetwork::from("ipv6").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -2284,6 +2503,7 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 1,
+                            per_message_omitted: None,
                         },
                         // This peer has an inv-to-send queue of 33.
                         PeerInfo {
@@ -2301,6 +2521,7 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: false,
                             inflight: vec![],
@@ -2312,11 +2533,13 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
                             minfeefilter: 0.000005, // 0.5 sat/vbyte,
                             minimum_ping: 13.0,
                             network: "ipv6".to_string(),
+                            network_kind: PeerNetwork::from("ipv6").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: true,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -2326,8 +2549,11 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 33,
+                            per_message_omitted: None,
                         },
                     ],
+                    chunk_index: None,
+                    chunk_total: None,
                 })),
             }))
             .unwrap(),
@@ -2344,6 +2570,42 @@ async fn test_integration_metrics_rpc_peerinfo_invtosend() {
     .await;
 }
 
+#[tokio::test]
+async fn test_integration_metrics_rpc_peerinfo_chunks() {
+    println!("test that the peer-info metrics are derived from all chunks of a split getpeerinfo response");
+
+    let chunk = |index: u32, inv_to_send: u64| {
+        Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+            raw_json: None,
+            correlation_id: None,
+            rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(PeerInfos {
+                infos: vec![PeerInfo {
+                    id: index,
+                    relay_transactions: true,
+                    inv_to_send,
+                    ..Default::default()
+                }],
+                chunk_index: Some(index),
+                chunk_total: Some(2),
+            })),
+        }))
+        .unwrap()
+    };
+
+    publish_and_check(
+        &[chunk(0, 33), chunk(1, 77)],
+        Subject::Rpc,
+        r#"
+        peerobserver_rpc_peer_info_invtosend_max 77
+        peerobserver_rpc_peer_info_invtosend_mean 55
+        peerobserver_rpc_peer_info_invtosend_median 55
+        peerobserver_rpc_peer_info_invtosend_min 33
+        peerobserver_rpc_peer_info_invtosend_sum 110
+        "#,
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn test_integration_metrics_rpc_peerinfo_cpuload() {
     println!("test that the cpuload metrics work");
@@ -2351,6 +2613,8 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
                 rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(PeerInfos {
                     infos: vec![
                         // This peer has a cpu_load 5.0
@@ -2371,6 +2635,7 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
                             bytes_sent: 0,
                             connection_time: 1,
                             connection_type: "type0".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type0").into(),
                             id: 1,
                             inbound: true,
                             inflight: vec![1337, 45324],
@@ -2382,11 +2647,13 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
                             minfeefilter: 0.000001, // 0.1 sat/vbyte
                             minimum_ping: 1234.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 1234.0,
                             ping_wait: 1234.0,
                             relay_transactions: true,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 1337,
                             subversion: "subversion".to_string(),
                             synced_blocks: 4,
@@ -2396,6 +2663,7 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
                             version: 2841,
                             cpu_load: 5.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                         // This peer has a cpu_load of 0.5.
                         PeerInfo {
@@ -2413,6 +2681,7 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: false,
                             inflight: vec![],
@@ -2424,11 +2693,13 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
                             minfeefilter: 0.00001, // 1 sat/vbyte,
                             minimum_ping: 13.0,
                             network: "ipv6".to_string(),
+                            network_kind: PeerNetwork::from("ipv6").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -2438,6 +2709,7 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
                             version: 2342,
                             cpu_load: 0.5,
                             inv_to_send: 1,
+                            per_message_omitted: None,
                         },
                         // This peer has a cpu_load of 1.5.
                         PeerInfo {
@@ -2455,6 +2727,7 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: false,
                             inflight: vec![],
@@ -2466,11 +2739,13 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
                             minfeefilter: 0.000005, // 0.5 sat/vbyte,
                             minimum_ping: 13.0,
                             network: "ipv6".to_string(),
+                            network_kind: PeerNetwork::from("ipv6").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: true,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -2480,8 +2755,11 @@ async fn test_integration_metrics_rpc_peerinfo_cpuload() {
                             version: 2342,
                             cpu_load: 1.5,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                     ],
+                    chunk_index: None,
+                    chunk_total: None,
                 })),
             }))
             .unwrap(),
@@ -2505,6 +2783,8 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
                 rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(PeerInfos {
                     infos: vec![
                         // The first two peers are from the same /16 (123.123.*) and
@@ -2525,6 +2805,7 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
                             bytes_sent: 0,
                             connection_time: 1,
                             connection_type: "type0".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type0").into(),
                             id: 1,
                             inbound: true,
                             inflight: vec![1337, 45324],
@@ -2536,11 +2817,13 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
                             minfeefilter: 1.0,
                             minimum_ping: 1234.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 1234.0,
                             ping_wait: 1234.0,
                             relay_transactions: true,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 1337,
                             subversion: "subversion".to_string(),
                             synced_blocks: 4,
@@ -2550,6 +2833,7 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
                             version: 2841,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                         PeerInfo {
                             addr_processed: 342,
@@ -2566,6 +2850,7 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: true,
                             inflight: vec![],
@@ -2577,11 +2862,13 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
                             minfeefilter: 1.0,
                             minimum_ping: 13.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -2591,6 +2878,7 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                         PeerInfo {
                             addr_processed: 342,
@@ -2607,6 +2895,7 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: true,
                             inflight: vec![],
@@ -2618,11 +2907,13 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
                             minfeefilter: 1.0,
                             minimum_ping: 13.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -2632,8 +2923,11 @@ async fn test_integration_metrics_rpc_peerinfo_ipv4_inbound_diversity() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                     ],
+                    chunk_index: None,
+                    chunk_total: None,
                 })),
             }))
             .unwrap(),
@@ -2653,6 +2947,8 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
                 rpc_event: Some(rpc_extractor::rpc::RpcEvent::PeerInfos(PeerInfos {
                     infos: vec![
                         // A bitprojects inbound peer.
@@ -2671,6 +2967,7 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
                             bytes_sent: 0,
                             connection_time: 1,
                             connection_type: "type0".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type0").into(),
                             id: 1,
                             inbound: true,
                             inflight: vec![1337, 45324],
@@ -2682,11 +2979,13 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
                             minfeefilter: 1.0,
                             minimum_ping: 1234.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 1234.0,
                             ping_wait: 1234.0,
                             relay_transactions: true,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 1337,
                             subversion: "subversion".to_string(),
                             synced_blocks: 4,
@@ -2696,6 +2995,7 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
                             version: 2841,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                         // A bitprojects outbound peer.
                         PeerInfo {
@@ -2713,6 +3013,7 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: false,
                             inflight: vec![],
@@ -2724,11 +3025,13 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
                             minfeefilter: 1.0,
                             minimum_ping: 13.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -2738,6 +3041,7 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                         // not related to bitprojects
                         PeerInfo {
@@ -2755,6 +3059,7 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
                             bytes_sent: 3483,
                             connection_time: 8432,
                             connection_type: "type1".to_string(),
+                            connection_type_kind: PeerConnectionType::from("type1").into(),
                             id: 2,
                             inbound: true,
                             inflight: vec![],
@@ -2766,11 +3071,13 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
                             minfeefilter: 1.0,
                             minimum_ping: 13.0,
                             network: "ipv4".to_string(),
+                            network_kind: PeerNetwork::from("ipv4").into(),
                             permissions: vec!["permission".to_string()],
                             ping_time: 23.0,
                             ping_wait: 53.0,
                             relay_transactions: false,
                             services: "service".to_string(),
+                            services_names: vec![],
                             starting_height: 231,
                             subversion: "subversion2".to_string(),
                             synced_blocks: 4,
@@ -2780,8 +3087,11 @@ async fn test_integration_metrics_rpc_peerinfo_peer_list_bitprojects() {
                             version: 2342,
                             cpu_load: 0.0,
                             inv_to_send: 0,
+                            per_message_omitted: None,
                         },
                     ],
+                    chunk_index: None,
+                    chunk_total: None,
                 })),
             }))
             .unwrap(),
@@ -2802,6 +3112,8 @@ async fn test_integration_metrics_rpc_uptime() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
                 rpc_event: Some(rpc_extractor::rpc::RpcEvent::Uptime(1234)),
             }))
             .unwrap(),
@@ -2815,78 +3127,326 @@ async fn test_integration_metrics_rpc_uptime() {
 }
 
 #[tokio::test]
-async fn test_integration_metrics_rpc_getnettotals() {
-    println!("test that the getnettotal metrics work");
+async fn test_integration_metrics_skips_undecodable_messages() {
+    println!("test that messages that aren't events don't stop the metrics tool");
 
-    publish_and_check(
+    publish_raw_and_check(
+        &[
+            ("rpc", b"not protobuf".as_slice()),
+            ("other-app.data", b"not ours".as_slice()),
+        ],
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
-                rpc_event: Some(rpc_extractor::rpc::RpcEvent::NetTotals(NetTotals {
-                    total_bytes_received: 2222,
-                    total_bytes_sent: 3333,
-                    // not covered
-                    time_millis: 1234,
-                    upload_target: UploadTarget {
-                        timeframe: 1,
-                        target: 1,
-                        target_reached: true,
-                        serve_historical_blocks: true,
-                        bytes_left_in_cycle: 1,
-                        time_left_in_cycle: 1,
-                    },
-                })),
+                raw_json: None,
+                correlation_id: None,
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::Uptime(1234)),
             }))
             .unwrap(),
         ],
         Subject::Rpc,
         r#"
-        peerobserver_rpc_nettotals_total_bytes_received 2222
-        peerobserver_rpc_nettotals_total_bytes_sent 3333
+        peerobserver_rpc_uptime 1234
         "#,
     )
     .await;
 }
 
 #[tokio::test]
-async fn test_integration_metrics_rpc_getmemoryinfo() {
-    println!("test that the getmemoryinfo metrics work");
+async fn test_integration_metrics_rpc_getblockchaininfo() {
+    println!("test that the getblockchaininfo metrics work");
 
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
-                rpc_event: Some(rpc_extractor::rpc::RpcEvent::MemoryInfo(MemoryInfo {
-                    free: 2,
-                    used: 6,
-                    locked: 4,
-                    total: 8,
-                    chunks_free: 10,
-                    chunks_used: 12,
-                })),
+                raw_json: None,
+                correlation_id: None,
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::BlockchainInfo(
+                    BlockchainInfo {
+                        chain: "main".to_string(),
+                        blocks: 900000,
+                        headers: 900002,
+                        best_block_hash:
+                            "0000000000000000000000000000000000000000000000000000000000000000"
+                                .to_string(), // not covered
+                        difficulty: 1.0, // not covered
+                        median_time: 0,  // not covered
+                        verification_progress: 0.75,
+                        initial_block_download: true,
+                        size_on_disk: 700000000000,
+                        pruned: false, // not covered
+                    },
+                )),
             }))
             .unwrap(),
         ],
         Subject::Rpc,
         r#"
-        peerobserver_rpc_memoryinfo_locked_chunks_free 10
-        peerobserver_rpc_memoryinfo_locked_chunks_used 12
-        peerobserver_rpc_memoryinfo_locked_free 2
-        peerobserver_rpc_memoryinfo_locked_locked 4
-        peerobserver_rpc_memoryinfo_locked_total 8
-        peerobserver_rpc_memoryinfo_locked_used 6
+        peerobserver_rpc_blockchaininfo_blocks 900000
+        peerobserver_rpc_blockchaininfo_headers 900002
+        peerobserver_rpc_blockchaininfo_initial_block_download 1
+        peerobserver_rpc_blockchaininfo_size_on_disk 700000000000
+        peerobserver_rpc_blockchaininfo_verification_progress 0.75
         "#,
     )
     .await;
 }
 
 #[tokio::test]
-async fn test_integration_metrics_rpc_mempoolinfo() {
-    println!("test that the mempoolinfo metrics work");
+async fn test_integration_metrics_rpc_estimatesmartfee() {
+    println!("test that the estimatesmartfee metrics work");
 
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
-                rpc_event: Some(rpc_extractor::rpc::RpcEvent::MempoolInfo(MempoolInfo {
-                    loaded: true,
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::FeeEstimates(FeeEstimates {
+                    estimate_mode: "economical".to_string(),
+                    estimates: [
+                        (
+                            1,
+                            FeeEstimate {
+                                feerate: Some(0.0002),
+                                blocks: 2,
+                                errors: vec![],
+                            },
+                        ),
+                        (
+                            144,
+                            FeeEstimate {
+                                feerate: Some(0.00001),
+                                blocks: 144,
+                                errors: vec![],
+                            },
+                        ),
+                        (
+                            1008,
+                            FeeEstimate {
+                                feerate: None,
+                                blocks: 0,
+                                errors: vec!["Insufficient data or no feerate found".to_string()],
+                            },
+                        ),
+                    ]
+                    .into(),
+                })),
+                raw_json: None,
+                correlation_id: None,
+            }))
+            .unwrap(),
+        ],
+        Subject::Rpc,
+        r#"
+        peerobserver_rpc_feeestimate_feerate{target="1"} 0.0002
+        peerobserver_rpc_feeestimate_feerate{target="144"} 0.00001
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_rpc_getorphantxs() {
+    println!("test that the getorphantxs metrics work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::OrphanTxs(OrphanTxs {
+                    orphans: vec![
+                        OrphanTx {
+                            txid: "aa".to_string(),
+                            wtxid: "bb".to_string(),
+                            vsize: 150,
+                            entry: None,
+                            expiration: None,
+                            from: vec![3],
+                        },
+                        OrphanTx {
+                            txid: "cc".to_string(),
+                            wtxid: "dd".to_string(),
+                            vsize: 250,
+                            entry: None,
+                            expiration: None,
+                            from: vec![3, 7],
+                        },
+                    ],
+                })),
+                raw_json: None,
+                correlation_id: None,
+            }))
+            .unwrap(),
+        ],
+        Subject::Rpc,
+        r#"
+        peerobserver_rpc_orphantxs 2
+        peerobserver_rpc_orphantxs_vsize 400
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_rpc_getnodeaddresses() {
+    println!("test that the getnodeaddresses metrics work");
+
+    let address = |address: &str, network: Option<&str>| NodeAddress {
+        address: address.to_string(),
+        port: 8333,
+        services: 9,
+        time: 1700000000,
+        network: network.map(str::to_string),
+    };
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::NodeAddresses(NodeAddresses {
+                    addresses: vec![
+                        address("1.2.3.4", Some("ipv4")),
+                        address("1.2.3.5", Some("ipv4")),
+                        address("2001:db8::1", None),
+                    ],
+                })),
+                raw_json: None,
+                correlation_id: None,
+            }))
+            .unwrap(),
+        ],
+        Subject::Rpc,
+        r#"
+        peerobserver_rpc_nodeaddresses{network="ipv4"} 2
+        peerobserver_rpc_nodeaddresses{network="unknown"} 1
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_rpc_connection_churn() {
+    println!("test that the connection churn metrics work");
+
+    let peer = |id: u32| ChurnPeer {
+        id,
+        address: "1.2.3.4:8333".to_string(),
+        connection_type: "inbound".to_string(),
+        connection_time: 1000,
+        session_duration: 60,
+    };
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::ConnectionChurn(
+                    ConnectionChurn {
+                        connected: vec![peer(3), peer(4)],
+                        disconnected: vec![peer(1)],
+                    },
+                )),
+                raw_json: None,
+                correlation_id: None,
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::ConnectionChurn(
+                    ConnectionChurn {
+                        connected: vec![peer(5)],
+                        disconnected: vec![],
+                    },
+                )),
+                raw_json: None,
+                correlation_id: None,
+            }))
+            .unwrap(),
+        ],
+        Subject::Rpc,
+        r#"
+        peerobserver_rpc_churn_connected_peers 3
+        peerobserver_rpc_churn_disconnected_peers 1
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_rpc_getnettotals() {
+    println!("test that the getnettotal metrics work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::NetTotals(NetTotals {
+                    total_bytes_received: 2222,
+                    total_bytes_sent: 3333,
+                    // not covered
+                    time_millis: 1234,
+                    upload_target: UploadTarget {
+                        timeframe: 1,
+                        target: 1,
+                        target_reached: true,
+                        serve_historical_blocks: true,
+                        bytes_left_in_cycle: 1,
+                        time_left_in_cycle: 1,
+                    },
+                    recv_bytes_per_sec: Some(12.5),
+                    sent_bytes_per_sec: Some(25.0),
+                })),
+            }))
+            .unwrap(),
+        ],
+        Subject::Rpc,
+        r#"
+        peerobserver_rpc_nettotals_recv_bytes_per_second 12.5
+        peerobserver_rpc_nettotals_sent_bytes_per_second 25
+        peerobserver_rpc_nettotals_total_bytes_received 2222
+        peerobserver_rpc_nettotals_total_bytes_sent 3333
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_rpc_getmemoryinfo() {
+    println!("test that the getmemoryinfo metrics work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::MemoryInfo(MemoryInfo {
+                    free: 2,
+                    used: 6,
+                    locked: 4,
+                    total: 8,
+                    chunks_free: 10,
+                    chunks_used: 12,
+                })),
+            }))
+            .unwrap(),
+        ],
+        Subject::Rpc,
+        r#"
+        peerobserver_rpc_memoryinfo_locked_chunks_free 10
+        peerobserver_rpc_memoryinfo_locked_chunks_used 12
+        peerobserver_rpc_memoryinfo_locked_free 2
+        peerobserver_rpc_memoryinfo_locked_locked 4
+        peerobserver_rpc_memoryinfo_locked_total 8
+        peerobserver_rpc_memoryinfo_locked_used 6
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_rpc_mempoolinfo() {
+    println!("test that the mempoolinfo metrics work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
+                rpc_event: Some(rpc_extractor::rpc::RpcEvent::MempoolInfo(MempoolInfo {
+                    loaded: true,
                     size: 1000,
                     bytes: 2000,
                     usage: 3000,
@@ -2895,8 +3455,10 @@ async fn test_integration_metrics_rpc_mempoolinfo() {
                     incrementalrelayfee: 6.0001,
                     mempoolminfee: 7.2,
                     minrelaytxfee: 8.3,
-                    unbroadcastcount: 0, // not covered
-                    fullrbf: false,      // not covered
+                    unbroadcastcount: 0,       // not covered
+                    fullrbf: false,            // not covered
+                    mempoolminfee_delta: None, // not covered
+                    mempoolminfee_trend: None, // not covered
                 })),
             }))
             .unwrap(),
@@ -2942,6 +3504,8 @@ async fn test_integration_metrics_rpc_getaddrmaninfo() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+                raw_json: None,
+                correlation_id: None,
                 rpc_event: Some(rpc_extractor::rpc::RpcEvent::AddrmanInfo(AddrManInfo {
                     networks,
                 })),
@@ -3066,11 +3630,15 @@ async fn test_integration_metrics_p2pextractor_feefilter_annoucement() {
     publish_and_check(
         &[
             Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
-                p2p_event: Some(p2p_extractor::p2p::P2pEvent::FeefilterAnnouncement(1234)),
+                p2p_event: Some(p2p_extractor::p2p::P2pEvent::FeefilterAnnouncement(
+                    p2p_extractor::FeefilterAnnouncement::new(1234),
+                )),
             }))
             .unwrap(),
             Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
-                p2p_event: Some(p2p_extractor::p2p::P2pEvent::FeefilterAnnouncement(2345)),
+                p2p_event: Some(p2p_extractor::p2p::P2pEvent::FeefilterAnnouncement(
+                    p2p_extractor::FeefilterAnnouncement::new(2345),
+                )),
             }))
             .unwrap(),
         ],
@@ -3084,148 +3652,923 @@ async fn test_integration_metrics_p2pextractor_feefilter_annoucement() {
 }
 
 #[tokio::test]
-async fn test_integration_metrics_logextractor_logevents() {
-    println!("test that log-extractor log events metric work");
+async fn test_integration_metrics_p2pextractor_compact_blocks() {
+    println!("test that p2p-extractor sendcmpct and cmpctblock metrics work");
 
     publish_and_check(
         &[
-            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
-                category: LogDebugCategory::Unknown.into(),
-                log_timestamp: 1234,
-                log_event: Some(log_extractor::log::LogEvent::UnknownLogMessage(
-                    log_extractor::UnknownLogMessage {
-                        raw_message: "test1".to_string(),
+            Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+                p2p_event: Some(p2p_extractor::p2p::P2pEvent::SendCompact(
+                    p2p_extractor::SendCompact {
+                        high_bandwidth: false,
+                        version: 2,
                     },
                 )),
             }))
             .unwrap(),
-            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
-                category: LogDebugCategory::Unknown.into(),
-                log_timestamp: 1234,
-                log_event: Some(log_extractor::log::LogEvent::UnknownLogMessage(
-                    log_extractor::UnknownLogMessage {
-                        raw_message: "test2".to_string(),
+            Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+                p2p_event: Some(p2p_extractor::p2p::P2pEvent::CompactBlockAnnouncement(
+                    p2p_extractor::CompactBlockAnnouncement {
+                        block_hash: vec![0; 32],
+                        short_ids: 12,
+                        prefilled_transactions: 1,
                     },
                 )),
             }))
             .unwrap(),
         ],
-        Subject::LogExtractor,
+        Subject::Validation,
         r#"
-        peerobserver_log_events{category="unknown"} 2
+        peerobserver_p2pextractor_cmpctblock_messages 1
+        peerobserver_p2pextractor_sendcmpct_messages{high_bandwidth="false"} 1
         "#,
     )
     .await;
 }
 
 #[tokio::test]
-async fn test_integration_metrics_logextractor_blockconnected_events() {
-    println!("test that log-extractor block connected log events metric work");
+async fn test_integration_metrics_p2pextractor_tx_relay() {
+    println!("test that p2p-extractor tx relay metrics work");
 
     publish_and_check(
         &[
-            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
-                category: LogDebugCategory::Validation.into(),
-                log_timestamp: 345,
-                log_event: Some(log_extractor::log::LogEvent::BlockConnectedLog(
-                    log_extractor::BlockConnectedLog {
-                        block_height: 1234,
-                        block_hash:
-                            "b00000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
-                                .to_string(),
-                    },
-                )),
-            }))
-            .unwrap(),
-            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
-                category: LogDebugCategory::Validation.into(),
-                log_timestamp: 3452,
-                log_event: Some(log_extractor::log::LogEvent::BlockConnectedLog(
-                    log_extractor::BlockConnectedLog {
-                        block_height: 2222,
-                        block_hash:
-                            "a00000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26d"
-                                .to_string(),
+            Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+                p2p_event: Some(p2p_extractor::p2p::P2pEvent::TransactionRelay(
+                    p2p_extractor::TransactionRelay {
+                        txid: vec![0; 32],
+                        wtxid: vec![1; 32],
+                        size: 222,
+                        raw_tx: None,
                     },
                 )),
             }))
             .unwrap(),
-            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
-                category: LogDebugCategory::Unknown.into(),
-                log_timestamp: 1234,
-                log_event: Some(log_extractor::log::LogEvent::UnknownLogMessage(
-                    log_extractor::UnknownLogMessage {
-                        raw_message: "test2".to_string(),
+            Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+                p2p_event: Some(p2p_extractor::p2p::P2pEvent::TransactionRelay(
+                    p2p_extractor::TransactionRelay {
+                        txid: vec![2; 32],
+                        wtxid: vec![3; 32],
+                        size: 100,
+                        raw_tx: None,
                     },
                 )),
             }))
             .unwrap(),
         ],
-        Subject::LogExtractor,
+        Subject::P2PExtractor,
         r#"
-        peerobserver_log_block_connected_events 2
-        peerobserver_log_events{category="unknown"} 1
-        peerobserver_log_events{category="validation"} 2
+        peerobserver_p2pextractor_tx_bytes 322
+        peerobserver_p2pextractor_tx_messages 2
         "#,
     )
     .await;
 }
 
 #[tokio::test]
-async fn test_integration_metrics_logextractor_blockchecked_events() {
-    println!("test that log-extractor block checked log events metric work");
+async fn test_integration_metrics_p2pextractor_block_relay() {
+    println!("test that p2p-extractor block relay metrics work");
 
     publish_and_check(
         &[
-            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
-                category: LogDebugCategory::Validation.into(),
-                log_timestamp: 345,
-                log_event: Some(log_extractor::log::LogEvent::BlockCheckedLog(
-                    log_extractor::BlockCheckedLog {
-                        debug_message: "".to_string(),
-                        state: "Valid".to_string(),
-                        block_hash:
-                            "309665469cc06cecdd0be45f3070aa446b4871bb5d1b0bc97965895d1d35f541"
-                                .to_string(),
+            Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+                p2p_event: Some(p2p_extractor::p2p::P2pEvent::BlockRelay(
+                    p2p_extractor::BlockRelay {
+                        header: bitcoin_primitives::BlockHeader {
+                            version: 4,
+                            prev_blockhash: vec![0; 32],
+                            merkle_root: vec![1; 32],
+                            time: 1234,
+                            bits: 5678,
+                            nonce: 9012,
+                            hash: vec![2; 32],
+                        },
+                        transactions: 2500,
+                        size: 1500000,
+                        raw_block: None,
                     },
                 )),
             }))
             .unwrap(),
         ],
-        Subject::LogExtractor,
+        Subject::P2PExtractor,
         r#"
-        peerobserver_log_block_checked_events 1
-        peerobserver_log_events{category="validation"} 1
+        peerobserver_p2pextractor_block_last_size 1500000
+        peerobserver_p2pextractor_block_last_transactions 2500
+        peerobserver_p2pextractor_block_messages 1
         "#,
     )
     .await;
 }
 
 #[tokio::test]
-async fn test_integration_metrics_logextractor_blockchecked_mutated_events() {
-    println!("test that log-extractor block checked mutated block events metric work");
+async fn test_integration_metrics_p2pextractor_addr_rate_alert() {
+    println!("test that p2p-extractor addr rate alert metrics work");
 
     publish_and_check(
         &[
-            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
-                category: LogDebugCategory::Validation.into(),
-                log_timestamp: 345,
-                log_event: Some(log_extractor::log::LogEvent::BlockCheckedLog(
-                    log_extractor::BlockCheckedLog {
-                        debug_message: "duplicate transaction".to_string(),
-                        state: "bad-txns-duplicate".to_string(),
-                        block_hash:
-                            "2eb63cc71bccef4f3d5da560d810513e2e1155ff7be04d5502ad3e038f956e92"
-                                .to_string(),
+            Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+                p2p_event: Some(p2p_extractor::p2p::P2pEvent::AddrRateAlert(
+                    p2p_extractor::AddrRateAlert {
+                        peer: "127.0.0.1:8333".to_string(),
+                        addresses: 1500,
+                        threshold: 1000,
                     },
                 )),
             }))
             .unwrap(),
         ],
-        Subject::LogExtractor,
+        Subject::P2PExtractor,
         r#"
-        peerobserver_log_block_checked_events 1
-        peerobserver_log_mutated_blocks{status="bad-txns-duplicate"} 1
+        peerobserver_p2pextractor_addr_rate_alerts 1
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_p2pextractor_feature_negotiation() {
+    println!("test that p2p-extractor feature negotiation metrics work");
+
+    let features = |send_headers: bool| {
+        Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+            p2p_event: Some(p2p_extractor::p2p::P2pEvent::FeatureNegotiation(
+                p2p_extractor::FeatureNegotiation {
+                    send_addr_v2: true,
+                    wtxid_relay: false,
+                    send_headers,
+                },
+            )),
+        }))
+        .unwrap()
+    };
+    publish_and_check(
+        &[features(false), features(true)],
+        Subject::P2PExtractor,
+        r#"
+        peerobserver_p2pextractor_feature_negotiated{feature="sendaddrv2"} 1
+        peerobserver_p2pextractor_feature_negotiated{feature="sendheaders"} 1
+        peerobserver_p2pextractor_feature_negotiated{feature="wtxidrelay"} 0
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_p2pextractor_header_requests() {
+    println!("test that p2p-extractor header request metrics work");
+
+    let request = |command: p2p_extractor::HeaderRequestCommand| {
+        Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+            p2p_event: Some(p2p_extractor::p2p::P2pEvent::HeaderRequest(
+                p2p_extractor::HeaderRequest {
+                    command: command.into(),
+                    version: 70016,
+                    locator_hashes: vec![vec![1u8; 32]],
+                    stop_hash: vec![0u8; 32],
+                },
+            )),
+        }))
+        .unwrap()
+    };
+    publish_and_check(
+        &[
+            request(p2p_extractor::HeaderRequestCommand::Getheaders),
+            request(p2p_extractor::HeaderRequestCommand::Getheaders),
+            request(p2p_extractor::HeaderRequestCommand::Getblocks),
+        ],
+        Subject::P2PExtractor,
+        r#"
+        peerobserver_p2pextractor_header_requests{message="getblocks"} 1
+        peerobserver_p2pextractor_header_requests{message="getheaders"} 2
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_p2pextractor_parse_errors() {
+    println!("test that p2p-extractor parse error metrics work");
+
+    let parse_error = |command: &str, suppressed: u64| {
+        Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+            p2p_event: Some(p2p_extractor::p2p::P2pEvent::ParseError(
+                p2p_extractor::ParseError {
+                    peer: "127.0.0.1:8333".to_string(),
+                    command: command.to_string(),
+                    length: 3,
+                    reason: "unexpected end of file".to_string(),
+                    suppressed,
+                },
+            )),
+        }))
+        .unwrap()
+    };
+    publish_and_check(
+        &[
+            parse_error("tx", 0),
+            parse_error("tx", 0),
+            parse_error("foo", 5),
+        ],
+        Subject::P2PExtractor,
+        r#"
+        peerobserver_p2pextractor_parse_errors{message="other"} 1
+        peerobserver_p2pextractor_parse_errors{message="tx"} 2
+        peerobserver_p2pextractor_parse_errors_suppressed 5
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_p2pextractor_service_mismatches() {
+    println!("test that p2p-extractor service mismatch metrics work");
+
+    let mismatch = |kind: p2p_extractor::ServiceMismatchKind, command: &str| {
+        Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
+            p2p_event: Some(p2p_extractor::p2p::P2pEvent::ServiceMismatch(
+                p2p_extractor::ServiceMismatch {
+                    peer: "127.0.0.1:8333".to_string(),
+                    kind: kind.into(),
+                    command: command.to_string(),
+                    version: 70013,
+                    services: 9,
+                    required_version: None,
+                },
+            )),
+        }))
+        .unwrap()
+    };
+    publish_and_check(
+        &[
+            mismatch(
+                p2p_extractor::ServiceMismatchKind::MessageAboveVersion,
+                "wtxidrelay",
+            ),
+            mismatch(p2p_extractor::ServiceMismatchKind::WitnessStripped, "block"),
+            mismatch(p2p_extractor::ServiceMismatchKind::WitnessStripped, "block"),
+        ],
+        Subject::P2PExtractor,
+        r#"
+        peerobserver_p2pextractor_service_mismatches{message="block",mismatch="witness_stripped"} 2
+        peerobserver_p2pextractor_service_mismatches{message="wtxidrelay",mismatch="message_above_version"} 1
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_logevents() {
+    println!("test that log-extractor log events metric work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 1234,
+                log_event: Some(log_extractor::log::LogEvent::UnknownLogMessage(
+                    log_extractor::UnknownLogMessage {
+                        raw_message: "test1".to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 1234,
+                log_event: Some(log_extractor::log::LogEvent::UnknownLogMessage(
+                    log_extractor::UnknownLogMessage {
+                        raw_message: "test2".to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_events{category="unknown"} 2
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_blockconnected_events() {
+    println!("test that log-extractor block connected log events metric work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Validation.into(),
+                log_timestamp: 345,
+                log_event: Some(log_extractor::log::LogEvent::BlockConnectedLog(
+                    log_extractor::BlockConnectedLog {
+                        block_height: 1234,
+                        block_hash:
+                            "b00000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+                                .to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Validation.into(),
+                log_timestamp: 3452,
+                log_event: Some(log_extractor::log::LogEvent::BlockConnectedLog(
+                    log_extractor::BlockConnectedLog {
+                        block_height: 2222,
+                        block_hash:
+                            "a00000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26d"
+                                .to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 1234,
+                log_event: Some(log_extractor::log::LogEvent::UnknownLogMessage(
+                    log_extractor::UnknownLogMessage {
+                        raw_message: "test2".to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_block_connected_events 2
+        peerobserver_log_events{category="unknown"} 1
+        peerobserver_log_events{category="validation"} 2
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_blockchecked_events() {
+    println!("test that log-extractor block checked log events metric work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Validation.into(),
+                log_timestamp: 345,
+                log_event: Some(log_extractor::log::LogEvent::BlockCheckedLog(
+                    log_extractor::BlockCheckedLog {
+                        debug_message: "".to_string(),
+                        state: "Valid".to_string(),
+                        block_hash:
+                            "309665469cc06cecdd0be45f3070aa446b4871bb5d1b0bc97965895d1d35f541"
+                                .to_string(),
+                        check_state: None,
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_block_checked_events 1
+        peerobserver_log_events{category="validation"} 1
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_blockchecked_mutated_events() {
+    println!("test that log-extractor block checked mutated block events metric work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Validation.into(),
+                log_timestamp: 345,
+                log_event: Some(log_extractor::log::LogEvent::BlockCheckedLog(
+                    log_extractor::BlockCheckedLog {
+                        debug_message: "duplicate transaction".to_string(),
+                        state: "bad-txns-duplicate".to_string(),
+                        block_hash:
+                            "2eb63cc71bccef4f3d5da560d810513e2e1155ff7be04d5502ad3e038f956e92"
+                                .to_string(),
+                        check_state: None,
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_block_checked_events 1
+        peerobserver_log_mutated_blocks{status="bad-txns-duplicate"} 1
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_header_sync_events() {
+    println!("test that log-extractor header sync log events metric work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 345,
+                log_event: Some(log_extractor::log::LogEvent::HeaderSyncLog(
+                    log_extractor::HeaderSyncLog {
+                        stage: log_extractor::HeaderSyncStage::Sync.into(),
+                        height: 420000,
+                        progress: Some(45.12),
+                        block_hash: None,
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 346,
+                log_event: Some(log_extractor::log::LogEvent::HeaderSyncLog(
+                    log_extractor::HeaderSyncLog {
+                        stage: log_extractor::HeaderSyncStage::NewHeader.into(),
+                        height: 921234,
+                        progress: None,
+                        block_hash: Some(
+                            "00000000000000000001b4a6e9cd8d8a1bc5fe1f0b5fb74f3af9e1e6e1ab8d7a"
+                                .to_string(),
+                        ),
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_header_sync_height{stage="new_header"} 921234
+        peerobserver_log_header_sync_height{stage="sync"} 420000
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_disk_activity_events() {
+    println!("test that log-extractor disk activity log events metric work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Bench.into(),
+                log_timestamp: 345,
+                log_event: Some(log_extractor::log::LogEvent::DiskActivityLog(
+                    log_extractor::DiskActivityLog {
+                        operation: log_extractor::DiskActivityOperation::FlushCoins.into(),
+                        completed: Some(false),
+                        coins: Some(2319),
+                        size_kib: Some(312.54),
+                        ..Default::default()
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Bench.into(),
+                log_timestamp: 346,
+                log_event: Some(log_extractor::log::LogEvent::DiskActivityLog(
+                    log_extractor::DiskActivityLog {
+                        operation: log_extractor::DiskActivityOperation::FlushCoins.into(),
+                        completed: Some(true),
+                        duration_ms: Some(4.12),
+                        coins: Some(2319),
+                        size_kib: Some(312.54),
+                        ..Default::default()
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Prune.into(),
+                log_timestamp: 347,
+                log_event: Some(log_extractor::log::LogEvent::DiskActivityLog(
+                    log_extractor::DiskActivityLog {
+                        operation: log_extractor::DiskActivityOperation::PruneUnlink.into(),
+                        file_number: Some(123),
+                        ..Default::default()
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_disk_activity_events{operation="flush_coins"} 1
+        peerobserver_log_disk_activity_events{operation="prune_unlink"} 1
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_discouraged_peer_events() {
+    println!("test that log-extractor discouraged peer log events metric work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Net.into(),
+                log_timestamp: 345,
+                log_event: Some(log_extractor::log::LogEvent::DiscouragedPeerLog(
+                    log_extractor::DiscouragedPeerLog {
+                        peer_id: 12,
+                        action: log_extractor::DiscourageAction::Misbehaving.into(),
+                        reason: Some("invalid header received".to_string()),
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Net.into(),
+                log_timestamp: 346,
+                log_event: Some(log_extractor::log::LogEvent::DiscouragedPeerLog(
+                    log_extractor::DiscouragedPeerLog {
+                        peer_id: 12,
+                        action: log_extractor::DiscourageAction::Discouraged.into(),
+                        reason: None,
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_discouraged_peer_events{action="discouraged"} 1
+        peerobserver_log_discouraged_peer_events{action="misbehaving"} 1
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_network_error_events() {
+    println!("test that log-extractor network error log events metric work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Net.into(),
+                log_timestamp: 345,
+                log_event: Some(log_extractor::log::LogEvent::NetworkErrorLog(
+                    log_extractor::NetworkErrorLog {
+                        peer_id: Some(7),
+                        error: "Connection reset by peer (104)".to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Net.into(),
+                log_timestamp: 346,
+                log_event: Some(log_extractor::log::LogEvent::NetworkErrorLog(
+                    log_extractor::NetworkErrorLog {
+                        peer_id: None,
+                        error: "connect() to 203.0.113.5:8333 failed after wait: Connection refused (111)".to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_network_error_events 2
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_critical_error_events() {
+    println!("test that log-extractor critical error log events metric work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 345,
+                log_event: Some(log_extractor::log::LogEvent::CriticalErrorLog(
+                    log_extractor::CriticalErrorLog {
+                        kind: log_extractor::CriticalErrorKind::Corruption.into(),
+                        severity: log_extractor::LogSeverity::Critical.into(),
+                        message: "Fatal LevelDB error: Corruption: block checksum mismatch"
+                            .to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 346,
+                log_event: Some(log_extractor::log::LogEvent::CriticalErrorLog(
+                    log_extractor::CriticalErrorLog {
+                        kind: log_extractor::CriticalErrorKind::BlockRead.into(),
+                        severity: log_extractor::LogSeverity::Error.into(),
+                        message: "ReadBlock: Deserialize or I/O error".to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_critical_error_events{kind="block_read",severity="error"} 1
+        peerobserver_log_critical_error_events{kind="corruption",severity="critical"} 1
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_ibd_complete() {
+    println!("test that the log-extractor IBD complete metric works");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 1762351353000000,
+                log_event: Some(log_extractor::log::LogEvent::IbdCompleteLog(
+                    log_extractor::IbdCompleteLog {},
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_ibd_complete_timestamp 1762351353
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_snapshot() {
+    println!("test that the log-extractor AssumeUTXO snapshot metrics work");
+
+    let block_hash = "0000000000000000000320283a032748cef8227873ff4872689bf23f1cda83a5".to_string();
+    let log = |log_timestamp: u64, log_event: log_extractor::log::LogEvent| {
+        Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+            occurrences: None,
+            raw_line: None,
+            category: LogDebugCategory::Unknown.into(),
+            log_timestamp,
+            log_event: Some(log_event),
+        }))
+        .unwrap()
+    };
+
+    publish_and_check(
+        &[
+            log(
+                1763633702000000,
+                log_extractor::log::LogEvent::SnapshotLoadLog(log_extractor::SnapshotLoadLog {
+                    block_hash: block_hash.clone(),
+                    coins: 176948713,
+                    completed: false,
+                    size_mb: None,
+                }),
+            ),
+            log(
+                1763634707000000,
+                log_extractor::log::LogEvent::SnapshotLoadLog(log_extractor::SnapshotLoadLog {
+                    block_hash: block_hash.clone(),
+                    coins: 176948713,
+                    completed: true,
+                    size_mb: Some(9127.43),
+                }),
+            ),
+            log(
+                1763783050000000,
+                log_extractor::log::LogEvent::SnapshotValidatedLog(
+                    log_extractor::SnapshotValidatedLog { block_hash },
+                ),
+            ),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_snapshot_coins_loaded 176948713
+        peerobserver_log_snapshot_validated_timestamp 1763783050
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_block_template() {
+    println!("test that the log-extractor block template metrics work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 1762936202000000,
+                log_event: Some(log_extractor::log::LogEvent::BlockTemplateLog(
+                    log_extractor::BlockTemplateLog {
+                        weight: 3996960,
+                        tx_count: 3140,
+                        fees: 2301450,
+                        sigops: 9516,
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Bench.into(),
+                log_timestamp: 1762936202000000,
+                log_event: Some(log_extractor::log::LogEvent::BlockTemplateTimingLog(
+                    log_extractor::BlockTemplateTimingLog {
+                        packages_ms: 21.07,
+                        packages: 3084,
+                        updated_descendants: 1822,
+                        validity_ms: 18.63,
+                        total_ms: 39.7,
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_block_template_events 1
+        peerobserver_log_block_template_weight 3996960
+        peerobserver_log_block_template_txs 3140
+        peerobserver_log_block_template_fees 2301450
+        peerobserver_log_block_template_duration_ms 39.7
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_logextractor_unknown_log_events() {
+    println!("test that log-extractor unknown log metrics work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 1234,
+                log_event: Some(log_extractor::log::LogEvent::UnknownLogMessage(
+                    log_extractor::UnknownLogMessage {
+                        raw_message: "test1".to_string(),
+                    },
+                )),
+            }))
+            .unwrap(),
+            Event::new(PeerObserverEvent::LogExtractor(log_extractor::Log {
+                occurrences: None,
+                raw_line: None,
+                category: LogDebugCategory::Unknown.into(),
+                log_timestamp: 1235,
+                log_event: Some(log_extractor::log::LogEvent::UnknownLogSummary(
+                    log_extractor::UnknownLogSummary {
+                        total_lines: 4,
+                        unknown_lines: 1,
+                        window_seconds: 600,
+                    },
+                )),
+            }))
+            .unwrap(),
+        ],
+        Subject::LogExtractor,
+        r#"
+        peerobserver_log_events{category="unknown"} 1
+        peerobserver_log_unknown_messages 1
+        peerobserver_log_unknown_ratio 0.25
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_error_events() {
+    println!("test that the error event metrics work");
+
+    publish_and_check(
+        &[
+            Event::new(PeerObserverEvent::ErrorEvent(ErrorEvent::new(
+                Extractor::RpcExtractor,
+                "getpeerinfo",
+                "RPC error: connection refused".to_string(),
+                ErrorSeverity::Error,
+            )))
+            .unwrap(),
+            Event::new(PeerObserverEvent::ErrorEvent(ErrorEvent::new(
+                Extractor::RpcExtractor,
+                "getpeerinfo",
+                "RPC error: connection refused".to_string(),
+                ErrorSeverity::Error,
+            )))
+            .unwrap(),
+        ],
+        Subject::Errors,
+        r#"
+        peerobserver_errors{extractor="rpc-extractor",operation="getpeerinfo",severity="error"} 2
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_extractor_metrics() {
+    println!("test that the extractor metrics work");
+
+    let extractor_metrics = |lines_read: u64, pending: f64| {
+        Event::new(PeerObserverEvent::ExtractorMetrics(ExtractorMetrics {
+            extractor: Extractor::LogExtractor.into(),
+            window: 60,
+            counters: BTreeMap::from([("lines_read".to_string(), lines_read)]),
+            gauges: BTreeMap::from([("pending".to_string(), pending)]),
+        }))
+        .unwrap()
+    };
+
+    publish_and_check(
+        &[extractor_metrics(10, 2.0), extractor_metrics(5, 3.0)],
+        Subject::ExtractorMetrics,
+        r#"
+        peerobserver_extractor_counter{extractor="log-extractor",name="lines_read"} 15
+        peerobserver_extractor_gauge{extractor="log-extractor",name="pending"} 3
+        "#,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_integration_metrics_extractor_heartbeats() {
+    println!("test that the extractor heartbeats work");
+
+    let heartbeat = |sequence: u64, timestamp: u64| Event {
+        timestamp,
+        ..Event::new(PeerObserverEvent::Heartbeat(Heartbeat {
+            extractor: Extractor::RpcExtractor.into(),
+            sequence,
+            interval: 30,
+        }))
+        .unwrap()
+    };
+
+    publish_and_check(
+        &[
+            heartbeat(0, 1_700_000_000_123),
+            heartbeat(1, 1_700_000_030_456),
+        ],
+        Subject::Heartbeat,
+        r#"
+        peerobserver_extractor_heartbeats{extractor="rpc-extractor"} 2
+        peerobserver_extractor_last_heartbeat_timestamp{extractor="rpc-extractor"} 1700000030
         "#,
     )
     .await;
@@ -3,10 +3,11 @@
 use shared::clap::Parser;
 use shared::futures::{stream::SplitSink, SinkExt, StreamExt};
 use shared::log;
-use shared::prost::Message;
-use shared::protobuf::event::{self, event::PeerObserverEvent};
+use shared::nats::{self, decode_events, parse_nats_address};
+use shared::nats_subjects::{is_event_subject, parse_subject_prefix};
+use shared::protobuf::event::event::PeerObserverEvent;
 use shared::{
-    async_nats, clap,
+    clap,
     tokio::{
         self,
         net::{TcpListener, TcpStream},
@@ -27,7 +28,8 @@ pub mod error;
 #[command(version, about, long_about = None)]
 pub struct Args {
     /// The NATS server address the tool should connect and subscribe to.
-    #[arg(short, long, default_value = "127.0.0.1:4222")]
+    /// Multiple comma-separated addresses, e.g. of the servers in a NATS cluster, can be given.
+    #[arg(short, long, default_value = "127.0.0.1:4222", value_parser = parse_nats_address)]
     pub nats_address: String,
 
     /// The websocket address the tool listens on.
@@ -38,6 +40,11 @@ pub struct Args {
     /// "debug", "info", "warn", "error". See https://docs.rs/log/latest/log/enum.Level.html
     #[arg(short, long, default_value_t = log::Level::Debug)]
     pub log_level: log::Level,
+
+    /// Subscribe to the events published below this subject prefix, e.g. on
+    /// `team-a.rpc` for extractors started with `--nats-subject-prefix team-a`.
+    #[arg(long, value_name = "PREFIX", value_parser = parse_subject_prefix)]
+    pub nats_subject_prefix: Option<String>,
 }
 
 impl Args {
@@ -46,6 +53,7 @@ impl Args {
             nats_address,
             websocket_address,
             log_level,
+            nats_subject_prefix: None,
         }
     }
 }
@@ -58,9 +66,9 @@ pub async fn run(
     mut shutdown_rx: watch::Receiver<bool>,
 ) -> Result<(), error::RuntimeError> {
     log::debug!("Connecting to NATS-server at {}", args.nats_address);
-    let nc = async_nats::connect(args.nats_address.clone()).await?;
+    let nc = nats::connect(&args.nats_address).await?;
     log::info!("Connected to NATS-server at {}", args.nats_address);
-    let mut sub = nc.subscribe("*").await?;
+    let mut sub = nats::subscribe_events(&nc, args.nats_subject_prefix.as_deref()).await?;
 
     let clients = Arc::new(Mutex::new(HashMap::new()));
 
@@ -69,10 +77,16 @@ pub async fn run(
         let clients = Arc::clone(&clients);
         tokio::spawn(async move {
             while let Some(msg) = sub.next().await {
-                match event::Event::decode(msg.payload) {
-                    Ok(event) => {
-                        if let Some(event) = event.peer_observer_event {
-                            match serde_json::to_string::<PeerObserverEvent>(&event.clone()) {
+                if !is_event_subject(&msg.subject) {
+                    continue;
+                }
+                match decode_events(&msg) {
+                    Ok(events) => {
+                        for event in events {
+                            let Some(event) = event.peer_observer_event else {
+                                continue;
+                            };
+                            match serde_json::to_string::<PeerObserverEvent>(&event) {
                                 Ok(msg) => {
                                     broadcast_to_clients(&msg, &clients).await;
                                 }
@@ -203,6 +203,9 @@ async fn test_integration_websocket_p2p_message_ping() {
                         command: "ping".to_string(),
                         inbound: true,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Ping(Ping { value: 1 })),
                 }))
@@ -217,6 +220,9 @@ async fn test_integration_websocket_p2p_message_ping() {
                         command: "pong".to_string(),
                         inbound: false,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Pong(Pong { value: 1 })),
                 }))
@@ -225,8 +231,8 @@ async fn test_integration_websocket_p2p_message_ping() {
         ],
         Subject::NetMsg,
         &vec![
-            r#"{"EbpfExtractor":{"ebpf_event":{"Message":{"meta":{"peer_id":0,"addr":"127.0.0.1:8333","conn_type":1,"command":"ping","inbound":true,"size":8},"msg":{"Ping":{"value":1}}}}}}"#,
-            r#"{"EbpfExtractor":{"ebpf_event":{"Message":{"meta":{"peer_id":0,"addr":"127.0.0.1:8333","conn_type":1,"command":"pong","inbound":false,"size":8},"msg":{"Pong":{"value":1}}}}}}"#,
+            r#"{"EbpfExtractor":{"ebpf_event":{"Message":{"meta":{"peer_id":0,"addr":"127.0.0.1:8333","conn_type":1,"command":"ping","inbound":true,"size":8,"sample_rate":null},"msg":{"Ping":{"value":1}}}}}}"#,
+            r#"{"EbpfExtractor":{"ebpf_event":{"Message":{"meta":{"peer_id":0,"addr":"127.0.0.1:8333","conn_type":1,"command":"pong","inbound":false,"size":8,"sample_rate":null},"msg":{"Pong":{"value":1}}}}}}"#,
         ],
         1,
         None
@@ -249,6 +255,9 @@ async fn test_integration_websocket_multi_client() {
                         command: "ping".to_string(),
                         inbound: true,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Ping(Ping { value: 1 })),
                 }))
@@ -263,6 +272,9 @@ async fn test_integration_websocket_multi_client() {
                         command: "pong".to_string(),
                         inbound: false,
                         size: 8,
+                        sample_rate: None,
+                        payload_bytes: None,
+                        total_bytes: None,
                     },
                     msg: Some(Msg::Pong(Pong { value: 1 })),
                 }))
@@ -271,8 +283,8 @@ async fn test_integration_websocket_multi_client() {
         ],
         Subject::NetMsg,
         &vec![
-            r#"{"EbpfExtractor":{"ebpf_event":{"Message":{"meta":{"peer_id":0,"addr":"127.0.0.1:8333","conn_type":1,"command":"ping","inbound":true,"size":8},"msg":{"Ping":{"value":1}}}}}}"#,
-            r#"{"EbpfExtractor":{"ebpf_event":{"Message":{"meta":{"peer_id":0,"addr":"127.0.0.1:8333","conn_type":1,"command":"pong","inbound":false,"size":8},"msg":{"Pong":{"value":1}}}}}}"#,
+            r#"{"EbpfExtractor":{"ebpf_event":{"Message":{"meta":{"peer_id":0,"addr":"127.0.0.1:8333","conn_type":1,"command":"ping","inbound":true,"size":8,"sample_rate":null},"msg":{"Ping":{"value":1}}}}}}"#,
+            r#"{"EbpfExtractor":{"ebpf_event":{"Message":{"meta":{"peer_id":0,"addr":"127.0.0.1:8333","conn_type":1,"command":"pong","inbound":false,"size":8,"sample_rate":null},"msg":{"Pong":{"value":1}}}}}}"#,
         ],
         12,
         None